@@ -0,0 +1,244 @@
+//! Burrows-Wheeler Transform
+//!
+//! The forward transform sorts the suffixes with [`SuffixArray`] in linear time,
+//! and the inverse transform follows the LF-mapping.
+//!
+//! Two kinds of the end of the block are supported:
+//!
+//! * [`BwtKind::Rotation`] sorts the cyclic rotations and records the row of the input, as bzip2 does.
+//! * [`BwtKind::Sentinel`] appends a virtual end marker smaller than any byte and records its row.
+//!
+//! See also: <https://en.wikipedia.org/wiki/Burrows%E2%80%93Wheeler_transform>
+
+use crate::lz::match_finder::sais::SuffixArray;
+use crate::*;
+use alloc::vec;
+
+#[cfg(test)]
+mod tests;
+
+/// How the end of the block is marked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BwtKind {
+    /// Sorts the cyclic rotations, and the primary index is the row of the input itself.
+    #[default]
+    Rotation,
+    /// Sorts the suffixes terminated by a sentinel, and the primary index is the row of the sentinel.
+    ///
+    /// The sentinel itself is not stored, so the data has the same length as the input.
+    Sentinel,
+}
+
+/// Block configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Configuration {
+    block_size: usize,
+    kind: BwtKind,
+}
+
+impl Configuration {
+    /// Maximum block size, which keeps the doubled input of [`BwtKind::Rotation`] within the suffix array.
+    pub const MAX_BLOCK_SIZE: usize = 0x3fff_0000;
+
+    /// 900k bytes, the largest block of bzip2
+    pub const DEFAULT: Self = Self::new(900_000);
+
+    /// Creates a configuration of [`BwtKind::Rotation`] with the block size, which is clamped to `1..=MAX_BLOCK_SIZE`.
+    #[inline]
+    pub const fn new(block_size: usize) -> Self {
+        Self {
+            block_size: if block_size == 0 {
+                1
+            } else if block_size > Self::MAX_BLOCK_SIZE {
+                Self::MAX_BLOCK_SIZE
+            } else {
+                block_size
+            },
+            kind: BwtKind::Rotation,
+        }
+    }
+
+    #[inline]
+    pub const fn kind(mut self, kind: BwtKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    #[inline]
+    pub const fn block_size(&self) -> usize {
+        self.block_size
+    }
+}
+
+impl Default for Configuration {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A transformed block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BwtBlock {
+    kind: BwtKind,
+    data: Vec<u8>,
+    primary_index: usize,
+}
+
+impl BwtBlock {
+    /// Creates a block from the transformed data, for example read from a file.
+    #[inline]
+    pub fn new(kind: BwtKind, data: Vec<u8>, primary_index: usize) -> Self {
+        Self {
+            kind,
+            data,
+            primary_index,
+        }
+    }
+
+    #[inline]
+    pub const fn kind(&self) -> BwtKind {
+        self.kind
+    }
+
+    /// Returns the last column of the sorted rows.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[inline]
+    pub const fn primary_index(&self) -> usize {
+        self.primary_index
+    }
+
+    #[inline]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Restores the original input.
+    #[inline]
+    pub fn inverse(&self) -> Result<Vec<u8>, DecodeError> {
+        inverse(&self.data, self.primary_index, self.kind)
+    }
+}
+
+/// Transforms the input as a single block.
+pub fn forward(input: &[u8], kind: BwtKind) -> Result<BwtBlock, EncodeError> {
+    if input.len() > Configuration::MAX_BLOCK_SIZE {
+        return Err(EncodeError::InvalidInput);
+    }
+    let len = input.len();
+    let mut data = Vec::with_capacity(len);
+    let mut primary_index = 0;
+    match kind {
+        _ if len == 0 => {}
+        BwtKind::Rotation => {
+            // Sorting the suffixes of the doubled input starting in the first half sorts the rotations,
+            // and equal rotations give the same output regardless of their order.
+            let doubled = [input, input].concat();
+            let sa = SuffixArray::new(&doubled);
+            for &pos in sa.as_slice().iter().filter(|&&v| (v as usize) < len) {
+                let pos = pos as usize;
+                if pos == 0 {
+                    primary_index = data.len();
+                }
+                data.push(input[pos.checked_sub(1).unwrap_or(len - 1)]);
+            }
+        }
+        BwtKind::Sentinel => {
+            // The row of the sentinel alone comes first.
+            data.push(input[len - 1]);
+            let sa = SuffixArray::new(input);
+            for &pos in sa.as_slice() {
+                match pos.checked_sub(1) {
+                    Some(pos) => data.push(input[pos as usize]),
+                    None => primary_index = data.len(),
+                }
+            }
+        }
+    }
+    Ok(BwtBlock {
+        kind,
+        data,
+        primary_index,
+    })
+}
+
+/// Restores the input from the last column and the primary index.
+pub fn inverse(data: &[u8], primary_index: usize, kind: BwtKind) -> Result<Vec<u8>, DecodeError> {
+    let len = data.len();
+    if len == 0 {
+        return if primary_index == 0 {
+            Ok(Vec::new())
+        } else {
+            Err(DecodeError::InvalidData)
+        };
+    }
+    // The sentinel is a virtual row at the primary index.
+    let (rows, sentinel) = match kind {
+        BwtKind::Rotation if primary_index < len => (len, None),
+        BwtKind::Sentinel if primary_index <= len => (len + 1, Some(primary_index)),
+        _ => return Err(DecodeError::InvalidData),
+    };
+    if rows > u32::MAX as usize {
+        return Err(DecodeError::OutOfMemory);
+    }
+
+    // The first row of each byte in the first column
+    let mut counts = [0usize; 256];
+    for &byte in data.iter() {
+        counts[byte as usize] += 1;
+    }
+    let mut starts = [0usize; 256];
+    let mut acc = sentinel.is_some() as usize;
+    for (start, &count) in starts.iter_mut().zip(counts.iter()) {
+        *start = acc;
+        acc += count;
+    }
+
+    // `next[j]` is the row that starts one byte after the row `j`, whose last byte is the first byte of the row `j`.
+    let mut next = vec![0u32; rows];
+    let mut last_column = Vec::with_capacity(rows);
+    if let Some(sentinel) = sentinel {
+        next[0] = sentinel as u32;
+    }
+    let mut bytes = data.iter();
+    for row in 0..rows {
+        if Some(row) == sentinel {
+            last_column.push(0);
+            continue;
+        }
+        let byte = *bytes.next().unwrap();
+        let start = &mut starts[byte as usize];
+        next[*start] = row as u32;
+        *start += 1;
+        last_column.push(byte);
+    }
+
+    let mut output = Vec::with_capacity(len);
+    let mut row = next[primary_index] as usize;
+    for _ in 0..len {
+        output.push(last_column[row]);
+        row = next[row] as usize;
+    }
+    Ok(output)
+}
+
+/// Splits the input into blocks of the configured size and transforms each of them.
+pub fn forward_blocks(input: &[u8], config: Configuration) -> Result<Vec<BwtBlock>, EncodeError> {
+    input
+        .chunks(config.block_size)
+        .map(|block| forward(block, config.kind))
+        .collect()
+}
+
+/// Restores the input from the blocks.
+pub fn inverse_blocks(blocks: &[BwtBlock]) -> Result<Vec<u8>, DecodeError> {
+    let mut output = Vec::with_capacity(blocks.iter().map(|v| v.data.len()).sum());
+    for block in blocks.iter() {
+        output.extend_from_slice(&block.inverse()?);
+    }
+    Ok(output)
+}
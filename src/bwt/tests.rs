@@ -0,0 +1,87 @@
+use super::*;
+
+/// Sorts the rotations naively.
+fn naive_rotation(input: &[u8]) -> (Vec<u8>, usize) {
+    let len = input.len();
+    let mut rows = (0..len).collect::<Vec<_>>();
+    rows.sort_by(|&a, &b| {
+        let a = input[a..].iter().chain(input[..a].iter());
+        let b = input[b..].iter().chain(input[..b].iter());
+        a.cmp(b)
+    });
+    let data = rows.iter().map(|&v| input[(v + len - 1) % len]).collect();
+    let primary_index = rows.iter().position(|&v| v == 0).unwrap();
+    (data, primary_index)
+}
+
+#[test]
+fn banana() {
+    let block = forward(b"banana", BwtKind::Rotation).unwrap();
+    assert_eq!(block.data(), b"nnbaaa");
+    assert_eq!(block.primary_index(), 3);
+    assert_eq!(block.inverse().unwrap(), b"banana");
+
+    // "annb$aa" with the sentinel
+    let block = forward(b"banana", BwtKind::Sentinel).unwrap();
+    assert_eq!(block.data(), b"annbaa");
+    assert_eq!(block.primary_index(), 4);
+    assert_eq!(block.inverse().unwrap(), b"banana");
+
+    let block = forward(b"abracadabra", BwtKind::Rotation).unwrap();
+    assert_eq!(block.data(), b"rdarcaaaabb");
+    assert_eq!(block.primary_index(), 2);
+}
+
+#[test]
+fn roundtrip() {
+    let inputs = [
+        Vec::new(),
+        b"a".to_vec(),
+        b"ab".to_vec(),
+        b"abab".repeat(100),
+        vec![0; 1000],
+        vec![0xff; 1000],
+        fib_str(b'a', b'b', 10000),
+        random_bytes(0x55, 0xaa, 10000),
+        (0..=255).cycle().take(5000).collect(),
+        include_bytes!("../../README.md").to_vec(),
+    ];
+    for input in inputs.iter() {
+        let block = forward(input, BwtKind::Rotation).unwrap();
+        if !input.is_empty() {
+            let (data, primary_index) = naive_rotation(input);
+            assert_eq!(block.data(), data);
+            // Equal rotations may be at any of their rows
+            assert_eq!(
+                block.data()[primary_index],
+                block.data()[block.primary_index()]
+            );
+        }
+        assert_eq!(block.inverse().unwrap(), *input);
+
+        let block = forward(input, BwtKind::Sentinel).unwrap();
+        assert_eq!(block.data().len(), input.len());
+        assert_eq!(block.inverse().unwrap(), *input);
+    }
+}
+
+#[test]
+fn blocks() {
+    let input = fib_str(b'x', b'y', 10000);
+    for kind in [BwtKind::Rotation, BwtKind::Sentinel] {
+        let config = Configuration::new(3000).kind(kind);
+        let blocks = forward_blocks(&input, config).unwrap();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[3].data().len(), 1000);
+        assert_eq!(inverse_blocks(&blocks).unwrap(), input);
+    }
+    assert_eq!(Configuration::new(0).block_size(), 1);
+}
+
+#[test]
+fn invalid() {
+    assert!(inverse(b"abc", 3, BwtKind::Rotation).is_err());
+    assert!(inverse(b"abc", 4, BwtKind::Sentinel).is_err());
+    assert!(inverse(b"", 1, BwtKind::Rotation).is_err());
+    assert!(inverse(b"abc", 3, BwtKind::Sentinel).is_ok());
+}
@@ -0,0 +1,213 @@
+//! bzip2 decoder
+
+use super::*;
+use crate::bwt::{self, BwtKind};
+use crate::transform::{ByteRunDecoder, MoveToFront, ZeroRunDecoder};
+
+/// Decompresses a bzip2 stream, or concatenated streams.
+pub fn decode(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut output = Vec::new();
+    let mut rest = decode_stream(input, &mut output)?;
+    while !rest.is_empty() {
+        rest = decode_stream(rest, &mut output)?;
+    }
+    Ok(output)
+}
+
+/// Decodes a stream and returns the rest of the input.
+fn decode_stream<'a>(input: &'a [u8], output: &mut Vec<u8>) -> Result<&'a [u8], DecodeError> {
+    let (header, input) = input
+        .split_first_chunk::<4>()
+        .ok_or(DecodeError::UnexpectedEof)?;
+    if header[..3] != STREAM_MAGIC || !(b'1'..=b'9').contains(&header[3]) {
+        return Err(DecodeError::InvalidData);
+    }
+    let max_block_size = (header[3] - b'0') as usize * BLOCK_SIZE_UNIT;
+
+    let mut reader = MsbBitStreamReader::new(input);
+    let mut stream_crc = 0;
+    loop {
+        match read_u48(&mut reader)? {
+            BLOCK_MAGIC => {
+                let expected_crc = reader
+                    .read_bits(BitSize::Bit32)
+                    .ok_or(DecodeError::UnexpectedEof)?;
+                if reader.read_bool().ok_or(DecodeError::UnexpectedEof)? {
+                    return Err(DecodeError::UnsupportedFormat);
+                }
+                let primary_index = reader
+                    .read_bits(BitSize::Bit24)
+                    .ok_or(DecodeError::UnexpectedEof)?
+                    as usize;
+                let block = read_block(&mut reader, max_block_size)?;
+                let block = bwt::inverse(&block, primary_index, BwtKind::Rotation)?;
+
+                let start = output.len();
+                ByteRunDecoder::new().decode(&block, output);
+                let crc = block_crc(&output[start..]);
+                if crc != expected_crc {
+                    return Err(DecodeError::InvalidData);
+                }
+                stream_crc = combine_crc(stream_crc, crc);
+            }
+            END_MAGIC => {
+                if reader
+                    .read_bits(BitSize::Bit32)
+                    .ok_or(DecodeError::UnexpectedEof)?
+                    != stream_crc
+                {
+                    return Err(DecodeError::InvalidData);
+                }
+                return Ok(reader.into_rest());
+            }
+            _ => return Err(DecodeError::InvalidData),
+        }
+    }
+}
+
+/// Reads the Huffman coded block and returns the last column of the BWT.
+fn read_block(
+    reader: &mut MsbBitStreamReader,
+    max_block_size: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    let used_ranges = reader
+        .read_bits(BitSize::Bit16)
+        .ok_or(DecodeError::UnexpectedEof)?;
+    let mut seq_to_byte = Vec::with_capacity(256);
+    for range in (0..16).filter(|v| used_ranges & (0x8000 >> v) != 0) {
+        let bits = reader
+            .read_bits(BitSize::Bit16)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        for index in (0..16).filter(|v| bits & (0x8000 >> v) != 0) {
+            seq_to_byte.push((range * 16 + index) as u8);
+        }
+    }
+    if seq_to_byte.is_empty() {
+        return Err(DecodeError::InvalidData);
+    }
+    let alphabet_size = seq_to_byte.len() + 2;
+    let end_of_block = seq_to_byte.len() as u16 + 1;
+
+    let num_tables = reader
+        .read_bits(BitSize::Bit3)
+        .ok_or(DecodeError::UnexpectedEof)? as usize;
+    if !(MIN_TABLES..=MAX_TABLES).contains(&num_tables) {
+        return Err(DecodeError::InvalidData);
+    }
+    let num_selectors = reader
+        .read_bits(BitSize::Bit15)
+        .ok_or(DecodeError::UnexpectedEof)? as usize;
+    if num_selectors == 0 {
+        return Err(DecodeError::InvalidData);
+    }
+    let mut order = (0..num_tables as u8).collect::<Vec<_>>();
+    let mut selectors = Vec::with_capacity(num_selectors.min(MAX_SELECTORS));
+    for _ in 0..num_selectors {
+        let mut index = 0;
+        while reader.read_bool().ok_or(DecodeError::UnexpectedEof)? {
+            index += 1;
+            if index >= num_tables {
+                return Err(DecodeError::InvalidData);
+            }
+        }
+        let selector = order[index];
+        order.copy_within(0..index, 1);
+        order[0] = selector;
+        // The extra selectors are ignored as the reference decoder does.
+        if selectors.len() < MAX_SELECTORS {
+            selectors.push(selector);
+        }
+    }
+
+    let mut decoders = Vec::with_capacity(num_tables);
+    for _ in 0..num_tables {
+        let mut lengths = Vec::with_capacity(alphabet_size);
+        let mut current = reader
+            .read_bits(BitSize::Bit5)
+            .ok_or(DecodeError::UnexpectedEof)? as u8;
+        for _ in 0..alphabet_size {
+            loop {
+                if !(1..=MAX_CODE_LEN).contains(&current) {
+                    return Err(DecodeError::InvalidData);
+                }
+                if !reader.read_bool().ok_or(DecodeError::UnexpectedEof)? {
+                    break;
+                }
+                if reader.read_bool().ok_or(DecodeError::UnexpectedEof)? {
+                    current -= 1;
+                } else {
+                    current += 1;
+                }
+            }
+            lengths.push(current);
+        }
+        decoders.push(HuffmanDecoder::new(&lengths));
+    }
+
+    let mut symbols = Vec::new();
+    let mut selectors = selectors.iter();
+    let mut decoder = &decoders[0];
+    for count in 0.. {
+        if count % GROUP_SIZE == 0 {
+            let &selector = selectors.next().ok_or(DecodeError::InvalidData)?;
+            decoder = &decoders[selector as usize];
+        }
+        let symbol = decoder.decode(reader)?;
+        if symbol == end_of_block {
+            break;
+        }
+        symbols.push(symbol);
+    }
+
+    let mut output = Vec::with_capacity(symbols.len());
+    let mut decoder = ZeroRunDecoder::new(max_block_size);
+    decoder.decode(&symbols, &mut output)?;
+    decoder.finish(&mut output);
+    MoveToFront::new().decode(&mut output);
+    for byte in output.iter_mut() {
+        *byte = *seq_to_byte
+            .get(*byte as usize)
+            .ok_or(DecodeError::InvalidData)?;
+    }
+    Ok(output)
+}
+
+/// Canonical Huffman decoder reading a bit at a time
+struct HuffmanDecoder {
+    /// Number of the codes of each length
+    counts: [u16; MAX_CODE_LEN as usize + 1],
+    /// Symbols ordered by the lengths and then by themselves
+    symbols: Vec<u16>,
+}
+
+impl HuffmanDecoder {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; MAX_CODE_LEN as usize + 1];
+        for &len in lengths.iter() {
+            counts[len as usize] += 1;
+        }
+        let mut symbols = (0..lengths.len() as u16).collect::<Vec<_>>();
+        symbols.sort_by_key(|&v| lengths[v as usize]);
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut MsbBitStreamReader) -> Result<u16, DecodeError> {
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+        for &count in self.counts[1..].iter() {
+            code |= reader
+                .read_bits(BitSize::Bit1)
+                .ok_or(DecodeError::UnexpectedEof)?;
+            let count = count as u32;
+            // `code >= first` holds since the shorter codes did not match.
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(DecodeError::InvalidData)
+    }
+}
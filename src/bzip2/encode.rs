@@ -0,0 +1,281 @@
+//! bzip2 encoder
+
+use super::*;
+use crate::bwt::{self, BwtKind};
+use crate::entropy::prefix::{CanonicalPrefixCoder, LengthLimitMethod};
+use crate::transform::{ByteRunEncoder, MAX_BYTE_RUN, MoveToFront, ZeroRunEncoder};
+use alloc::vec;
+
+/// Maximum length of the codes written by the encoder, as the reference encoder does
+const MAX_ENCODE_CODE_LEN: BitSize = BitSize::Bit17;
+
+/// Number of iterations to refine the tables
+const NUM_ITERATIONS: usize = 4;
+
+/// Encoder configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Configuration {
+    level: u8,
+}
+
+impl Configuration {
+    pub const MIN_LEVEL: u8 = 1;
+
+    pub const MAX_LEVEL: u8 = 9;
+
+    pub const DEFAULT: Self = Self::new(Self::MAX_LEVEL);
+
+    /// Creates a configuration with the block size of `level * 100k` bytes, from 1 to 9.
+    #[inline]
+    pub const fn new(level: u8) -> Self {
+        Self {
+            level: if level < Self::MIN_LEVEL {
+                Self::MIN_LEVEL
+            } else if level > Self::MAX_LEVEL {
+                Self::MAX_LEVEL
+            } else {
+                level
+            },
+        }
+    }
+
+    #[inline]
+    pub const fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Returns the maximum size of a block after the initial run-length encoding.
+    ///
+    /// Smaller by 19 bytes than `level * 100k` as the reference encoder does.
+    #[inline]
+    pub const fn block_size(&self) -> usize {
+        self.level as usize * BLOCK_SIZE_UNIT - 19
+    }
+}
+
+impl Default for Configuration {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Compresses the input into a bzip2 stream.
+pub fn encode(input: &[u8], config: Configuration) -> Result<Vec<u8>, EncodeError> {
+    let mut writer = MsbBitStreamWriter::new();
+    writer.extend_from_slice(&STREAM_MAGIC);
+    writer.push_byte(b'0' + config.level);
+
+    let max_block_size = config.block_size();
+    let mut block = Vec::with_capacity(max_block_size.min(input.len() + 1));
+    let mut stream_crc = 0;
+    let mut cursor = 0;
+    while cursor < input.len() {
+        let start = cursor;
+        block.clear();
+        while let Some(&byte) = input.get(cursor) {
+            let run = input[cursor..]
+                .iter()
+                .take(MAX_BYTE_RUN)
+                .take_while(|&&v| v == byte)
+                .count();
+            if block.len() + ByteRunEncoder::encoded_len(run) > max_block_size {
+                break;
+            }
+            let mut encoder = ByteRunEncoder::new();
+            encoder.encode(&input[cursor..cursor + run], &mut block);
+            encoder.finish(&mut block);
+            cursor += run;
+        }
+
+        let crc = block_crc(&input[start..cursor]);
+        stream_crc = combine_crc(stream_crc, crc);
+        write_block(&mut writer, &block, crc)?;
+    }
+
+    write_u48(&mut writer, END_MAGIC);
+    write_u32(&mut writer, stream_crc);
+    Ok(writer.into_bytes())
+}
+
+/// Writes a block after the initial run-length encoding.
+fn write_block(writer: &mut MsbBitStreamWriter, block: &[u8], crc: u32) -> Result<(), EncodeError> {
+    let bwt = bwt::forward(block, BwtKind::Rotation)?;
+    let data = bwt.data();
+
+    write_u48(writer, BLOCK_MAGIC);
+    write_u32(writer, crc);
+    writer.push_bool(false); // randomized
+    writer.push(VarLenInteger::new(
+        BitSize::Bit24,
+        bwt.primary_index() as u32,
+    ));
+
+    let mut in_use = [false; 256];
+    for &byte in data.iter() {
+        in_use[byte as usize] = true;
+    }
+    let mut used_ranges = 0;
+    for (index, range) in in_use.chunks_exact(16).enumerate() {
+        if range.iter().any(|&v| v) {
+            used_ranges |= 0x8000 >> index;
+        }
+    }
+    writer.push(VarLenInteger::new(BitSize::Bit16, used_ranges));
+    for range in in_use.chunks_exact(16).filter(|v| v.iter().any(|&v| v)) {
+        let bits = range.iter().fold(0, |acc, &v| (acc << 1) | v as u32);
+        writer.push(VarLenInteger::new(BitSize::Bit16, bits));
+    }
+
+    let symbols = move_to_front(data, &in_use);
+    let num_in_use = in_use.iter().filter(|&&v| v).count();
+    let alphabet_size = num_in_use + 2;
+
+    let (selectors, lengths) = make_tables(&symbols, alphabet_size);
+    let num_tables = lengths.len();
+    writer.push(VarLenInteger::new(BitSize::Bit3, num_tables as u32));
+    writer.push(VarLenInteger::new(BitSize::Bit15, selectors.len() as u32));
+    let mut order = (0..num_tables as u8).collect::<Vec<_>>();
+    for &selector in selectors.iter() {
+        let index = order.iter().position(|&v| v == selector).unwrap();
+        order.copy_within(0..index, 1);
+        order[0] = selector;
+        for _ in 0..index {
+            writer.push_bool(true);
+        }
+        writer.push_bool(false);
+    }
+
+    for lengths in lengths.iter() {
+        let mut current = lengths[0];
+        writer.push(VarLenInteger::new(BitSize::Bit5, current as u32));
+        for &len in lengths.iter() {
+            while current < len {
+                writer.push(VarLenInteger::new(BitSize::Bit2, 0b10));
+                current += 1;
+            }
+            while current > len {
+                writer.push(VarLenInteger::new(BitSize::Bit2, 0b11));
+                current -= 1;
+            }
+            writer.push_bool(false);
+        }
+    }
+
+    let codes = lengths.iter().map(|v| assign_codes(v)).collect::<Vec<_>>();
+    for (group, &selector) in symbols.chunks(GROUP_SIZE).zip(selectors.iter()) {
+        let lengths = &lengths[selector as usize];
+        let codes = &codes[selector as usize];
+        for &symbol in group.iter() {
+            writer.push(VarLenInteger::new(
+                BitSize::new(lengths[symbol as usize]).unwrap(),
+                codes[symbol as usize],
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Applies move-to-front to the bytes in use, and codes the zero runs in RUNA/RUNB.
+///
+/// The other positions `n` are coded as `n + 1`, and the block ends with `num_in_use + 1`.
+fn move_to_front(data: &[u8], in_use: &[bool; 256]) -> Vec<u16> {
+    let mut seq = [0u8; 256];
+    let mut num_in_use = 0;
+    for byte in (0..=255u8).filter(|&v| in_use[v as usize]) {
+        seq[byte as usize] = num_in_use as u8;
+        num_in_use += 1;
+    }
+
+    let mut indices = data.iter().map(|&v| seq[v as usize]).collect::<Vec<_>>();
+    MoveToFront::new().encode(&mut indices);
+    let mut symbols = Vec::with_capacity(data.len() + 1);
+    let mut encoder = ZeroRunEncoder::new();
+    encoder.encode(&indices, &mut symbols);
+    encoder.finish(&mut symbols);
+    symbols.push(num_in_use as u16 + 1);
+    symbols
+}
+
+/// Chooses the tables for each group of 50 symbols, and returns the selectors and the code lengths.
+///
+/// The tables start from the ranges of symbols with similar frequencies,
+/// and then are refined with the symbols of the groups they are selected for, as the reference encoder does.
+fn make_tables(symbols: &[u16], alphabet_size: usize) -> (Vec<u8>, Vec<Vec<u8>>) {
+    let num_tables = num_tables(symbols.len());
+    let mut freq_table = vec![0usize; alphabet_size];
+    for &symbol in symbols.iter() {
+        freq_table[symbol as usize] += 1;
+    }
+
+    let mut tables = vec![vec![0u8; alphabet_size]; num_tables];
+    let mut remaining = symbols.len();
+    let mut start = 0;
+    for num_parts in (1..=num_tables).rev() {
+        let target = remaining / num_parts;
+        let mut end = start;
+        let mut acc = 0;
+        while acc < target && end < alphabet_size {
+            acc += freq_table[end];
+            end += 1;
+        }
+        if end > start + 1
+            && num_parts != num_tables
+            && num_parts != 1
+            && (num_tables - num_parts) % 2 == 1
+        {
+            end -= 1;
+            acc -= freq_table[end];
+        }
+        for (symbol, len) in tables[num_parts - 1].iter_mut().enumerate() {
+            *len = if (start..end).contains(&symbol) {
+                0
+            } else {
+                15
+            };
+        }
+        start = end;
+        remaining -= acc;
+    }
+
+    let mut selectors = vec![0u8; symbols.len().div_ceil(GROUP_SIZE)];
+    for _ in 0..NUM_ITERATIONS {
+        let mut freq_tables = vec![vec![0usize; alphabet_size]; num_tables];
+        for (group, selector) in symbols.chunks(GROUP_SIZE).zip(selectors.iter_mut()) {
+            let (best, _) = tables
+                .iter()
+                .enumerate()
+                .map(|(index, lengths)| {
+                    let cost = group
+                        .iter()
+                        .map(|&v| lengths[v as usize] as usize)
+                        .sum::<usize>();
+                    (index, cost)
+                })
+                .min_by_key(|v| v.1)
+                .unwrap();
+            *selector = best as u8;
+            for &symbol in group.iter() {
+                freq_tables[best][symbol as usize] += 1;
+            }
+        }
+
+        for (lengths, freq_table) in tables.iter_mut().zip(freq_tables.iter_mut()) {
+            // Every symbol needs a code.
+            for freq in freq_table.iter_mut() {
+                *freq = (*freq).max(1);
+            }
+            let prefix_table = CanonicalPrefixCoder::make_prefix_table(
+                freq_table,
+                MAX_ENCODE_CODE_LEN,
+                alphabet_size,
+                LengthLimitMethod::Heuristic,
+            );
+            for (len, code) in lengths.iter_mut().zip(prefix_table.iter()) {
+                *len = code.map(|v| v.size().as_u8()).unwrap_or_default();
+            }
+        }
+    }
+
+    (selectors, tables)
+}
@@ -0,0 +1,139 @@
+//! bzip2 compressed format
+//!
+//! A stream consists of blocks of up to 900k bytes, each of them is processed by
+//!
+//! 1. run-length encoding of 4 to 255 identical bytes,
+//! 2. the Burrows-Wheeler transform,
+//! 3. move-to-front with the zero runs coded in RUNA/RUNB,
+//! 4. up to six Huffman tables switched every 50 symbols.
+//!
+//! Randomized blocks, which are no longer written since bzip2 0.9.5, are not supported.
+//!
+//! See also: <https://sourceware.org/bzip2/> and <https://github.com/dsnet/compress/blob/master/doc/bzip2-format.pdf>
+
+use crate::num::VarLenInteger;
+use crate::num::bits::{BitSize, MsbBitStreamReader, MsbBitStreamWriter};
+use crate::*;
+use alloc::vec;
+
+#[cfg(test)]
+mod tests;
+
+mod decode;
+mod encode;
+pub use decode::*;
+pub use encode::*;
+
+/// Stream signature followed by the level digit
+const STREAM_MAGIC: [u8; 3] = *b"BZh";
+
+/// Block header, BCD of pi
+const BLOCK_MAGIC: u64 = 0x3141_5926_5359;
+
+/// Stream footer, BCD of sqrt(pi)
+const END_MAGIC: u64 = 0x1772_4538_5090;
+
+/// Unit of the block size
+const BLOCK_SIZE_UNIT: usize = 100_000;
+
+/// Number of symbols coded with the same table
+const GROUP_SIZE: usize = 50;
+
+const MIN_TABLES: usize = 2;
+const MAX_TABLES: usize = 6;
+
+/// Maximum length of the codes accepted by the decoder
+const MAX_CODE_LEN: u8 = 20;
+
+/// Maximum number of selectors, which is enough for a block of 900k bytes
+const MAX_SELECTORS: usize = 18002;
+
+/// Non-reflected polynomial of CRC-32 (BZIP2)
+const CRC_POLYNOMIAL: u32 = 0x04c1_1db7;
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ CRC_POLYNOMIAL
+            } else {
+                crc << 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Calculates the block CRC, which is CRC-32 in MSB-first order.
+#[inline]
+pub fn block_crc(data: &[u8]) -> u32 {
+    !data.iter().fold(u32::MAX, |crc, &byte| {
+        (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    })
+}
+
+/// Combines the block CRC into the stream CRC.
+#[inline]
+const fn combine_crc(stream_crc: u32, block_crc: u32) -> u32 {
+    stream_crc.rotate_left(1) ^ block_crc
+}
+
+/// Returns the number of tables used for `len` symbols, as the reference encoder does.
+#[inline]
+const fn num_tables(len: usize) -> usize {
+    match len {
+        0..200 => 2,
+        200..600 => 3,
+        600..1200 => 4,
+        1200..2400 => 5,
+        _ => 6,
+    }
+}
+
+/// Assigns canonical codes ordered by the lengths and then by the symbols.
+fn assign_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut codes = vec![0; lengths.len()];
+    let mut code = 0;
+    for len in 1..=MAX_CODE_LEN {
+        for (symbol, _) in lengths.iter().enumerate().filter(|v| *v.1 == len) {
+            codes[symbol] = code;
+            code += 1;
+        }
+        code <<= 1;
+    }
+    codes
+}
+
+/// Writes the 32-bit CRC, which does not fit in a single `VarLenInteger`.
+#[inline]
+fn write_u32(writer: &mut MsbBitStreamWriter, value: u32) {
+    writer.push(VarLenInteger::new(BitSize::Bit16, value >> 16));
+    writer.push(VarLenInteger::new(BitSize::Bit16, value & 0xffff));
+}
+
+/// Writes the 48-bit magic number.
+#[inline]
+fn write_u48(writer: &mut MsbBitStreamWriter, value: u64) {
+    writer.push(VarLenInteger::new(BitSize::Bit24, (value >> 24) as u32));
+    writer.push(VarLenInteger::new(BitSize::Bit24, value as u32));
+}
+
+/// Reads the 48-bit magic number.
+#[inline]
+fn read_u48(reader: &mut MsbBitStreamReader) -> Result<u64, DecodeError> {
+    let mut value = 0;
+    for _ in 0..2 {
+        let bits = reader
+            .read_bits(BitSize::Bit24)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        value = (value << 24) | bits as u64;
+    }
+    Ok(value)
+}
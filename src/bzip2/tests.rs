@@ -0,0 +1,96 @@
+use super::*;
+use base64::prelude::*;
+
+/// Compressed by Python's `bz2` at level 9
+///
+/// `"The quick brown fox jumps over the lazy dog.\n"` 4 times, `0..=255` and 300 zeros
+const TEXT_BZ2: &str = "QlpoOTFBWSZTWbdQhPcAAET/////////////////////////////////////////////sAEZpYgAAAAAAAAAAAAAAAAAAAAABMAmAAAAAAAAAAAAAAEwABMACqgJgJgACZMAAAAIwmAA0AAAAAjIwEYRhMAAAAAAAAAAAAAAATATEwEwJVU0nkynppNlMBA0YQDR6jJoBoNDEaaNNDQGI00xBkyD0ZTRoA0DTEDQaBoMgADTRo00yAaGQBpkDJgQwgZNMrrBbBMgWkKJcRpLKlXVK8gWl2zL6dRZ2BhYmNkZWa0Wm1Wu2M9ttzRnwWBCAsDOgqBACoLndLq2Xa7qbxeVLa3XpvgwvjZfb9f8BgXDBYPCYVxw2HxDklpianJ6goqSmqBFVWV1hZWltcXV5fCBRkwMLExsjKzM7Q0tTW2fQAG5IABv+YAONkCFAhFYYtCGkDRQ4MwOKsEqZICYFS2DyoRgsCHUBkkYZEE4KFwFaIGAGIFiEEiIMmhBODGlBfQAPhYADD0AC6RAAvBr2+ff8Pr8/wH5/UDBQcJ+4WGh4iJiouMjY6PkJGSk5SVlpeYmaChoqOkpaanqKmqq6ytrq+wsbKztLW2t7i5uru8vb6/wMHCw8TFxsfIycrLzM3Oz9DR0tPU1dbX2Nna29zd3t/g4eLj5P9y83P0dPV19nb3d/h4+Xn//T19vf40vn5+vv8/RdyRThQkLdQhPcA==";
+
+/// Compressed by Python's `bz2` at level 1, `"abc"` 50000 times in two blocks
+const MULTI_BLOCK_BZ2: &str = "QlpoMTFBWSZTWVoLN+QAQRcBADgAIAAwzAUppqpCB1UhA1UhA8xQVkmU1kbDD3YACCQAQA4ACAAMMwFKaaCIHQRA0EQPF3JFOFCQrxpSEA==";
+
+/// Compressed by Python's `bz2`, an empty input
+const EMPTY_BZ2: &str = "QlpoORdyRThQkAAAAAA=";
+
+fn sample_text() -> Vec<u8> {
+    let mut text = b"The quick brown fox jumps over the lazy dog.\n".repeat(4);
+    text.extend(0..=255);
+    text.extend_from_slice(&[0; 300]);
+    text
+}
+
+#[test]
+fn decode_reference() {
+    let input = BASE64_STANDARD.decode(TEXT_BZ2).unwrap();
+    assert_eq!(decode(&input).unwrap(), sample_text());
+
+    let input = BASE64_STANDARD.decode(MULTI_BLOCK_BZ2).unwrap();
+    assert_eq!(decode(&input).unwrap(), b"abc".repeat(50000));
+
+    let input = BASE64_STANDARD.decode(EMPTY_BZ2).unwrap();
+    assert_eq!(decode(&input).unwrap(), b"");
+    assert_eq!(encode(&[], Configuration::DEFAULT).unwrap(), input);
+}
+
+#[test]
+fn concatenated_and_broken() {
+    let text = BASE64_STANDARD.decode(TEXT_BZ2).unwrap();
+    let empty = BASE64_STANDARD.decode(EMPTY_BZ2).unwrap();
+    let input = [text.as_slice(), &empty, &text].concat();
+    assert_eq!(
+        decode(&input).unwrap(),
+        [sample_text(), sample_text()].concat()
+    );
+
+    assert_eq!(decode(&[]), Err(DecodeError::UnexpectedEof));
+    assert_eq!(decode(b"BZh0"), Err(DecodeError::InvalidData));
+    assert!(decode(&text[..text.len() - 1]).is_err());
+    assert!(decode(&[text.as_slice(), b"garbage"].concat()).is_err());
+    // Block CRC
+    let mut broken = text.clone();
+    broken[10] ^= 1;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    // Stream CRC
+    let mut broken = text.clone();
+    let len = broken.len();
+    broken[len - 2] ^= 1;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+}
+
+#[test]
+fn roundtrip() {
+    let mut runs = Vec::new();
+    for len in [1, 3, 4, 5, 254, 255, 256, 258, 259, 260, 1000] {
+        runs.extend(core::iter::repeat_n(len as u8, len));
+    }
+    let inputs = [
+        b"a".to_vec(),
+        b"ab".to_vec(),
+        sample_text(),
+        runs,
+        fib_str(b'a', b'b', 10000),
+        random_bytes(0, 0xff, 10000),
+        (0..=255).cycle().take(20000).collect(),
+        include_bytes!("../lib.rs").to_vec(),
+    ];
+    for input in inputs.iter() {
+        let encoded = encode(input, Configuration::DEFAULT).unwrap();
+        assert_eq!(decode(&encoded).unwrap(), *input);
+    }
+
+    // Multiple blocks
+    let input = fib_str(b'x', b'y', 250_000);
+    let encoded = encode(&input, Configuration::new(1)).unwrap();
+    assert_eq!(&encoded[..4], b"BZh1");
+    assert_eq!(decode(&encoded).unwrap(), input);
+
+    let input = sample_text().repeat(100);
+    let encoded = encode(&input, Configuration::DEFAULT).unwrap();
+    assert!(encoded.len() < 1000);
+}
+
+#[test]
+fn block_crc_check() {
+    assert_eq!(block_crc(b""), 0);
+    assert_eq!(block_crc(b"123456789"), 0xfc89_1918);
+}
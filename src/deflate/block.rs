@@ -0,0 +1,523 @@
+//! Prefix coded and stored blocks shared by the compressors
+//!
+//! The prefix codes are built in fixed arrays without allocation, and are written with any
+//! [`BitWrite`], so both [`deflate()`] and [`deflate_into()`] use them.
+
+use super::*;
+use crate::{
+    entropy::prefix::{PermutationFlavor, REP3P2, REP3Z3, REP11Z7},
+    num::bits::BitStreamWriter,
+};
+use core::{cmp::Reverse, convert::Infallible};
+
+/// Maximum length of a stored block
+pub(super) const MAX_STORED_LEN: usize = 65535;
+
+/// Number of the literal/length codes, excluding the two reserved ones
+const NUM_LIT: usize = 286;
+
+/// Number of the literal/length codes of the static blocks, including the two reserved ones
+const NUM_FIXED_LIT: usize = 288;
+
+/// Number of the distance codes
+const NUM_DIST: usize = 30;
+
+/// Number of the code length codes
+const NUM_CODE_LEN: usize = 19;
+
+/// Maximum length of the literal/length and distance codes
+const MAX_CODE_LEN: u8 = 15;
+
+/// Maximum length of the code length codes
+const MAX_CODE_LEN_CODE_LEN: u8 = 7;
+
+/// LSB-first bit writer of the blocks
+pub(super) trait BitWrite {
+    type Error;
+
+    /// Writes the lowest `size` bits of `value`, where `size` is at most 16.
+    fn write_bits(&mut self, value: u32, size: u32) -> Result<(), Self::Error>;
+
+    /// Pads the current byte with zeros.
+    fn align(&mut self) -> Result<(), Self::Error>;
+
+    /// Writes the bytes, which must be at a byte boundary.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl BitWrite for BitStreamWriter {
+    type Error = Infallible;
+
+    #[inline]
+    fn write_bits(&mut self, value: u32, size: u32) -> Result<(), Self::Error> {
+        if let Some(size) = BitSize::new(size as u8) {
+            self.push(VarLenInteger::new(size, value));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn align(&mut self) -> Result<(), Self::Error> {
+        self.skip_to_next_byte_boundary();
+        Ok(())
+    }
+
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Symbol frequencies of a block, and its static and dynamic prefix codes
+pub(super) struct BlockCoder {
+    freq_lit: [u32; NUM_LIT],
+    freq_dist: [u32; NUM_DIST],
+    /// The number of the extra bits of the lengths and distances
+    extra_bits: usize,
+    fixed: FixedTables,
+    dynamic: DynamicTables,
+}
+
+impl BlockCoder {
+    pub(super) fn new(symbols: &[DeflateLZIR]) -> Self {
+        let mut freq_lit = [0u32; NUM_LIT];
+        let mut freq_dist = [0u32; NUM_DIST];
+        for symbol in symbols {
+            let lit_len = symbol.literal_value() as usize;
+            freq_lit[lit_len] += 1;
+            if lit_len > 256 {
+                freq_dist[symbol.distance_value() as usize] += 1;
+            }
+        }
+        freq_lit[256] = 1; // end of block
+
+        let extra_bits = symbols
+            .iter()
+            .map(|symbol| {
+                symbol.length_extra_bit_size().map_or(0, |v| v.as_usize())
+                    + if symbol.literal_value() > 256 {
+                        symbol.distance_extra_bit_size().map_or(0, |v| v.as_usize())
+                    } else {
+                        0
+                    }
+            })
+            .sum::<usize>();
+
+        let dynamic = DynamicTables::new(&freq_lit, &freq_dist);
+        Self {
+            freq_lit,
+            freq_dist,
+            extra_bits,
+            fixed: FixedTables::new(),
+            dynamic,
+        }
+    }
+
+    /// Returns the number of bits of the block with the static prefix codes.
+    pub(super) fn static_bits(&self) -> usize {
+        3 + self.fixed.lit.cost(&self.freq_lit)
+            + self.fixed.dist.cost(&self.freq_dist)
+            + self.extra_bits
+    }
+
+    /// Returns the number of bits of the block with the dynamic prefix codes.
+    pub(super) fn dynamic_bits(&self) -> usize {
+        self.dynamic.header_bits()
+            + self.dynamic.lit.cost(&self.freq_lit)
+            + self.dynamic.dist.cost(&self.freq_dist)
+            + self.extra_bits
+    }
+
+    /// Writes the block of `symbols` with the static or dynamic prefix codes.
+    pub(super) fn write<W: BitWrite>(
+        &self,
+        writer: &mut W,
+        symbols: &[DeflateLZIR],
+        is_final: bool,
+        use_static: bool,
+    ) -> Result<(), W::Error> {
+        if use_static {
+            writer.write_bits(is_final as u32 | 0b01 << 1, 3)?;
+            write_symbols(writer, symbols, &self.fixed.lit, &self.fixed.dist)
+        } else {
+            writer.write_bits(is_final as u32 | 0b10 << 1, 3)?;
+            self.dynamic.write_header(writer)?;
+            write_symbols(writer, symbols, &self.dynamic.lit, &self.dynamic.dist)
+        }
+    }
+}
+
+/// Returns the size of the stored blocks of `len` bytes, when `pending_bits` bits of the current
+/// byte have been written.
+#[inline]
+pub(super) const fn stored_bits(pending_bits: u32, len: usize) -> usize {
+    let chunks = if len == 0 {
+        1
+    } else {
+        len.div_ceil(MAX_STORED_LEN)
+    };
+    let padding = (8 - (pending_bits as usize + 3) % 8) % 8;
+    3 + padding + 32 + (chunks - 1) * 40 + len * 8
+}
+
+pub(super) fn write_stored<W: BitWrite>(
+    writer: &mut W,
+    raw: &[u8],
+    is_final: bool,
+) -> Result<(), W::Error> {
+    let mut chunks = raw.chunks(MAX_STORED_LEN).peekable();
+    let mut chunk = chunks.next().unwrap_or_default();
+    loop {
+        let is_last = chunks.peek().is_none();
+        writer.write_bits((is_final && is_last) as u32, 3)?;
+        writer.align()?;
+        let len = chunk.len() as u16;
+        writer.write_bytes(&len.to_le_bytes())?;
+        writer.write_bytes(&(!len).to_le_bytes())?;
+        writer.write_bytes(chunk)?;
+        match chunks.next() {
+            Some(next) => chunk = next,
+            None => return Ok(()),
+        }
+    }
+}
+
+fn write_symbols<W: BitWrite>(
+    writer: &mut W,
+    symbols: &[DeflateLZIR],
+    table_lit: &PrefixTable<NUM_LIT>,
+    table_dist: &PrefixTable<NUM_DIST>,
+) -> Result<(), W::Error> {
+    for symbol in symbols {
+        let lit_len = symbol.literal_value();
+        table_lit.write(writer, lit_len as usize)?;
+        if lit_len > 256 {
+            if let Some(size) = symbol.length_extra_bit_size() {
+                writer.write_bits(symbol.length_extra_bits_raw(), size.as_u8() as u32)?;
+            }
+            table_dist.write(writer, symbol.distance_value() as usize)?;
+            if let Some(size) = symbol.distance_extra_bit_size() {
+                writer.write_bits(symbol.distance_extra_bits_raw(), size.as_u8() as u32)?;
+            }
+        }
+    }
+    table_lit.write(writer, 256) // end of block
+}
+
+/// Canonical prefix codes of `N` symbols
+struct PrefixTable<const N: usize> {
+    lengths: [u8; N],
+    /// The codes in the reversed bit order, as deflate writes them
+    codes: [u16; N],
+}
+
+impl<const N: usize> PrefixTable<N> {
+    fn from_lengths(lengths: [u8; N]) -> Self {
+        let mut bl_count = [0u16; MAX_CODE_LEN as usize + 1];
+        for &len in lengths.iter() {
+            bl_count[len as usize] += 1;
+        }
+        bl_count[0] = 0;
+        let mut next_code = [0u16; MAX_CODE_LEN as usize + 1];
+        let mut code = 0u16;
+        for bits in 1..=MAX_CODE_LEN as usize {
+            code = (code + bl_count[bits - 1]) << 1;
+            next_code[bits] = code;
+        }
+        let mut codes = [0u16; N];
+        for (code, &len) in codes.iter_mut().zip(lengths.iter()) {
+            if len > 0 {
+                *code = next_code[len as usize].reverse_bits() >> (16 - len);
+                next_code[len as usize] += 1;
+            }
+        }
+        Self { lengths, codes }
+    }
+
+    /// Returns the table of the first `M` symbols.
+    fn truncated<const M: usize>(&self) -> PrefixTable<M> {
+        let mut lengths = [0u8; M];
+        let mut codes = [0u16; M];
+        lengths.copy_from_slice(&self.lengths[..M]);
+        codes.copy_from_slice(&self.codes[..M]);
+        PrefixTable { lengths, codes }
+    }
+
+    fn from_freqs(freqs: &[u32; N], max_len: u8) -> Self {
+        let mut lengths = [0u8; N];
+        build_lengths(freqs, &mut lengths, max_len);
+        Self::from_lengths(lengths)
+    }
+
+    /// Returns the number of bits of the symbols of `freqs`.
+    #[inline]
+    fn cost(&self, freqs: &[u32; N]) -> usize {
+        freqs
+            .iter()
+            .zip(self.lengths.iter())
+            .map(|(&freq, &len)| freq as usize * len as usize)
+            .sum()
+    }
+
+    #[inline]
+    fn write<W: BitWrite>(&self, writer: &mut W, symbol: usize) -> Result<(), W::Error> {
+        writer.write_bits(self.codes[symbol] as u32, self.lengths[symbol] as u32)
+    }
+}
+
+/// Builds the lengths of the prefix codes of `freqs`, limited to `max_len` bits.
+fn build_lengths(freqs: &[u32], lengths: &mut [u8], max_len: u8) {
+    let mut leaves = [0u16; NUM_LIT];
+    let mut count = 0;
+    for (symbol, &freq) in freqs.iter().enumerate() {
+        if freq > 0 {
+            leaves[count] = symbol as u16;
+            count += 1;
+        }
+    }
+    lengths.fill(0);
+    if count < 2 {
+        // Deflate needs at least two codes to make a complete code.
+        let symbol = if count == 1 { leaves[0] as usize } else { 0 };
+        lengths[symbol] = 1;
+        lengths[if symbol == 0 { 1 } else { 0 }] = 1;
+        return;
+    }
+    let leaves = &mut leaves[..count];
+    // The lower symbols get the shorter codes among the equally frequent ones.
+    leaves.sort_unstable_by_key(|&symbol| (freqs[symbol as usize], Reverse(symbol)));
+
+    // Huffman's algorithm with two queues, since the leaves are sorted and the internal nodes
+    // are created in increasing order of weight.
+    let mut weights = [0u32; NUM_LIT * 2];
+    let mut parents = [0u16; NUM_LIT * 2];
+    for (weight, &symbol) in weights.iter_mut().zip(leaves.iter()) {
+        *weight = freqs[symbol as usize];
+    }
+    let mut next_leaf = 0;
+    let mut next_node = count;
+    let mut n_nodes = count;
+    for _ in 1..count {
+        let mut pick = || {
+            if next_leaf < count
+                && (next_node >= n_nodes || weights[next_leaf] <= weights[next_node])
+            {
+                next_leaf += 1;
+                next_leaf - 1
+            } else {
+                next_node += 1;
+                next_node - 1
+            }
+        };
+        let (a, b) = (pick(), pick());
+        weights[n_nodes] = weights[a] + weights[b];
+        parents[a] = n_nodes as u16;
+        parents[b] = n_nodes as u16;
+        n_nodes += 1;
+    }
+
+    // The parent of a node always comes after it, so the depths are resolved from the root.
+    let root = n_nodes - 1;
+    let mut depths = [0u16; NUM_LIT * 2];
+    let mut bl_count = [0u32; MAX_CODE_LEN as usize + 1];
+    for node in (0..root).rev() {
+        depths[node] = depths[parents[node] as usize] + 1;
+        if node < count {
+            bl_count[depths[node].min(max_len as u16) as usize] += 1;
+        }
+    }
+
+    // Moves the codes clamped to `max_len` up the tree until the code is complete.
+    let max_len = max_len as usize;
+    let mut total = (1..=max_len)
+        .map(|len| bl_count[len] << (max_len - len))
+        .sum::<u32>();
+    while total > 1 << max_len {
+        bl_count[max_len] -= 1;
+        for len in (1..max_len).rev() {
+            if bl_count[len] != 0 {
+                bl_count[len] -= 1;
+                bl_count[len + 1] += 2;
+                break;
+            }
+        }
+        total -= 1;
+    }
+
+    // The least frequent symbols get the longest codes.
+    let mut leaves = leaves.iter();
+    for len in (1..=max_len).rev() {
+        for &symbol in leaves.by_ref().take(bl_count[len] as usize) {
+            lengths[symbol as usize] = len as u8;
+        }
+    }
+}
+
+/// Prefix codes of the static blocks
+struct FixedTables {
+    lit: PrefixTable<NUM_LIT>,
+    dist: PrefixTable<NUM_DIST>,
+}
+
+impl FixedTables {
+    fn new() -> Self {
+        // The reserved codes take part in the static code, so its lengths cover all of them.
+        let mut lengths_lit = [0u8; NUM_FIXED_LIT];
+        for (symbol, len) in lengths_lit.iter_mut().enumerate() {
+            *len = match symbol {
+                0..=143 => 8,
+                144..=255 => 9,
+                256..=279 => 7,
+                _ => 8,
+            };
+        }
+        Self {
+            lit: PrefixTable::from_lengths(lengths_lit).truncated(),
+            dist: PrefixTable::from_lengths([5; NUM_DIST]),
+        }
+    }
+}
+
+/// Prefix codes of a dynamic block and the run-length encoded code lengths of them
+struct DynamicTables {
+    lit: PrefixTable<NUM_LIT>,
+    dist: PrefixTable<NUM_DIST>,
+    code_len: PrefixTable<NUM_CODE_LEN>,
+    hlit: usize,
+    hdist: usize,
+    hclen: usize,
+    /// The code length codes and their extra bits
+    runs: [(u8, u8); NUM_LIT + NUM_DIST],
+    n_runs: usize,
+}
+
+impl DynamicTables {
+    fn new(freq_lit: &[u32; NUM_LIT], freq_dist: &[u32; NUM_DIST]) -> Self {
+        let lit = PrefixTable::from_freqs(freq_lit, MAX_CODE_LEN);
+        let dist = PrefixTable::from_freqs(freq_dist, MAX_CODE_LEN);
+        let hlit = 257.max(lit.lengths.iter().rposition(|&v| v > 0).unwrap_or_default() + 1);
+        let hdist = 1.max(
+            dist.lengths
+                .iter()
+                .rposition(|&v| v > 0)
+                .unwrap_or_default()
+                + 1,
+        );
+
+        let mut lengths = [0u8; NUM_LIT + NUM_DIST];
+        lengths[..hlit].copy_from_slice(&lit.lengths[..hlit]);
+        lengths[hlit..hlit + hdist].copy_from_slice(&dist.lengths[..hdist]);
+        let lengths = &lengths[..hlit + hdist];
+
+        let mut runs = [(0u8, 0u8); NUM_LIT + NUM_DIST];
+        let mut n_runs = 0;
+        let mut push = |code: u8, extra: usize| {
+            runs[n_runs] = (code, extra as u8);
+            n_runs += 1;
+        };
+        let mut index = 0;
+        while index < lengths.len() {
+            let len = lengths[index];
+            let run = lengths[index..].iter().take_while(|&&v| v == len).count();
+            index += run;
+            let mut rest = run;
+            if len == 0 {
+                while rest >= 11 {
+                    let repeat = rest.min(138);
+                    push(REP11Z7, repeat - 11);
+                    rest -= repeat;
+                }
+                if rest >= 3 {
+                    push(REP3Z3, rest - 3);
+                    rest = 0;
+                }
+            } else {
+                push(len, 0);
+                rest -= 1;
+                while rest >= 3 {
+                    let repeat = rest.min(6);
+                    push(REP3P2, repeat - 3);
+                    rest -= repeat;
+                }
+            }
+            for _ in 0..rest {
+                push(len, 0);
+            }
+        }
+
+        let mut freq_code_len = [0u32; NUM_CODE_LEN];
+        for &(code, _) in runs[..n_runs].iter() {
+            freq_code_len[code as usize] += 1;
+        }
+        let code_len = PrefixTable::from_freqs(&freq_code_len, MAX_CODE_LEN_CODE_LEN);
+        let hclen = 4.max(
+            PermutationFlavor::Deflate
+                .permutation_order()
+                .iter()
+                .rposition(|&code| code_len.lengths[code as usize] > 0)
+                .unwrap_or_default()
+                + 1,
+        );
+
+        Self {
+            lit,
+            dist,
+            code_len,
+            hlit,
+            hdist,
+            hclen,
+            runs,
+            n_runs,
+        }
+    }
+
+    #[inline]
+    fn runs(&self) -> &[(u8, u8)] {
+        &self.runs[..self.n_runs]
+    }
+
+    #[inline]
+    fn extra_bits_of(code: u8) -> u32 {
+        match code {
+            REP3P2 => 2,
+            REP3Z3 => 3,
+            REP11Z7 => 7,
+            _ => 0,
+        }
+    }
+
+    /// Returns the number of bits from the block header to the end of the code lengths.
+    fn header_bits(&self) -> usize {
+        3 + 5
+            + 5
+            + 4
+            + 3 * self.hclen
+            + self
+                .runs()
+                .iter()
+                .map(|&(code, _)| {
+                    (self.code_len.lengths[code as usize] as u32 + Self::extra_bits_of(code))
+                        as usize
+                })
+                .sum::<usize>()
+    }
+
+    /// Writes the header after the block type.
+    fn write_header<W: BitWrite>(&self, writer: &mut W) -> Result<(), W::Error> {
+        writer.write_bits(self.hlit as u32 - 257, 5)?;
+        writer.write_bits(self.hdist as u32 - 1, 5)?;
+        writer.write_bits(self.hclen as u32 - 4, 4)?;
+        for &code in PermutationFlavor::Deflate.permutation_order()[..self.hclen].iter() {
+            writer.write_bits(self.code_len.lengths[code as usize] as u32, 3)?;
+        }
+        for &(code, extra) in self.runs() {
+            self.code_len.write(writer, code as usize)?;
+            let size = Self::extra_bits_of(code);
+            if size > 0 {
+                writer.write_bits(extra as u32, size)?;
+            }
+        }
+        Ok(())
+    }
+}
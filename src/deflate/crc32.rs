@@ -0,0 +1,48 @@
+//! CRC-32 checksum implementation
+//!
+//! References:
+//!
+//! * <https://www.ietf.org/rfc/rfc1952.txt>
+//! * <https://en.wikipedia.org/wiki/Cyclic_redundancy_check>
+//!
+
+/// Reversed polynomial of CRC-32 (ISO-HDLC)
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+const TABLE: [u32; 256] = make_table(POLYNOMIAL);
+
+/// Makes the table of a reflected CRC-32 with the reversed polynomial.
+pub(crate) const fn make_table(polynomial: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ polynomial
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32 checksum implementation
+#[inline]
+pub fn checksum(data: &[u8]) -> u32 {
+    update(0, data)
+}
+
+/// Continues the checksum `crc` of the preceding data with `data`.
+pub fn update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc = (crc >> 8) ^ TABLE[((crc as u8) ^ byte) as usize];
+    }
+    !crc
+}
@@ -1,11 +1,8 @@
 //! Deflate compressor
 
-use super::*;
+use super::{block::BlockCoder, *};
 use crate::{
-    entropy::{
-        entropy_of,
-        prefix::{CanonicalPrefixCoder, CanonicalPrefixDecoder, PermutationFlavor},
-    },
+    entropy::entropy_of,
     lz::{
         Match,
         lzss::{self, LZSS},
@@ -68,16 +65,8 @@ pub fn deflate(
 
     for block in blocks {
         if !config.level.is_fast_method() && block.estimated_size() < THRESHOLD_STATIC {
-            let mut ref_static = BitStreamWriter::new();
-            block.encode(&mut ref_static, true);
-            let mut ref_dynamic = BitStreamWriter::new();
-            block.encode(&mut ref_dynamic, false);
-
             // choose the smaller one
-            block.encode(
-                &mut output,
-                ref_static.bit_count() < ref_dynamic.bit_count(),
-            );
+            block.encode(&mut output, block.bit_count(true) < block.bit_count(false));
         } else {
             block.encode(&mut output, false);
         }
@@ -100,6 +89,7 @@ pub fn deflate(
 /// * bit 14-18: length extra bits
 /// * bit 19-31: distance extra bits
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
 pub struct DeflateLZIR(u32);
 
 impl DeflateLZIR {
@@ -324,99 +314,20 @@ impl<'a> DeflateIrBlock<'a> {
         self.estimated_size
     }
 
-    /// Encode the block to the output stream.
-    pub fn encode(&self, output: &mut BitStreamWriter, use_static: bool) {
-        let (prefix_table_lit, prefix_table_dist) = if use_static {
-            let mut lengths_lit = [0u8; 288];
-            for i in 0..288 {
-                lengths_lit[i] = if i < 144 {
-                    8
-                } else if i < 256 {
-                    9
-                } else if i < 280 {
-                    7
-                } else {
-                    8
-                };
-            }
-            let mut prefix_table_lit = Vec::with_capacity(288);
-            prefix_table_lit.resize(288, None);
-            for (index, value) in CanonicalPrefixDecoder::make_prefix_table(
-                lengths_lit.into_iter().enumerate(),
-                false,
-            )
-            .unwrap()
-            {
-                prefix_table_lit[index] = Some(value);
-            }
-
-            let prefix_table_dist = (0..30)
-                .map(|v| Some(VarLenInteger::new(BitSize::Bit5, v as u32)))
-                .collect::<Vec<_>>();
-
-            (prefix_table_lit, prefix_table_dist)
-        } else {
-            let prefix_table_lit =
-                CanonicalPrefixCoder::make_prefix_table(self.freq_count_lit(), BitSize::Bit15, 257);
-            let mut prefix_table_dist =
-                CanonicalPrefixCoder::make_prefix_table(self.freq_count_dist(), BitSize::Bit15, 1);
-
-            // fix prefix table for dist
-            let prefix_table_dist_count = prefix_table_dist.iter().filter(|v| v.is_some()).count();
-            if prefix_table_dist_count == 0 {
-                prefix_table_dist.push(Some(VarLenInteger::with_bool(true)));
-                prefix_table_dist.push(Some(VarLenInteger::with_bool(true)));
-            } else if prefix_table_dist_count < 2 {
-                prefix_table_dist.push(Some(VarLenInteger::with_bool(true)));
-            }
-
-            (prefix_table_lit, prefix_table_dist)
-        };
-
-        output.write(self.is_final()); // bfinal
+    /// Returns the number of bits of the encoded block, without encoding it.
+    pub fn bit_count(&self, use_static: bool) -> usize {
+        let coder = BlockCoder::new(self.block);
         if use_static {
-            output.write(VarLenInteger::new(BitSize::Bit2, 0b01)); // btype
+            coder.static_bits()
         } else {
-            let prefix_tables = prefix_table_lit
-                .iter()
-                .chain(prefix_table_dist.iter())
-                .map(|v| v.map(|v| v.size().as_u8()).unwrap_or_default())
-                .collect::<Vec<_>>();
-            let prefix_tables = CanonicalPrefixCoder::encode_prefix_tables(
-                &[&prefix_tables],
-                PermutationFlavor::Deflate,
-            )
-            .unwrap();
-
-            output.write(VarLenInteger::new(BitSize::Bit2, 0b10)); // btype
-            output.write(VarLenInteger::new(
-                BitSize::Bit5,
-                prefix_table_lit.len() as u32 - 257,
-            )); // hlit
-            output.write(VarLenInteger::new(
-                BitSize::Bit5,
-                prefix_table_dist.len() as u32 - 1,
-            )); // hdist
-            output.write(prefix_tables.hclen); // hclen
-            output.write(prefix_tables.prefix_table.as_slice());
-            output.write(prefix_tables.content.as_slice());
+            coder.dynamic_bits()
         }
+    }
 
-        for lzir in self.block.iter() {
-            let lit_len = lzir.literal_value();
-            output.write(prefix_table_lit[lit_len as usize].unwrap().reversed());
-            if lit_len > 256 {
-                if let Some(len_extra) = lzir.length_extra_bits() {
-                    output.write(len_extra);
-                }
-                let dist = lzir.distance_value();
-                output.write(prefix_table_dist[dist as usize].unwrap().reversed());
-                if let Some(dist_extra) = lzir.distance_extra_bits() {
-                    output.write(dist_extra);
-                }
-            }
-        }
-        output.write(prefix_table_lit[256].unwrap().reversed()); // end of block
+    /// Encode the block to the output stream.
+    pub fn encode(&self, output: &mut BitStreamWriter, use_static: bool) {
+        let Ok(()) =
+            BlockCoder::new(self.block).write(output, self.block, self.is_final(), use_static);
     }
 }
 
@@ -459,6 +370,11 @@ impl OptionConfig {
         self.is_zlib = true;
         self
     }
+
+    #[inline]
+    pub const fn is_zlib(&self) -> bool {
+        self.is_zlib
+    }
 }
 
 impl Default for OptionConfig {
@@ -0,0 +1,279 @@
+//! Deflate compressor without allocation
+//!
+//! [`deflate_into`] writes the compressed data into a caller-provided buffer, and keeps the hash
+//! chains and the symbols of the current block in a caller-provided scratch arena. Each block is
+//! stored, or coded with the static or dynamic prefix codes, whichever is the smallest, and their
+//! sizes are computed from the frequencies of the symbols instead of encoding the block.
+
+use super::{
+    block::{BitWrite, BlockCoder, MAX_STORED_LEN, stored_bits, write_stored},
+    *,
+};
+use crate::lz::{hash_chain::HashChain, lzss::LZSS};
+use core::slice;
+
+/// Maximum length of the matches
+const MAX_MATCH: usize = 258;
+
+/// Minimum number of the symbols in a block, which is for the smallest window
+const MIN_BLOCK_SYMBOLS: usize = 1024;
+
+/// Maximum number of the symbols in a block
+const MAX_BLOCK_SYMBOLS: usize = 16 * 1024;
+
+/// Compresses `input` into `output` and returns the number of bytes written.
+///
+/// `scratch` is the working memory, which decides the largest window, up to the one preferred
+/// for the input. See [`scratch_len`] for its size.
+///
+/// Returns [`EncodeError::BufferTooSmall`] with [`scratch_len`] of the smallest window if
+/// `scratch` is smaller than that, or with [`deflate_bound`] of the input if `output` is too
+/// small. An `output` of [`deflate_bound`] bytes is always large enough.
+pub fn deflate_into(
+    input: &[u8],
+    output: &mut [u8],
+    scratch: &mut [u8],
+    level: CompressionLevel,
+    options: Option<OptionConfig>,
+) -> Result<usize, EncodeError> {
+    if input.len() >= u32::MAX as usize {
+        return Err(EncodeError::InvalidInput);
+    }
+    let options = options.unwrap_or_default();
+    let too_small = EncodeError::BufferTooSmall(scratch_len(WindowSize::Size256));
+    let mut window_size = WindowSize::preferred(input.len());
+    while scratch_len(window_size) > scratch.len() {
+        window_size = match window_size {
+            WindowSize::Size256 => return Err(too_small),
+            WindowSize::Size512 => WindowSize::Size256,
+            WindowSize::Size1024 => WindowSize::Size512,
+            WindowSize::Size2048 => WindowSize::Size1024,
+            WindowSize::Size4096 => WindowSize::Size2048,
+            WindowSize::Size8192 => WindowSize::Size4096,
+            WindowSize::Size16384 => WindowSize::Size8192,
+            WindowSize::Size32768 => WindowSize::Size16384,
+        };
+    }
+    let window = window_size.value();
+    let n_symbols = block_symbols(window_size);
+
+    // `scratch_len` reserves 3 bytes for the alignment, and the rest fits the head, the symbols
+    // and the links in this order, each of which ends at the 4-byte alignment.
+    let offset = scratch.as_mut_ptr().align_offset(align_of::<u32>());
+    if offset > 3 {
+        return Err(too_small);
+    }
+    let (_, scratch) = scratch.split_at_mut(offset);
+    let (head, scratch) = scratch.split_at_mut(window * 4);
+    let (symbols, scratch) = scratch.split_at_mut(n_symbols * 4);
+    let (prev, _) = scratch.split_at_mut(window * 2);
+    // Safety: Each slice is aligned for its type and has the size of its elements. `u32` and
+    // `u16` have no invalid bit patterns, and `DeflateLZIR` is a transparent `u32`.
+    let (head, symbols, prev) = unsafe {
+        (
+            slice::from_raw_parts_mut(head.as_mut_ptr().cast::<u32>(), window),
+            slice::from_raw_parts_mut(symbols.as_mut_ptr().cast::<DeflateLZIR>(), n_symbols),
+            slice::from_raw_parts_mut(prev.as_mut_ptr().cast::<u16>(), window),
+        )
+    };
+    head.fill(0);
+
+    let mut writer = SliceBitWriter::new(output, deflate_bound(input.len()));
+    if options.is_zlib() {
+        let cmf = ((window.trailing_zeros() as u8 - 8) << 4) | 0x08;
+        let mut flg = level.zlib_flevel() << 6;
+        let fcheck = 31 - (cmf as u16 * 256 + flg as u16) % 31;
+        flg |= fcheck as u8;
+        writer.write_bytes(&[cmf, flg])?;
+    }
+
+    let (max_chain, nice_len, lazy) = match level {
+        CompressionLevel::Fastest | CompressionLevel::Fast => (8, 32, false),
+        CompressionLevel::Default => (128, 128, true),
+        CompressionLevel::Best => (1024, MAX_MATCH, true),
+    };
+    let mut chain = HashChain::new(head, prev, max_chain, nice_len, MAX_MATCH);
+    let mut deflater = Deflater {
+        input,
+        symbols,
+        n_symbols: 0,
+        block_start: 0,
+        writer,
+    };
+    deflater.compress(&mut chain, lazy)?;
+    let mut writer = deflater.writer;
+
+    if options.is_zlib() {
+        writer.align()?;
+        writer.write_bytes(&adler32::checksum(input).to_be_bytes())?;
+    }
+
+    writer.finish()
+}
+
+/// Returns the size of the output buffer that is always large enough for [`deflate_into`] to
+/// compress `len` bytes, including the zlib header and checksum.
+pub const fn deflate_bound(len: usize) -> usize {
+    // Each block is never larger than the stored blocks of the same data, which take at most
+    // 42 bits of the header and padding for each 65535 bytes.
+    let chunks = len / MIN_BLOCK_SYMBOLS + len / MAX_STORED_LEN + 1;
+    len + chunks * 6 + 6
+}
+
+/// Returns the size of the scratch arena with which [`deflate_into`] uses the window of `window_size`.
+pub const fn scratch_len(window_size: WindowSize) -> usize {
+    let window = window_size.value();
+    // The head of the hash chains, the symbols, the links of the hash chains, and the alignment.
+    window * 4 + block_symbols(window_size) * 4 + window * 2 + 3
+}
+
+#[inline]
+const fn block_symbols(window_size: WindowSize) -> usize {
+    let window = window_size.value();
+    if window < MIN_BLOCK_SYMBOLS {
+        MIN_BLOCK_SYMBOLS
+    } else if window > MAX_BLOCK_SYMBOLS {
+        MAX_BLOCK_SYMBOLS
+    } else {
+        window
+    }
+}
+
+struct Deflater<'a, 'b> {
+    input: &'a [u8],
+    symbols: &'b mut [DeflateLZIR],
+    n_symbols: usize,
+    block_start: usize,
+    writer: SliceBitWriter<'b>,
+}
+
+impl Deflater<'_, '_> {
+    fn compress(&mut self, chain: &mut HashChain, lazy: bool) -> Result<(), EncodeError> {
+        let input = self.input;
+        let mut pos = 0;
+        chain.encode(input, lazy, |lzss| {
+            pos += match lzss {
+                LZSS::Literal(_) => 1,
+                LZSS::Match(matches) => matches.len,
+            };
+            self.push(DeflateLZIR::from_lzss(lzss), pos)
+        })?;
+        self.flush_block(input.len(), true)
+    }
+
+    /// Pushes the symbol which ends at `end` of the input.
+    #[inline]
+    fn push(&mut self, symbol: DeflateLZIR, end: usize) -> Result<(), EncodeError> {
+        self.symbols[self.n_symbols] = symbol;
+        self.n_symbols += 1;
+        if self.n_symbols == self.symbols.len() && end < self.input.len() {
+            self.flush_block(end, false)?;
+        }
+        Ok(())
+    }
+
+    fn flush_block(&mut self, end: usize, is_final: bool) -> Result<(), EncodeError> {
+        write_block(
+            &mut self.writer,
+            &self.symbols[..self.n_symbols],
+            &self.input[self.block_start..end],
+            is_final,
+        )?;
+        self.n_symbols = 0;
+        self.block_start = end;
+        Ok(())
+    }
+}
+
+fn write_block(
+    writer: &mut SliceBitWriter,
+    symbols: &[DeflateLZIR],
+    raw: &[u8],
+    is_final: bool,
+) -> Result<(), EncodeError> {
+    let coder = BlockCoder::new(symbols);
+    let static_bits = coder.static_bits();
+    let dynamic_bits = coder.dynamic_bits();
+    let stored_bits = stored_bits(writer.pending_bits(), raw.len());
+
+    if stored_bits < static_bits.min(dynamic_bits) {
+        write_stored(writer, raw, is_final)
+    } else {
+        coder.write(writer, symbols, is_final, static_bits <= dynamic_bits)
+    }
+}
+
+/// LSB-first bit writer into a fixed buffer
+struct SliceBitWriter<'a> {
+    output: &'a mut [u8],
+    len: usize,
+    acc: u64,
+    bits: u32,
+    /// The size of the output reported when it overflows
+    bound: usize,
+}
+
+impl<'a> SliceBitWriter<'a> {
+    #[inline]
+    fn new(output: &'a mut [u8], bound: usize) -> Self {
+        Self {
+            output,
+            len: 0,
+            acc: 0,
+            bits: 0,
+            bound,
+        }
+    }
+
+    /// Returns the number of bits written in the current byte.
+    #[inline]
+    const fn pending_bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Pads the last byte and returns the number of bytes written.
+    #[inline]
+    fn finish(mut self) -> Result<usize, EncodeError> {
+        self.align()?;
+        Ok(self.len)
+    }
+}
+
+impl BitWrite for SliceBitWriter<'_> {
+    type Error = EncodeError;
+
+    #[inline]
+    fn write_bits(&mut self, value: u32, size: u32) -> Result<(), EncodeError> {
+        self.acc |= (value as u64) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            *self
+                .output
+                .get_mut(self.len)
+                .ok_or(EncodeError::BufferTooSmall(self.bound))? = self.acc as u8;
+            self.len += 1;
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn align(&mut self) -> Result<(), EncodeError> {
+        if self.bits > 0 {
+            self.write_bits(0, 8 - self.bits)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        debug_assert_eq!(self.bits, 0);
+        self.output
+            .get_mut(self.len..self.len + bytes.len())
+            .ok_or(EncodeError::BufferTooSmall(self.bound))?
+            .copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+}
@@ -14,10 +14,14 @@ use crate::{
 mod tests;
 
 pub mod adler32;
+pub mod crc32;
 
+mod block;
 mod deflate;
+mod deflate_into;
 mod inflate;
 pub use deflate::*;
+pub use deflate_into::*;
 pub use inflate::*;
 
 macro_rules! var_uint32 {
@@ -1551,3 +1551,171 @@ fn huffman_test() {
     let decoded = inflate(data, expected.len()).unwrap();
     assert_eq!(decoded.as_slice(), expected);
 }
+
+#[test]
+fn crc32_checksum() {
+    assert_eq!(crc32::checksum(b""), 0);
+    assert_eq!(crc32::checksum(b"123456789"), 0xcbf4_3926);
+    assert_eq!(
+        crc32::update(crc32::checksum(b"1234"), b"56789"),
+        0xcbf4_3926
+    );
+}
+
+#[test]
+fn block_bit_count() {
+    use crate::{lz::lzss::LZSS, num::bits::BitStreamWriter};
+
+    for input in [
+        LOREM_TXT.to_vec(),
+        fib_str(0x55, 0xaa, 0x4000),
+        random_bytes(0x55, 0xaa, 0x4000),
+    ] {
+        let mut buff = Vec::new();
+        LZSS::encode_lcp(&input, Configuration::DEFAULT.lzss_config(), |lzss| {
+            buff.push(DeflateLZIR::from_lzss(lzss));
+            Ok(())
+        })
+        .unwrap();
+        let block = DeflateIrBlock::new(&buff);
+        for use_static in [false, true] {
+            let mut output = BitStreamWriter::new();
+            block.encode(&mut output, use_static);
+            assert_eq!(block.bit_count(use_static), output.bit_count());
+        }
+    }
+}
+
+#[test]
+fn deflate_into_roundtrip() {
+    let mut scratch = vec![0u8; scratch_len(WindowSize::Size32768)];
+    for input in [
+        Vec::new(),
+        vec![0u8; 25 * 8],
+        LOREM_TXT.to_vec(),
+        vec![0u8; 0x10_0000],
+        fib_str(0x55, 0xaa, 0x10000),
+        random_bytes(0x55, 0xaa, 0x10000),
+    ] {
+        for level in [
+            CompressionLevel::Fastest,
+            CompressionLevel::Fast,
+            CompressionLevel::Default,
+            CompressionLevel::Best,
+        ] {
+            for options in [None, OptionConfig::new().zlib().into()] {
+                let mut output = vec![0u8; deflate_bound(input.len())];
+                let len = deflate_into(&input, &mut output, &mut scratch, level, options).unwrap();
+                let decoded = inflate(&output[..len], input.len()).unwrap();
+                assert_eq_array(&decoded, &input);
+            }
+        }
+    }
+
+    // no larger than the stored blocks
+    let input = random_bytes(0x55, 0xaa, 0x10000);
+    let mut output = vec![0u8; deflate_bound(input.len())];
+    let len = deflate_into(
+        &input,
+        &mut output,
+        &mut scratch,
+        CompressionLevel::Best,
+        None,
+    )
+    .unwrap();
+    assert!(len <= input.len() + 5 * 2);
+
+    let len = deflate_into(
+        LOREM_TXT,
+        &mut output,
+        &mut scratch,
+        CompressionLevel::Best,
+        None,
+    )
+    .unwrap();
+    let encoded = deflate(LOREM_TXT, CompressionLevel::Best, None).unwrap();
+    assert!(len <= encoded.len() + encoded.len() / 10);
+}
+
+#[test]
+fn deflate_into_static_high_bytes() {
+    use rand::RngCore;
+    let mut rng = rand::rng();
+    let mut scratch = vec![0u8; scratch_len(WindowSize::Size32768)];
+
+    // Short inputs are coded as the static blocks, whose literals 144-255 have the 9-bit codes.
+    let mut inputs = vec![(144..=255).collect::<Vec<u8>>()];
+    for len in 1..64 {
+        let mut input = vec![0u8; len];
+        rng.fill_bytes(&mut input);
+        inputs.push(input);
+    }
+    for input in inputs {
+        for level in [CompressionLevel::Fastest, CompressionLevel::Best] {
+            let mut output = vec![0u8; deflate_bound(input.len())];
+            let len = deflate_into(&input, &mut output, &mut scratch, level, None).unwrap();
+            let decoded = inflate(&output[..len], input.len()).unwrap();
+            assert_eq_array(&decoded, &input);
+        }
+    }
+}
+
+#[test]
+fn deflate_into_out_of_memory() {
+    let input = fib_str(0x55, 0xaa, 0x10000);
+    let mut output = vec![0u8; deflate_bound(input.len())];
+
+    // the window shrinks to fit in the scratch
+    for window_size in [WindowSize::Size256, WindowSize::Size4096] {
+        let mut scratch = vec![0u8; scratch_len(window_size)];
+        let len = deflate_into(
+            &input,
+            &mut output,
+            &mut scratch,
+            CompressionLevel::Default,
+            OptionConfig::new().zlib().into(),
+        )
+        .unwrap();
+        assert_eq!(output[0] >> 4, window_size as u8);
+        let decoded = inflate(&output[..len], input.len()).unwrap();
+        assert_eq_array(&decoded, &input);
+    }
+
+    let mut scratch = vec![0u8; scratch_len(WindowSize::Size256) - 4];
+    assert_eq!(
+        deflate_into(
+            &input,
+            &mut output,
+            &mut scratch,
+            CompressionLevel::Default,
+            None
+        ),
+        Err(EncodeError::BufferTooSmall(scratch_len(
+            WindowSize::Size256
+        )))
+    );
+
+    let mut scratch = vec![0u8; scratch_len(WindowSize::Size32768)];
+    let len = deflate_into(
+        &input,
+        &mut output,
+        &mut scratch,
+        CompressionLevel::Default,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        deflate_into(
+            &input,
+            &mut output[..len - 1],
+            &mut scratch,
+            CompressionLevel::Default,
+            None
+        ),
+        Err(EncodeError::BufferTooSmall(deflate_bound(input.len())))
+    );
+    assert_eq!(
+        deflate_into(&[], &mut [], &mut scratch, CompressionLevel::Default, None),
+        Err(EncodeError::BufferTooSmall(deflate_bound(0)))
+    );
+}
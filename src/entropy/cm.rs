@@ -0,0 +1,357 @@
+//! Context mixing byte model
+//!
+//! Predicts each bit of a byte from order-0 to order-N contexts and combines the predictions
+//! with a logistic mixer before handing them to the [`FseEncoder`].
+//!
+//! See also: <https://en.wikipedia.org/wiki/Context_mixing>
+
+use crate::{
+    entropy::fse::{ContextConfig, ContextState, FseDecoder, FseEncoder},
+    num::bits::BitSize,
+    *,
+};
+
+/// Context mixing byte model
+pub struct ContextModel {
+    order0: ContextState,
+    orders: Vec<HashedContexts>,
+    mixer: Mixer,
+    stretch: Box<[i16; 4096]>,
+    /// Precision of the probabilities of the contexts in bits
+    precision: u32,
+    history: u32,
+    node: usize,
+    slots: [usize; ContextModel::MAX_ORDER],
+}
+
+impl ContextModel {
+    /// Maximum order of contexts
+    pub const MAX_ORDER: usize = 4;
+
+    pub const DEFAULT_ORDER: usize = 2;
+
+    pub const MIN_TABLE_BITS: usize = 8;
+
+    pub const MAX_TABLE_BITS: usize = 24;
+
+    pub const DEFAULT_TABLE_BITS: usize = 18;
+
+    /// Probability model of each context
+    pub const DEFAULT_COUNTER: ContextConfig = ContextConfig::new(BitSize::Bit16, 4)
+        .dual_rate(7)
+        .adaptive();
+
+    /// Configuration of the coder that receives the mixed predictions
+    pub const CODER_CONFIG: ContextConfig = ContextConfig::new(BitSize::Bit12, 4);
+
+    /// Creates a model using order-0 to order-`max_order` contexts.
+    ///
+    /// Each context set of order 1 or higher is hashed into a table of `1 << table_bits` entries.
+    /// `max_order` is clamped to `0..=MAX_ORDER`
+    /// and `table_bits` to `MIN_TABLE_BITS..=MAX_TABLE_BITS`.
+    pub fn new(max_order: usize, table_bits: usize) -> Self {
+        Self::with_counter(max_order, table_bits, Self::DEFAULT_COUNTER)
+    }
+
+    /// Creates a model whose contexts follow the given probability model.
+    pub fn with_counter(max_order: usize, table_bits: usize, counter: ContextConfig) -> Self {
+        let max_order = max_order.min(Self::MAX_ORDER);
+        let table_bits = table_bits.clamp(Self::MIN_TABLE_BITS, Self::MAX_TABLE_BITS);
+        let orders = (1..=max_order)
+            .map(|order| HashedContexts::new(order, table_bits, counter))
+            .collect::<Vec<_>>();
+        let mut model = Self {
+            order0: ContextState::with_config(256, counter),
+            mixer: Mixer::new(2 + orders.len(), 8),
+            orders,
+            stretch: Self::_stretch_table(),
+            precision: counter.precision() as u32,
+            history: 0,
+            node: 1,
+            slots: [0; Self::MAX_ORDER],
+        };
+        model._update_history();
+        model
+    }
+
+    /// Returns the probability that the next bit is `1`, scaled by `CODER_CONFIG.one()`.
+    pub fn predict(&mut self) -> u16 {
+        let node = self.node;
+        let stretch = &self.stretch;
+        let precision = self.precision;
+        // The stretch table takes 12-bit probabilities.
+        let stretch_prob = |prob: u16| {
+            let prob = prob as usize;
+            let index = if precision >= 12 {
+                prob >> (precision - 12)
+            } else {
+                prob << (12 - precision)
+            };
+            stretch[index] as i32
+        };
+        let inputs = &mut self.mixer.inputs;
+        inputs[0] = 256;
+        inputs[1] = stretch_prob(self.order0.context_mut(node).prob());
+        for (index, order) in self.orders.iter_mut().enumerate() {
+            let slot = order.slot(node);
+            self.slots[index] = slot;
+            inputs[2 + index] = stretch_prob(order.contexts.context_mut(slot).prob());
+        }
+        let prob = self.mixer.mix((self.node.ilog2()) as usize);
+        prob as u16
+    }
+
+    /// Updates the model with the actual bit.
+    ///
+    /// Must be called after [`ContextModel::predict`].
+    pub fn update(&mut self, bit: bool) {
+        self.mixer.update(bit);
+        self.order0.context_mut(self.node).update(bit);
+        for (index, order) in self.orders.iter_mut().enumerate() {
+            order.contexts.context_mut(self.slots[index]).update(bit);
+        }
+
+        self.node = (self.node << 1) | bit as usize;
+        if self.node >= 256 {
+            self.history = (self.history << 8) | (self.node & 0xff) as u32;
+            self.node = 1;
+            self._update_history();
+        }
+    }
+
+    pub fn encode_byte(&mut self, encoder: &mut FseEncoder, value: u8) {
+        for bit_position in (0..8).rev() {
+            let bit = (value >> bit_position) & 1 != 0;
+            let prob = self.predict();
+            encoder.encode_bit_with_prob(bit, prob);
+            self.update(bit);
+        }
+    }
+
+    pub fn decode_byte(&mut self, decoder: &mut FseDecoder) -> Option<u8> {
+        let mut acc = 0;
+        for _ in 0..8 {
+            let prob = self.predict();
+            let bit = decoder.decode_bit_with_prob(prob)?;
+            self.update(bit);
+            acc = (acc << 1) | bit as u8;
+        }
+        Some(acc)
+    }
+
+    fn _update_history(&mut self) {
+        for order in self.orders.iter_mut() {
+            order.select(self.history);
+        }
+    }
+
+    /// Squashing function: `4096 / (1 + e^-(x / 256))`
+    ///
+    /// The input is clamped to `-2047..=2047` and the result is in `0..4096`.
+    pub fn squash(x: i32) -> i32 {
+        const TABLE: [i32; 33] = [
+            1, 2, 3, 6, 10, 16, 27, 45, 73, 120, 194, 310, 488, 747, 1101, 1546, 2047, 2549, 2994,
+            3348, 3607, 3785, 3901, 3975, 4022, 4050, 4068, 4079, 4085, 4089, 4092, 4093, 4094,
+        ];
+        if x > 2047 {
+            return 4095;
+        }
+        if x < -2047 {
+            return 0;
+        }
+        let w = x & 127;
+        let index = ((x >> 7) + 16) as usize;
+        (TABLE[index] * (128 - w) + TABLE[index + 1] * w + 64) >> 7
+    }
+
+    /// Inverse of `squash`: `ln(p / (1 - p))`, scaled the same way.
+    fn _stretch_table() -> Box<[i16; 4096]> {
+        let mut table = Box::new([0i16; 4096]);
+        let mut prev = 0;
+        for x in -2047..=2047 {
+            let value = Self::squash(x) as usize;
+            for p in table.iter_mut().take(value + 1).skip(prev) {
+                *p = x as i16;
+            }
+            prev = value + 1;
+        }
+        for p in table.iter_mut().skip(prev) {
+            *p = 2047;
+        }
+        table
+    }
+}
+
+impl Default for ContextModel {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_ORDER, Self::DEFAULT_TABLE_BITS)
+    }
+}
+
+/// A set of contexts selected by a hash of the previous `order` bytes
+struct HashedContexts {
+    contexts: ContextState,
+    history_mask: u32,
+    hash_shift: u32,
+    base: usize,
+    mask: usize,
+}
+
+impl HashedContexts {
+    fn new(order: usize, table_bits: usize, counter: ContextConfig) -> Self {
+        Self {
+            contexts: ContextState::with_config(1 << table_bits, counter),
+            history_mask: u32::MAX >> (32 - 8 * order),
+            hash_shift: 32 - (table_bits as u32 - 8),
+            base: 0,
+            mask: (1 << table_bits) - 1,
+        }
+    }
+
+    #[inline]
+    fn select(&mut self, history: u32) {
+        let hash = (history & self.history_mask).wrapping_mul(0x9E37_79B1);
+        self.base = (hash.checked_shr(self.hash_shift).unwrap_or_default() as usize) << 8;
+    }
+
+    #[inline]
+    fn slot(&self, node: usize) -> usize {
+        (self.base | node) & self.mask
+    }
+}
+
+/// Logistic mixer
+///
+/// Combines stretched predictions with a weight set chosen by a small context
+/// and trains the weights online to reduce the coding cost.
+struct Mixer {
+    weights: Vec<i32>,
+    inputs: [i32; ContextModel::MAX_ORDER + 2],
+    n_inputs: usize,
+    selected: usize,
+    prob: i32,
+}
+
+impl Mixer {
+    /// Weights are fixed point values with 16 fractional bits.
+    const WEIGHT_ONE: i32 = 1 << 16;
+
+    const LEARNING_SHIFT: u32 = 8;
+
+    fn new(n_inputs: usize, n_sets: usize) -> Self {
+        let mut weights = Vec::new();
+        weights.resize(
+            n_inputs * n_sets,
+            Self::WEIGHT_ONE / (n_inputs as i32 - 1).max(1),
+        );
+        Self {
+            weights,
+            inputs: [0; ContextModel::MAX_ORDER + 2],
+            n_inputs,
+            selected: 0,
+            prob: 2048,
+        }
+    }
+
+    fn mix(&mut self, set: usize) -> i32 {
+        self.selected = set * self.n_inputs;
+        let weights = &self.weights[self.selected..self.selected + self.n_inputs];
+        let dot = self
+            .inputs
+            .iter()
+            .zip(weights.iter())
+            .fold(0i64, |a, (&x, &w)| a + x as i64 * w as i64);
+        self.prob = ContextModel::squash((dot >> 16).clamp(-2047, 2047) as i32);
+        self.prob
+    }
+
+    fn update(&mut self, bit: bool) {
+        let err = ((bit as i32) << 12) - self.prob;
+        let weights = &mut self.weights[self.selected..self.selected + self.n_inputs];
+        for (w, &x) in weights.iter_mut().zip(self.inputs.iter()) {
+            *w += (x * err) >> Self::LEARNING_SHIFT;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entropy::fse::FSE;
+
+    const TEXT: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.
+Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.
+Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur.
+Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.
+";
+
+    #[test]
+    fn squash_stretch() {
+        let model = ContextModel::default();
+        assert_eq!(ContextModel::squash(0), 2047);
+        assert_eq!(ContextModel::squash(-4000), 0);
+        assert_eq!(ContextModel::squash(4000), 4095);
+        for p in 1..4095 {
+            let x = model.stretch[p] as i32;
+            let q = ContextModel::squash(x);
+            assert!((p as i32 - q).abs() <= 1 + p as i32 / 64, "{p} {x} {q}");
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut telemetry = Vec::new();
+        for i in 0..0x2000u32 {
+            telemetry.extend_from_slice(&(1000 + (i % 17) * 3 + i / 64).to_le_bytes());
+        }
+        for input in [
+            TEXT.to_vec(),
+            fib_str(b'a', b'b', 0x4000),
+            random_bytes(0x55, 0xaa, 0x4000),
+            telemetry,
+            Vec::new(),
+        ] {
+            for order in 0..=ContextModel::MAX_ORDER {
+                let encoded = FSE::encode_bytes_with(&input, ContextModel::new(order, 12));
+                let decoded =
+                    FSE::decode_bytes_with(&encoded, input.len(), ContextModel::new(order, 12))
+                        .unwrap();
+                assert_eq!(decoded, input, "order {order}");
+            }
+        }
+    }
+
+    #[test]
+    fn mixing_ratio() {
+        let mut input = Vec::new();
+        for _ in 0..8 {
+            input.extend_from_slice(TEXT);
+        }
+        let order0 = FSE::encode_bytes(&input);
+        let mixed = FSE::encode_bytes_with(&input, ContextModel::default());
+        assert!(
+            mixed.len() * 3 < order0.len() * 2,
+            "{} {}",
+            mixed.len(),
+            order0.len()
+        );
+        let decoded = FSE::decode_bytes_with(&mixed, input.len(), ContextModel::default()).unwrap();
+        assert_eq!(decoded, input);
+
+        // Counters of any precision feed the mixer at the same scale
+        for precision in [BitSize::Bit8, BitSize::Bit12, BitSize::Bit15] {
+            let counter = ContextConfig::new(precision, 4);
+            let model = || ContextModel::with_counter(2, 16, counter);
+            let encoded = FSE::encode_bytes_with(&input, model());
+            assert!(
+                encoded.len() * 3 < order0.len() * 2,
+                "{precision:?} {} {}",
+                encoded.len(),
+                order0.len()
+            );
+            let decoded = FSE::decode_bytes_with(&encoded, input.len(), model()).unwrap();
+            assert_eq!(decoded, input);
+        }
+    }
+}
@@ -2,10 +2,12 @@
 //!
 //! See also: <https://en.wikipedia.org/wiki/Asymmetric_numeral_systems>
 
-use crate::{num::bits::BitSize, *};
+use crate::{entropy::cm::ContextModel, num::bits::BitSize, *};
 
-const INIT_PROB: u8 = 0x80;
-const INIT_STATE: u32 = 0x1000;
+/// Number of bits the coder state holds above the probability precision.
+///
+/// The state is kept in `[1 << (precision + 4), 1 << (precision + 12))` and renormalized byte by byte.
+const STATE_HEADROOM_BITS: usize = 4;
 
 pub const CONTEXT_INITIAL: usize = 0;
 pub const CONTEXT_BYTE: usize = 1;
@@ -32,22 +34,56 @@ impl FSE {
         }
         Some(result)
     }
+
+    /// Encodes bytes with predictions from a context mixing model.
+    ///
+    /// The decoder must start from a model created with the same parameters.
+    pub fn encode_bytes_with(input: &[u8], mut model: ContextModel) -> Vec<u8> {
+        let mut encoder = FseEncoder::with_config(0, ContextModel::CODER_CONFIG);
+        for &byte in input {
+            model.encode_byte(&mut encoder, byte);
+        }
+        encoder.finish()
+    }
+
+    pub fn decode_bytes_with(input: &[u8], len: usize, mut model: ContextModel) -> Option<Vec<u8>> {
+        let mut iter = input.iter().copied();
+        let mut decoder = FseDecoder::with_config(&mut iter, 0, ContextModel::CODER_CONFIG)?;
+        let mut result = Vec::with_capacity(len);
+        while result.len() < len {
+            result.push(model.decode_byte(&mut decoder)?);
+        }
+        Some(result)
+    }
 }
 
 pub struct FseEncoder {
-    bits: Vec<(bool, u8)>,
+    bits: Vec<(bool, u16)>,
     contexts: ContextState,
 }
 
 impl FseEncoder {
     #[inline]
     pub fn new(size: usize) -> Self {
+        Self::with_config(size, ContextConfig::DEFAULT)
+    }
+
+    /// Creates an encoder whose contexts follow the given model.
+    ///
+    /// The decoder must be created with the same configuration.
+    #[inline]
+    pub fn with_config(size: usize, config: ContextConfig) -> Self {
         FseEncoder {
             bits: Vec::new(),
-            contexts: ContextState::new(size),
+            contexts: ContextState::with_config(size, config),
         }
     }
 
+    #[inline]
+    pub fn config(&self) -> &ContextConfig {
+        self.contexts.config()
+    }
+
     #[inline]
     pub fn encode_bit(&mut self, bit: bool, context_index: usize) {
         let mut context = self.contexts.context_mut(context_index);
@@ -55,6 +91,16 @@ impl FseEncoder {
         context.update(bit);
     }
 
+    /// Encodes a bit with a probability predicted outside of this encoder.
+    ///
+    /// `prob` is the probability that the bit is `1`, scaled by `ContextConfig::one`
+    /// and clamped to `1..one`.
+    #[inline]
+    pub fn encode_bit_with_prob(&mut self, bit: bool, prob: u16) {
+        let prob = (prob as u32).clamp(1, self.contexts.config().one() - 1) as u16;
+        self.bits.push((bit, prob));
+    }
+
     #[inline]
     pub fn encode_byte(&mut self, value: u8) {
         self.encode_byte_with(value, CONTEXT_BYTE);
@@ -93,19 +139,51 @@ impl FseEncoder {
         }
     }
 
+    /// Encodes a value with a reverse bit-tree.
+    ///
+    /// The value is encoded in little-endian,
+    /// and each bit is coded in the context selected by the bits below it.
+    /// The context must have a size of a bit power of 2.
+    #[inline]
+    pub fn encode_reverse_bits(&mut self, value: u32, bits: BitSize, context_base: usize) {
+        let context_base = context_base - 1;
+        let mut context_index = 1;
+        let mut acc = value;
+        for _ in 0..bits.as_usize() {
+            let bit = (acc & 1) != 0;
+            self.encode_bit(bit, context_base + context_index);
+            context_index = (context_index << 1) | bit as usize;
+            acc >>= 1;
+        }
+    }
+
+    /// Encodes bits with a fixed probability of 1/2.
+    ///
+    /// The value is encoded in big-endian.
+    #[inline]
+    pub fn encode_direct_bits(&mut self, value: u32, bits: usize) {
+        let half = self.contexts.config().init_prob();
+        for bit_position in (0..bits).rev() {
+            self.encode_bit_with_prob((value >> bit_position) & 1 != 0, half);
+        }
+    }
+
     pub fn finish(self) -> Vec<u8> {
+        let config = self.contexts.config();
+        let precision = config.precision().as_usize();
+        let one = config.one();
         let mut result = Vec::new();
-        let mut state = INIT_STATE;
+        let mut state = config.init_state();
         for &data in self.bits.iter().rev() {
             let bit = data.0;
             let prob = data.1 as u32;
-            let (start, prob) = if bit { (0, prob) } else { (prob, 256 - prob) };
-            let max_state = prob << 12;
+            let (start, prob) = if bit { (0, prob) } else { (prob, one - prob) };
+            let max_state = prob << (STATE_HEADROOM_BITS + 8);
             while state >= max_state {
                 result.push((state & 0xff) as u8);
                 state >>= 8;
             }
-            state = ((state / prob) << 8) + (state % prob) + start;
+            state = ((state / prob) << precision) + (state % prob) + start;
         }
         while state > 0 {
             result.push((state & 0xff) as u8);
@@ -116,40 +194,229 @@ impl FseEncoder {
     }
 }
 
+/// Configuration of the adaptive probability model shared by the encoder and the decoder.
+///
+/// The default configuration uses 8-bit probabilities updated with a shift of 4,
+/// which is the model `FSE::encode_bytes` has always used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextConfig {
+    precision: BitSize,
+    shift: u8,
+    slow_shift: Option<u8>,
+    is_adaptive: bool,
+}
+
+impl ContextConfig {
+    pub const DEFAULT: Self = Self::new(BitSize::Bit8, 4);
+
+    /// Minimum precision of probabilities
+    pub const MIN_PRECISION: BitSize = BitSize::Bit8;
+
+    /// Maximum precision of probabilities
+    pub const MAX_PRECISION: BitSize = BitSize::Bit16;
+
+    /// Shift used for the first update of a context when the adaptive rate is enabled.
+    pub const ADAPTIVE_MIN_SHIFT: u8 = 2;
+
+    /// Creates a model with `precision`-bit probabilities updated by `1 / (1 << shift)`.
+    ///
+    /// The precision is clamped to `8..=16` and the shift to `1..precision`.
+    #[inline]
+    pub const fn new(precision: BitSize, shift: u8) -> Self {
+        let precision = if (precision as u8) < (Self::MIN_PRECISION as u8) {
+            Self::MIN_PRECISION
+        } else if (precision as u8) > (Self::MAX_PRECISION as u8) {
+            Self::MAX_PRECISION
+        } else {
+            precision
+        };
+        Self {
+            precision,
+            shift: Self::_clamp_shift(precision, shift),
+            slow_shift: None,
+            is_adaptive: false,
+        }
+    }
+
+    /// Mixes a second, slower counter into the prediction.
+    ///
+    /// Each context keeps two probabilities updated at different rates and predicts their average.
+    #[inline]
+    pub const fn dual_rate(mut self, slow_shift: u8) -> Self {
+        self.slow_shift = Some(Self::_clamp_shift(self.precision, slow_shift));
+        self
+    }
+
+    /// Starts every context with a fast adaptation rate and slows it down on each update
+    /// until the configured shift is reached.
+    #[inline]
+    pub const fn adaptive(mut self) -> Self {
+        self.is_adaptive = true;
+        self
+    }
+
+    #[inline]
+    const fn _clamp_shift(precision: BitSize, shift: u8) -> u8 {
+        if shift < 1 {
+            1
+        } else if shift >= precision as u8 {
+            precision as u8 - 1
+        } else {
+            shift
+        }
+    }
+
+    #[inline]
+    pub const fn precision(&self) -> BitSize {
+        self.precision
+    }
+
+    #[inline]
+    pub const fn shift(&self) -> u8 {
+        self.shift
+    }
+
+    #[inline]
+    pub const fn slow_shift(&self) -> Option<u8> {
+        self.slow_shift
+    }
+
+    #[inline]
+    pub const fn is_adaptive(&self) -> bool {
+        self.is_adaptive
+    }
+
+    /// Returns the probability scale, i.e. the value that represents a probability of 1.
+    #[inline]
+    pub const fn one(&self) -> u32 {
+        self.precision.power_of_two()
+    }
+
+    /// Initial probability of each context (1/2)
+    #[inline]
+    pub const fn init_prob(&self) -> u16 {
+        (self.one() >> 1) as u16
+    }
+
+    /// Lower bound of the coder state
+    #[inline]
+    pub const fn init_state(&self) -> u32 {
+        1 << (self.precision.as_usize() + STATE_HEADROOM_BITS)
+    }
+}
+
+impl Default for ContextConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ContextSlot {
+    fast: u16,
+    slow: u16,
+    count: u8,
+}
+
 pub struct ContextState {
-    contexts: Vec<u8>,
+    contexts: Vec<ContextSlot>,
+    config: ContextConfig,
 }
 
 impl ContextState {
     #[inline]
     pub fn new(size: usize) -> Self {
+        Self::with_config(size, ContextConfig::DEFAULT)
+    }
+
+    #[inline]
+    pub fn with_config(size: usize, config: ContextConfig) -> Self {
+        let init_prob = config.init_prob();
+        let slot = ContextSlot {
+            fast: init_prob,
+            slow: init_prob,
+            count: 0,
+        };
         let mut vec = Vec::with_capacity(size);
-        vec.resize(size, INIT_PROB);
-        Self { contexts: vec }
+        vec.resize(size, slot);
+        Self {
+            contexts: vec,
+            config,
+        }
+    }
+
+    #[inline]
+    pub fn config(&self) -> &ContextConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.contexts.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
     }
 
     #[inline]
     pub fn context_mut<'a>(&'a mut self, index: usize) -> Context<'a> {
-        Context(&mut self.contexts[index])
+        Context {
+            slot: &mut self.contexts[index],
+            config: &self.config,
+        }
     }
 }
 
-pub struct Context<'a>(&'a mut u8);
+pub struct Context<'a> {
+    slot: &'a mut ContextSlot,
+    config: &'a ContextConfig,
+}
 
 impl Context<'_> {
+    /// Returns the probability that the next bit is `1`, scaled by `ContextConfig::one`.
     #[inline]
-    pub fn prob(&self) -> u8 {
-        *self.0
+    pub fn prob(&self) -> u16 {
+        match self.config.slow_shift {
+            Some(_) => ((self.slot.fast as u32 + self.slot.slow as u32 + 1) >> 1) as u16,
+            None => self.slot.fast,
+        }
     }
 
     pub fn update(&mut self, bit: bool) {
-        let prob = *self.0 as u32;
-        if bit {
-            *self.0 = (prob + ((256 - prob + 8) >> 4)) as u8;
+        let config = self.config;
+        let one = config.one();
+        let (shift, slow_shift) = if config.is_adaptive {
+            let ramp = ContextConfig::ADAPTIVE_MIN_SHIFT.saturating_add(self.slot.count);
+            if ramp < config.shift.max(config.slow_shift.unwrap_or_default()) {
+                self.slot.count += 1;
+            }
+            (
+                config.shift.min(ramp),
+                config.slow_shift.map(|v| v.min(ramp)),
+            )
         } else {
-            *self.0 = (prob - ((prob + 8) >> 4)) as u8;
+            (config.shift, config.slow_shift)
+        };
+        self.slot.fast = Self::_adapt(self.slot.fast, bit, shift, one);
+        if let Some(slow_shift) = slow_shift {
+            self.slot.slow = Self::_adapt(self.slot.slow, bit, slow_shift, one);
         }
     }
+
+    #[inline]
+    fn _adapt(prob: u16, bit: bool, shift: u8, one: u32) -> u16 {
+        let prob = prob as u32;
+        let round = 1 << (shift - 1);
+        let prob = if bit {
+            prob + ((one - prob + round) >> shift)
+        } else {
+            prob - ((prob + round) >> shift)
+        };
+        prob.clamp(1, one - 1) as u16
+    }
 }
 
 pub struct FseDecoder<'a> {
@@ -161,17 +428,35 @@ pub struct FseDecoder<'a> {
 impl FseDecoder<'_> {
     #[inline]
     pub fn new<'a>(reader: &'a mut dyn Iterator<Item = u8>, size: usize) -> Option<FseDecoder<'a>> {
+        Self::with_config(reader, size, ContextConfig::DEFAULT)
+    }
+
+    /// Creates a decoder whose contexts follow the given model.
+    ///
+    /// The configuration must be the same as the one used by the encoder.
+    #[inline]
+    pub fn with_config<'a>(
+        reader: &'a mut dyn Iterator<Item = u8>,
+        size: usize,
+        config: ContextConfig,
+    ) -> Option<FseDecoder<'a>> {
         let mut fse = FseDecoder {
             state: 0,
-            contexts: ContextState::new(size),
+            contexts: ContextState::with_config(size, config),
             reader,
         };
         fse.refill()?;
         Some(fse)
     }
 
+    #[inline]
+    pub fn config(&self) -> &ContextConfig {
+        self.contexts.config()
+    }
+
     pub fn refill(&mut self) -> Option<()> {
-        while self.state < INIT_STATE {
+        let init_state = self.contexts.config().init_state();
+        while self.state < init_state {
             let byte = self.reader.next()?;
             self.state = (self.state << 8) | byte as u32;
         }
@@ -179,19 +464,30 @@ impl FseDecoder<'_> {
     }
 
     pub fn decode_bit(&mut self, context_index: usize) -> Option<bool> {
+        let prob = self.contexts.context_mut(context_index).prob();
+        let bit = self.decode_bit_with_prob(prob)?;
+        self.contexts.context_mut(context_index).update(bit);
+        Some(bit)
+    }
+
+    /// Decodes a bit with a probability predicted outside of this decoder.
+    ///
+    /// See also: [`FseEncoder::encode_bit_with_prob`]
+    pub fn decode_bit_with_prob(&mut self, prob: u16) -> Option<bool> {
         self.refill()?;
 
-        let mut context = self.contexts.context_mut(context_index);
-        let prob = context.prob() as u32;
-        let bit = (self.state & 255) < prob;
+        let config = self.contexts.config();
+        let precision = config.precision();
+        let one = config.one();
+        let prob = (prob as u32).clamp(1, one - 1);
+        let slot = self.state & precision.mask();
+        let bit = slot < prob;
 
         if bit {
-            self.state = (prob as u32) * (self.state >> 8) + (self.state & 255);
+            self.state = prob * (self.state >> precision.as_usize()) + slot;
         } else {
-            self.state =
-                (256 - prob as u32) * (self.state >> 8) + (self.state & 255) - (prob as u32);
+            self.state = (one - prob) * (self.state >> precision.as_usize()) + slot - prob;
         }
-        context.update(bit);
 
         Some(bit)
     }
@@ -218,6 +514,29 @@ impl FseDecoder<'_> {
         Some((context_index & 1usize.wrapping_shl(bits.as_u32()).wrapping_sub(1)) as u32)
     }
 
+    #[inline]
+    pub fn decode_reverse_bits(&mut self, bits: BitSize, context_base: usize) -> Option<u32> {
+        let context_base = context_base - 1;
+        let mut context_index = 1;
+        let mut acc = 0;
+        for bit_position in 0..bits.as_usize() {
+            let bit = self.decode_bit(context_base + context_index)?;
+            context_index = (context_index << 1) | bit as usize;
+            acc |= (bit as u32) << bit_position;
+        }
+        Some(acc)
+    }
+
+    #[inline]
+    pub fn decode_direct_bits(&mut self, bits: usize) -> Option<u32> {
+        let half = self.contexts.config().init_prob();
+        let mut acc = 0;
+        for _ in 0..bits {
+            acc = (acc << 1) | self.decode_bit_with_prob(half)? as u32;
+        }
+        Some(acc)
+    }
+
     #[inline]
     pub fn decode_bit_array(&mut self, bits: BitSize, context_base: usize) -> Option<u32> {
         let mut acc = 0;
@@ -230,3 +549,91 @@ impl FseDecoder<'_> {
         Some(acc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_with(input: &[u8], config: ContextConfig) -> Vec<u8> {
+        let mut encoder = FseEncoder::with_config(CONTEXT_BYTE_MAX, config);
+        for &byte in input {
+            encoder.encode_byte(byte);
+        }
+        encoder.finish()
+    }
+
+    fn decode_with(input: &[u8], len: usize, config: ContextConfig) -> Vec<u8> {
+        let mut iter = input.iter().copied();
+        let mut decoder = FseDecoder::with_config(&mut iter, CONTEXT_BYTE_MAX, config).unwrap();
+        (0..len).map(|_| decoder.decode_byte().unwrap()).collect()
+    }
+
+    #[test]
+    fn roundtrip_configs() {
+        let mut skewed = random_bytes(0x00, 0x01, 0x4000);
+        for (index, byte) in skewed.iter_mut().enumerate() {
+            if index % 61 != 0 {
+                *byte = 0;
+            }
+        }
+        let inputs = [
+            fib_str(b'a', b'b', 0x4000),
+            random_bytes(0x55, 0xaa, 0x4000),
+            skewed,
+            b"Lorem ipsum dolor sit amet".to_vec(),
+        ];
+        for precision in [BitSize::Bit8, BitSize::Bit12, BitSize::Bit16] {
+            for shift in [1, 4, 5, 7] {
+                for config in [
+                    ContextConfig::new(precision, shift),
+                    ContextConfig::new(precision, shift).adaptive(),
+                    ContextConfig::new(precision, shift).dual_rate(7),
+                    ContextConfig::new(precision, shift).dual_rate(7).adaptive(),
+                ] {
+                    for input in inputs.iter() {
+                        let encoded = encode_with(input, config);
+                        let decoded = decode_with(&encoded, input.len(), config);
+                        assert_eq!(&decoded, input, "{config:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn default_config() {
+        // The default coder uses 8-bit probabilities updated by 1/16.
+        let input = fib_str(b'a', b'b', 0x1000);
+        let encoded = FSE::encode_bytes(&input);
+        let explicit = ContextConfig::new(BitSize::Bit8, 4);
+        assert_eq!(encoded, encode_with(&input, explicit));
+        assert_eq!(decode_with(&encoded, input.len(), explicit), input);
+        for other in [
+            ContextConfig::new(BitSize::Bit12, 4),
+            ContextConfig::new(BitSize::Bit8, 5),
+        ] {
+            assert_ne!(encoded, encode_with(&input, other), "{other:?}");
+        }
+        assert_eq!(
+            ContextConfig::new(BitSize::Bit24, 30).precision(),
+            ContextConfig::MAX_PRECISION
+        );
+        assert_eq!(ContextConfig::new(BitSize::Bit4, 0).shift(), 1);
+    }
+
+    #[test]
+    fn high_precision_skewed() {
+        let mut input = vec![0u8; 0x10000];
+        for index in (0..input.len()).step_by(997) {
+            input[index] = 1;
+        }
+        let low = encode_with(&input, ContextConfig::DEFAULT);
+        let high = encode_with(
+            &input,
+            ContextConfig::new(BitSize::Bit16, 5)
+                .dual_rate(8)
+                .adaptive(),
+        );
+        assert!(high.len() < low.len(), "{} >= {}", high.len(), low.len());
+    }
+}
@@ -5,6 +5,7 @@ use crate::num::math;
 #[path = "prefix/prefix.rs"]
 pub mod prefix;
 
+pub mod cm;
 pub mod fse;
 
 /// Calculates the entropy of a sequence of blocks.
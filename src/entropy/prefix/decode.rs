@@ -363,6 +363,41 @@ impl CanonicalPrefixDecoder {
         Ok(())
     }
 
+    /// Decodes a table of code lengths in the WebP lossless (VP8L) format,
+    /// including both the simple and the normal code.
+    ///
+    /// If the result has only one non-zero length, the symbol is coded with zero bits.
+    pub fn decode_prefix_table_webp(
+        reader: &mut BitStreamReader,
+        alphabet_size: usize,
+    ) -> Result<Vec<u8>, DecodeError> {
+        let mut output = Vec::new();
+        if reader.read_bool().ok_or(DecodeError::InvalidData)? {
+            // simple code
+            output.resize(alphabet_size, 0);
+            let num_symbols = 1 + reader.read_bool().ok_or(DecodeError::InvalidData)? as usize;
+            let first_symbol_bits = if reader.read_bool().ok_or(DecodeError::InvalidData)? {
+                BitSize::Bit8
+            } else {
+                BitSize::Bit1
+            };
+            for bits in [first_symbol_bits, BitSize::Bit8]
+                .into_iter()
+                .take(num_symbols)
+            {
+                let symbol = reader.read_bits(bits).ok_or(DecodeError::InvalidData)?;
+                let p = output
+                    .get_mut(symbol as usize)
+                    .ok_or(DecodeError::InvalidData)?;
+                *p = 1;
+            }
+        } else {
+            Self::decode_length_table_webp(reader, &mut output, alphabet_size)?;
+        }
+        Ok(output)
+    }
+
+    /// Decodes the normal code of the WebP lossless (VP8L) format.
     pub fn decode_length_table_webp(
         reader: &mut BitStreamReader,
         output: &mut Vec<u8>,
@@ -384,11 +419,39 @@ impl CanonicalPrefixDecoder {
             *p = prefix_bit as u8;
         }
 
+        let max_symbol = if reader.read_bool().ok_or(DecodeError::InvalidData)? {
+            let length_nbits = 2 + 2 * reader
+                .read_bits(BitSize::Bit3)
+                .ok_or(DecodeError::InvalidData)?;
+            let max_symbol = 2 + reader
+                .read_bits(BitSize::new(length_nbits as u8).unwrap())
+                .ok_or(DecodeError::InvalidData)? as usize;
+            if max_symbol > output_size {
+                return Err(DecodeError::InvalidData);
+            }
+            max_symbol
+        } else {
+            output_size
+        };
+
+        // A code with only one code length code takes no bits.
+        let decoder = if lengths.iter().filter(|&&v| v > 0).count() == 1 {
+            None
+        } else {
+            Some(CanonicalPrefixDecoder::with_lengths(&lengths, false)?)
+        };
+        let single_code = lengths.iter().position(|&v| v > 0).unwrap_or_default() as u8;
+
         output.reserve(output_size);
-        let decoder = CanonicalPrefixDecoder::with_lengths(&lengths, false)?;
         let mut prev = 8;
-        while output.len() < output_size {
-            let decoded = decoder.decode(reader)? as u8;
+        for _ in 0..max_symbol {
+            if output.len() >= output_size {
+                break;
+            }
+            let decoded = match decoder.as_ref() {
+                Some(decoder) => decoder.decode(reader)? as u8,
+                None => single_code,
+            };
             match decoded {
                 0 => {
                     output.push(decoded);
@@ -424,7 +487,11 @@ impl CanonicalPrefixDecoder {
                 }
                 _ => return Err(DecodeError::InvalidData),
             }
+            if output.len() > output_size {
+                return Err(DecodeError::InvalidData);
+            }
         }
+        output.resize(output_size, 0);
 
         Ok(())
     }
@@ -478,9 +545,12 @@ pub struct LookupTableEntry(u16);
 impl LookupTableEntry {
     pub const EMPTY: Self = Self(0);
 
+    /// Larger symbols are decoded with the tree.
+    pub const MAX_SYMBOL: usize = 0x1ff;
+
     #[inline]
     pub fn new(symbol1: usize, bits: BitSize) -> Option<Self> {
-        if bits > BitSize::Bit15 {
+        if bits > BitSize::Bit15 || symbol1 > Self::MAX_SYMBOL {
             return None;
         }
         Some(Self((bits.as_usize() as u16) | (symbol1 as u16) << 7))
@@ -5,6 +5,7 @@ use crate::{
     stats::*,
     *,
 };
+use alloc::vec;
 use core::cmp;
 use core::convert::Infallible;
 
@@ -15,6 +16,7 @@ impl CanonicalPrefixCoder {
         freq_table: &[usize],
         max_len: BitSize,
         min_size: usize,
+        method: LengthLimitMethod,
     ) -> Vec<Option<VarLenInteger>> {
         let mut freq_table = freq_table
             .iter()
@@ -25,7 +27,8 @@ impl CanonicalPrefixCoder {
             cmp::Ordering::Equal => a.0.cmp(&b.0),
             ord => ord,
         });
-        let prefix_table = CanonicalPrefixCoder::generate_prefix_table(&freq_table, max_len, None);
+        let prefix_table =
+            CanonicalPrefixCoder::generate_prefix_table(&freq_table, max_len, method, None);
         let max_symbol = prefix_table.iter().fold(0usize, |a, v| a.max((v.0).into()));
         let mut prefix_map = Vec::new();
         prefix_map.resize((1 + max_symbol).max(min_size), None);
@@ -38,6 +41,7 @@ impl CanonicalPrefixCoder {
     pub fn generate_prefix_table<K>(
         freq_table: &[(K, usize)],
         max_len: BitSize,
+        method: LengthLimitMethod,
         ref_tree: Option<&mut Vec<HuffmanTreeNode<K>>>,
     ) -> Vec<(K, VarLenInteger)>
     where
@@ -86,7 +90,17 @@ impl CanonicalPrefixCoder {
             drop(tree);
         }
 
-        Self::_adjust_prefix_lengths(&mut prefix_lengths, max_len);
+        if prefix_lengths.len() > max_len as usize + 1 {
+            match method {
+                LengthLimitMethod::Heuristic => {
+                    Self::_adjust_prefix_lengths(&mut prefix_lengths, max_len)
+                }
+                LengthLimitMethod::PackageMerge => {
+                    let weights = freq_table.iter().rev().map(|v| v.1).collect::<Vec<_>>();
+                    prefix_lengths = Self::_package_merge(&weights, max_len);
+                }
+            }
+        }
 
         let mut acc = 0;
         let mut last_bits = 0;
@@ -155,6 +169,65 @@ impl CanonicalPrefixCoder {
         }
     }
 
+    /// Returns the number of codes for each length, limited to `max_len`.
+    ///
+    /// `weights` must be sorted in ascending order and have at most `1 << max_len` items.
+    fn _package_merge(weights: &[usize], max_len: BitSize) -> Vec<usize> {
+        let max_len = max_len as usize;
+        let n_leaves = weights.len();
+        assert!(n_leaves <= 1 << max_len);
+
+        // Each level is a list of items sorted by weight, where `true` marks a package.
+        // The packages of a level are made of the consecutive pairs of the level below.
+        let mut levels = Vec::with_capacity(max_len);
+        let mut prev = weights.iter().map(|&w| (w, false)).collect::<Vec<_>>();
+        for _ in 1..max_len {
+            let mut current = Vec::with_capacity(n_leaves + prev.len() / 2);
+            let mut leaves = weights.iter().peekable();
+            for pair in prev.chunks_exact(2) {
+                let package = pair[0].0 + pair[1].0;
+                while let Some(&&leaf) = leaves.peek() {
+                    if leaf > package {
+                        break;
+                    }
+                    current.push((leaf, false));
+                    leaves.next();
+                }
+                current.push((package, true));
+            }
+            current.extend(leaves.map(|&w| (w, false)));
+            levels.push(prev);
+            prev = current;
+        }
+        levels.push(prev);
+
+        // The first `2n - 2` items of the top level are selected,
+        // and each selected package selects two items of the level below.
+        // A leaf gets one bit longer for each level in which it is selected.
+        let mut selected = 2 * n_leaves - 2;
+        let mut leaf_lengths = vec![0usize; n_leaves];
+        for level in levels.iter().rev() {
+            let mut n_packages = 0;
+            let mut n_selected_leaves = 0;
+            for &(_, is_package) in level.iter().take(selected) {
+                if is_package {
+                    n_packages += 1;
+                } else {
+                    n_selected_leaves += 1;
+                }
+            }
+            for len in leaf_lengths.iter_mut().take(n_selected_leaves) {
+                *len += 1;
+            }
+            selected = n_packages * 2;
+        }
+        let mut prefix_lengths = vec![0; max_len + 1];
+        for len in leaf_lengths {
+            prefix_lengths[len] += 1;
+        }
+        prefix_lengths
+    }
+
     pub fn rle_match_len(prev_value: u8, data: &[u8], cursor: usize, max_len: usize) -> usize {
         let max_len = (data.len() - cursor).min(max_len);
         for len in 0..max_len {
@@ -165,10 +238,17 @@ impl CanonicalPrefixCoder {
         max_len
     }
 
-    fn rle_compress_prefix_table(input: &[u8]) -> Vec<VarLenInteger> {
+    fn rle_compress_prefix_table(
+        input: &[u8],
+        permutation_flavor: PermutationFlavor,
+    ) -> Vec<VarLenInteger> {
         let mut output = Vec::new();
         let mut cursor = 0;
-        let mut prev = 0; //8;
+        // In WebP, code 16 repeats the last non-zero length, which is initially 8.
+        let mut prev = match permutation_flavor {
+            PermutationFlavor::Deflate => 0,
+            PermutationFlavor::WebP => 8,
+        };
         while let Some(current) = input.get(cursor) {
             let current = *current;
             cursor += {
@@ -192,7 +272,9 @@ impl CanonicalPrefixCoder {
                     }
                 } else {
                     let len = Self::rle_match_len(0, &input, cursor, 138);
-                    prev = 0;
+                    if matches!(permutation_flavor, PermutationFlavor::Deflate) {
+                        prev = 0;
+                    }
                     if len >= 11 {
                         output.push(VarLenInteger::with_byte(REP11Z7));
                         output.push(
@@ -239,7 +321,7 @@ impl CanonicalPrefixCoder {
 
         let tables = tables
             .iter()
-            .map(|v| Self::rle_compress_prefix_table(v))
+            .map(|v| Self::rle_compress_prefix_table(v, permutation_flavor))
             .collect::<Vec<_>>();
 
         let mut freq_table = BTreeMap::new();
@@ -252,8 +334,12 @@ impl CanonicalPrefixCoder {
         }
         let freq_table = freq_table.into_freq_table(true);
 
-        let prefix_table =
-            CanonicalPrefixCoder::generate_prefix_table(&freq_table, BitSize::Bit7, None);
+        let prefix_table = CanonicalPrefixCoder::generate_prefix_table(
+            &freq_table,
+            BitSize::Bit7,
+            LengthLimitMethod::Heuristic,
+            None,
+        );
         let mut prefix_map = [None; 20];
         for prefix in prefix_table.iter() {
             assert!(prefix.1.size() < BitSize::OCTET);
@@ -299,6 +385,88 @@ impl CanonicalPrefixCoder {
             intermediate_tables: tables,
         })
     }
+
+    /// Encodes a table of code lengths in the WebP lossless (VP8L) format.
+    ///
+    /// Up to two symbols less than 256 with the length of 1 are written as a simple code,
+    /// and other tables as a normal code whose trailing zeros are omitted with `max_symbol`.
+    /// An empty table is written as a simple code with the single symbol 0.
+    ///
+    /// Note that in VP8L, a code with only one symbol takes no bits at all.
+    pub fn encode_prefix_table_webp(lengths: &[u8]) -> Vec<VarLenInteger> {
+        let mut output = Vec::new();
+
+        let used = lengths
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| **v > 0)
+            .take(3)
+            .collect::<Vec<_>>();
+        if used.len() <= 2 && used.iter().all(|&(symbol, &len)| symbol < 256 && len == 1) {
+            // simple code
+            let symbols = if used.is_empty() {
+                [0].to_vec()
+            } else {
+                used.iter().map(|v| v.0 as u32).collect::<Vec<_>>()
+            };
+            output.push(VarLenInteger::with_bool(true));
+            output.push(VarLenInteger::with_bool(symbols.len() == 2));
+            if symbols[0] < 2 {
+                output.push(VarLenInteger::with_bool(false));
+                output.push(VarLenInteger::with_bool(symbols[0] != 0));
+            } else {
+                output.push(VarLenInteger::with_bool(true));
+                output.push(VarLenInteger::with_byte(symbols[0] as u8));
+            }
+            if let Some(&symbol1) = symbols.get(1) {
+                output.push(VarLenInteger::with_byte(symbol1 as u8));
+            }
+            return output;
+        }
+
+        // normal code
+        let trimmed_len = lengths.iter().rposition(|&v| v > 0).map_or(0, |v| v + 1);
+        let trimmed = &lengths[..trimmed_len];
+        let meta = Self::encode_prefix_tables(&[trimmed], PermutationFlavor::WebP).unwrap();
+
+        output.push(VarLenInteger::with_bool(false));
+        output.push(VarLenInteger::new(BitSize::Bit4, meta.hclen.as_u8() as u32));
+        output.extend_from_slice(&meta.prefix_table);
+
+        let max_symbol = meta.intermediate_tables[0]
+            .iter()
+            .filter(|v| v.size() == BitSize::OCTET)
+            .count();
+        if trimmed_len < lengths.len() && max_symbol >= 2 {
+            let value = max_symbol as u32 - 2;
+            let length_nbits = (value.checked_ilog2().map_or(1, |v| v + 1))
+                .max(2)
+                .div_ceil(2)
+                * 2;
+            output.push(VarLenInteger::with_bool(true));
+            output.push(VarLenInteger::new(BitSize::Bit3, (length_nbits - 2) / 2));
+            output.push(VarLenInteger::new(
+                BitSize::new(length_nbits as u8).unwrap(),
+                value,
+            ));
+        } else {
+            output.push(VarLenInteger::with_bool(false));
+        }
+
+        let n_code_length_codes = meta.prefix_table.iter().filter(|v| v.value() > 0).count();
+        if n_code_length_codes > 1 {
+            output.extend_from_slice(&meta.content);
+        } else {
+            // A single code length code takes no bits, only the extra bits of repeat codes remain.
+            output.extend(
+                meta.intermediate_tables[0]
+                    .iter()
+                    .filter(|v| v.size() != BitSize::OCTET),
+            );
+        }
+
+        output
+    }
 }
 
 #[derive(Debug)]
@@ -386,3 +554,145 @@ impl<K> HuffmanTreeNode<K> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_bits(freq_table: &[usize], max_len: BitSize, method: LengthLimitMethod) -> usize {
+        let prefix_table = CanonicalPrefixCoder::make_prefix_table(freq_table, max_len, 0, method);
+        let mut kraft = 0;
+        let mut total = 0;
+        for (freq, code) in freq_table.iter().zip(prefix_table.iter()) {
+            if let Some(code) = code {
+                assert!(code.size() <= max_len);
+                kraft += 1usize << (max_len as usize - code.size().as_usize());
+                total += freq * code.size().as_usize();
+            }
+        }
+        assert!(kraft <= 1 << max_len as usize);
+        total
+    }
+
+    #[test]
+    fn package_merge() {
+        let mut fib = Vec::new();
+        let (mut a, mut b) = (1usize, 1usize);
+        for _ in 0..30 {
+            fib.push(a);
+            (a, b) = (b, a + b);
+        }
+        let mut skewed = Vec::new();
+        for i in 0..286 {
+            skewed.push(if i < 16 { 100_000 >> i } else { 1 + i % 3 });
+        }
+        let random = random_bytes(0, 255, 4096)
+            .into_iter()
+            .map(|v| v as usize * v as usize)
+            .collect::<Vec<_>>();
+
+        for (freq_table, max_len) in [
+            (&fib[..], BitSize::Bit7),
+            (&fib[..], BitSize::Bit15),
+            (&fib[..], BitSize::Bit16),
+            (&fib[..19], BitSize::Bit7),
+            (&skewed[..], BitSize::Bit15),
+            (&skewed[..], BitSize::Bit16),
+            (&random[..128], BitSize::Bit7),
+            (&random[..], BitSize::Bit15),
+        ] {
+            let heuristic = total_bits(freq_table, max_len, LengthLimitMethod::Heuristic);
+            let optimal = total_bits(freq_table, max_len, LengthLimitMethod::PackageMerge);
+            assert!(optimal <= heuristic, "{max_len:?} {optimal} {heuristic}");
+        }
+
+        // Without the limit, both are Huffman codes.
+        let unlimited = total_bits(&fib[..10], BitSize::Bit15, LengthLimitMethod::Heuristic);
+        let optimal = total_bits(&fib[..10], BitSize::Bit15, LengthLimitMethod::PackageMerge);
+        assert_eq!(unlimited, optimal);
+
+        // The longest codes are capped, so all symbols get the same length.
+        let optimal = total_bits(&fib[..16], BitSize::Bit4, LengthLimitMethod::PackageMerge);
+        assert_eq!(optimal, fib[..16].iter().sum::<usize>() * 4);
+
+        assert!(
+            total_bits(&fib, BitSize::Bit7, LengthLimitMethod::PackageMerge)
+                < total_bits(&fib, BitSize::Bit7, LengthLimitMethod::Heuristic)
+        );
+    }
+
+    #[test]
+    fn webp_prefix_table() {
+        use crate::num::bits::{BitStreamReader, BitStreamWriter, Write};
+
+        let mut tables = Vec::new();
+        for (alphabet_size, freq_table) in [
+            (256, random_bytes(1, 255, 256)),
+            (280, random_bytes(0, 40, 280)),
+            (280, random_bytes(0, 3, 20)),
+            (40, random_bytes(0, 1, 40)),
+            (256, [1; 256].to_vec()),
+        ] {
+            let freq_table = freq_table.iter().map(|&v| v as usize).collect::<Vec<_>>();
+            let lengths = CanonicalPrefixCoder::make_prefix_table(
+                &freq_table,
+                BitSize::Bit15,
+                alphabet_size,
+                LengthLimitMethod::PackageMerge,
+            )
+            .iter()
+            .map(|v| v.map(|v| v.size().as_u8()).unwrap_or_default())
+            .collect::<Vec<_>>();
+            tables.push(lengths);
+        }
+        let mut single = [0; 280];
+        single[3] = 1;
+        tables.push(single.to_vec());
+        single[3] = 0;
+        single[270] = 1;
+        tables.push(single.to_vec());
+        let mut pair = [0; 256];
+        pair[1] = 1;
+        pair[200] = 1;
+        tables.push(pair.to_vec());
+        pair[1] = 0;
+        pair[0] = 1;
+        tables.push(pair.to_vec());
+        let mut same = [0; 40];
+        same[2..34].fill(5);
+        tables.push(same.to_vec());
+
+        for lengths in tables.iter() {
+            let mut writer = BitStreamWriter::new();
+            writer.write(CanonicalPrefixCoder::encode_prefix_table_webp(lengths).as_slice());
+            writer.write(VarLenInteger::new(BitSize::Bit7, 0x5a));
+            let bytes = writer.into_bytes();
+
+            let mut reader = BitStreamReader::new(&bytes);
+            let decoded =
+                CanonicalPrefixDecoder::decode_prefix_table_webp(&mut reader, lengths.len())
+                    .unwrap();
+            assert_eq!(&decoded, lengths);
+            assert_eq!(reader.read_bits(BitSize::Bit7), Some(0x5a));
+        }
+
+        // An empty table is written as a simple code of the symbol 0.
+        let mut writer = BitStreamWriter::new();
+        writer.write(CanonicalPrefixCoder::encode_prefix_table_webp(&[0; 40]).as_slice());
+        assert_eq!(writer.bit_count(), 4);
+        let bytes = writer.into_bytes();
+        let decoded =
+            CanonicalPrefixDecoder::decode_prefix_table_webp(&mut BitStreamReader::new(&bytes), 40)
+                .unwrap();
+        assert_eq!(decoded.iter().position(|&v| v > 0), Some(0));
+
+        // 1 symbol, 8-bit symbol 0x41 from the specification of VP8L
+        let bytes = [0x0d, 0x02];
+        let decoded = CanonicalPrefixDecoder::decode_prefix_table_webp(
+            &mut BitStreamReader::new(&bytes),
+            256,
+        )
+        .unwrap();
+        assert_eq!(decoded.iter().position(|&v| v > 0), Some(0x41));
+    }
+}
@@ -26,6 +26,18 @@ pub enum PermutationFlavor {
     WebP,
 }
 
+/// How to limit the length of prefix codes that exceed `max_len`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthLimitMethod {
+    /// Moves the overflowing codes up the tree, which is fast but not always optimal.
+    #[default]
+    Heuristic,
+    /// Package-merge algorithm, which gives the optimal lengths for the limit.
+    ///
+    /// See also: <https://en.wikipedia.org/wiki/Package-merge_algorithm>
+    PackageMerge,
+}
+
 impl PermutationFlavor {
     const ORDER_DEFLATE: &[u8; 19] = &[
         16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
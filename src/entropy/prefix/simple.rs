@@ -1,9 +1,19 @@
 //! Simple Prefix Coding
 //!
-//! Special Huffman code encoder available only when the number of symbols is two.
+//! Special Huffman code available only when the number of symbols is small.
 //!
-//! Note that if the number of symbols is one, it is not strictly a Huffman code.
+//! * One symbol is repeated, which is not strictly a Huffman code.
+//! * Two symbols are stored as a bitmap.
+//! * Up to 16 symbols are packed at 2 or 4 bits per symbol, or coded with a tiny canonical prefix code.
+//!
+//! The bitmap and the packed data may be nested, that is, coded again as long as they are small enough.
+//!
+//! The length is not stored by [`SimplePrefixCoder::to_bytes`], so it must be given to
+//! [`SimplePrefixCoder::from_bytes`]. The framed format prefixes it in [`S7s`].
 
+use super::*;
+use crate::num::bits::{BitSize, BitStreamReader, BitStreamWriter};
+use crate::stk1::S7s;
 use crate::*;
 
 /// Simple Prefix Coding
@@ -19,48 +29,118 @@ pub enum SimplePrefixTable {
     Binary(u8, u8),
     NestedRepeat(u8, u8, u8),
     NestedBinary(u8, u8, u8, u8),
+    /// Indices into the alphabet at 2 bits per symbol for up to 4 symbols, 4 bits otherwise
+    Packed(SmallAlphabet),
+    /// The packed indices coded again by a non-nested coder
+    NestedPacked(SmallAlphabet),
+    /// Canonical prefix code with the lengths of the alphabet
+    Canonical(SmallAlphabet),
+}
+
+/// Up to 16 symbols with the lengths of their prefix codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmallAlphabet {
+    keys: [u8; 16],
+    lengths: [u8; 16],
+    len: u8,
+}
+
+impl SmallAlphabet {
+    pub const MAX_LEN: usize = 16;
+
+    /// Maximum length of the prefix codes, which fits in a nibble
+    pub const MAX_CODE_LEN: BitSize = BitSize::Bit15;
+
+    #[inline]
+    pub fn new(keys: &[u8]) -> Option<Self> {
+        if keys.is_empty() || keys.len() > Self::MAX_LEN {
+            return None;
+        }
+        let mut result = Self {
+            keys: [0; 16],
+            lengths: [0; 16],
+            len: keys.len() as u8,
+        };
+        result.keys[..keys.len()].copy_from_slice(keys);
+        Some(result)
+    }
+
+    #[inline]
+    pub fn keys(&self) -> &[u8] {
+        &self.keys[..self.len as usize]
+    }
+
+    /// Returns the lengths of the prefix codes, or zeros if they are not used.
+    #[inline]
+    pub fn lengths(&self) -> &[u8] {
+        &self.lengths[..self.len as usize]
+    }
+
+    /// Returns the number of bits per symbol in the packed form.
+    #[inline]
+    pub const fn packed_bits(&self) -> usize {
+        if self.len <= 4 { 2 } else { 4 }
+    }
+
+    /// Returns the size of the packed data for `len` symbols.
+    #[inline]
+    pub const fn packed_len(&self, len: usize) -> usize {
+        (len * self.packed_bits()).div_ceil(8)
+    }
 }
 
 impl SimplePrefixCoder {
+    const TAG_REPEAT: u8 = 0;
+    const TAG_BINARY: u8 = 1;
+    const TAG_NESTED_REPEAT: u8 = 2;
+    const TAG_NESTED_BINARY: u8 = 3;
+    const TAG_PACKED: u8 = 4;
+    const TAG_NESTED_PACKED: u8 = 5;
+    const TAG_CANONICAL: u8 = 6;
+
+    /// Encodes the input if it has up to 16 distinct symbols.
+    ///
+    /// The smallest representation is chosen for three or more symbols.
     pub fn encode(input: &[u8], allows_nest: bool) -> Option<Self> {
-        let mut key1 = None;
-        let mut key2 = None;
+        let mut freq_table = [0usize; 256];
         for &byte in input.iter() {
-            let Some(key1) = key1 else {
-                key1 = Some(byte);
-                continue;
-            };
-            let Some(key2) = key2 else {
-                if byte != key1 {
-                    key2 = Some(byte);
-                }
-                continue;
-            };
-            if byte != key1 && byte != key2 {
-                return None;
-            }
+            freq_table[byte as usize] += 1;
         }
+        let keys = (0..=255u8)
+            .filter(|&v| freq_table[v as usize] > 0)
+            .collect::<Vec<_>>();
+        match keys.len() {
+            0 => None,
+            1 => Some(Self {
+                table: SimplePrefixTable::Repeat(keys[0]),
+                data: Vec::new(),
+                len: input.len(),
+            }),
+            2 => Some(Self::encode_binary(input, keys[0], keys[1], allows_nest)),
+            3..=SmallAlphabet::MAX_LEN => {
+                let alphabet = SmallAlphabet::new(&keys)?;
+                let mut indices = [0u8; 256];
+                for (index, &key) in keys.iter().enumerate() {
+                    indices[key as usize] = index as u8;
+                }
+                let freq_table = keys
+                    .iter()
+                    .map(|&key| freq_table[key as usize])
+                    .collect::<Vec<_>>();
 
-        let Some(key1) = key1 else {
-            return None;
-        };
-        let key2 = match key2 {
-            Some(key2) => key2,
-            None => {
-                // Only one unique value
-                return Some(Self {
-                    table: SimplePrefixTable::Repeat(key1),
-                    data: Vec::new(),
-                    len: input.len(),
-                });
+                let packed = Self::encode_packed(input, alphabet, &indices, allows_nest);
+                let canonical = Self::encode_canonical(input, alphabet, &indices, &freq_table);
+                Some(if canonical.encoded_len() < packed.encoded_len() {
+                    canonical
+                } else {
+                    packed
+                })
             }
-        };
-        let (key1, key2) = if key1 < key2 {
-            (key1, key2)
-        } else {
-            (key2, key1)
-        };
+            _ => None,
+        }
+    }
 
+    fn encode_binary(input: &[u8], key1: u8, key2: u8, allows_nest: bool) -> Self {
         let mut data = Vec::new();
         let mut acc = 0;
         let mut bit = 0x01;
@@ -92,54 +172,375 @@ impl SimplePrefixCoder {
                         table = SimplePrefixTable::NestedBinary(key1, key2, key3, key4);
                         data = nested.data;
                     }
-                    SimplePrefixTable::NestedRepeat(_, _, _)
-                    | SimplePrefixTable::NestedBinary(_, _, _, _) => {
-                        unreachable!()
-                    }
+                    // The bitmap is kept as is for more than two symbols
+                    _ => {}
                 }
             }
         }
 
-        let encoded = Self {
+        Self {
             table,
             data,
             len: input.len(),
-        };
+        }
+    }
+
+    fn encode_packed(
+        input: &[u8],
+        alphabet: SmallAlphabet,
+        indices: &[u8; 256],
+        allows_nest: bool,
+    ) -> Self {
+        let bits = alphabet.packed_bits();
+        let mut data = Vec::with_capacity(alphabet.packed_len(input.len()));
+        for chunk in input.chunks(8 / bits) {
+            let mut acc = 0;
+            for (index, &byte) in chunk.iter().enumerate() {
+                acc |= indices[byte as usize] << (index * bits);
+            }
+            data.push(acc);
+        }
+
+        let mut table = SimplePrefixTable::Packed(alphabet);
+        if allows_nest
+            && data.len() >= 4
+            && let Some(nested) = Self::encode(&data, false)
+        {
+            let nested = nested.to_bytes();
+            if nested.len() < data.len() {
+                table = SimplePrefixTable::NestedPacked(alphabet);
+                data = nested;
+            }
+        }
+
+        Self {
+            table,
+            data,
+            len: input.len(),
+        }
+    }
+
+    fn encode_canonical(
+        input: &[u8],
+        mut alphabet: SmallAlphabet,
+        indices: &[u8; 256],
+        freq_table: &[usize],
+    ) -> Self {
+        let prefix_table = CanonicalPrefixCoder::make_prefix_table(
+            freq_table,
+            SmallAlphabet::MAX_CODE_LEN,
+            freq_table.len(),
+            LengthLimitMethod::Heuristic,
+        );
+        for (length, code) in alphabet.lengths.iter_mut().zip(prefix_table.iter()) {
+            *length = code.map(|v| v.size().as_u8()).unwrap_or_default();
+        }
+
+        let mut writer = BitStreamWriter::new();
+        for &byte in input.iter() {
+            writer.push(
+                prefix_table[indices[byte as usize] as usize]
+                    .unwrap()
+                    .reversed(),
+            );
+        }
 
-        Some(encoded)
+        Self {
+            table: SimplePrefixTable::Canonical(alphabet),
+            data: writer.into_bytes(),
+            len: input.len(),
+        }
+    }
+
+    /// Returns the size of [`Self::to_bytes`].
+    pub fn encoded_len(&self) -> usize {
+        let table_len = match self.table {
+            SimplePrefixTable::Repeat(_) => 2,
+            SimplePrefixTable::Binary(_, _) => 3,
+            SimplePrefixTable::NestedRepeat(_, _, _) => 4,
+            SimplePrefixTable::NestedBinary(_, _, _, _) => 5,
+            SimplePrefixTable::Packed(alphabet) | SimplePrefixTable::NestedPacked(alphabet) => {
+                2 + alphabet.keys().len()
+            }
+            SimplePrefixTable::Canonical(alphabet) => {
+                2 + alphabet.keys().len() + alphabet.keys().len().div_ceil(2)
+            }
+        };
+        table_len + self.data.len()
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut vec = Vec::new();
+        let mut vec = Vec::with_capacity(self.encoded_len());
         match self.table {
             SimplePrefixTable::Repeat(key) => {
-                vec.push(0);
+                vec.push(Self::TAG_REPEAT);
                 vec.push(key);
             }
             SimplePrefixTable::Binary(key1, key2) => {
-                vec.push(1);
+                vec.push(Self::TAG_BINARY);
                 vec.push(key1);
                 vec.push(key2);
-                vec.extend_from_slice(&self.data);
             }
             SimplePrefixTable::NestedRepeat(key1, key2, key3) => {
-                vec.push(2);
+                vec.push(Self::TAG_NESTED_REPEAT);
                 vec.push(key1);
                 vec.push(key2);
                 vec.push(key3);
-                vec.extend_from_slice(&self.data);
             }
             SimplePrefixTable::NestedBinary(key1, key2, key3, key4) => {
-                vec.push(3);
+                vec.push(Self::TAG_NESTED_BINARY);
                 vec.push(key1);
                 vec.push(key2);
                 vec.push(key3);
                 vec.push(key4);
-                vec.extend_from_slice(&self.data);
+            }
+            SimplePrefixTable::Packed(alphabet) | SimplePrefixTable::NestedPacked(alphabet) => {
+                vec.push(if matches!(self.table, SimplePrefixTable::Packed(_)) {
+                    Self::TAG_PACKED
+                } else {
+                    Self::TAG_NESTED_PACKED
+                });
+                vec.push(alphabet.len);
+                vec.extend_from_slice(alphabet.keys());
+            }
+            SimplePrefixTable::Canonical(alphabet) => {
+                vec.push(Self::TAG_CANONICAL);
+                vec.push(alphabet.len);
+                vec.extend_from_slice(alphabet.keys());
+                for pair in alphabet.lengths().chunks(2) {
+                    vec.push(pair[0] | pair.get(1).map_or(0, |v| v << 4));
+                }
             }
         }
+        vec.extend_from_slice(&self.data);
         vec
     }
+
+    /// Writes the length in [`S7s`] followed by [`Self::to_bytes`].
+    pub fn to_framed_bytes(&self) -> Vec<u8> {
+        let mut vec = Vec::new();
+        S7s::write(&mut vec, self.len);
+        vec.extend_from_slice(&self.to_bytes());
+        vec
+    }
+
+    /// Reads the output of [`Self::to_bytes`] holding `len` symbols.
+    ///
+    /// The input must not contain extra bytes.
+    pub fn from_bytes(input: &[u8], len: usize) -> Result<Self, DecodeError> {
+        let (coder, rest) = Self::read(input, len, true)?;
+        if !rest.is_empty() {
+            return Err(DecodeError::InvalidData);
+        }
+        Ok(coder)
+    }
+
+    /// Reads the output of [`Self::to_framed_bytes`] and returns the rest of the input.
+    pub fn from_framed_bytes(input: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        let (len, header_len) = S7s::read_slice(input)?;
+        Self::read(&input[header_len..], len, true)
+    }
+
+    fn read(input: &[u8], len: usize, allows_nest: bool) -> Result<(Self, &[u8]), DecodeError> {
+        let (table, rest) = Self::read_table(input, allows_nest)?;
+        let data_len = Self::data_len(table, rest, len)?;
+        let coder = Self {
+            table,
+            data: rest[..data_len].to_vec(),
+            len,
+        };
+        Ok((coder, &rest[data_len..]))
+    }
+
+    /// Reads the tag and the keys, and returns the table with the rest of the input.
+    fn read_table(
+        input: &[u8],
+        allows_nest: bool,
+    ) -> Result<(SimplePrefixTable, &[u8]), DecodeError> {
+        let (&tag, rest) = input.split_first().ok_or(DecodeError::UnexpectedEof)?;
+        let (table, rest) = match tag {
+            Self::TAG_REPEAT..=Self::TAG_NESTED_BINARY => {
+                if tag >= Self::TAG_NESTED_REPEAT && !allows_nest {
+                    return Err(DecodeError::InvalidData);
+                }
+                let key_len = tag as usize + 1;
+                let keys = rest.get(..key_len).ok_or(DecodeError::UnexpectedEof)?;
+                let table = match *keys {
+                    [key] => SimplePrefixTable::Repeat(key),
+                    [key1, key2] => SimplePrefixTable::Binary(key1, key2),
+                    [key1, key2, key3] => SimplePrefixTable::NestedRepeat(key1, key2, key3),
+                    [key1, key2, key3, key4] => {
+                        SimplePrefixTable::NestedBinary(key1, key2, key3, key4)
+                    }
+                    _ => unreachable!(),
+                };
+                (table, &rest[key_len..])
+            }
+            Self::TAG_PACKED..=Self::TAG_CANONICAL => {
+                if tag == Self::TAG_NESTED_PACKED && !allows_nest {
+                    return Err(DecodeError::InvalidData);
+                }
+                let (&key_len, rest) = rest.split_first().ok_or(DecodeError::UnexpectedEof)?;
+                let keys = rest
+                    .get(..key_len as usize)
+                    .ok_or(DecodeError::UnexpectedEof)?;
+                let mut alphabet = SmallAlphabet::new(keys).ok_or(DecodeError::InvalidData)?;
+                let rest = &rest[keys.len()..];
+                match tag {
+                    Self::TAG_PACKED => (SimplePrefixTable::Packed(alphabet), rest),
+                    Self::TAG_NESTED_PACKED => (SimplePrefixTable::NestedPacked(alphabet), rest),
+                    _ => {
+                        let lengths_len = keys.len().div_ceil(2);
+                        let lengths = rest.get(..lengths_len).ok_or(DecodeError::UnexpectedEof)?;
+                        for (index, length) in alphabet.lengths[..keys.len()].iter_mut().enumerate()
+                        {
+                            *length = (lengths[index / 2] >> ((index & 1) * 4)) & 0x0f;
+                        }
+                        (SimplePrefixTable::Canonical(alphabet), &rest[lengths_len..])
+                    }
+                }
+            }
+            _ => return Err(DecodeError::InvalidData),
+        };
+        Ok((table, rest))
+    }
+
+    /// Returns the size of the data of `len` symbols at the beginning of `data`.
+    ///
+    /// Only the prefix codes are walked through, without decoding the symbols.
+    fn data_len(table: SimplePrefixTable, data: &[u8], len: usize) -> Result<usize, DecodeError> {
+        let data_len = match table {
+            SimplePrefixTable::Repeat(_) | SimplePrefixTable::NestedRepeat(_, _, _) => 0,
+            SimplePrefixTable::Binary(_, _) => len.div_ceil(8),
+            SimplePrefixTable::NestedBinary(_, _, _, _) => len.div_ceil(8).div_ceil(8),
+            SimplePrefixTable::Packed(alphabet) => alphabet.packed_len(len),
+            SimplePrefixTable::NestedPacked(alphabet) => {
+                let (nested, rest) = Self::read_table(data, false)?;
+                data.len() - rest.len() + Self::data_len(nested, rest, alphabet.packed_len(len))?
+            }
+            SimplePrefixTable::Canonical(alphabet) => {
+                decode_canonical(&alphabet, data, len, |_| {})?
+            }
+        };
+        if data_len > data.len() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        Ok(data_len)
+    }
+
+    /// Rebuilds the original input.
+    pub fn decode(&self) -> Result<Vec<u8>, DecodeError> {
+        let (output, data_len) = Self::decode_data(self.table, &self.data, self.len)?;
+        if data_len != self.data.len() {
+            return Err(DecodeError::InvalidData);
+        }
+        Ok(output)
+    }
+
+    /// Decodes `len` symbols at the beginning of `data` and returns them with the size of the consumed data.
+    fn decode_data(
+        table: SimplePrefixTable,
+        data: &[u8],
+        len: usize,
+    ) -> Result<(Vec<u8>, usize), DecodeError> {
+        let data_len = match table {
+            SimplePrefixTable::NestedPacked(_) | SimplePrefixTable::Canonical(_) => 0,
+            _ => Self::data_len(table, data, len)?,
+        };
+        let output = match table {
+            SimplePrefixTable::Repeat(key) => repeat(key, len)?,
+            SimplePrefixTable::Binary(key1, key2) => expand(key1, key2, data, len),
+            SimplePrefixTable::NestedRepeat(key1, key2, key3) => {
+                // Every byte of the bitmap is `key3`, so the output repeats every 8 symbols.
+                let mut output = repeat(key1, len)?;
+                for (index, value) in output.iter_mut().enumerate() {
+                    if key3 & (1 << (index % 8)) != 0 {
+                        *value = key2;
+                    }
+                }
+                output
+            }
+            SimplePrefixTable::NestedBinary(key1, key2, key3, key4) => {
+                let bitmap = expand(key3, key4, &data[..data_len], len.div_ceil(8));
+                expand(key1, key2, &bitmap, len)
+            }
+            SimplePrefixTable::Packed(alphabet) => unpack(&alphabet, &data[..data_len], len)?,
+            SimplePrefixTable::NestedPacked(alphabet) => {
+                let (nested, rest) = Self::read_table(data, false)?;
+                let (packed, nested_len) =
+                    Self::decode_data(nested, rest, alphabet.packed_len(len))?;
+                let output = unpack(&alphabet, &packed, len)?;
+                return Ok((output, data.len() - rest.len() + nested_len));
+            }
+            SimplePrefixTable::Canonical(alphabet) => {
+                let mut output = Vec::with_capacity(len.min(data.len() * 8));
+                let data_len = decode_canonical(&alphabet, data, len, |key| output.push(key))?;
+                return Ok((output, data_len));
+            }
+        };
+        Ok((output, data_len))
+    }
+}
+
+/// Returns `len` copies of `key`, which is not bounded by the data.
+fn repeat(key: u8, len: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut output = Vec::new();
+    output
+        .try_reserve_exact(len)
+        .map_err(|_| DecodeError::OutOfMemory)?;
+    output.resize(len, key);
+    Ok(output)
+}
+
+/// Expands the bitmap in LSB first order into `key1` for 0 and `key2` for 1.
+fn expand(key1: u8, key2: u8, bitmap: &[u8], len: usize) -> Vec<u8> {
+    bitmap
+        .iter()
+        .flat_map(|&byte| (0..8).map(move |bit| if byte & (1 << bit) != 0 { key2 } else { key1 }))
+        .take(len)
+        .collect()
+}
+
+/// Expands the packed indices in LSB first order into the keys.
+fn unpack(alphabet: &SmallAlphabet, data: &[u8], len: usize) -> Result<Vec<u8>, DecodeError> {
+    let bits = alphabet.packed_bits();
+    let mask = (1u8 << bits) - 1;
+    let keys = alphabet.keys();
+    data.iter()
+        .flat_map(|&byte| {
+            (0..8)
+                .step_by(bits)
+                .map(move |shift| (byte >> shift) & mask)
+        })
+        .take(len)
+        .map(|index| keys.get(index as usize).copied())
+        .collect::<Option<Vec<_>>>()
+        .ok_or(DecodeError::InvalidData)
+}
+
+/// Decodes `len` symbols into `f` and returns the size of the consumed data.
+fn decode_canonical<F>(
+    alphabet: &SmallAlphabet,
+    data: &[u8],
+    len: usize,
+    mut f: F,
+) -> Result<usize, DecodeError>
+where
+    F: FnMut(u8),
+{
+    let decoder = CanonicalPrefixDecoder::with_lengths(alphabet.lengths(), false)?;
+    let mut reader = BitStreamReader::new(data);
+    let mut bits = 0;
+    for _ in 0..len {
+        let index = decoder.decode(&mut reader)? as usize;
+        bits += alphabet.lengths[index] as usize;
+        f(alphabet.keys[index]);
+    }
+    let data_len = bits.div_ceil(8);
+    if data_len > data.len() {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    Ok(data_len)
 }
 
 #[test]
@@ -157,6 +558,14 @@ fn simple_prefix() {
     assert_eq!(coder.table, SimplePrefixTable::Binary(1, 2));
 
     let input = vec![2, 1, 2, 1, 2, 1, 2, 3];
+    let coder = SimplePrefixCoder::encode(&input, true).unwrap();
+    assert_eq!(coder.data, [0b00010001, 0b10010001]);
+    assert_eq!(
+        coder.table,
+        SimplePrefixTable::Packed(SmallAlphabet::new(&[1, 2, 3]).unwrap())
+    );
+
+    let input = (0..17).collect::<Vec<u8>>();
     assert!(SimplePrefixCoder::encode(&input, true).is_none());
 
     let input = vec![1, 1, 1, 1];
@@ -165,3 +574,90 @@ fn simple_prefix() {
     assert_eq!(coder.data.len(), 0);
     assert_eq!(coder.table, SimplePrefixTable::Repeat(1));
 }
+
+#[test]
+fn simple_prefix_roundtrip() {
+    let inputs = [
+        vec![7; 100],
+        vec![1, 2, 1, 2, 1, 2, 1, 2, 1, 1, 1, 1, 2, 2, 2, 2, 1],
+        fib_str(b'a', b'b', 1000),
+        random_bytes(3, 5, 1000),
+        [vec![9; 64], vec![8; 64]].concat(),
+        b"ACGT".repeat(100),
+        b"GATTACA".iter().cycle().take(1001).copied().collect(),
+        (0..1000).map(|v| (v / 50 % 3) as u8).collect(),
+        (0..1000).map(|v| (v * v % 16) as u8).collect(),
+        [fib_str(0, 1, 500), vec![2; 3]].concat(),
+    ];
+    for input in inputs.iter() {
+        for allows_nest in [false, true] {
+            let coder = SimplePrefixCoder::encode(input, allows_nest).unwrap();
+            assert_eq!(coder.decode().unwrap(), *input);
+
+            let bytes = coder.to_bytes();
+            assert_eq!(bytes.len(), coder.encoded_len());
+            let decoded = SimplePrefixCoder::from_bytes(&bytes, input.len()).unwrap();
+            assert_eq!(decoded.table, coder.table);
+            assert_eq!(decoded.decode().unwrap(), *input);
+            // The nested repeat has no data to run out of
+            let nested_repeat = matches!(coder.table, SimplePrefixTable::NestedPacked(_))
+                && coder.data[0] == SimplePrefixCoder::TAG_REPEAT;
+            if !coder.data.is_empty() && !nested_repeat {
+                assert_eq!(
+                    SimplePrefixCoder::from_bytes(&bytes, input.len() + 64).err(),
+                    Some(DecodeError::UnexpectedEof)
+                );
+            }
+
+            let mut framed = coder.to_framed_bytes();
+            framed.push(0xaa);
+            let (decoded, rest) = SimplePrefixCoder::from_framed_bytes(&framed).unwrap();
+            assert_eq!(rest, [0xaa]);
+            assert_eq!(decoded.decode().unwrap(), *input);
+        }
+    }
+
+    let coder = SimplePrefixCoder::encode(&[1, 2].repeat(32), true).unwrap();
+    assert_eq!(coder.table, SimplePrefixTable::NestedRepeat(1, 2, 0xaa));
+    let coder = SimplePrefixCoder::encode(&[[9; 64], [8; 64]].concat(), true).unwrap();
+    assert_eq!(
+        coder.table,
+        SimplePrefixTable::NestedBinary(8, 9, 0x00, 0xff)
+    );
+
+    // Runs of three symbols are nested, skewed frequencies prefer the prefix code
+    let input = (0..1000).map(|v| (v / 50 % 3) as u8).collect::<Vec<_>>();
+    let coder = SimplePrefixCoder::encode(&input, true).unwrap();
+    assert!(matches!(coder.table, SimplePrefixTable::NestedPacked(_)));
+    assert!(coder.encoded_len() < 200);
+    let input = [fib_str(0, 1, 500), vec![2; 3]].concat();
+    let coder = SimplePrefixCoder::encode(&input, false).unwrap();
+    assert!(matches!(coder.table, SimplePrefixTable::Canonical(_)));
+    assert!(coder.encoded_len() < 100);
+
+    assert_eq!(
+        SimplePrefixCoder::from_bytes(&[0x7f, 0], 1).err(),
+        Some(DecodeError::InvalidData)
+    );
+    // The repeated symbols are not bounded by the input
+    for table in [
+        SimplePrefixTable::Repeat(7),
+        SimplePrefixTable::NestedRepeat(1, 2, 0xaa),
+    ] {
+        let coder = SimplePrefixCoder {
+            table,
+            data: Vec::new(),
+            len: 1 << 62,
+        };
+        let (decoded, _) = SimplePrefixCoder::from_framed_bytes(&coder.to_framed_bytes()).unwrap();
+        assert_eq!(decoded.decode().err(), Some(DecodeError::OutOfMemory));
+    }
+    // Index out of the alphabet
+    assert_eq!(
+        SimplePrefixCoder::from_bytes(&[4, 3, 1, 2, 3, 0xff], 4)
+            .unwrap()
+            .decode()
+            .err(),
+        Some(DecodeError::InvalidData)
+    );
+}
@@ -0,0 +1,33 @@
+//! ARM64 filter
+
+pub(super) fn code(data: &mut [u8], now_pos: u32, is_encoder: bool) -> usize {
+    let mut len = 0;
+    for (index, chunk) in data.chunks_exact_mut(4).enumerate() {
+        let pc = now_pos.wrapping_add(index as u32 * 4);
+        let instr = u32::from_le_bytes(chunk.try_into().unwrap());
+        len += 4;
+
+        if instr >> 26 == 0x25 {
+            // BL with the 26-bit word offset
+            let pc = pc >> 2;
+            let pc = if is_encoder { pc } else { pc.wrapping_neg() };
+            let instr = 0x9400_0000 | (instr.wrapping_add(pc) & 0x03ff_ffff);
+            chunk.copy_from_slice(&instr.to_le_bytes());
+        } else if instr & 0x9f00_0000 == 0x9000_0000 {
+            // ADRP with the 21-bit page offset, only within +/-512 MiB
+            let src = ((instr >> 29) & 3) | ((instr >> 3) & 0x001f_fffc);
+            if src.wrapping_add(0x0002_0000) & 0x001c_0000 != 0 {
+                continue;
+            }
+            let pc = pc >> 12;
+            let pc = if is_encoder { pc } else { pc.wrapping_neg() };
+            let dest = src.wrapping_add(pc);
+            let instr = (instr & 0x9000_001f)
+                | (dest & 3) << 29
+                | (dest & 0x0003_fffc) << 3
+                | (0u32.wrapping_sub(dest & 0x0002_0000) & 0x00e0_0000);
+            chunk.copy_from_slice(&instr.to_le_bytes());
+        }
+    }
+    len
+}
@@ -0,0 +1,141 @@
+//! Executable filters
+//!
+//! The branch/call/jump (BCJ) filters convert the relative addresses in the machine code to absolute ones,
+//! so the calls to the same function become the same bytes and compress better.
+//! They are reversible for any data, not only for the machine code.
+//!
+//! The algorithms are the same as the ones of xz, so the filtered data is identical to the output of
+//! `xz --x86`, `xz --arm64` and `xz --riscv` before LZMA2.
+//!
+//! ```
+//! use compress::filter::{self, BcjKind};
+//!
+//! let mut data = b"\xe8\x10\x00\x00\x00\xe8\x0b\x00\x00\x00".to_vec();
+//! filter::encode(&mut data, BcjKind::X86);
+//! assert_eq!(data, b"\xe8\x15\x00\x00\x00\xe8\x15\x00\x00\x00");
+//! filter::decode(&mut data, BcjKind::X86);
+//! assert_eq!(data, b"\xe8\x10\x00\x00\x00\xe8\x0b\x00\x00\x00");
+//! ```
+//!
+//! See also: <https://tukaani.org/xz/>
+
+#[cfg(test)]
+mod tests;
+
+mod arm64;
+mod riscv;
+mod x86;
+
+/// Instruction set of the filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BcjKind {
+    /// x86 and x86-64, the relative operands of `CALL` (E8) and `JMP` (E9)
+    X86,
+    /// ARM64, `BL` and `ADRP`
+    Arm64,
+    /// RISC-V, `JAL` and `AUIPC` with the following instruction
+    RiscV,
+}
+
+impl BcjKind {
+    /// Returns the alignment of the instructions, which the start offset should be a multiple of.
+    #[inline]
+    pub const fn alignment(&self) -> usize {
+        match self {
+            Self::X86 => 1,
+            Self::Arm64 => 4,
+            Self::RiscV => 2,
+        }
+    }
+
+    /// Returns the maximum number of bytes at the end that may be left unprocessed.
+    #[inline]
+    pub const fn lookahead(&self) -> usize {
+        match self {
+            Self::X86 => 4,
+            Self::Arm64 => 3,
+            Self::RiscV => 7,
+        }
+    }
+}
+
+/// Converts the relative addresses in place, as the code starts at the address zero.
+#[inline]
+pub fn encode(data: &mut [u8], kind: BcjKind) {
+    Bcj::new(kind).encode(data);
+}
+
+/// Restores the relative addresses in place, as the code starts at the address zero.
+#[inline]
+pub fn decode(data: &mut [u8], kind: BcjKind) {
+    Bcj::new(kind).decode(data);
+}
+
+/// BCJ filter which processes the data in pieces
+///
+/// Each call processes the data as far as possible and returns the number of bytes processed.
+/// The rest, up to [`BcjKind::lookahead`] bytes, should be given again at the beginning of the next call,
+/// or left as it is at the end of the data.
+#[derive(Debug, Clone)]
+pub struct Bcj {
+    kind: BcjKind,
+    position: u32,
+    x86: x86::State,
+}
+
+impl Bcj {
+    #[inline]
+    pub const fn new(kind: BcjKind) -> Self {
+        Self {
+            kind,
+            position: 0,
+            x86: x86::State::new(),
+        }
+    }
+
+    /// Sets the address of the beginning of the data, which is zero by default.
+    #[inline]
+    pub const fn with_start_offset(mut self, offset: u32) -> Self {
+        self.position = offset;
+        self
+    }
+
+    #[inline]
+    pub const fn kind(&self) -> BcjKind {
+        self.kind
+    }
+
+    /// Returns the address of the next byte to process.
+    #[inline]
+    pub const fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Converts the relative addresses in place, and returns the number of bytes processed.
+    #[inline]
+    pub fn encode(&mut self, data: &mut [u8]) -> usize {
+        self.code(data, true)
+    }
+
+    /// Restores the relative addresses in place, and returns the number of bytes processed.
+    #[inline]
+    pub fn decode(&mut self, data: &mut [u8]) -> usize {
+        self.code(data, false)
+    }
+
+    fn code(&mut self, data: &mut [u8], is_encoder: bool) -> usize {
+        let len = match self.kind {
+            BcjKind::X86 => self.x86.code(data, self.position, is_encoder),
+            BcjKind::Arm64 => arm64::code(data, self.position, is_encoder),
+            BcjKind::RiscV => {
+                if is_encoder {
+                    riscv::encode(data, self.position)
+                } else {
+                    riscv::decode(data, self.position)
+                }
+            }
+        };
+        self.position = self.position.wrapping_add(len as u32);
+        len
+    }
+}
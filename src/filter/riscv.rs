@@ -0,0 +1,171 @@
+//! RISC-V filter
+//!
+//! `JAL` with `rd` of `x1` or `x5` is converted to the absolute address in big endian.
+//!
+//! `AUIPC` followed by an instruction whose `rs1` is the `rd` of `AUIPC` is converted to
+//! `AUIPC` with `rd` of `x2` holding the second instruction without its immediate,
+//! followed by the absolute address in big endian.
+//! An `AUIPC` with `rd` of `x2`, which is rare, is swapped so that it is not confused with the converted pairs.
+
+#[inline]
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    u32::from_le_bytes(data[index..index + 4].try_into().unwrap())
+}
+
+#[inline]
+fn write_u32(data: &mut [u8], index: usize, value: u32) {
+    data[index..index + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Whether the instructions are not a pair of `AUIPC` and one with the same register in `rs1`
+///
+/// It also checks that the lowest two bits of the opcode are set, i.e. it is not a compressed instruction.
+#[inline]
+const fn is_not_pair(auipc: u32, inst2: u32) -> bool {
+    ((auipc << 8) ^ inst2.wrapping_sub(3)) & 0xf_8003 != 0
+}
+
+/// Whether the `AUIPC` with `rd` of `x0` or `x2` is not in the form of the converted pairs
+///
+/// The converted pair has `rd` of `x2`, the lowest two opcode bits of the second instruction set in bits 12-13,
+/// and the `rs1` of the second instruction, which is neither `x0` nor `x2`, in bits 27-31.
+#[inline]
+const fn is_not_special(auipc: u32, inst2_rs1: u32) -> bool {
+    auipc.wrapping_sub(0x3117) << 18 >= inst2_rs1 & 0x1d
+}
+
+/// Converts the `JAL` immediate to the absolute address in big endian.
+#[inline]
+fn encode_jal(data: &mut [u8], i: usize, pc: u32) {
+    let [b1, b2, b3] = [data[i + 1], data[i + 2], data[i + 3]].map(|v| v as u32);
+    let addr = ((b1 & 0xf0) << 8)
+        | ((b2 & 0x0f) << 16)
+        | ((b2 & 0x10) << 7)
+        | ((b2 & 0xe0) >> 4)
+        | ((b3 & 0x7f) << 4)
+        | ((b3 & 0x80) << 13);
+    let addr = addr.wrapping_add(pc);
+    data[i + 1] = ((b1 & 0x0f) | ((addr >> 13) & 0xf0)) as u8;
+    data[i + 2] = (addr >> 9) as u8;
+    data[i + 3] = (addr >> 1) as u8;
+}
+
+#[inline]
+fn decode_jal(data: &mut [u8], i: usize, pc: u32) {
+    let [b1, b2, b3] = [data[i + 1], data[i + 2], data[i + 3]].map(|v| v as u32);
+    let addr = ((b1 & 0xf0) << 13) | (b2 << 9) | (b3 << 1);
+    let addr = addr.wrapping_sub(pc);
+    data[i + 1] = ((b1 & 0x0f) | ((addr >> 8) & 0xf0)) as u8;
+    data[i + 2] = (((addr >> 16) & 0x0f) | ((addr >> 7) & 0x10) | ((addr << 4) & 0xe0)) as u8;
+    data[i + 3] = (((addr >> 4) & 0x7f) | ((addr >> 13) & 0x80)) as u8;
+}
+
+/// Whether it is `JAL` with `rd` of `x1` or `x5`
+#[inline]
+fn is_jal(data: &[u8], i: usize) -> bool {
+    data[i] == 0xef && data[i + 1] & 0x0d == 0
+}
+
+pub(super) fn encode(data: &mut [u8], now_pos: u32) -> usize {
+    if data.len() < 8 {
+        return 0;
+    }
+    let limit = data.len() - 8;
+    let mut i = 0;
+    while i <= limit {
+        let pc = now_pos.wrapping_add(i as u32);
+        if data[i] == 0xef {
+            if is_jal(data, i) {
+                encode_jal(data, i, pc);
+                i += 4;
+            } else {
+                i += 2;
+            }
+            continue;
+        }
+        if data[i] & 0x7f != 0x17 {
+            i += 2;
+            continue;
+        }
+
+        let inst = read_u32(data, i);
+        if inst & 0xe80 != 0 {
+            // AUIPC with rd other than x0 and x2
+            let inst2 = read_u32(data, i + 4);
+            if is_not_pair(inst, inst2) {
+                // Skips enough so that the second instruction does not become a part of another pair.
+                i += 6;
+                continue;
+            }
+            let addr = (inst & 0xffff_f000)
+                .wrapping_add(inst2 >> 20)
+                .wrapping_sub((inst2 >> 19) & 0x1000)
+                .wrapping_add(pc);
+            write_u32(data, i, 0x17 | (2 << 7) | (inst2 << 12));
+            data[i + 4..i + 8].copy_from_slice(&addr.to_be_bytes());
+        } else {
+            // AUIPC with rd of x0 or x2
+            let fake_rs1 = inst >> 27;
+            if is_not_special(inst, fake_rs1) {
+                i += 4;
+                continue;
+            }
+            let fake_addr = read_u32(data, i + 4);
+            write_u32(data, i, 0x17 | (fake_rs1 << 7) | (fake_addr & 0xffff_f000));
+            write_u32(data, i + 4, (inst >> 12) | (fake_addr << 20));
+        }
+        i += 8;
+    }
+    i
+}
+
+pub(super) fn decode(data: &mut [u8], now_pos: u32) -> usize {
+    if data.len() < 8 {
+        return 0;
+    }
+    let limit = data.len() - 8;
+    let mut i = 0;
+    while i <= limit {
+        let pc = now_pos.wrapping_add(i as u32);
+        if data[i] == 0xef {
+            if is_jal(data, i) {
+                decode_jal(data, i, pc);
+                i += 4;
+            } else {
+                i += 2;
+            }
+            continue;
+        }
+        if data[i] & 0x7f != 0x17 {
+            i += 2;
+            continue;
+        }
+
+        let mut inst = read_u32(data, i);
+        let inst2;
+        if inst & 0xe80 != 0 {
+            // The swapped AUIPC with rd of x2
+            let fake_inst2 = read_u32(data, i + 4);
+            if is_not_pair(inst, fake_inst2) {
+                i += 6;
+                continue;
+            }
+            inst2 = (inst & 0xffff_f000).wrapping_add(fake_inst2 >> 20);
+            inst = 0x17 | (2 << 7) | (fake_inst2 << 12);
+        } else {
+            // The converted pair
+            let inst2_rs1 = inst >> 27;
+            if is_not_special(inst, inst2_rs1) {
+                i += 4;
+                continue;
+            }
+            let addr = u32::from_be_bytes(data[i + 4..i + 8].try_into().unwrap()).wrapping_sub(pc);
+            inst2 = (inst >> 12) | (addr << 20);
+            inst = 0x17 | (inst2_rs1 << 7) | (addr.wrapping_add(0x800) & 0xffff_f000);
+        }
+        write_u32(data, i, inst);
+        write_u32(data, i + 4, inst2);
+        i += 8;
+    }
+    i
+}
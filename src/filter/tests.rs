@@ -0,0 +1,109 @@
+use super::*;
+use crate::deflate::{CompressionLevel, deflate, inflate};
+use crate::*;
+use base64::prelude::*;
+
+/// Filtered by `xz --format=raw --x86 --lzma2` and decoded without the filter
+const X86_INPUT: &str = "6BAAAADp8P///+joAAEAAJDoAAAAAQ+F6P/////o6OgSNFYASInl6EAAAADD6AAA";
+const X86_OUTPUT: &str = "6BUAAADp+v///+j3AAEAAJDoAAAAAQ+F6BwAAADo6OgSNFYASInl6GwAAADD6AAA";
+/// With `--x86=start=4096`
+const X86_OUTPUT_4096: &str = "6BUQAADp+g8AAOj3EAEAAJDoAAAAAQ+F6BwQAADo6OgSNFYASInl6GwQAADD6AAA";
+
+/// `BL`, `ADRP` in and out of the range, `NOP` and a partial instruction
+const ARM64_INPUT: &str = "AQAAlP///5cFAACQYAAAsOD//5AfIAPVEAAAlAAAAJElAA==";
+const ARM64_OUTPUT: &str = "AQAAlAAAAJQFAACQYAAAsOD//5AfIAPVFgAAlAAAAJElAA==";
+
+/// `JAL`, `AUIPC` pairs, a compressed instruction, `AUIPC` of `x2` and a partial instruction
+const RISCV_INPUT: &str = "7xDAABcFAQATBTUSAUXvEgAAF/P//4MjA4AXMQD4776t3m8AAQCXVQAAEwAAABcFAA==";
+const RISCV_OUTPUT: &str = "7wAIBhcxUVAAAQEnAUXvAggHFzE4Mv//6BKXv63eA4D/7m8AAQCXVQAAEwAAABcFAA==";
+
+/// Encodes the data in pieces of varying sizes.
+fn encode_in_pieces(data: &mut [u8], mut bcj: Bcj) {
+    let mut start = 0;
+    let mut end = 0;
+    let mut step = 1;
+    while end < data.len() {
+        end = (end + step).min(data.len());
+        start += bcj.encode(&mut data[start..end]);
+        step = step * 7 % 100 + 1;
+    }
+}
+
+#[test]
+fn xz_compatible() {
+    let vectors = [
+        (BcjKind::X86, X86_INPUT, X86_OUTPUT),
+        (BcjKind::Arm64, ARM64_INPUT, ARM64_OUTPUT),
+        (BcjKind::RiscV, RISCV_INPUT, RISCV_OUTPUT),
+    ];
+    for (kind, input, output) in vectors {
+        let input = BASE64_STANDARD.decode(input).unwrap();
+        let output = BASE64_STANDARD.decode(output).unwrap();
+
+        let mut data = input.clone();
+        encode(&mut data, kind);
+        assert_eq!(data, output, "{kind:?}");
+        decode(&mut data, kind);
+        assert_eq!(data, input, "{kind:?}");
+
+        let mut data = input.clone();
+        encode_in_pieces(&mut data, Bcj::new(kind));
+        assert_eq!(data, output, "{kind:?}");
+    }
+
+    let mut data = BASE64_STANDARD.decode(X86_INPUT).unwrap();
+    let mut bcj = Bcj::new(BcjKind::X86).with_start_offset(4096);
+    let len = bcj.encode(&mut data);
+    assert!(data.len() - len <= BcjKind::X86.lookahead());
+    assert_eq!(bcj.position(), 4096 + len as u32);
+    assert_eq!(data, BASE64_STANDARD.decode(X86_OUTPUT_4096).unwrap());
+}
+
+#[test]
+fn roundtrip() {
+    let inputs = [
+        Vec::new(),
+        b"\xe8\x00\x00".to_vec(),
+        random_bytes(0, 0xff, 10000),
+        random_bytes(0xe7, 0xea, 10000),
+        [0xe8, 0xff, 0x00, 0x17, 0xef, 0x94, 0x90].repeat(1000),
+        include_bytes!("../lib.rs").to_vec(),
+    ];
+    for kind in [BcjKind::X86, BcjKind::Arm64, BcjKind::RiscV] {
+        for input in inputs.iter() {
+            let mut data = input.clone();
+            encode(&mut data, kind);
+            let mut pieces = input.clone();
+            encode_in_pieces(&mut pieces, Bcj::new(kind));
+            assert_eq!(pieces, data);
+
+            let mut bcj = Bcj::new(kind);
+            let len = bcj.decode(&mut data);
+            assert!(data.len() - len <= kind.lookahead());
+            assert_eq!(data, *input, "{kind:?}");
+        }
+    }
+}
+
+#[test]
+fn before_deflate() {
+    // Calls to a few functions from everywhere
+    let mut input = Vec::new();
+    for i in 0..5000u32 {
+        let target = 0x1000 * (i.wrapping_mul(0x9e37_79b9) >> 29) as i32;
+        let offset = target - input.len() as i32 - 5;
+        input.extend_from_slice(&[0x48, 0x89, 0xc7]);
+        input.push(0xe8);
+        input.extend_from_slice(&offset.to_le_bytes());
+    }
+
+    let mut data = input.clone();
+    encode(&mut data, BcjKind::X86);
+    let filtered = deflate(&data, CompressionLevel::Default, None).unwrap();
+    let unfiltered = deflate(&input, CompressionLevel::Default, None).unwrap();
+    assert!(filtered.len() * 2 < unfiltered.len());
+
+    let mut data = inflate(&filtered, input.len()).unwrap();
+    decode(&mut data, BcjKind::X86);
+    assert_eq!(data, input);
+}
@@ -0,0 +1,100 @@
+//! x86 filter
+
+/// Whether the conversion is allowed for the previous bytes of E8/E9 in the mask
+const MASK_TO_ALLOWED_STATUS: [bool; 8] = [true, true, true, false, true, false, false, false];
+
+const MASK_TO_BIT_NUMBER: [u32; 8] = [0, 1, 2, 2, 3, 3, 3, 3];
+
+/// Whether the byte is the sign extension of the 25-bit address, 0x00 or 0xFF
+#[inline]
+const fn is_msb(byte: u8) -> bool {
+    byte.wrapping_add(1) & 0xfe == 0
+}
+
+/// Positions of the recent E8/E9 bytes
+#[derive(Debug, Clone)]
+pub(super) struct State {
+    prev_mask: u32,
+    prev_pos: u32,
+}
+
+impl State {
+    #[inline]
+    pub(super) const fn new() -> Self {
+        Self {
+            prev_mask: 0,
+            prev_pos: 0u32.wrapping_sub(5),
+        }
+    }
+
+    pub(super) fn code(&mut self, data: &mut [u8], now_pos: u32, is_encoder: bool) -> usize {
+        if data.len() < 5 {
+            return 0;
+        }
+        let mut prev_mask = self.prev_mask;
+        let mut prev_pos = self.prev_pos;
+        if now_pos.wrapping_sub(prev_pos) > 5 {
+            prev_pos = now_pos.wrapping_sub(5);
+        }
+
+        let limit = data.len() - 5;
+        let mut i = 0;
+        while i <= limit {
+            if data[i] != 0xe8 && data[i] != 0xe9 {
+                i += 1;
+                continue;
+            }
+
+            let pos = now_pos.wrapping_add(i as u32);
+            let offset = pos.wrapping_sub(prev_pos);
+            prev_pos = pos;
+            if offset > 5 {
+                prev_mask = 0;
+            } else {
+                for _ in 0..offset {
+                    prev_mask &= 0x77;
+                    prev_mask <<= 1;
+                }
+            }
+
+            let msb = data[i + 4];
+            if is_msb(msb)
+                && MASK_TO_ALLOWED_STATUS[((prev_mask >> 1) & 7) as usize]
+                && (prev_mask >> 1) < 0x10
+            {
+                let mut src = u32::from_le_bytes([data[i + 1], data[i + 2], data[i + 3], msb]);
+                let mut dest;
+                loop {
+                    dest = if is_encoder {
+                        src.wrapping_add(pos.wrapping_add(5))
+                    } else {
+                        src.wrapping_sub(pos.wrapping_add(5))
+                    };
+                    if prev_mask == 0 {
+                        break;
+                    }
+                    let index = MASK_TO_BIT_NUMBER[(prev_mask >> 1) as usize];
+                    if !is_msb((dest >> (24 - index * 8)) as u8) {
+                        break;
+                    }
+                    src = dest ^ ((1 << (32 - index * 8)) - 1);
+                }
+                // Sign-extends the 25-bit address.
+                let dest = (dest & 0x00ff_ffff) | 0u32.wrapping_sub((dest >> 24) & 1) << 24;
+                data[i + 1..i + 5].copy_from_slice(&dest.to_le_bytes());
+                i += 5;
+                prev_mask = 0;
+            } else {
+                i += 1;
+                prev_mask |= 1;
+                if is_msb(msb) {
+                    prev_mask |= 0x10;
+                }
+            }
+        }
+
+        self.prev_mask = prev_mask;
+        self.prev_pos = prev_pos;
+        i
+    }
+}
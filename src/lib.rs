@@ -1,7 +1,6 @@
 //! My compression library
 
-// #![cfg_attr(not(any(test, feature = "std")), no_std)]
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 
 extern crate alloc;
 
@@ -20,6 +19,31 @@ pub mod stk1;
 
 pub mod deflate;
 
+#[path = "lzac/lzac.rs"]
+pub mod lzac;
+
+pub mod webp;
+
+pub mod png;
+
+pub mod zip;
+
+pub mod bwt;
+
+pub mod bzip2;
+
+pub mod transform;
+
+pub mod filter;
+
+pub mod lz4;
+
+pub mod snappy;
+
+pub mod zstd;
+
+pub mod lzw;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DecodeError {
     InvalidInput,
@@ -35,6 +59,8 @@ pub enum EncodeError {
     InvalidData,
     OutOfMemory,
     EntropyError,
+    /// A caller-provided buffer is smaller than the size given.
+    BufferTooSmall(usize),
 }
 
 /// A Fibonacci word generator for testing purposes.
@@ -0,0 +1,162 @@
+//! Hash chains of 3-byte prefixes in caller-provided tables
+//!
+//! Unlike the other match finders, [`HashChain`] does not allocate, so it also works with a
+//! fixed scratch arena.
+
+use crate::{
+    EncodeError,
+    lz::{self, Match, lzss::LZSS},
+};
+
+/// Hash chains of the positions in the window
+pub struct HashChain<'a> {
+    /// The latest position plus one for each hash, or zero
+    head: &'a mut [u32],
+    /// The distance to the previous position of the same hash for each position, or zero
+    prev: &'a mut [u16],
+    hash_shift: u32,
+    max_chain: usize,
+    nice_len: usize,
+    max_len: usize,
+}
+
+impl<'a> HashChain<'a> {
+    /// Minimum length of the matches
+    pub const MIN_LEN: usize = 3;
+
+    /// Creates the hash chains, where `prev.len()` is the window size.
+    ///
+    /// Both `head.len()` and `prev.len()` must be powers of two, and the window must not be
+    /// larger than 65536. `head` must be filled with zeros.
+    ///
+    /// The search follows up to `max_chain` links, and stops at a match of `nice_len`.
+    pub fn new(
+        head: &'a mut [u32],
+        prev: &'a mut [u16],
+        max_chain: usize,
+        nice_len: usize,
+        max_len: usize,
+    ) -> Self {
+        debug_assert!(head.len().is_power_of_two() && prev.len().is_power_of_two());
+        debug_assert!(prev.len() <= 0x1_0000);
+        let hash_shift = 32 - head.len().trailing_zeros();
+        Self {
+            head,
+            prev,
+            hash_shift,
+            max_chain,
+            nice_len,
+            max_len,
+        }
+    }
+
+    #[inline]
+    pub fn window(&self) -> usize {
+        self.prev.len()
+    }
+
+    #[inline]
+    fn hash(&self, input: &[u8], pos: usize) -> usize {
+        let value = u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], 0]);
+        (value.wrapping_mul(0x9e37_79b1) >> self.hash_shift) as usize
+    }
+
+    #[inline]
+    pub fn insert(&mut self, input: &[u8], pos: usize) {
+        if pos + Self::MIN_LEN > input.len() {
+            return;
+        }
+        let hash = self.hash(input, pos);
+        let last = self.head[hash] as usize;
+        let distance = pos + 1 - last;
+        self.prev[pos & (self.window() - 1)] = if last != 0 && distance <= self.window() {
+            distance as u16
+        } else {
+            0
+        };
+        self.head[hash] = pos as u32 + 1;
+    }
+
+    /// Returns the longest match at `pos` and inserts `pos` into the chains.
+    #[inline]
+    pub fn find_and_insert(&mut self, input: &[u8], pos: usize) -> Option<Match> {
+        let matches = self.find(input, pos);
+        self.insert(input, pos);
+        matches
+    }
+
+    pub fn find(&self, input: &[u8], pos: usize) -> Option<Match> {
+        let limit = input.len().saturating_sub(pos).min(self.max_len);
+        if limit < Self::MIN_LEN {
+            return None;
+        }
+        let window = self.window();
+        let mut best_len = Self::MIN_LEN - 1;
+        let mut best_distance = 0;
+        let mut candidate = self.head[self.hash(input, pos)] as usize;
+        let mut chain = self.max_chain;
+        while candidate != 0 && chain > 0 {
+            let start = candidate - 1;
+            let distance = pos - start;
+            if distance > window {
+                break;
+            }
+            if input[start + best_len] == input[pos + best_len] {
+                let len = lz::matching_len(&input[..pos + limit], pos, distance);
+                if len > best_len {
+                    best_len = len;
+                    best_distance = distance;
+                    if len >= self.nice_len || len == limit {
+                        break;
+                    }
+                }
+            }
+            let link = self.prev[start & (window - 1)] as usize;
+            if link == 0 {
+                break;
+            }
+            candidate -= link;
+            chain -= 1;
+        }
+        (best_distance != 0).then(|| Match::new(best_len, best_distance))
+    }
+
+    /// Parses `input` greedily, or with the lazy matching if `lazy` is `true`, which defers a
+    /// match if the next position has a longer one.
+    pub fn encode<F>(&mut self, input: &[u8], lazy: bool, mut f: F) -> Result<(), EncodeError>
+    where
+        F: FnMut(LZSS) -> Result<(), EncodeError>,
+    {
+        let mut pos = 0;
+        let mut next = self.find_and_insert(input, pos);
+        while pos < input.len() {
+            let Some(matches) = next else {
+                f(LZSS::Literal(input[pos]))?;
+                pos += 1;
+                next = self.find_and_insert(input, pos);
+                continue;
+            };
+
+            let mut inserted = pos + 1;
+            if lazy && matches.len < self.nice_len {
+                let lazy = self.find_and_insert(input, pos + 1);
+                inserted = pos + 2;
+                if lazy.is_some_and(|lazy| lazy.len > matches.len) {
+                    f(LZSS::Literal(input[pos]))?;
+                    pos += 1;
+                    next = lazy;
+                    continue;
+                }
+            }
+
+            let end = pos + matches.len;
+            for pos in inserted..end {
+                self.insert(input, pos);
+            }
+            f(LZSS::Match(matches))?;
+            pos = end;
+            next = self.find_and_insert(input, pos);
+        }
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@
 //! See also: <https://en.wikipedia.org/wiki/LZ77_and_LZ78>
 
 pub mod cache;
+pub mod hash_chain;
 pub mod lzss;
 
 #[path = "match_finder/match_finder.rs"]
@@ -106,11 +107,45 @@ impl<'a> LzOutputBuffer<'a> {
         }
     }
 
+    /// Creates a buffer whose first `position` bytes are already written, so the matches may refer to them.
+    #[inline]
+    #[track_caller]
+    pub fn with_position(buffer: &'a mut [u8], position: usize) -> Self {
+        assert!(position <= buffer.len());
+        Self { buffer, position }
+    }
+
+    /// Returns the number of bytes that can still be written.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
     #[inline]
     pub fn is_eof(&self) -> bool {
         self.position >= self.buffer.len()
     }
 
+    /// Returns the number of bytes written so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the byte written `distance` bytes before the current position.
+    ///
+    /// Returns `None` if `distance` is zero or points before the start of the buffer.
+    #[inline]
+    pub fn peek_back(&self, distance: usize) -> Option<u8> {
+        if distance == 0 {
+            return None;
+        }
+        self.position
+            .checked_sub(distance)
+            .and_then(|index| self.buffer.get(index))
+            .copied()
+    }
+
     #[inline]
     pub fn push_literal(&mut self, literal: u8) -> LzOutputBufferResult {
         if self.position < self.buffer.len() {
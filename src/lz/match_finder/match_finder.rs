@@ -5,7 +5,7 @@ use lcp::LcpArray;
 use sais::SuffixArray;
 
 mod lcp;
-mod sais;
+pub mod sais;
 
 #[cfg(test)]
 mod tests;
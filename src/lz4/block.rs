@@ -0,0 +1,180 @@
+//! LZ4 block format
+
+use super::*;
+use crate::lz::lzss::{self, LZSS};
+use crate::lz::{LzOutputBuffer, Match};
+
+/// Maximum size of the input of a block, as the reference implementation
+pub const MAX_INPUT_SIZE: usize = 0x7e00_0000;
+
+/// Returns the maximum size of a compressed block of `len` bytes.
+#[inline]
+pub const fn max_compressed_len(len: usize) -> usize {
+    len + len / 255 + 16
+}
+
+/// Compresses the input into a block.
+pub fn encode_block(input: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    let mut output = Vec::with_capacity(max_compressed_len(input.len()));
+    encode_block_with_prefix(input, 0, &mut output)?;
+    Ok(output)
+}
+
+/// Compresses `data[prefix_len..]` into a block appended to the output,
+/// where the matches may refer to the prefix of up to 64 KiB.
+pub(super) fn encode_block_with_prefix(
+    data: &[u8],
+    prefix_len: usize,
+    output: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+    let prefix_len = prefix_len.min(MAX_DISTANCE);
+    let len = data.len() - prefix_len;
+    if len > MAX_INPUT_SIZE {
+        return Err(EncodeError::InvalidInput);
+    }
+    if len < MF_LIMIT + 1 {
+        write_sequence(output, &data[prefix_len..], None);
+        return Ok(());
+    }
+
+    let end = data.len();
+    let mut cursor = 0;
+    let mut literal_start = prefix_len;
+    let config = lzss::Configuration::new(
+        MAX_DISTANCE,
+        LZSS::MAX_LEN,
+        prefix_len.saturating_sub(1),
+        0,
+        0,
+        0,
+    );
+    LZSS::encode_fast(data, config, |lzss| {
+        match lzss {
+            LZSS::Literal(_) => cursor += 1,
+            LZSS::Match(matches) => {
+                // The matches near the end are written as literals.
+                let len = matches
+                    .len
+                    .min((end - LAST_LITERALS).saturating_sub(cursor));
+                if matches.len < MIN_MATCH || cursor + MF_LIMIT > end {
+                    cursor += matches.len;
+                } else {
+                    let literals = &data[literal_start..cursor];
+                    write_sequence(output, literals, Some(Match::new(len, matches.distance)));
+                    literal_start = cursor + len;
+                    cursor += matches.len;
+                }
+            }
+        }
+        Ok(())
+    })?;
+    write_sequence(output, &data[literal_start..], None);
+    Ok(())
+}
+
+/// Writes the literals followed by the match, or the literals only for the last sequence.
+fn write_sequence(output: &mut Vec<u8>, literals: &[u8], matches: Option<Match>) {
+    let match_len = matches.map(|v| v.len - MIN_MATCH);
+    let token = (literals.len().min(RUN_MASK) << 4) | match_len.unwrap_or_default().min(RUN_MASK);
+    output.push(token as u8);
+    if literals.len() >= RUN_MASK {
+        write_length(output, literals.len() - RUN_MASK);
+    }
+    output.extend_from_slice(literals);
+
+    if let Some(matches) = matches {
+        output.extend_from_slice(&(matches.distance as u16).to_le_bytes());
+        let match_len = matches.len - MIN_MATCH;
+        if match_len >= RUN_MASK {
+            write_length(output, match_len - RUN_MASK);
+        }
+    }
+}
+
+#[inline]
+fn write_length(output: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        output.push(255);
+        len -= 255;
+    }
+    output.push(len as u8);
+}
+
+/// Decompresses a block of up to `max_size` bytes.
+pub fn decode_block(input: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut output = Vec::new();
+    output
+        .try_reserve_exact(max_size)
+        .map_err(|_| DecodeError::OutOfMemory)?;
+    output.resize(max_size, 0);
+    let len = decode_block_in_place(input, &mut output)?;
+    output.truncate(len);
+    Ok(output)
+}
+
+/// Decompresses a block into the output buffer, and returns the size of the decompressed data.
+///
+/// Returns [`DecodeError::InvalidData`] if the data does not fit in the buffer.
+#[inline]
+pub fn decode_block_in_place(input: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+    let mut output = LzOutputBuffer::new(output);
+    decode_sequences(input, &mut output)?;
+    Ok(output.position())
+}
+
+/// Decodes the sequences of a block, where the matches may refer to the data already in the output.
+pub(super) fn decode_sequences(
+    input: &[u8],
+    output: &mut LzOutputBuffer,
+) -> Result<(), DecodeError> {
+    let mut iter = input.iter();
+    loop {
+        let token = *iter.next().ok_or(DecodeError::UnexpectedEof)? as usize;
+
+        let literal_len = read_length(&mut iter, token >> 4)?;
+        let literals = iter.as_slice();
+        if literal_len > literals.len() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        output
+            .extend_from_slice(&literals[..literal_len])
+            .ok_or(DecodeError::InvalidData)?;
+        iter = literals[literal_len..].iter();
+
+        // The last sequence has no match.
+        if iter.len() == 0 {
+            return Ok(());
+        }
+
+        let offset = iter
+            .next()
+            .zip(iter.next())
+            .map(|(&lo, &hi)| u16::from_le_bytes([lo, hi]) as usize)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        let match_len = read_length(&mut iter, token & 15)?.saturating_add(MIN_MATCH);
+        if offset == 0 || match_len > output.remaining() {
+            return Err(DecodeError::InvalidData);
+        }
+        output
+            .copy_lz(offset, match_len)
+            .ok_or(DecodeError::InvalidData)?;
+    }
+}
+
+/// Reads the length extended by the bytes of 255.
+#[inline]
+fn read_length(iter: &mut core::slice::Iter<u8>, len: usize) -> Result<usize, DecodeError> {
+    if len != RUN_MASK {
+        return Ok(len);
+    }
+    let mut len = len;
+    loop {
+        let byte = *iter.next().ok_or(DecodeError::UnexpectedEof)?;
+        len = len
+            .checked_add(byte as usize)
+            .ok_or(DecodeError::InvalidData)?;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
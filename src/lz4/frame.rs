@@ -0,0 +1,287 @@
+//! LZ4 frame format
+
+use super::*;
+use crate::lz::LzOutputBuffer;
+
+/// Version 01 in the highest 2 bits of the flags
+const VERSION: u8 = 0x40;
+const VERSION_MASK: u8 = 0xc0;
+
+const FLAG_INDEPENDENT_BLOCKS: u8 = 0x20;
+const FLAG_BLOCK_CHECKSUM: u8 = 0x10;
+const FLAG_CONTENT_SIZE: u8 = 0x08;
+const FLAG_CONTENT_CHECKSUM: u8 = 0x04;
+const FLAG_RESERVED: u8 = 0x02;
+const FLAG_DICT_ID: u8 = 0x01;
+
+/// The highest bit of the block size marks an uncompressed block.
+const UNCOMPRESSED_BLOCK: u32 = 0x8000_0000;
+
+/// Frame configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Configuration {
+    block_size: BlockSize,
+    independent_blocks: bool,
+    block_checksum: bool,
+    content_checksum: bool,
+    content_size: bool,
+}
+
+impl Configuration {
+    /// Independent blocks of 4 MiB with the content checksum, as the reference command line tool
+    pub const DEFAULT: Self = Self::new(BlockSize::Max4MB);
+
+    #[inline]
+    pub const fn new(block_size: BlockSize) -> Self {
+        Self {
+            block_size,
+            independent_blocks: true,
+            block_checksum: false,
+            content_checksum: true,
+            content_size: false,
+        }
+    }
+
+    /// Sets whether the blocks are compressed independently,
+    /// otherwise the matches may refer to the last 64 KiB of the previous blocks.
+    #[inline]
+    pub const fn independent_blocks(mut self, value: bool) -> Self {
+        self.independent_blocks = value;
+        self
+    }
+
+    /// Sets whether each block is followed by its xxHash32.
+    #[inline]
+    pub const fn block_checksum(mut self, value: bool) -> Self {
+        self.block_checksum = value;
+        self
+    }
+
+    /// Sets whether the frame ends with the xxHash32 of the decompressed data.
+    #[inline]
+    pub const fn content_checksum(mut self, value: bool) -> Self {
+        self.content_checksum = value;
+        self
+    }
+
+    /// Sets whether the header has the size of the decompressed data.
+    #[inline]
+    pub const fn content_size(mut self, value: bool) -> Self {
+        self.content_size = value;
+        self
+    }
+
+    #[inline]
+    pub const fn block_size(&self) -> BlockSize {
+        self.block_size
+    }
+}
+
+impl Default for Configuration {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Compresses the input into a frame.
+pub fn encode(input: &[u8], config: Configuration) -> Result<Vec<u8>, EncodeError> {
+    let mut output = Vec::with_capacity(max_compressed_len(input.len()) + 32);
+    output.extend_from_slice(&FRAME_MAGIC.to_le_bytes());
+
+    let mut flags = VERSION;
+    if config.independent_blocks {
+        flags |= FLAG_INDEPENDENT_BLOCKS;
+    }
+    if config.block_checksum {
+        flags |= FLAG_BLOCK_CHECKSUM;
+    }
+    if config.content_size {
+        flags |= FLAG_CONTENT_SIZE;
+    }
+    if config.content_checksum {
+        flags |= FLAG_CONTENT_CHECKSUM;
+    }
+    let descriptor_start = output.len();
+    output.push(flags);
+    output.push(config.block_size.as_u8() << 4);
+    if config.content_size {
+        output.extend_from_slice(&(input.len() as u64).to_le_bytes());
+    }
+    let header_checksum = (xxhash32::checksum(&output[descriptor_start..]) >> 8) as u8;
+    output.push(header_checksum);
+
+    let block_size = config.block_size.bytes();
+    let mut block = Vec::with_capacity(max_compressed_len(block_size.min(input.len())));
+    for start in (0..input.len()).step_by(block_size) {
+        let end = (start + block_size).min(input.len());
+        let data = &input[start..end];
+        let prefix_len = if config.independent_blocks {
+            0
+        } else {
+            start.min(MAX_DISTANCE)
+        };
+        block.clear();
+        encode_block_with_prefix(&input[start - prefix_len..end], prefix_len, &mut block)?;
+
+        let stored = if block.len() < data.len() {
+            output.extend_from_slice(&(block.len() as u32).to_le_bytes());
+            block.as_slice()
+        } else {
+            output.extend_from_slice(&(data.len() as u32 | UNCOMPRESSED_BLOCK).to_le_bytes());
+            data
+        };
+        output.extend_from_slice(stored);
+        if config.block_checksum {
+            output.extend_from_slice(&xxhash32::checksum(stored).to_le_bytes());
+        }
+    }
+
+    // End mark
+    output.extend_from_slice(&0u32.to_le_bytes());
+    if config.content_checksum {
+        output.extend_from_slice(&xxhash32::checksum(input).to_le_bytes());
+    }
+    Ok(output)
+}
+
+/// Decompresses the frames, which may be concatenated and interleaved with the skippable frames.
+pub fn decode(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let mut reader = Reader { input };
+    let mut output = Vec::new();
+    let mut has_frame = false;
+    while !reader.input.is_empty() || !has_frame {
+        let magic = reader.read_u32()?;
+        if magic == FRAME_MAGIC {
+            decode_frame(&mut reader, &mut output)?;
+        } else if magic & 0xffff_fff0 == SKIPPABLE_MAGIC {
+            let len = reader.read_u32()? as usize;
+            reader.read_bytes(len)?;
+        } else if magic == LEGACY_MAGIC {
+            return Err(DecodeError::UnsupportedFormat);
+        } else {
+            return Err(DecodeError::InvalidData);
+        }
+        has_frame = true;
+    }
+    Ok(output)
+}
+
+fn decode_frame(reader: &mut Reader, output: &mut Vec<u8>) -> Result<(), DecodeError> {
+    let descriptor = reader.input;
+    let flags = reader.read_u8()?;
+    let bd = reader.read_u8()?;
+    if flags & VERSION_MASK != VERSION {
+        return Err(DecodeError::UnsupportedFormat);
+    }
+    if flags & FLAG_RESERVED != 0 || bd & 0x8f != 0 {
+        return Err(DecodeError::InvalidData);
+    }
+    let block_size = BlockSize::from_u8(bd >> 4)
+        .ok_or(DecodeError::InvalidData)?
+        .bytes();
+    let content_size = if flags & FLAG_CONTENT_SIZE != 0 {
+        let size = u64::from_le_bytes(reader.read_array()?);
+        Some(usize::try_from(size).map_err(|_| DecodeError::OutOfMemory)?)
+    } else {
+        None
+    };
+    if flags & FLAG_DICT_ID != 0 {
+        return Err(DecodeError::UnsupportedFormat);
+    }
+    let descriptor = &descriptor[..descriptor.len() - reader.input.len()];
+    let header_checksum = reader.read_u8()?;
+    if header_checksum != (xxhash32::checksum(descriptor) >> 8) as u8 {
+        return Err(DecodeError::InvalidData);
+    }
+
+    let independent_blocks = flags & FLAG_INDEPENDENT_BLOCKS != 0;
+    let frame_start = output.len();
+    loop {
+        let size = reader.read_u32()?;
+        if size == 0 {
+            break;
+        }
+        let is_uncompressed = size & UNCOMPRESSED_BLOCK != 0;
+        let size = (size & !UNCOMPRESSED_BLOCK) as usize;
+        if size > block_size {
+            return Err(DecodeError::InvalidData);
+        }
+        let data = reader.read_bytes(size)?;
+        if flags & FLAG_BLOCK_CHECKSUM != 0 {
+            let checksum = u32::from_le_bytes(reader.read_array()?);
+            if checksum != xxhash32::checksum(data) {
+                return Err(DecodeError::InvalidData);
+            }
+        }
+
+        if is_uncompressed {
+            output.extend_from_slice(data);
+            continue;
+        }
+        // Each byte of the block expands to at most 255 bytes.
+        let capacity = block_size.min(
+            size.saturating_mul(255)
+                .saturating_add(MIN_MATCH + RUN_MASK),
+        );
+        let start = output.len();
+        output
+            .try_reserve(capacity)
+            .map_err(|_| DecodeError::OutOfMemory)?;
+        output.resize(start + capacity, 0);
+        let position = if independent_blocks {
+            let mut buffer = LzOutputBuffer::new(&mut output[start..]);
+            decode_sequences(data, &mut buffer)?;
+            start + buffer.position()
+        } else {
+            let mut buffer =
+                LzOutputBuffer::with_position(&mut output[frame_start..], start - frame_start);
+            decode_sequences(data, &mut buffer)?;
+            frame_start + buffer.position()
+        };
+        output.truncate(position);
+    }
+
+    let content = &output[frame_start..];
+    if content_size.is_some_and(|v| v != content.len()) {
+        return Err(DecodeError::InvalidData);
+    }
+    if flags & FLAG_CONTENT_CHECKSUM != 0 {
+        let checksum = u32::from_le_bytes(reader.read_array()?);
+        if checksum != xxhash32::checksum(content) {
+            return Err(DecodeError::InvalidData);
+        }
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    #[inline]
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if len > self.input.len() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        self.read_bytes(N).map(|v| v.try_into().unwrap())
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        self.read_array::<1>().map(|v| v[0])
+    }
+
+    #[inline]
+    fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+}
@@ -0,0 +1,79 @@
+//! LZ4 compressed format
+//!
+//! The block format is a sequence of literals and matches, each of them starts with a token of
+//! the literal length and the match length in 4 bits, extended by the following bytes of 255.
+//! The frame format splits the data into blocks of up to 4 MiB with the optional xxHash32 checksums.
+//!
+//! The encoder finds the matches with [`LZSS::encode_fast`](crate::lz::lzss::LZSS::encode_fast),
+//! and the decoder checks every length and offset, so it never reads or writes out of bounds on malformed input.
+//!
+//! See also: <https://github.com/lz4/lz4/blob/dev/doc/lz4_Block_format.md> and <https://github.com/lz4/lz4/blob/dev/doc/lz4_Frame_format.md>
+
+use crate::*;
+
+#[cfg(test)]
+mod tests;
+
+pub mod xxhash32;
+
+mod block;
+mod frame;
+pub use block::*;
+pub use frame::*;
+
+/// Minimum length of the matches
+const MIN_MATCH: usize = 4;
+
+/// Maximum offset of the matches
+const MAX_DISTANCE: usize = 0xffff;
+
+/// The last bytes of a block are always literals.
+const LAST_LITERALS: usize = 5;
+
+/// The last match must start at least this many bytes before the end of a block.
+const MF_LIMIT: usize = 12;
+
+/// Value of the 4-bit lengths in the token to be extended
+const RUN_MASK: usize = 15;
+
+/// Signature of the frame
+const FRAME_MAGIC: u32 = 0x184d_2204;
+
+/// Signature of the skippable frames, whose lowest 4 bits are any value
+const SKIPPABLE_MAGIC: u32 = 0x184d_2a50;
+
+/// Signature of the legacy frame, which is not supported
+const LEGACY_MAGIC: u32 = 0x184c_2102;
+
+/// Maximum size of the blocks in a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockSize {
+    Max64KB = 4,
+    Max256KB = 5,
+    Max1MB = 6,
+    #[default]
+    Max4MB = 7,
+}
+
+impl BlockSize {
+    #[inline]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            4 => Some(Self::Max64KB),
+            5 => Some(Self::Max256KB),
+            6 => Some(Self::Max1MB),
+            7 => Some(Self::Max4MB),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub const fn as_u8(&self) -> u8 {
+        *self as u8
+    }
+
+    #[inline]
+    pub const fn bytes(&self) -> usize {
+        1 << (8 + 2 * self.as_u8())
+    }
+}
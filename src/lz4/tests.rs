@@ -0,0 +1,175 @@
+use super::*;
+use base64::prelude::*;
+
+/// Compressed by `lz4 -9`
+///
+/// `"The quick brown fox jumps over the lazy dog.\n"` 4 times, `0..=255` and 300 zeros
+const TEXT_LZ4: &str = "BCJNGGRApz8BAAD/HlRoZSBxdWljayBicm93biBmb3gganVtcHMgb3ZlciB0aGUgbGF6eSBkb2cuCi0AdP/yAAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4OTo7PD0+P0BBQkNERUZHSElKS0xNTk9QUVJTVFVWV1hZWltcXV5fYGFiY2RlZmdoaWprbG1ub3BxcnN0dXZ3eHl6e3x9fn+AgYKDhIWGh4iJiouMjY6PkJGSk5SVlpeYmZqbnJ2en6ChoqOkpaanqKmqq6ytrq+wsbKztLW2t7i5uru8vb6/wMHCw8TFxsfIycrLzM3Oz9DR0tPU1dbX2Nna29zd3t/g4eLj5OXm5+jp6uvs7e7v8PHy8/T19vf4+fr7/P3+/wABAP8UUAAAAAAAAAAAAI1hGcY=";
+
+/// Compressed by `lz4 -BD -B4 -BX`, `"abc"` 50000 times in dependent blocks of 64 KiB with the block checksums
+const DEPENDENT_LZ4: &str = "BCJNGFRArg0BAAA/YWJjAwD/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////5VBjYWJjYQ/DSU4KAQAAD////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////+hQYWJjYWInhUwvVwAAAD9jYWIDAP//////////////////////////////////////////////////////////////////////////////////////////////////H1BiY2FiY+cuWzcAAAAAnILlxw==";
+
+/// Compressed by `lz4`, an empty input
+const EMPTY_LZ4: &str = "BCJNGGRApwAAAAAFXcwC";
+
+fn sample_text() -> Vec<u8> {
+    let mut text = b"The quick brown fox jumps over the lazy dog.\n".repeat(4);
+    text.extend(0..=255);
+    text.extend_from_slice(&[0; 300]);
+    text
+}
+
+#[test]
+fn xxhash32() {
+    assert_eq!(xxhash32::checksum(b""), 0x02cc_5d05);
+    assert_eq!(xxhash32::checksum(b"a"), 0x550d_7456);
+    assert_eq!(xxhash32::checksum(b"abc"), 0x32d1_53ff);
+    assert_eq!(
+        xxhash32::checksum(b"Nobody inspects the spammish repetition"),
+        0xe229_3b2f
+    );
+    assert_eq!(xxhash32::checksum_with_seed(b"", 1), 0x0b2c_b792);
+}
+
+#[test]
+fn decode_reference() {
+    let input = BASE64_STANDARD.decode(TEXT_LZ4).unwrap();
+    assert_eq!(decode(&input).unwrap(), sample_text());
+    // The payload of the only block
+    let len = u32::from_le_bytes(input[7..11].try_into().unwrap()) as usize;
+    let block = &input[11..11 + len];
+    assert_eq!(decode_block(block, 4096).unwrap(), sample_text());
+    assert_eq!(decode_block(block, 100), Err(DecodeError::InvalidData));
+
+    let input = BASE64_STANDARD.decode(DEPENDENT_LZ4).unwrap();
+    assert_eq!(decode(&input).unwrap(), b"abc".repeat(50000));
+
+    let input = BASE64_STANDARD.decode(EMPTY_LZ4).unwrap();
+    assert_eq!(decode(&input).unwrap(), b"");
+    // The reference tool chooses the smallest block size for small inputs.
+    let config = Configuration::new(BlockSize::Max64KB);
+    assert_eq!(encode(&[], config).unwrap(), input);
+}
+
+#[test]
+fn frames() {
+    let text = BASE64_STANDARD.decode(TEXT_LZ4).unwrap();
+    let empty = BASE64_STANDARD.decode(EMPTY_LZ4).unwrap();
+    let mut skippable = 0x184d_2a5fu32.to_le_bytes().to_vec();
+    skippable.extend_from_slice(&3u32.to_le_bytes());
+    skippable.extend_from_slice(b"xyz");
+    let input = [text.as_slice(), &skippable, &empty, &text].concat();
+    assert_eq!(
+        decode(&input).unwrap(),
+        [sample_text(), sample_text()].concat()
+    );
+
+    assert_eq!(decode(&[]), Err(DecodeError::UnexpectedEof));
+    assert_eq!(decode(&skippable[..10]), Err(DecodeError::UnexpectedEof));
+    assert_eq!(
+        decode(&0x184c_2102u32.to_le_bytes()),
+        Err(DecodeError::UnsupportedFormat)
+    );
+    assert!(decode(&text[..text.len() - 1]).is_err());
+    assert!(decode(&[text.as_slice(), b"garbage"].concat()).is_err());
+    // Header checksum
+    let mut broken = text.clone();
+    broken[5] ^= 0x10;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    // Content checksum
+    let mut broken = text.clone();
+    let len = broken.len();
+    broken[len - 1] ^= 1;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    // Block checksum
+    let mut broken = BASE64_STANDARD.decode(DEPENDENT_LZ4).unwrap();
+    broken[12] ^= 1;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+}
+
+#[test]
+fn roundtrip() {
+    let mut runs = Vec::new();
+    for len in [1, 3, 4, 5, 15, 16, 19, 270, 1000] {
+        runs.extend(core::iter::repeat_n(len as u8, len));
+    }
+    let inputs = [
+        Vec::new(),
+        b"a".to_vec(),
+        b"abcabcabcabc".to_vec(),
+        b"abcabcabcabca".to_vec(),
+        sample_text(),
+        runs,
+        fib_str(b'a', b'b', 10000),
+        random_bytes(0, 0xff, 10000),
+        include_bytes!("../lib.rs").to_vec(),
+    ];
+    for input in inputs.iter() {
+        let block = encode_block(input).unwrap();
+        assert!(block.len() <= max_compressed_len(input.len()));
+        assert_eq!(decode_block(&block, input.len()).unwrap(), *input);
+
+        let encoded = encode(input, Configuration::DEFAULT).unwrap();
+        assert_eq!(decode(&encoded).unwrap(), *input);
+    }
+
+    let input = [fib_str(b'x', b'y', 100_000), random_bytes(0, 0xff, 100_000)].concat();
+    let configs = [
+        Configuration::new(BlockSize::Max64KB),
+        Configuration::new(BlockSize::Max64KB)
+            .independent_blocks(false)
+            .block_checksum(true)
+            .content_size(true)
+            .content_checksum(false),
+    ];
+    for config in configs {
+        let encoded = encode(&input, config).unwrap();
+        assert_eq!(decode(&encoded).unwrap(), input);
+    }
+
+    let input = sample_text().repeat(100);
+    let encoded = encode(&input, Configuration::DEFAULT).unwrap();
+    assert!(encoded.len() < 1000);
+}
+
+#[test]
+fn malformed_blocks() {
+    let cases: [&[u8]; 9] = [
+        // Empty
+        b"",
+        // Literals beyond the input
+        b"\x50abc",
+        // Truncated length extension
+        b"\xf0\xff",
+        // Truncated offset
+        b"\x10a\x01",
+        // Zero offset
+        b"\x10a\x00\x00\x00",
+        // Offset before the start
+        b"\x10a\x02\x00\x00",
+        // Match beyond the capacity
+        b"\x1fa\x01\x00\xff\xff\x00",
+        // Truncated match length extension
+        b"\x1fa\x01\x00\xff",
+        // Literals beyond the capacity
+        b"\xf0\xff\x00",
+    ];
+    for input in cases {
+        assert!(decode_block(input, 256).is_err(), "{input:?}");
+    }
+    assert_eq!(decode_block(b"\x00", 0).unwrap(), b"");
+    assert_eq!(
+        decode_block(b"\x13a\x01\x00\x00", 256).unwrap(),
+        b"aaaaaaaa"
+    );
+
+    // Random data never panics.
+    let noise = random_bytes(0, 0xff, 100_000);
+    for chunk in noise.chunks(37) {
+        let _ = decode_block(chunk, 1000);
+        let mut frame = BASE64_STANDARD.decode(TEXT_LZ4).unwrap()[..7].to_vec();
+        frame.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        frame.extend_from_slice(chunk);
+        let _ = decode(&frame);
+    }
+}
@@ -0,0 +1,72 @@
+//! xxHash32
+//!
+//! See also: <https://github.com/Cyan4973/xxHash/blob/dev/doc/xxhash_spec.md>
+
+const PRIME32_1: u32 = 0x9e37_79b1;
+const PRIME32_2: u32 = 0x85eb_ca77;
+const PRIME32_3: u32 = 0xc2b2_ae3d;
+const PRIME32_4: u32 = 0x27d4_eb2f;
+const PRIME32_5: u32 = 0x1656_67b1;
+
+#[inline]
+const fn round(acc: u32, lane: u32) -> u32 {
+    acc.wrapping_add(lane.wrapping_mul(PRIME32_2))
+        .rotate_left(13)
+        .wrapping_mul(PRIME32_1)
+}
+
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+/// Calculates xxHash32 with the seed of zero.
+#[inline]
+pub fn checksum(data: &[u8]) -> u32 {
+    checksum_with_seed(data, 0)
+}
+
+/// Calculates xxHash32.
+pub fn checksum_with_seed(data: &[u8], seed: u32) -> u32 {
+    let mut stripes = data.chunks_exact(16);
+    let mut acc = if data.len() >= 16 {
+        let mut v = [
+            seed.wrapping_add(PRIME32_1).wrapping_add(PRIME32_2),
+            seed.wrapping_add(PRIME32_2),
+            seed,
+            seed.wrapping_sub(PRIME32_1),
+        ];
+        for stripe in stripes.by_ref() {
+            for (v, lane) in v.iter_mut().zip(stripe.chunks_exact(4)) {
+                *v = round(*v, read_u32(lane));
+            }
+        }
+        v[0].rotate_left(1)
+            .wrapping_add(v[1].rotate_left(7))
+            .wrapping_add(v[2].rotate_left(12))
+            .wrapping_add(v[3].rotate_left(18))
+    } else {
+        seed.wrapping_add(PRIME32_5)
+    };
+    acc = acc.wrapping_add(data.len() as u32);
+
+    let mut lanes = stripes.remainder().chunks_exact(4);
+    for lane in lanes.by_ref() {
+        acc = acc
+            .wrapping_add(read_u32(lane).wrapping_mul(PRIME32_3))
+            .rotate_left(17)
+            .wrapping_mul(PRIME32_4);
+    }
+    for &byte in lanes.remainder() {
+        acc = acc
+            .wrapping_add((byte as u32).wrapping_mul(PRIME32_5))
+            .rotate_left(11)
+            .wrapping_mul(PRIME32_1);
+    }
+
+    acc ^= acc >> 15;
+    acc = acc.wrapping_mul(PRIME32_2);
+    acc ^= acc >> 13;
+    acc = acc.wrapping_mul(PRIME32_3);
+    acc ^ (acc >> 16)
+}
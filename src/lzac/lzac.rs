@@ -0,0 +1,784 @@
+//! LZ77 + adaptive binary coder (LZMA-like)
+//!
+//! The LZSS parse is coded bit by bit with the adaptive [`FseEncoder`]:
+//!
+//! * Literals are coded with contexts selected by the previous byte,
+//!   and right after a match also by the byte at the last distance.
+//! * Match lengths use a low/mid/high bit-tree coder.
+//! * Distances use a 6-bit slot, then a reverse bit-tree or direct bits with 4 aligned bits.
+//! * The last four distances are kept and can be reused with a short repeat code.
+//!
+//! # Format
+//!
+//! | offset | size | description                                      |
+//! |--------|------|--------------------------------------------------|
+//! | 0      | 4    | magic number `LZAC`                              |
+//! | 4      | 1    | properties: `lc + pb * 9`                        |
+//! | 5      | 4    | dictionary size (little endian)                  |
+//! | 9      | 8    | uncompressed size (little endian)                |
+//! | 17     | -    | coded stream                                     |
+//!
+//! The format is similar to LZMA, but it is not compatible with it.
+//!
+//! See also: <https://en.wikipedia.org/wiki/Lempel%E2%80%93Ziv%E2%80%93Markov_chain_algorithm>
+
+use crate::{
+    entropy::fse::{ContextConfig, FseDecoder, FseEncoder},
+    lz::{
+        self, LzOutputBuffer,
+        lzss::{self, LZSS},
+    },
+    num::bits::BitSize,
+    *,
+};
+
+/// Magic number of the header
+pub const MAGIC: [u8; 4] = *b"LZAC";
+
+/// Size of the header in bytes
+pub const HEADER_SIZE: usize = 17;
+
+/// Minimum match length
+pub const MIN_MATCH_LEN: usize = 2;
+
+/// Maximum match length
+pub const MAX_MATCH_LEN: usize = MIN_MATCH_LEN + LEN_LOW_SYMBOLS + LEN_MID_SYMBOLS + 255;
+
+/// Minimum size by which the output of [`Lzac::decode_to_vec`] grows
+const MIN_GROWTH: usize = 0x10000;
+
+const NUM_STATES: usize = 12;
+const MAX_POS_BITS: usize = 4;
+const MAX_POS_STATES: usize = 1 << MAX_POS_BITS;
+const MAX_LC: u8 = 8;
+
+const LEN_LOW_BITS: BitSize = BitSize::Bit3;
+const LEN_MID_BITS: BitSize = BitSize::Bit3;
+const LEN_HIGH_BITS: BitSize = BitSize::Bit8;
+const LEN_LOW_SYMBOLS: usize = 1 << LEN_LOW_BITS.as_usize();
+const LEN_MID_SYMBOLS: usize = 1 << LEN_MID_BITS.as_usize();
+
+const LEN_CHOICE: usize = 0;
+const LEN_CHOICE2: usize = LEN_CHOICE + 1;
+const LEN_LOW: usize = LEN_CHOICE2 + 1;
+const LEN_MID: usize = LEN_LOW + MAX_POS_STATES * LEN_LOW_SYMBOLS;
+const LEN_HIGH: usize = LEN_MID + MAX_POS_STATES * LEN_MID_SYMBOLS;
+const LEN_CONTEXTS: usize = LEN_HIGH + (1 << LEN_HIGH_BITS.as_usize());
+
+const DIST_SLOT_BITS: BitSize = BitSize::Bit6;
+const DIST_LEN_STATES: usize = 4;
+const DIST_ALIGN_BITS: BitSize = BitSize::Bit4;
+/// The first distance slot that has footer bits
+const DIST_START_MODEL_SLOT: u32 = 4;
+/// The first distance slot whose footer bits are coded directly
+const DIST_END_MODEL_SLOT: u32 = 14;
+const DIST_FULL_DISTANCES: usize = 1 << (DIST_END_MODEL_SLOT >> 1);
+
+const IS_MATCH: usize = 0;
+const IS_REP: usize = IS_MATCH + NUM_STATES * MAX_POS_STATES;
+const IS_REP0: usize = IS_REP + NUM_STATES;
+const IS_REP1: usize = IS_REP0 + NUM_STATES;
+const IS_REP2: usize = IS_REP1 + NUM_STATES;
+const IS_REP0_LONG: usize = IS_REP2 + NUM_STATES;
+const DIST_SLOT: usize = IS_REP0_LONG + NUM_STATES * MAX_POS_STATES;
+const DIST_SPECIAL: usize = DIST_SLOT + DIST_LEN_STATES * (1 << DIST_SLOT_BITS.as_usize());
+const DIST_ALIGN: usize = DIST_SPECIAL + DIST_FULL_DISTANCES - DIST_END_MODEL_SLOT as usize;
+const MATCH_LEN: usize = DIST_ALIGN + (1 << DIST_ALIGN_BITS.as_usize());
+const REP_LEN: usize = MATCH_LEN + LEN_CONTEXTS;
+const LITERAL: usize = REP_LEN + LEN_CONTEXTS;
+const LITERAL_CONTEXTS: usize = 0x300;
+
+/// Probability model of each context
+const CONTEXT_CONFIG: ContextConfig = ContextConfig::new(BitSize::Bit12, 4);
+
+/// LZ77 + adaptive binary coder
+pub struct Lzac;
+
+/// Lzac configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Configuration {
+    max_distance: usize,
+    lc: u8,
+    pb: u8,
+    is_fast: bool,
+}
+
+impl Configuration {
+    pub const DEFAULT: Self = Self::new(LZSS::MAX_DISTANCE, 3, 2);
+
+    pub const FAST: Self = Self::DEFAULT.fast();
+
+    /// Creates a configuration.
+    ///
+    /// * `max_distance`: dictionary size, up to `LZSS::MAX_DISTANCE`
+    /// * `lc`: number of high bits of the previous byte used as the literal context (0-8)
+    /// * `pb`: number of low bits of the position used as the context of matches (0-4)
+    #[inline]
+    pub const fn new(max_distance: usize, lc: u8, pb: u8) -> Self {
+        Self {
+            max_distance: if max_distance > LZSS::MAX_DISTANCE {
+                LZSS::MAX_DISTANCE
+            } else if max_distance == 0 {
+                1
+            } else {
+                max_distance
+            },
+            lc: if lc > MAX_LC { MAX_LC } else { lc },
+            pb: if pb as usize > MAX_POS_BITS {
+                MAX_POS_BITS as u8
+            } else {
+                pb
+            },
+            is_fast: false,
+        }
+    }
+
+    /// Uses the fastest match finder instead of the suffix array.
+    #[inline]
+    pub const fn fast(mut self) -> Self {
+        self.is_fast = true;
+        self
+    }
+
+    #[inline]
+    pub fn max_distance(&self) -> usize {
+        self.max_distance
+    }
+
+    #[inline]
+    pub fn lc(&self) -> u8 {
+        self.lc
+    }
+
+    #[inline]
+    pub fn pb(&self) -> u8 {
+        self.pb
+    }
+
+    pub fn lzss_config(&self) -> lzss::Configuration {
+        let search_attempts = if self.is_fast { 1 } else { 0 };
+        lzss::Configuration::new(self.max_distance, MAX_MATCH_LEN, 0, search_attempts, 0, 0)
+    }
+}
+
+impl Default for Configuration {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Lzac {
+    pub fn encode(input: &[u8], config: Configuration) -> Result<Vec<u8>, EncodeError> {
+        let mut output = Vec::with_capacity(HEADER_SIZE + input.len() / 2);
+        output.extend_from_slice(&MAGIC);
+        output.push(config.lc + config.pb * 9);
+        output.extend_from_slice(&(config.max_distance as u32).to_le_bytes());
+        output.extend_from_slice(&(input.len() as u64).to_le_bytes());
+        if input.is_empty() {
+            return Ok(output);
+        }
+
+        let mut encoder = LzacEncoder::new(input, &config);
+        LZSS::encode_lcp(input, config.lzss_config(), |lzss| {
+            match lzss {
+                LZSS::Literal(literal) => encoder.encode_literal(literal),
+                LZSS::Match(matches) => encoder.encode_match(matches.len, matches.distance),
+            }
+            Ok(())
+        })?;
+        output.extend_from_slice(&encoder.fse.finish());
+
+        Ok(output)
+    }
+
+    /// Returns the uncompressed size stored in the header.
+    pub fn decoded_len(input: &[u8]) -> Result<usize, DecodeError> {
+        let header = input.get(..HEADER_SIZE).ok_or(DecodeError::UnexpectedEof)?;
+        if header[..4] != MAGIC {
+            return Err(DecodeError::InvalidInput);
+        }
+        let len = u64::from_le_bytes(header[9..17].try_into().unwrap());
+        usize::try_from(len).map_err(|_| DecodeError::OutOfMemory)
+    }
+
+    /// Decodes the stream into `output`, whose length must be the one returned by `decoded_len`.
+    pub fn decode(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+        let (size, lc, pb) = Self::_read_header(input)?;
+        if size != output.len() {
+            return Err(DecodeError::InvalidInput);
+        }
+        if output.is_empty() {
+            return Ok(());
+        }
+
+        let mut iter = input[HEADER_SIZE..].iter().copied();
+        let mut decoder = LzacDecoder::new(&mut iter, lc, pb)?;
+        decoder
+            .decode(&mut LzOutputBuffer::new(output))
+            .ok_or(DecodeError::UnexpectedEof)?
+    }
+
+    pub fn decode_to_vec(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        Self::decode_to_vec_with_limit(input, usize::MAX)
+    }
+
+    /// Decodes the stream of up to `max_size` bytes.
+    ///
+    /// Returns [`DecodeError::InvalidData`] if the size in the header is larger.
+    /// The output grows as the stream is decoded, since the size in the header is not trusted.
+    pub fn decode_to_vec_with_limit(input: &[u8], max_size: usize) -> Result<Vec<u8>, DecodeError> {
+        let (size, lc, pb) = Self::_read_header(input)?;
+        if size > max_size {
+            return Err(DecodeError::InvalidData);
+        }
+        let mut output = Vec::new();
+        if size == 0 {
+            return Ok(output);
+        }
+
+        let mut iter = input[HEADER_SIZE..].iter().copied();
+        let mut decoder = LzacDecoder::new(&mut iter, lc, pb)?;
+        while output.len() < size {
+            let position = output.len();
+            let len = size.min((position * 2).max(MIN_GROWTH));
+            output
+                .try_reserve_exact(len - position)
+                .map_err(|_| DecodeError::OutOfMemory)?;
+            output.resize(len, 0);
+            decoder
+                .decode(&mut LzOutputBuffer::with_position(&mut output, position))
+                .ok_or(DecodeError::UnexpectedEof)??;
+        }
+        Ok(output)
+    }
+
+    /// Returns the uncompressed size, `lc` and `pb` in the header.
+    fn _read_header(input: &[u8]) -> Result<(usize, u8, u8), DecodeError> {
+        let size = Self::decoded_len(input)?;
+        let props = input[4];
+        if props >= (MAX_POS_BITS as u8 + 1) * 9 {
+            return Err(DecodeError::UnsupportedFormat);
+        }
+        Ok((size, props % 9, props / 9))
+    }
+}
+
+#[inline]
+const fn context_size(lc: u8) -> usize {
+    LITERAL + (LITERAL_CONTEXTS << lc)
+}
+
+/// State of the coder, which remembers the kinds of the last few packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct State(usize);
+
+impl State {
+    const INIT: Self = Self(0);
+
+    /// Returns whether the previous packet was a literal.
+    #[inline]
+    const fn is_literal(&self) -> bool {
+        self.0 < 7
+    }
+
+    #[inline]
+    const fn next_literal(self) -> Self {
+        match self.0 {
+            0..4 => Self(0),
+            4..10 => Self(self.0 - 3),
+            _ => Self(self.0 - 6),
+        }
+    }
+
+    #[inline]
+    const fn next_match(self) -> Self {
+        Self(if self.is_literal() { 7 } else { 10 })
+    }
+
+    #[inline]
+    const fn next_rep(self) -> Self {
+        Self(if self.is_literal() { 8 } else { 11 })
+    }
+
+    #[inline]
+    const fn next_short_rep(self) -> Self {
+        Self(if self.is_literal() { 9 } else { 11 })
+    }
+}
+
+#[inline]
+fn dist_slot(dist: u32) -> u32 {
+    if dist < DIST_START_MODEL_SLOT {
+        dist
+    } else {
+        let n = dist.ilog2();
+        (n << 1) | ((dist >> (n - 1)) & 1)
+    }
+}
+
+#[inline]
+fn len_state(len: usize) -> usize {
+    (len - MIN_MATCH_LEN).min(DIST_LEN_STATES - 1)
+}
+
+struct LzacEncoder<'a> {
+    fse: FseEncoder,
+    input: &'a [u8],
+    pos: usize,
+    state: State,
+    /// The last four distances minus one
+    reps: [usize; 4],
+    lc: u8,
+    pos_mask: usize,
+}
+
+impl<'a> LzacEncoder<'a> {
+    fn new(input: &'a [u8], config: &Configuration) -> Self {
+        Self {
+            fse: FseEncoder::with_config(context_size(config.lc), CONTEXT_CONFIG),
+            input,
+            pos: 0,
+            state: State::INIT,
+            reps: [0; 4],
+            lc: config.lc,
+            pos_mask: (1 << config.pb) - 1,
+        }
+    }
+
+    #[inline]
+    fn pos_state(&self) -> usize {
+        self.pos & self.pos_mask
+    }
+
+    fn encode_literal(&mut self, literal: u8) {
+        let pos_state = self.pos_state();
+        let rep0 = self.reps[0] + 1;
+        if !self.state.is_literal() && self.input[self.pos - rep0] == literal {
+            self.encode_rep(0, 1);
+            return;
+        }
+
+        self.fse
+            .encode_bit(false, IS_MATCH + self.state.0 * MAX_POS_STATES + pos_state);
+        let prev_byte = if self.pos > 0 {
+            self.input[self.pos - 1]
+        } else {
+            0
+        };
+        let base = LITERAL + LITERAL_CONTEXTS * ((prev_byte as usize) >> (8 - self.lc));
+        let mut offs = if self.state.is_literal() { 0 } else { 0x100 };
+        let mut match_byte = if self.state.is_literal() {
+            0
+        } else {
+            self.input[self.pos - rep0] as usize
+        };
+        let mut symbol = 1;
+        for bit_position in (0..8).rev() {
+            let bit = (literal >> bit_position) & 1 != 0;
+            match_byte <<= 1;
+            let match_bit = offs;
+            offs &= match_byte;
+            self.fse.encode_bit(bit, base + offs + match_bit + symbol);
+            symbol = (symbol << 1) | bit as usize;
+            if !bit {
+                offs ^= match_bit;
+            }
+        }
+
+        self.state = self.state.next_literal();
+        self.pos += 1;
+    }
+
+    fn encode_match(&mut self, len: usize, distance: usize) {
+        let mut rep_index = self.reps.iter().position(|&rep| rep + 1 == distance);
+        if rep_index.is_none() {
+            rep_index = self.reps.iter().position(|&rep| {
+                rep < self.pos && lz::matching_len(self.input, self.pos, rep + 1) >= len
+            });
+        }
+        if let Some(rep_index) = rep_index {
+            self.encode_rep(rep_index, len);
+            return;
+        }
+
+        let pos_state = self.pos_state();
+        self.fse
+            .encode_bit(true, IS_MATCH + self.state.0 * MAX_POS_STATES + pos_state);
+        self.fse.encode_bit(false, IS_REP + self.state.0);
+        self.encode_len(MATCH_LEN, len, pos_state);
+
+        let dist = (distance - 1) as u32;
+        let slot = dist_slot(dist);
+        self.fse.encode_consecutive_bits(
+            slot,
+            DIST_SLOT_BITS,
+            DIST_SLOT + (len_state(len) << DIST_SLOT_BITS.as_usize()),
+        );
+        if slot >= DIST_START_MODEL_SLOT {
+            let footer_bits = (slot >> 1) - 1;
+            let base = (2 | (slot & 1)) << footer_bits;
+            let reduced = dist - base;
+            if slot < DIST_END_MODEL_SLOT {
+                self.fse.encode_reverse_bits(
+                    reduced,
+                    BitSize::new(footer_bits as u8).unwrap(),
+                    DIST_SPECIAL + (base - slot) as usize,
+                );
+            } else {
+                self.fse.encode_direct_bits(
+                    reduced >> DIST_ALIGN_BITS.as_usize(),
+                    footer_bits as usize - DIST_ALIGN_BITS.as_usize(),
+                );
+                self.fse.encode_reverse_bits(
+                    reduced & DIST_ALIGN_BITS.mask(),
+                    DIST_ALIGN_BITS,
+                    DIST_ALIGN,
+                );
+            }
+        }
+
+        self.reps = [distance - 1, self.reps[0], self.reps[1], self.reps[2]];
+        self.state = self.state.next_match();
+        self.pos += len;
+    }
+
+    /// Encodes a repeat match. A length of 1 means a short repeat of the last distance.
+    fn encode_rep(&mut self, rep_index: usize, len: usize) {
+        let pos_state = self.pos_state();
+        let state = self.state.0;
+        self.fse
+            .encode_bit(true, IS_MATCH + state * MAX_POS_STATES + pos_state);
+        self.fse.encode_bit(true, IS_REP + state);
+        if rep_index == 0 {
+            self.fse.encode_bit(false, IS_REP0 + state);
+            self.fse
+                .encode_bit(len > 1, IS_REP0_LONG + state * MAX_POS_STATES + pos_state);
+        } else {
+            self.fse.encode_bit(true, IS_REP0 + state);
+            if rep_index == 1 {
+                self.fse.encode_bit(false, IS_REP1 + state);
+            } else {
+                self.fse.encode_bit(true, IS_REP1 + state);
+                self.fse.encode_bit(rep_index == 3, IS_REP2 + state);
+            }
+            let dist = self.reps[rep_index];
+            self.reps.copy_within(0..rep_index, 1);
+            self.reps[0] = dist;
+        }
+
+        if len == 1 {
+            self.state = self.state.next_short_rep();
+        } else {
+            self.encode_len(REP_LEN, len, pos_state);
+            self.state = self.state.next_rep();
+        }
+        self.pos += len;
+    }
+
+    fn encode_len(&mut self, context_base: usize, len: usize, pos_state: usize) {
+        let value = (len - MIN_MATCH_LEN) as u32;
+        if value < LEN_LOW_SYMBOLS as u32 {
+            self.fse.encode_bit(false, context_base + LEN_CHOICE);
+            self.fse.encode_consecutive_bits(
+                value,
+                LEN_LOW_BITS,
+                context_base + LEN_LOW + pos_state * LEN_LOW_SYMBOLS,
+            );
+        } else if value < (LEN_LOW_SYMBOLS + LEN_MID_SYMBOLS) as u32 {
+            self.fse.encode_bit(true, context_base + LEN_CHOICE);
+            self.fse.encode_bit(false, context_base + LEN_CHOICE2);
+            self.fse.encode_consecutive_bits(
+                value - LEN_LOW_SYMBOLS as u32,
+                LEN_MID_BITS,
+                context_base + LEN_MID + pos_state * LEN_MID_SYMBOLS,
+            );
+        } else {
+            self.fse.encode_bit(true, context_base + LEN_CHOICE);
+            self.fse.encode_bit(true, context_base + LEN_CHOICE2);
+            self.fse.encode_consecutive_bits(
+                value - (LEN_LOW_SYMBOLS + LEN_MID_SYMBOLS) as u32,
+                LEN_HIGH_BITS,
+                context_base + LEN_HIGH,
+            );
+        }
+    }
+}
+
+struct LzacDecoder<'a> {
+    fse: FseDecoder<'a>,
+    state: State,
+    reps: [usize; 4],
+    lc: u8,
+    pos_mask: usize,
+    /// Length of the match cut at the end of the previous output
+    pending: usize,
+}
+
+impl<'a> LzacDecoder<'a> {
+    fn new(iter: &'a mut dyn Iterator<Item = u8>, lc: u8, pb: u8) -> Result<Self, DecodeError> {
+        let fse = FseDecoder::with_config(iter, context_size(lc), CONTEXT_CONFIG)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        Ok(Self {
+            fse,
+            state: State::INIT,
+            reps: [0; 4],
+            lc,
+            pos_mask: (1 << pb) - 1,
+            pending: 0,
+        })
+    }
+
+    /// Decodes packets until the output is full.
+    ///
+    /// Returns `None` if the coded stream ends unexpectedly.
+    fn decode(&mut self, output: &mut LzOutputBuffer) -> Option<Result<(), DecodeError>> {
+        if self.pending > 0 {
+            if let Err(err) = self.copy(output, self.pending) {
+                return Some(Err(err));
+            }
+        }
+        while !output.is_eof() {
+            let pos_state = output.position() & self.pos_mask;
+            let state = self.state.0;
+            if !self
+                .fse
+                .decode_bit(IS_MATCH + state * MAX_POS_STATES + pos_state)?
+            {
+                let literal = match self.decode_literal(output)? {
+                    Some(v) => v,
+                    None => return Some(Err(DecodeError::InvalidData)),
+                };
+                let _ = output.push_literal(literal);
+                self.state = self.state.next_literal();
+                continue;
+            }
+
+            let len = if !self.fse.decode_bit(IS_REP + state)? {
+                let len = self.decode_len(MATCH_LEN, pos_state)?;
+                let dist = self.decode_dist(len)?;
+                self.reps = [dist, self.reps[0], self.reps[1], self.reps[2]];
+                self.state = self.state.next_match();
+                len
+            } else if !self.fse.decode_bit(IS_REP0 + state)? {
+                if !self
+                    .fse
+                    .decode_bit(IS_REP0_LONG + state * MAX_POS_STATES + pos_state)?
+                {
+                    self.state = self.state.next_short_rep();
+                    1
+                } else {
+                    self.state = self.state.next_rep();
+                    self.decode_len(REP_LEN, pos_state)?
+                }
+            } else {
+                let rep_index = if !self.fse.decode_bit(IS_REP1 + state)? {
+                    1
+                } else if !self.fse.decode_bit(IS_REP2 + state)? {
+                    2
+                } else {
+                    3
+                };
+                let dist = self.reps[rep_index];
+                self.reps.copy_within(0..rep_index, 1);
+                self.reps[0] = dist;
+                self.state = self.state.next_rep();
+                self.decode_len(REP_LEN, pos_state)?
+            };
+
+            if let Err(err) = self.copy(output, len) {
+                return Some(Err(err));
+            }
+        }
+        Some(Ok(()))
+    }
+
+    /// Copies the match at the last distance, and keeps the rest that does not fit in the output.
+    fn copy(&mut self, output: &mut LzOutputBuffer, len: usize) -> Result<(), DecodeError> {
+        let copy_len = len.min(output.remaining());
+        self.pending = len - copy_len;
+        output
+            .copy_lz(self.reps[0] + 1, copy_len)
+            .ok_or(DecodeError::InvalidData)
+    }
+
+    /// Decodes a literal. The inner `None` means that the distance to the match byte is invalid.
+    fn decode_literal(&mut self, output: &LzOutputBuffer) -> Option<Option<u8>> {
+        let prev_byte = output.peek_back(1).unwrap_or_default();
+        let base = LITERAL + LITERAL_CONTEXTS * ((prev_byte as usize) >> (8 - self.lc));
+        let (mut offs, mut match_byte) = if self.state.is_literal() {
+            (0, 0)
+        } else {
+            match output.peek_back(self.reps[0] + 1) {
+                Some(v) => (0x100, v as usize),
+                None => return Some(None),
+            }
+        };
+        let mut symbol = 1;
+        while symbol < 0x100 {
+            match_byte <<= 1;
+            let match_bit = offs;
+            offs &= match_byte;
+            let bit = self.fse.decode_bit(base + offs + match_bit + symbol)?;
+            symbol = (symbol << 1) | bit as usize;
+            if !bit {
+                offs ^= match_bit;
+            }
+        }
+        Some(Some(symbol as u8))
+    }
+
+    fn decode_len(&mut self, context_base: usize, pos_state: usize) -> Option<usize> {
+        let value = if !self.fse.decode_bit(context_base + LEN_CHOICE)? {
+            self.fse.decode_consecutive_bits(
+                LEN_LOW_BITS,
+                context_base + LEN_LOW + pos_state * LEN_LOW_SYMBOLS,
+            )? as usize
+        } else if !self.fse.decode_bit(context_base + LEN_CHOICE2)? {
+            LEN_LOW_SYMBOLS
+                + self.fse.decode_consecutive_bits(
+                    LEN_MID_BITS,
+                    context_base + LEN_MID + pos_state * LEN_MID_SYMBOLS,
+                )? as usize
+        } else {
+            LEN_LOW_SYMBOLS
+                + LEN_MID_SYMBOLS
+                + self
+                    .fse
+                    .decode_consecutive_bits(LEN_HIGH_BITS, context_base + LEN_HIGH)?
+                    as usize
+        };
+        Some(value + MIN_MATCH_LEN)
+    }
+
+    /// Decodes a distance minus one.
+    fn decode_dist(&mut self, len: usize) -> Option<usize> {
+        let slot = self.fse.decode_consecutive_bits(
+            DIST_SLOT_BITS,
+            DIST_SLOT + (len_state(len) << DIST_SLOT_BITS.as_usize()),
+        )?;
+        if slot < DIST_START_MODEL_SLOT {
+            return Some(slot as usize);
+        }
+        let footer_bits = (slot >> 1) - 1;
+        let base = (2 | (slot & 1)) << footer_bits;
+        let reduced = if slot < DIST_END_MODEL_SLOT {
+            self.fse.decode_reverse_bits(
+                BitSize::new(footer_bits as u8).unwrap(),
+                DIST_SPECIAL + (base - slot) as usize,
+            )?
+        } else {
+            let direct = self
+                .fse
+                .decode_direct_bits(footer_bits as usize - DIST_ALIGN_BITS.as_usize())?;
+            let align = self.fse.decode_reverse_bits(DIST_ALIGN_BITS, DIST_ALIGN)?;
+            (direct << DIST_ALIGN_BITS.as_usize()) | align
+        };
+        Some(base as usize + reduced as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.
+Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.
+Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur.
+Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.
+";
+
+    #[test]
+    fn roundtrip() {
+        let mut records = Vec::new();
+        for i in 0..0x4000u32 {
+            records.extend_from_slice(&(i * 7 % 1000).to_le_bytes());
+        }
+        for input in [
+            Vec::new(),
+            b"a".to_vec(),
+            TEXT.to_vec(),
+            TEXT.repeat(20),
+            fib_str(b'a', b'b', 0x10000),
+            random_bytes(0x00, 0xff, 0x10000),
+            random_bytes(0x30, 0x39, 0x10000),
+            records,
+            vec![0; 0x12345],
+        ] {
+            for config in [
+                Configuration::DEFAULT,
+                Configuration::FAST,
+                Configuration::new(0x1000, 0, 0),
+                Configuration::new(LZSS::MAX_DISTANCE, 8, 4),
+            ] {
+                let encoded = Lzac::encode(&input, config).unwrap();
+                assert_eq!(Lzac::decoded_len(&encoded).unwrap(), input.len());
+                let decoded = Lzac::decode_to_vec(&encoded).unwrap();
+                assert_eq!(decoded, input, "{config:?}");
+                let mut output = vec![0; input.len()];
+                Lzac::decode(&encoded, &mut output).unwrap();
+                assert_eq!(output, input, "{config:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn ratio() {
+        let mut input = Vec::new();
+        for i in 0..2000u32 {
+            input.extend_from_slice(format!("{i}: {}\n", i * i % 977).as_bytes());
+        }
+        let encoded = Lzac::encode(&input, Configuration::DEFAULT).unwrap();
+        let deflated = crate::deflate::deflate(&input, Default::default(), None).unwrap();
+        assert!(
+            encoded.len() < deflated.len(),
+            "{} {}",
+            encoded.len(),
+            deflated.len()
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        let encoded = Lzac::encode(TEXT, Configuration::DEFAULT).unwrap();
+        assert_eq!(
+            Lzac::decode_to_vec(&encoded[..HEADER_SIZE - 1]),
+            Err(DecodeError::UnexpectedEof)
+        );
+        assert_eq!(
+            Lzac::decode_to_vec(&encoded[..encoded.len() / 2]),
+            Err(DecodeError::UnexpectedEof)
+        );
+        let mut broken = encoded.clone();
+        broken[0] = b'X';
+        assert_eq!(Lzac::decode_to_vec(&broken), Err(DecodeError::InvalidInput));
+        let mut broken = encoded.clone();
+        broken[4] = 45;
+        assert_eq!(
+            Lzac::decode_to_vec(&broken),
+            Err(DecodeError::UnsupportedFormat)
+        );
+        let mut output = vec![0; TEXT.len() + 1];
+        assert_eq!(
+            Lzac::decode(&encoded, &mut output),
+            Err(DecodeError::InvalidInput)
+        );
+
+        // The size in the header is not trusted.
+        let mut huge = encoded.clone();
+        huge[9..17].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        assert_eq!(Lzac::decode_to_vec(&huge), Err(DecodeError::UnexpectedEof));
+        assert_eq!(
+            Lzac::decode_to_vec_with_limit(&huge, 1 << 20),
+            Err(DecodeError::InvalidData)
+        );
+        assert_eq!(
+            Lzac::decode_to_vec_with_limit(&encoded, TEXT.len()).unwrap(),
+            TEXT
+        );
+        assert_eq!(
+            Lzac::decode_to_vec_with_limit(&encoded, TEXT.len() - 1),
+            Err(DecodeError::InvalidData)
+        );
+    }
+}
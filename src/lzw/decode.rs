@@ -0,0 +1,99 @@
+//! LZW decoder
+
+use super::*;
+use alloc::vec;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    prefix: u16,
+    byte: u8,
+    first: u8,
+    len: u16,
+}
+
+/// Decompresses the input up to the end of information code.
+///
+/// The codes may start without the clear code, and the dictionary stays full until the next clear code.
+pub fn decode(input: &[u8], config: Configuration) -> Result<Vec<u8>, DecodeError> {
+    decode_with_limit(input, config, usize::MAX)
+}
+
+/// Decompresses the input of up to `max_size` bytes.
+///
+/// Returns [`DecodeError::InvalidData`] if the data is larger, since a short input
+/// of the long strings may expand into a huge output.
+pub fn decode_with_limit(
+    input: &[u8],
+    config: Configuration,
+    max_size: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    if !config.is_valid() {
+        return Err(DecodeError::InvalidInput);
+    }
+    let clear_code = config.clear_code();
+    let end_code = config.end_code();
+    let first_code = clear_code as usize + 2;
+
+    let mut entries = vec![Entry::default(); MAX_CODES];
+    for (byte, entry) in entries.iter_mut().take(clear_code as usize).enumerate() {
+        *entry = Entry {
+            prefix: 0,
+            byte: byte as u8,
+            first: byte as u8,
+            len: 1,
+        };
+    }
+
+    let mut reader = CodeReader::new(input, config.bit_order);
+    let mut output = Vec::new();
+    let mut next = first_code;
+    let mut previous: Option<usize> = None;
+    loop {
+        let code = reader.read(config.code_size(next))?;
+        if code == clear_code {
+            next = first_code;
+            previous = None;
+            continue;
+        } else if code == end_code {
+            break;
+        }
+
+        let code = code as usize;
+        if let Some(previous) = previous {
+            // The string of the code just being added is the previous one followed by its own first byte.
+            let first = match code {
+                _ if code < next && (code < clear_code as usize || code >= first_code) => {
+                    entries[code].first
+                }
+                _ if code == next && next < MAX_CODES => entries[previous].first,
+                _ => return Err(DecodeError::InvalidData),
+            };
+            if next < MAX_CODES {
+                entries[next] = Entry {
+                    prefix: previous as u16,
+                    byte: first,
+                    first: entries[previous].first,
+                    len: entries[previous].len + 1,
+                };
+                next += 1;
+            }
+        } else if code >= clear_code as usize {
+            return Err(DecodeError::InvalidData);
+        }
+
+        let start = output.len();
+        let len = entries[code].len as usize;
+        if len > max_size - start {
+            return Err(DecodeError::InvalidData);
+        }
+        output.resize(start + len, 0);
+        let mut current = code;
+        for byte in output[start..].iter_mut().rev() {
+            let entry = entries[current];
+            *byte = entry.byte;
+            current = entry.prefix as usize;
+        }
+        previous = Some(code);
+    }
+    Ok(output)
+}
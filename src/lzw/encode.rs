@@ -0,0 +1,93 @@
+//! LZW encoder
+
+use super::*;
+use alloc::vec;
+
+/// Number of the slots of the dictionary, which is kept at most half full
+const DICTIONARY_SLOTS: usize = MAX_CODES * 2;
+
+/// Compresses the input, which starts with the clear code and ends with the end of information code.
+///
+/// Each byte must be less than `1 << min_code_size`.
+pub fn encode(input: &[u8], config: Configuration) -> Result<Vec<u8>, EncodeError> {
+    if !config.is_valid() {
+        return Err(EncodeError::InvalidInput);
+    }
+    let clear_code = config.clear_code();
+    if input.iter().any(|&v| v as u16 >= clear_code) {
+        return Err(EncodeError::InvalidInput);
+    }
+    let first_code = clear_code as usize + 2;
+
+    let mut writer = CodeWriter::new(config.bit_order);
+    writer.write(clear_code, config.code_size(first_code));
+    let mut dictionary = Dictionary::new();
+    let mut next = first_code;
+    if let Some((&first, input)) = input.split_first() {
+        let mut prefix = first as u16;
+        for &byte in input {
+            let slot = match dictionary.find(prefix, byte) {
+                Ok(code) => {
+                    prefix = code;
+                    continue;
+                }
+                Err(slot) => slot,
+            };
+            // The decoder adds the entry when it reads the next code, so it sees one code less.
+            writer.write(prefix, config.code_size((next - 1).max(first_code)));
+            dictionary.insert(slot, prefix, byte, next as u16);
+            next += 1;
+            if next == config.max_codes() {
+                writer.write(clear_code, config.code_size(next - 1));
+                dictionary.clear();
+                next = first_code;
+            }
+            prefix = byte as u16;
+        }
+        writer.write(prefix, config.code_size((next - 1).max(first_code)));
+    }
+    writer.write(config.end_code(), config.code_size(next));
+    Ok(writer.finish())
+}
+
+/// Hash table of the strings, each of which is a code followed by a byte
+struct Dictionary {
+    /// The code and the byte of each slot plus 1, or 0 if the slot is empty
+    keys: Vec<u32>,
+    codes: Vec<u16>,
+}
+
+impl Dictionary {
+    #[inline]
+    fn new() -> Self {
+        Self {
+            keys: vec![0; DICTIONARY_SLOTS],
+            codes: vec![0; DICTIONARY_SLOTS],
+        }
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.keys.fill(0);
+    }
+
+    /// Returns the code of the string, or the empty slot to insert it.
+    #[inline]
+    fn find(&self, prefix: u16, byte: u8) -> Result<u16, usize> {
+        let key = ((prefix as u32) << 8 | byte as u32) + 1;
+        let mut slot = (key.wrapping_mul(0x9e37_79b1) >> 19) as usize;
+        loop {
+            match self.keys[slot] {
+                0 => return Err(slot),
+                v if v == key => return Ok(self.codes[slot]),
+                _ => slot = (slot + 1) % DICTIONARY_SLOTS,
+            }
+        }
+    }
+
+    #[inline]
+    fn insert(&mut self, slot: usize, prefix: u16, byte: u8, code: u16) {
+        self.keys[slot] = ((prefix as u32) << 8 | byte as u32) + 1;
+        self.codes[slot] = code;
+    }
+}
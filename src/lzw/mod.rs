@@ -0,0 +1,193 @@
+//! Lempel–Ziv–Welch compressed format of GIF and TIFF
+//!
+//! The codes below `1 << min_code_size` are the literals, followed by the clear code, which resets
+//! the dictionary, and the end of information code. Each of the other codes adds the previous string
+//! followed by the first byte of the current one to the dictionary. The codes start at
+//! `min_code_size + 1` bits and widen as the dictionary grows, up to 12 bits.
+//!
+//! GIF packs the codes in LSB-first order. TIFF packs them in MSB-first order, and widens the codes
+//! one code earlier, which is called the early change.
+//!
+//! See also: <https://www.w3.org/Graphics/GIF/spec-gif89a.txt> and the section 13 of TIFF 6.0
+
+use crate::num::VarLenInteger;
+use crate::num::bits::{
+    BitSize, BitStreamReader, BitStreamWriter, MsbBitStreamReader, MsbBitStreamWriter,
+};
+use crate::*;
+
+#[cfg(test)]
+mod tests;
+
+mod decode;
+mod encode;
+pub use decode::*;
+pub use encode::*;
+
+/// Maximum width of the codes
+const MAX_CODE_SIZE: u8 = 12;
+
+/// Number of the codes of the maximum width
+const MAX_CODES: usize = 1 << MAX_CODE_SIZE;
+
+/// Order of the bits in the bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitOrder {
+    /// LSB-first, as GIF
+    #[default]
+    Lsb,
+    /// MSB-first, as TIFF
+    Msb,
+}
+
+/// Configuration of the codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Configuration {
+    min_code_size: u8,
+    bit_order: BitOrder,
+    early_change: bool,
+}
+
+impl Configuration {
+    /// TIFF, which has the literals of 8 bits in MSB-first order with the early change
+    pub const TIFF: Self = Self::new(8, BitOrder::Msb).early_change(true);
+
+    /// GIF of 256 colors
+    pub const DEFAULT: Self = Self::gif(8);
+
+    /// Creates a configuration of the literals of `min_code_size` bits, which is 2 to 8.
+    #[inline]
+    pub const fn new(min_code_size: u8, bit_order: BitOrder) -> Self {
+        Self {
+            min_code_size,
+            bit_order,
+            early_change: false,
+        }
+    }
+
+    /// GIF, which has the literals of `min_code_size` bits in LSB-first order
+    #[inline]
+    pub const fn gif(min_code_size: u8) -> Self {
+        Self::new(min_code_size, BitOrder::Lsb)
+    }
+
+    /// Sets whether the codes widen one code earlier than the dictionary needs, as TIFF does.
+    #[inline]
+    pub const fn early_change(mut self, value: bool) -> Self {
+        self.early_change = value;
+        self
+    }
+
+    #[inline]
+    pub const fn min_code_size(&self) -> u8 {
+        self.min_code_size
+    }
+
+    #[inline]
+    pub const fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    #[inline]
+    const fn is_valid(&self) -> bool {
+        matches!(self.min_code_size, 2..=8)
+    }
+
+    #[inline]
+    const fn clear_code(&self) -> u16 {
+        1 << self.min_code_size
+    }
+
+    #[inline]
+    const fn end_code(&self) -> u16 {
+        self.clear_code() + 1
+    }
+
+    /// Returns the width of the code read while the dictionary has `next` codes.
+    #[inline]
+    fn code_size(&self, next: usize) -> u8 {
+        let limit = if self.early_change { next + 1 } else { next };
+        // The width grows when the next code reaches the limit of the current width.
+        let bits = (usize::BITS - limit.leading_zeros()) as u8;
+        bits.clamp(self.min_code_size + 1, MAX_CODE_SIZE)
+    }
+
+    /// Returns the number of codes in the dictionary, at which the encoder clears it.
+    #[inline]
+    const fn max_codes(&self) -> usize {
+        if self.early_change {
+            // libtiff clears one code earlier, so that the codes never widen to 13 bits.
+            MAX_CODES - 2
+        } else {
+            MAX_CODES
+        }
+    }
+}
+
+impl Default for Configuration {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Writer of the codes in either bit order
+enum CodeWriter {
+    Lsb(BitStreamWriter),
+    Msb(MsbBitStreamWriter),
+}
+
+impl CodeWriter {
+    #[inline]
+    fn new(bit_order: BitOrder) -> Self {
+        match bit_order {
+            BitOrder::Lsb => Self::Lsb(BitStreamWriter::new()),
+            BitOrder::Msb => Self::Msb(MsbBitStreamWriter::new()),
+        }
+    }
+
+    #[inline]
+    fn write(&mut self, code: u16, code_size: u8) {
+        let value = VarLenInteger::new(BitSize::new(code_size).unwrap(), code as u32);
+        match self {
+            Self::Lsb(writer) => writer.push(value),
+            Self::Msb(writer) => writer.push(value),
+        }
+    }
+
+    /// Pads the last byte with zeros.
+    #[inline]
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Self::Lsb(writer) => writer.into_bytes(),
+            Self::Msb(writer) => writer.into_bytes(),
+        }
+    }
+}
+
+/// Reader of the codes in either bit order
+enum CodeReader<'a> {
+    Lsb(BitStreamReader<'a>),
+    Msb(MsbBitStreamReader<'a>),
+}
+
+impl<'a> CodeReader<'a> {
+    #[inline]
+    fn new(input: &'a [u8], bit_order: BitOrder) -> Self {
+        match bit_order {
+            BitOrder::Lsb => Self::Lsb(BitStreamReader::new(input)),
+            BitOrder::Msb => Self::Msb(MsbBitStreamReader::new(input)),
+        }
+    }
+
+    #[inline]
+    fn read(&mut self, code_size: u8) -> Result<u16, DecodeError> {
+        let bits = BitSize::new(code_size).unwrap();
+        match self {
+            Self::Lsb(reader) => reader.read_bits(bits),
+            Self::Msb(reader) => reader.read_bits(bits),
+        }
+        .map(|v| v as u16)
+        .ok_or(DecodeError::UnexpectedEof)
+    }
+}
@@ -0,0 +1,151 @@
+use super::*;
+use crate::zstd::xxhash64;
+use alloc::vec;
+use base64::prelude::*;
+
+/// LZW data of Python's test image `python.gif`, whose minimum code size is 7
+const PYTHON_GIF: &str = "gGWCZWFaUkxGQDeDjIJdZFJKRD04jYxXkUQ+ODWWZV9XTEQ5Mjk4lYxjYVxVUEdAPDWnZScjGWViX1RQSrA1vzFlIx0Tn1dUkkCnM8EbxAtlx0tHnY0dFwsHZYdHM2UtLywnJh0aCwgEZUySZS4wLCkmIRcRCAUCgkI+Ze/x5ecFCliCZwLCAwMBAADwFG9ehAQCPZWRd0FCAjLpJJap8LDAAI0gBwUC";
+
+/// Indices of the colors of `python.gif` decoded by giflib
+const PYTHON_GIF_PIXELS: &str = "ZWVlZWFaUkxGQDdlZWVlZWVlZWVdZFJKRD04ZWVlZWVlZWVlV1JKRD44NWVlZWVlZWVfV0xEOTI5ODhlZWVlZWNhXFVQR0A8NTg4ZScjGWViX1RQSkA8NTU1MWUjHRNlX1dUSkRAODgzMWUbHRMLZVdUS0c1ZWVlZWVlHRcLB2VSTEczZS0vLCcmHRoLCARlTEpEZS4wLCkmIRcRCAUCZWVCPmUwLCkmHRoLCAUFZWVlZWVlLCkmEA8GAQAAZWVlZWVlZSkmIRcRCQVlZWVlZWVlZWUmIRcSCWQEZWVlZWVlZWVlZRURCQUDZWVlZWVlZWVlZWVlZWVlZWVlZWVlZQ==";
+
+/// Encoded by giflib, `pixels(600, 4)` with the minimum code size 2
+const PIXELS_GIF: &str = "hG+hMevvwJiUMviQ1pWKjw0fiCnb+XxJGU1MA3XVuQmyOOajpvfkPaAFfDthcQXx0QyCE/E5CrKmLwpNBeykRtShwJQgIhce6IKTTc8O3YUKpbBA2HGf4mNdStI85eEWoac2OAZQAA==";
+
+/// Encoded by libtiff, `pixels(600, 256)`
+const PIXELS_TIFF: &str = "gAAgUDgT1gz1G7xhULhkNeJIOcRiUSTpTWsXjEZHKMjkdj0ee8hkT3B0lFsnlAta5ilhiGo1NMxbszmZkm03nE3XM7nYKBQoFApoVDolDHEhdLnc5lplNMrHqFRqLsqlVq1VQ1KrVKdQGrwGbyGsViOFlsoqtFpFTatltDIZNdxuRre9AoDAvDALpqNQavwaIWBIRUJGFwxIfApL2LImNIlFobmyWTcxYy2XzDKHGbzg4r+fVWh0Wj0RDdgx1EvaOrfOt100mgTKezKcEbG3bDqdT4W+932/3zVSvDSt8vgF5HJ5XIeTyZrNYnRcIu6nV6lUUPZ7UiU3d7wS8HgIvjXnl8rD9Hp9Xr9npSiUBAI57NXv1+33+rOZ04GH9GCDpE4kBFRAgQwMPZZQTBRZGCYJhGEAYBkWeEKQrCx4F2X8NQ3DiCF4GxpRDERpHBEpwFmD8UxUpyrxafcXxhGMZRkP8ah4HgqxyKoOICA=";
+
+/// xxHash64 of the outputs of libtiff and giflib for `pixels(60000, 256)` and `pixels(60000, 4)`,
+/// which clear the dictionary several times
+const LONG_TIFF_XXHASH64: u64 = 0x9e37_7299_3530_1a5a;
+const LONG_GIF_XXHASH64: u64 = 0x5346_a5d3_b212_6f98;
+const LONG_GIF2_XXHASH64: u64 = 0xa4c8_1136_6bb0_906b;
+
+/// Pixels of runs of random colors
+fn pixels(len: usize, colors: u32) -> Vec<u8> {
+    let mut seed = 1u32;
+    let mut value = 0;
+    (0..len)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let r = seed >> 16;
+            if r.is_multiple_of(4) {
+                value = ((r >> 4) % colors) as u8;
+            }
+            value
+        })
+        .collect()
+}
+
+#[test]
+fn decode_reference() {
+    let input = BASE64_STANDARD.decode(PYTHON_GIF).unwrap();
+    let pixels_gif = BASE64_STANDARD.decode(PYTHON_GIF_PIXELS).unwrap();
+    assert_eq!(decode(&input, Configuration::gif(7)).unwrap(), pixels_gif);
+
+    let input = BASE64_STANDARD.decode(PIXELS_GIF).unwrap();
+    assert_eq!(
+        decode(&input, Configuration::gif(2)).unwrap(),
+        pixels(600, 4)
+    );
+
+    let input = BASE64_STANDARD.decode(PIXELS_TIFF).unwrap();
+    assert_eq!(
+        decode(&input, Configuration::TIFF).unwrap(),
+        pixels(600, 256)
+    );
+
+    // The long strings expand beyond the limit
+    let encoded = encode(&[0; 100_000], Configuration::gif(2)).unwrap();
+    assert!(encoded.len() < 1000);
+    let config = Configuration::gif(2);
+    assert_eq!(
+        decode_with_limit(&encoded, config, 100_000).unwrap(),
+        [0; 100_000]
+    );
+    assert_eq!(
+        decode_with_limit(&encoded, config, 99_999),
+        Err(DecodeError::InvalidData)
+    );
+}
+
+#[test]
+fn encode_reference() {
+    let expected = BASE64_STANDARD.decode(PIXELS_GIF).unwrap();
+    assert_eq!(
+        encode(&pixels(600, 4), Configuration::gif(2)).unwrap(),
+        expected
+    );
+
+    let expected = BASE64_STANDARD.decode(PIXELS_TIFF).unwrap();
+    assert_eq!(
+        encode(&pixels(600, 256), Configuration::TIFF).unwrap(),
+        expected
+    );
+
+    for (input, config, checksum) in [
+        (pixels(60000, 256), Configuration::TIFF, LONG_TIFF_XXHASH64),
+        (pixels(60000, 256), Configuration::gif(8), LONG_GIF_XXHASH64),
+        (pixels(60000, 4), Configuration::gif(2), LONG_GIF2_XXHASH64),
+    ] {
+        let encoded = encode(&input, config).unwrap();
+        assert_eq!(xxhash64::checksum(&encoded), checksum);
+        assert_eq!(decode(&encoded, config).unwrap(), input);
+    }
+}
+
+#[test]
+fn encode_decode() {
+    for min_code_size in 2..=8 {
+        let colors = 1 << min_code_size;
+        let inputs = [
+            Vec::new(),
+            vec![(colors - 1) as u8],
+            vec![1; 100_000],
+            pixels(20000, colors),
+            fib_str(0, 1, 20000),
+        ];
+        for config in [
+            Configuration::gif(min_code_size),
+            Configuration::gif(min_code_size).early_change(true),
+            Configuration::new(min_code_size, BitOrder::Msb),
+            Configuration::new(min_code_size, BitOrder::Msb).early_change(true),
+        ] {
+            for input in inputs.iter() {
+                let encoded = encode(input, config).unwrap();
+                assert_eq!(decode(&encoded, config).unwrap(), *input);
+            }
+        }
+    }
+}
+
+#[test]
+fn invalid() {
+    for config in [Configuration::gif(1), Configuration::gif(9)] {
+        assert_eq!(encode(b"", config), Err(EncodeError::InvalidInput));
+        assert_eq!(decode(&[0; 4], config), Err(DecodeError::InvalidInput));
+    }
+    assert_eq!(
+        encode(&[4], Configuration::gif(2)),
+        Err(EncodeError::InvalidInput)
+    );
+
+    let input = encode(&pixels(1000, 4), Configuration::gif(2)).unwrap();
+    for len in 0..input.len() - 1 {
+        assert_eq!(
+            decode(&input[..len], Configuration::gif(2)),
+            Err(DecodeError::UnexpectedEof)
+        );
+    }
+
+    // Clear, a code not yet in the dictionary
+    assert_eq!(
+        decode(&[0x34, 0x00], Configuration::gif(2)),
+        Err(DecodeError::InvalidData)
+    );
+    // Clear, 0, a code after the next one
+    assert_eq!(
+        decode(&[0xc4, 0x01], Configuration::gif(2)),
+        Err(DecodeError::InvalidData)
+    );
+}
@@ -31,6 +31,14 @@ pub enum BitSize {
     Bit22,
     Bit23,
     Bit24,
+    Bit25,
+    Bit26,
+    Bit27,
+    Bit28,
+    Bit29,
+    Bit30,
+    Bit31,
+    Bit32,
 }
 
 impl BitSize {
@@ -40,8 +48,7 @@ impl BitSize {
 
     pub const OCTET: Self = Self::Bit8;
 
-    /// Currently maximum size
-    pub const MAX: Self = Self::Bit24;
+    pub const MAX: Self = Self::Bit32;
 
     #[inline]
     pub const fn as_usize(&self) -> usize {
@@ -85,6 +92,14 @@ impl BitSize {
             22 => Some(Self::Bit22),
             23 => Some(Self::Bit23),
             24 => Some(Self::Bit24),
+            25 => Some(Self::Bit25),
+            26 => Some(Self::Bit26),
+            27 => Some(Self::Bit27),
+            28 => Some(Self::Bit28),
+            29 => Some(Self::Bit29),
+            30 => Some(Self::Bit30),
+            31 => Some(Self::Bit31),
+            32 => Some(Self::Bit32),
             _ => None,
         }
     }
@@ -99,14 +114,22 @@ impl BitSize {
 
     #[inline]
     pub const fn mask(&self) -> u32 {
-        (1 << *self as usize) - 1
+        u32::MAX >> (32 - *self as usize)
     }
 
+    /// # Panics
+    ///
+    /// Panics if `self` is [`BitSize::Bit32`], use [`BitSize::power_of_two_u64`] instead.
     #[inline]
     pub const fn power_of_two(&self) -> u32 {
         1 << *self as usize
     }
 
+    #[inline]
+    pub const fn power_of_two_u64(&self) -> u64 {
+        1 << *self as usize
+    }
+
     #[inline]
     pub const fn checked_add(self, other: Self) -> Option<Self> {
         Self::new(self.as_u8() + other.as_u8())
@@ -144,6 +167,7 @@ pub const fn nearest_power_of_two(value: usize) -> usize {
     if value >= threshold { next } else { next >> 1 }
 }
 
+#[derive(Clone)]
 pub struct BitStreamWriter {
     buf: Vec<u8>,
     acc: u8,
@@ -199,8 +223,7 @@ impl BitStreamWriter {
             self.bit_position = 0;
 
             if remain_bits > 0 {
-                let value_mask = (1u32 << value.size().as_usize()) - 1;
-                let mut acc32 = (value.value() & value_mask) >> lowest_bits;
+                let mut acc32 = (value.value() & value.size().mask()) >> lowest_bits;
                 while remain_bits >= 8 {
                     self.buf.push(acc32 as u8);
                     acc32 >>= 8;
@@ -246,52 +269,127 @@ pub trait Write<T> {
     fn write(&mut self, value: T);
 }
 
-impl Write<bool> for BitStreamWriter {
+macro_rules! impl_write {
+    ($writer:ty) => {
+        impl Write<bool> for $writer {
+            #[inline]
+            fn write(&mut self, value: bool) {
+                self.push_bool(value);
+            }
+        }
+
+        impl Write<Nibble> for $writer {
+            #[inline]
+            fn write(&mut self, value: Nibble) {
+                self.push_nibble(value);
+            }
+        }
+
+        impl Write<u8> for $writer {
+            #[inline]
+            fn write(&mut self, value: u8) {
+                self.push_byte(value);
+            }
+        }
+
+        impl Write<&[u8]> for $writer {
+            #[inline]
+            fn write(&mut self, value: &[u8]) {
+                for &byte in value.iter() {
+                    self.push_byte(byte);
+                }
+            }
+        }
+
+        impl Write<VarLenInteger> for $writer {
+            #[inline]
+            fn write(&mut self, value: VarLenInteger) {
+                self.push(value);
+            }
+        }
+
+        impl Write<&[VarLenInteger]> for $writer {
+            #[inline]
+            fn write(&mut self, value: &[VarLenInteger]) {
+                self.push_slice(value);
+            }
+        }
+    };
+}
+
+impl_write!(BitStreamWriter);
+impl_write!(MsbBitStreamWriter);
+
+/// Writer of the bits wider than [`VarLenInteger::MAX_SIZE`], which is implemented for both bit orders
+pub trait WriteBits: Write<VarLenInteger> {
+    /// Writes the lowest `bits` bits of the value.
+    fn write_bits(&mut self, bits: BitSize, value: u32);
+}
+
+impl WriteBits for BitStreamWriter {
     #[inline]
-    fn write(&mut self, value: bool) {
-        self.push_bool(value);
+    fn write_bits(&mut self, bits: BitSize, value: u32) {
+        if bits > VarLenInteger::MAX_SIZE {
+            // Safety: `bits` is greater than 24, so the upper part is 9 to 16 bits.
+            let high = unsafe { BitSize::new_unchecked(bits.as_u8() - 16) };
+            self.push(VarLenInteger::new_truncated(BitSize::Bit16, value));
+            self.push(VarLenInteger::new_truncated(high, value >> 16));
+        } else {
+            self.push(VarLenInteger::new_truncated(bits, value));
+        }
     }
 }
 
-impl Write<Nibble> for BitStreamWriter {
+impl WriteBits for MsbBitStreamWriter {
     #[inline]
-    fn write(&mut self, value: Nibble) {
-        self.push_nibble(value);
+    fn write_bits(&mut self, bits: BitSize, value: u32) {
+        if bits > VarLenInteger::MAX_SIZE {
+            // Safety: `bits` is greater than 24, so the upper part is 9 to 16 bits.
+            let high = unsafe { BitSize::new_unchecked(bits.as_u8() - 16) };
+            self.push(VarLenInteger::new_truncated(high, value >> 16));
+            self.push(VarLenInteger::new_truncated(BitSize::Bit16, value));
+        } else {
+            self.push(VarLenInteger::new_truncated(bits, value));
+        }
     }
 }
 
-impl Write<u8> for BitStreamWriter {
+/// Reader of the bits, which is implemented for both bit orders
+pub trait ReadBits {
+    fn read_bits(&mut self, bits: BitSize) -> Option<u32>;
+
+    fn read_bool(&mut self) -> Option<bool>;
+}
+
+impl ReadBits for BitStreamReader<'_> {
     #[inline]
-    fn write(&mut self, value: u8) {
-        self.push_byte(value);
+    fn read_bits(&mut self, bits: BitSize) -> Option<u32> {
+        BitStreamReader::read_bits(self, bits)
     }
-}
 
-impl Write<&[u8]> for BitStreamWriter {
     #[inline]
-    fn write(&mut self, value: &[u8]) {
-        for &byte in value.iter() {
-            self.push_byte(byte);
-        }
+    fn read_bool(&mut self) -> Option<bool> {
+        BitStreamReader::read_bool(self)
     }
 }
 
-impl Write<VarLenInteger> for BitStreamWriter {
+impl ReadBits for MsbBitStreamReader<'_> {
     #[inline]
-    fn write(&mut self, value: VarLenInteger) {
-        self.push(value);
+    fn read_bits(&mut self, bits: BitSize) -> Option<u32> {
+        MsbBitStreamReader::read_bits(self, bits)
     }
-}
 
-impl Write<&[VarLenInteger]> for BitStreamWriter {
     #[inline]
-    fn write(&mut self, value: &[VarLenInteger]) {
-        self.push_slice(value);
+    fn read_bool(&mut self) -> Option<bool> {
+        MsbBitStreamReader::read_bool(self)
     }
 }
 
 type AccRepr = usize;
 
+/// The accumulator of the MSB-first reader, which holds a whole 32-bit read on any target
+type MsbAccRepr = u64;
+
 #[repr(C)]
 pub struct BitStreamReader<'a> {
     acc: AccRepr,
@@ -487,6 +585,278 @@ impl Iterator for BitStreamReader<'_> {
     }
 }
 
+/// Bit stream writer in MSB-first order, as JPEG, bzip2 and TIFF LZW
+#[derive(Clone)]
+pub struct MsbBitStreamWriter {
+    buf: Vec<u8>,
+    acc: u8,
+    bit_position: u8,
+}
+
+impl MsbBitStreamWriter {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            acc: 0,
+            bit_position: 0,
+        }
+    }
+
+    /// Creates a writer that appends to the existing bytes.
+    #[inline]
+    pub const fn with_bytes(buf: Vec<u8>) -> Self {
+        Self {
+            buf,
+            acc: 0,
+            bit_position: 0,
+        }
+    }
+
+    #[inline]
+    pub fn bit_count(&self) -> usize {
+        self.buf.len() * 8 + self.bit_position as usize
+    }
+
+    #[inline]
+    pub fn push_bool(&mut self, value: bool) {
+        self.push(VarLenInteger::with_bool(value));
+    }
+
+    #[inline]
+    pub fn push_byte(&mut self, value: u8) {
+        self.push(VarLenInteger::with_byte(value))
+    }
+
+    #[inline]
+    pub fn push_nibble(&mut self, value: Nibble) {
+        self.push(VarLenInteger::with_nibble(value))
+    }
+
+    #[inline]
+    pub fn push_slice(&mut self, value: &[VarLenInteger]) {
+        for &item in value.iter() {
+            self.push(item);
+        }
+    }
+
+    /// Pushes the value with its highest bit first.
+    pub fn push(&mut self, value: VarLenInteger) {
+        let size = value.size().as_u8() + self.bit_position;
+        let mut acc =
+            ((self.acc as u64) << value.size().as_usize()) | value.canonical_value() as u64;
+        let mut remain_bits = size;
+        while remain_bits >= 8 {
+            remain_bits -= 8;
+            self.buf.push((acc >> remain_bits) as u8);
+        }
+        acc &= (1 << remain_bits) - 1;
+        self.acc = acc as u8;
+        self.bit_position = remain_bits;
+    }
+
+    /// Pads the last byte with zeros.
+    #[inline]
+    pub fn skip_to_next_byte_boundary(&mut self) {
+        if self.bit_position > 0 {
+            self.buf.push(self.acc << (8 - self.bit_position));
+            self.acc = 0;
+            self.bit_position = 0;
+        }
+    }
+
+    #[inline]
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.skip_to_next_byte_boundary();
+        self.buf.extend_from_slice(bytes);
+    }
+
+    #[inline]
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.skip_to_next_byte_boundary();
+        self.buf
+    }
+}
+
+impl Default for MsbBitStreamWriter {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bit stream reader in MSB-first order
+///
+/// The accumulator holds the unread bits from its highest bit.
+pub struct MsbBitStreamReader<'a> {
+    acc: MsbAccRepr,
+    left: usize,
+    slice: &'a [u8],
+}
+
+impl<'a> MsbBitStreamReader<'a> {
+    #[inline]
+    pub fn new(slice: &'a [u8]) -> Self {
+        Self {
+            slice,
+            left: 0,
+            acc: 0,
+        }
+    }
+
+    #[inline]
+    fn _iter_next(&mut self) -> Option<u8> {
+        let (left, right) = self.slice.split_first()?;
+        self.slice = right;
+        Some(*left)
+    }
+
+    /// Fills the accumulator up to `bits` bits.
+    #[inline]
+    fn _fill(&mut self, bits: usize) -> Option<()> {
+        while self.left < bits {
+            let byte = self._iter_next()?;
+            self.acc |= (byte as MsbAccRepr) << (MsbAccRepr::BITS as usize - 8 - self.left);
+            self.left += 8;
+        }
+        Some(())
+    }
+
+    #[inline]
+    fn _peek(&self, bits: usize) -> u32 {
+        (self.acc >> (MsbAccRepr::BITS as usize - bits)) as u32
+    }
+
+    #[inline]
+    pub fn advance(&mut self, bits: BitSize) -> Option<()> {
+        self._fill(bits.as_usize())?;
+        unsafe {
+            // Safety: The value is checked
+            self._advance(bits.as_usize());
+        }
+        Some(())
+    }
+
+    /// # SAFETY
+    ///
+    /// The `bits` must be less than or equal to `self.left`. Otherwise, UB
+    #[inline]
+    pub unsafe fn _advance(&mut self, bits: usize) {
+        self.acc <<= bits;
+        self.left -= bits;
+    }
+
+    #[inline]
+    pub fn read_bool(&mut self) -> Option<bool> {
+        self.read_bits(BitSize::Bit1).map(|v| v != 0)
+    }
+
+    #[inline]
+    pub fn read_nibble(&mut self) -> Option<Nibble> {
+        self.read_bits(BitSize::NIBBLE)
+            .and_then(|v| Nibble::new(v as u8))
+    }
+
+    #[inline]
+    pub fn read_byte(&mut self) -> Option<u8> {
+        self.read_bits(BitSize::BYTE).map(|v| v as u8)
+    }
+
+    #[inline]
+    pub fn read_bits(&mut self, bits: BitSize) -> Option<u32> {
+        self._fill(bits.as_usize())?;
+        let result = self._peek(bits.as_usize());
+        unsafe {
+            // Safety: The value is checked
+            self._advance(bits.as_usize());
+        }
+        Some(result)
+    }
+
+    #[inline]
+    pub fn peek_bits(&mut self, bits: BitSize) -> Option<u32> {
+        if bits.as_usize() > self.left {
+            // Reads ahead as much as possible, since the following bits are likely to be read soon.
+            let _ = self._fill(MsbAccRepr::BITS as usize - 8);
+            if bits.as_usize() > self.left {
+                return None;
+            }
+        }
+        Some(self._peek(bits.as_usize()))
+    }
+
+    #[inline]
+    pub fn skip_to_next_byte_boundary(&mut self) {
+        if self.left & 7 != 0 {
+            unsafe {
+                // Safety: The value is checked
+                self._advance(self.left & 7);
+            }
+        }
+    }
+
+    /// Skip to the next byte boundary and read the next byte
+    #[inline]
+    pub fn read_next_byte(&mut self) -> Option<u8> {
+        self.skip_to_next_byte_boundary();
+        self.read_byte()
+    }
+
+    /// Skip to the next byte boundary and read the specified number of bytes
+    #[inline]
+    pub fn read_next_bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.skip_to_next_byte_boundary();
+        let mut result = [0; N];
+        for p in result.iter_mut() {
+            *p = self.read_byte()?;
+        }
+        Some(result)
+    }
+
+    /// Skips to the next byte boundary and returns a slice with the specified number of bytes
+    #[inline]
+    pub fn read_next_bytes_slice(&mut self, size: usize) -> Option<&'a [u8]> {
+        self._rewind();
+        let (left, right) = self.slice.split_at_checked(size)?;
+        self.slice = right;
+        Some(left)
+    }
+
+    /// Skips to the next byte boundary and returns the rest of the input.
+    #[inline]
+    pub fn into_rest(mut self) -> &'a [u8] {
+        self._rewind();
+        self.slice
+    }
+
+    /// Skips to the next byte boundary and puts the bytes in the accumulator back to the slice.
+    #[inline]
+    fn _rewind(&mut self) {
+        self.skip_to_next_byte_boundary();
+        if self.left > 0 {
+            let rewind = self.left / 8;
+            self.acc = 0;
+            self.left = 0;
+            self.slice = unsafe {
+                // Safety: The bytes in the accumulator are taken from just before the slice.
+                core::slice::from_raw_parts(
+                    self.slice.as_ptr().sub(rewind),
+                    self.slice.len() + rewind,
+                )
+            }
+        }
+    }
+}
+
+impl Iterator for MsbBitStreamReader<'_> {
+    type Item = bool;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_bool()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -575,6 +945,73 @@ mod tests {
         }
     }
 
+    #[test]
+    fn msb_bit_test() {
+        let mut writer = MsbBitStreamWriter::new();
+        writer.push(VarLenInteger::new(BitSize::Bit3, 0b101));
+        writer.push(VarLenInteger::new(BitSize::Bit6, 0b000111));
+        writer.write_bits(BitSize::Bit32, 0x1234_5678);
+        assert_eq!(writer.bit_count(), 41);
+        assert_eq!(
+            writer.into_bytes(),
+            [0b1010_0011, 0b1000_1001, 0x1a, 0x2b, 0x3c, 0x00]
+        );
+
+        let tail = b"Lorem ipsum";
+        for padding_size in 1..=16 {
+            let padding_mask = BitSize::new(padding_size).unwrap().mask();
+            for value_size in 1..=32 {
+                let mask = BitSize::new(value_size).unwrap().mask();
+                for pattern in [
+                    0x0u32,
+                    u32::MAX,
+                    0x5A5A5A5A,
+                    0x0F0F0F0F,
+                    1234578,
+                    0xEDB88320,
+                ] {
+                    let padding_size = BitSize::new(padding_size).unwrap();
+                    let value_size = BitSize::new(value_size).unwrap();
+                    let pattern_n = !pattern & mask;
+
+                    let mut writer = MsbBitStreamWriter::new();
+                    writer.push(VarLenInteger::new_checked(padding_size, 0).unwrap());
+                    writer.write_bits(value_size, pattern);
+                    writer.push(VarLenInteger::new_truncated(padding_size, u32::MAX));
+                    writer.write_bits(value_size, pattern_n);
+                    writer.push(VarLenInteger::with_bool(true));
+                    writer.extend_from_slice(tail);
+                    let stream = writer.into_bytes();
+
+                    // test for read_bits
+                    let mut reader = MsbBitStreamReader::new(&stream);
+                    assert_eq!(reader.read_bits(padding_size).unwrap(), 0);
+                    assert_eq!(reader.read_bits(value_size).unwrap(), pattern & mask);
+                    assert_eq!(reader.read_bits(padding_size).unwrap(), padding_mask);
+                    assert_eq!(reader.read_bits(value_size).unwrap(), pattern_n & mask);
+                    assert!(reader.read_bool().unwrap());
+                    assert_eq!(reader.read_next_bytes_slice(tail.len()).unwrap(), tail);
+                    assert_eq!(reader.read_bool(), None);
+
+                    // test for peek_bits + advance
+                    let mut reader = MsbBitStreamReader::new(&stream);
+                    assert_eq!(reader.peek_bits(padding_size).unwrap(), 0);
+                    reader.advance(padding_size).unwrap();
+                    assert_eq!(reader.peek_bits(value_size).unwrap(), pattern & mask);
+                    reader.advance(value_size).unwrap();
+                    assert_eq!(reader.peek_bits(padding_size).unwrap(), padding_mask);
+                    reader.advance(padding_size).unwrap();
+                    assert_eq!(reader.peek_bits(value_size).unwrap(), pattern_n & mask);
+                    reader.advance(value_size).unwrap();
+                    assert_eq!(reader.peek_bits(BitSize::Bit1).unwrap(), 1);
+                    reader.advance(BitSize::Bit1).unwrap();
+                    assert_eq!(reader.read_next_bytes::<5>().unwrap(), tail[..5]);
+                    assert_eq!(reader.into_rest(), &tail[5..]);
+                }
+            }
+        }
+    }
+
     #[test]
     fn nearest() {
         for (value, expected) in [
@@ -604,11 +1041,14 @@ mod tests {
 
     #[test]
     fn bit_mask() {
-        for i in 1..=24 {
-            let mask = (1u32 << i) - 1;
+        for i in 1..=32 {
+            let mask = ((1u64 << i) - 1) as u32;
             assert_eq!(mask, BitSize::new(i).unwrap().mask());
-            let shifted = 1 << i;
-            assert_eq!(shifted, BitSize::new(i).unwrap().power_of_two());
+            let shifted = 1u64 << i;
+            assert_eq!(shifted, BitSize::new(i).unwrap().power_of_two_u64());
+            if i < 32 {
+                assert_eq!(shifted as u32, BitSize::new(i).unwrap().power_of_two());
+            }
         }
     }
 }
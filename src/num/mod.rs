@@ -2,5 +2,7 @@ mod nibble;
 pub use nibble::*;
 pub mod bits;
 pub mod math;
+pub mod universal;
+pub mod varint;
 mod vl_integer;
 pub use vl_integer::*;
@@ -0,0 +1,482 @@
+//! Universal codes of integers
+//!
+//! The codes start with a unary prefix of zeros terminated by a one, followed by binary fields.
+//! In an MSB-first stream the bits are the same as the textbook codes. In an LSB-first stream each
+//! field is packed from its lowest bit, as the extra bits of deflate.
+//!
+//! | Value | Elias gamma | Elias delta | Exp-Golomb (k = 0) | Rice (k = 2) |
+//! |------:|-------------|-------------|--------------------|--------------|
+//! |     0 |             |             | `1`                | `1 00`       |
+//! |     1 | `1`         | `1`         | `01 0`             | `1 01`       |
+//! |     2 | `01 0`      | `010 0`     | `01 1`             | `1 10`       |
+//! |     5 | `001 01`    | `011 01`    | `001 10`           | `01 01`      |
+
+use super::VarLenInteger;
+use super::bits::{BitSize, ReadBits, WriteBits};
+use core::num::NonZeroU32;
+
+/// Writes the lowest `bits` bits of the value, where `bits` is 0 to 32.
+#[inline]
+fn write_bits<W: WriteBits>(writer: &mut W, bits: u32, value: u32) {
+    if let Some(size) = BitSize::new(bits as u8) {
+        writer.write_bits(size, value);
+    }
+}
+
+/// Reads `bits` bits, where `bits` is 0 to 32.
+#[inline]
+fn read_bits<R: ReadBits>(reader: &mut R, bits: u32) -> Option<u32> {
+    match BitSize::new(bits as u8) {
+        Some(size) => reader.read_bits(size),
+        None => Some(0),
+    }
+}
+
+/// Writes `value` zeros followed by a one.
+#[inline]
+fn write_unary<W: WriteBits>(writer: &mut W, mut value: u32) {
+    while value >= u32::BITS {
+        write_bits(writer, u32::BITS, 0);
+        value -= u32::BITS;
+    }
+    write_bits(writer, value, 0);
+    writer.write(VarLenInteger::with_bool(true));
+}
+
+/// Reads the zeros up to a one, and returns `None` if there are more than `max` zeros.
+#[inline]
+fn read_unary<R: ReadBits>(reader: &mut R, max: u32) -> Option<u32> {
+    let mut value = 0;
+    while !reader.read_bool()? {
+        if value == max {
+            return None;
+        }
+        value += 1;
+    }
+    Some(value)
+}
+
+/// Elias gamma code, the bit length in unary followed by the bits below the highest one
+pub struct EliasGamma;
+
+impl EliasGamma {
+    /// Returns the number of bits of the code.
+    #[inline]
+    pub const fn bit_len(value: NonZeroU32) -> usize {
+        value.ilog2() as usize * 2 + 1
+    }
+
+    #[inline]
+    pub fn write<W: WriteBits>(writer: &mut W, value: NonZeroU32) {
+        let bits = value.ilog2();
+        write_unary(writer, bits);
+        write_bits(writer, bits, value.get());
+    }
+
+    #[inline]
+    pub fn read<R: ReadBits>(reader: &mut R) -> Option<NonZeroU32> {
+        let bits = read_unary(reader, u32::BITS - 1)?;
+        let value = read_bits(reader, bits)?;
+        NonZeroU32::new((1 << bits) | value)
+    }
+}
+
+/// Elias delta code, the bit length in Elias gamma followed by the bits below the highest one
+pub struct EliasDelta;
+
+impl EliasDelta {
+    /// Returns the number of bits of the code.
+    #[inline]
+    pub const fn bit_len(value: NonZeroU32) -> usize {
+        let bits = value.ilog2();
+        (bits + 1).ilog2() as usize * 2 + 1 + bits as usize
+    }
+
+    #[inline]
+    pub fn write<W: WriteBits>(writer: &mut W, value: NonZeroU32) {
+        let bits = value.ilog2();
+        EliasGamma::write(writer, NonZeroU32::MIN.saturating_add(bits));
+        write_bits(writer, bits, value.get());
+    }
+
+    #[inline]
+    pub fn read<R: ReadBits>(reader: &mut R) -> Option<NonZeroU32> {
+        let bits = EliasGamma::read(reader)?.get() - 1;
+        if bits >= u32::BITS {
+            return None;
+        }
+        let value = read_bits(reader, bits)?;
+        NonZeroU32::new((1 << bits) | value)
+    }
+}
+
+/// Golomb code, the quotient by the divisor in unary followed by the remainder in truncated binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Golomb {
+    divisor: u32,
+    /// Number of bits of the larger remainders
+    bits: u32,
+    /// The remainders below the cutoff are written in one bit less.
+    cutoff: u32,
+}
+
+impl Golomb {
+    /// Returns `None` if the divisor is zero.
+    #[inline]
+    pub const fn new(divisor: u32) -> Option<Self> {
+        if divisor == 0 {
+            return None;
+        }
+        let bits = u32::BITS - (divisor - 1).leading_zeros();
+        let cutoff = ((1u64 << bits) - divisor as u64) as u32;
+        Some(Self {
+            divisor,
+            bits,
+            cutoff,
+        })
+    }
+
+    #[inline]
+    pub const fn divisor(&self) -> u32 {
+        self.divisor
+    }
+
+    /// Returns the number of bits of the code.
+    #[inline]
+    pub const fn bit_len(&self, value: u32) -> usize {
+        let quotient = (value / self.divisor) as usize;
+        let remainder = value % self.divisor;
+        if remainder < self.cutoff {
+            quotient + self.bits as usize
+        } else {
+            quotient + self.bits as usize + 1
+        }
+    }
+
+    #[inline]
+    pub fn write<W: WriteBits>(&self, writer: &mut W, value: u32) {
+        let remainder = value % self.divisor;
+        write_unary(writer, value / self.divisor);
+        if remainder < self.cutoff {
+            write_bits(writer, self.bits - 1, remainder);
+        } else if self.cutoff == 0 {
+            write_bits(writer, self.bits, remainder);
+        } else {
+            // The lowest bit follows, so that the reader can tell the shorter remainders first.
+            let value = remainder + self.cutoff;
+            write_bits(writer, self.bits - 1, value >> 1);
+            write_bits(writer, 1, value);
+        }
+    }
+
+    #[inline]
+    pub fn read<R: ReadBits>(&self, reader: &mut R) -> Option<u32> {
+        let quotient = read_unary(reader, u32::MAX / self.divisor)?;
+        let remainder = if self.cutoff == 0 {
+            read_bits(reader, self.bits)?
+        } else {
+            let value = read_bits(reader, self.bits - 1)?;
+            if value < self.cutoff {
+                value
+            } else {
+                ((value << 1) | read_bits(reader, 1)?) - self.cutoff
+            }
+        };
+        (quotient * self.divisor).checked_add(remainder)
+    }
+}
+
+/// Rice code, the Golomb code of the divisor `1 << k`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rice {
+    k: u8,
+}
+
+impl Rice {
+    /// Maximum parameter
+    pub const MAX_K: u8 = 31;
+
+    /// Returns `None` if `k` is greater than [`Self::MAX_K`].
+    #[inline]
+    pub const fn new(k: u8) -> Option<Self> {
+        if k <= Self::MAX_K {
+            Some(Self { k })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the parameter that codes the values in the fewest bits.
+    pub fn optimal(values: &[u32]) -> Self {
+        let mut best = Self { k: 0 };
+        let mut best_len = u64::MAX;
+        for k in 0..=Self::MAX_K {
+            let quotients = values.iter().fold(0, |a, &v| a + (v >> k) as u64);
+            let len = quotients + values.len() as u64 * (k as u64 + 1);
+            if len < best_len {
+                best = Self { k };
+                best_len = len;
+            }
+        }
+        best
+    }
+
+    #[inline]
+    pub const fn k(&self) -> u8 {
+        self.k
+    }
+
+    /// Returns the number of bits of the code.
+    #[inline]
+    pub const fn bit_len(&self, value: u32) -> usize {
+        (value >> self.k) as usize + 1 + self.k as usize
+    }
+
+    #[inline]
+    pub fn write<W: WriteBits>(&self, writer: &mut W, value: u32) {
+        write_unary(writer, value >> self.k);
+        write_bits(writer, self.k as u32, value);
+    }
+
+    #[inline]
+    pub fn read<R: ReadBits>(&self, reader: &mut R) -> Option<u32> {
+        let quotient = read_unary(reader, u32::MAX >> self.k)?;
+        let remainder = read_bits(reader, self.k as u32)?;
+        Some((quotient << self.k) | remainder)
+    }
+}
+
+/// Exponential-Golomb code of order `k`, the Elias gamma code of `value + (1 << k)` without the
+/// first `k` zeros
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpGolomb {
+    k: u8,
+}
+
+impl ExpGolomb {
+    /// Maximum order
+    pub const MAX_K: u8 = 31;
+
+    /// Returns `None` if `k` is greater than [`Self::MAX_K`].
+    #[inline]
+    pub const fn new(k: u8) -> Option<Self> {
+        if k <= Self::MAX_K {
+            Some(Self { k })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub const fn k(&self) -> u8 {
+        self.k
+    }
+
+    /// Returns the number of bits of the code.
+    #[inline]
+    pub const fn bit_len(&self, value: u32) -> usize {
+        let bits = (value as u64 + (1 << self.k)).ilog2() as usize;
+        bits * 2 + 1 - self.k as usize
+    }
+
+    #[inline]
+    pub fn write<W: WriteBits>(&self, writer: &mut W, value: u32) {
+        let value = value as u64 + (1 << self.k);
+        let bits = value.ilog2();
+        write_unary(writer, bits - self.k as u32);
+        write_bits(writer, bits, value as u32);
+    }
+
+    #[inline]
+    pub fn read<R: ReadBits>(&self, reader: &mut R) -> Option<u32> {
+        let bits = read_unary(reader, u32::BITS - self.k as u32)? + self.k as u32;
+        let value = (1u64 << bits) | read_bits(reader, bits)? as u64;
+        u32::try_from(value - (1 << self.k)).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::num::bits::{
+        BitStreamReader, BitStreamWriter, MsbBitStreamReader, MsbBitStreamWriter,
+    };
+    use crate::*;
+
+    /// Returns the bits written in MSB-first order as a string.
+    fn bit_string(f: impl FnOnce(&mut MsbBitStreamWriter)) -> String {
+        let mut writer = MsbBitStreamWriter::new();
+        f(&mut writer);
+        let len = writer.bit_count();
+        let bytes = writer.into_bytes();
+        (0..len)
+            .map(|i| {
+                if bytes[i / 8] & (0x80 >> (i % 8)) != 0 {
+                    '1'
+                } else {
+                    '0'
+                }
+            })
+            .collect()
+    }
+
+    fn values() -> Vec<u32> {
+        let mut values = (0..=300).collect::<Vec<_>>();
+        for bits in 9..=32 {
+            let max = u32::MAX >> (32 - bits);
+            values.extend_from_slice(&[max / 3, max - 1, max]);
+        }
+        values
+    }
+
+    #[test]
+    fn textbook() {
+        let nz = |v| NonZeroU32::new(v).unwrap();
+        for (value, gamma, delta) in [
+            (1, "1", "1"),
+            (2, "010", "0100"),
+            (5, "00101", "01101"),
+            (10, "0001010", "00100010"),
+            (17, "000010001", "001010001"),
+        ] {
+            assert_eq!(bit_string(|w| EliasGamma::write(w, nz(value))), gamma);
+            assert_eq!(bit_string(|w| EliasDelta::write(w, nz(value))), delta);
+        }
+
+        let exp_golomb = ExpGolomb::new(0).unwrap();
+        let exp_golomb2 = ExpGolomb::new(2).unwrap();
+        let golomb = Golomb::new(10).unwrap();
+        let rice = Rice::new(2).unwrap();
+        for (value, exp, exp2, golomb10, rice2) in [
+            (0, "1", "100", "1000", "100"),
+            (1, "010", "101", "1001", "101"),
+            (2, "011", "110", "1010", "110"),
+            (3, "00100", "111", "1011", "111"),
+            (5, "00110", "01001", "1101", "0101"),
+            (6, "00111", "01010", "11100", "0110"),
+            (9, "0001010", "01101", "11111", "00101"),
+            (10, "0001011", "01110", "01000", "00110"),
+        ] {
+            assert_eq!(bit_string(|w| exp_golomb.write(w, value)), exp);
+            assert_eq!(bit_string(|w| exp_golomb2.write(w, value)), exp2);
+            assert_eq!(bit_string(|w| golomb.write(w, value)), golomb10);
+            assert_eq!(bit_string(|w| rice.write(w, value)), rice2);
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let values = values();
+        let golombs = [1, 2, 3, 5, 10, 64, 1000, 65537, u32::MAX].map(|v| Golomb::new(v).unwrap());
+        let rices = [0, 1, 4, 12, 31].map(|v| Rice::new(v).unwrap());
+        let exp_golombs = [0, 1, 5, 31].map(|v| ExpGolomb::new(v).unwrap());
+
+        let mut lsb = BitStreamWriter::new();
+        let mut msb = MsbBitStreamWriter::new();
+        let mut bit_len = 0;
+        for &value in values.iter() {
+            // The long unary codes are tested with the small quotients only.
+            if let Some(value) = NonZeroU32::new(value) {
+                EliasGamma::write(&mut lsb, value);
+                EliasGamma::write(&mut msb, value);
+                EliasDelta::write(&mut lsb, value);
+                EliasDelta::write(&mut msb, value);
+                bit_len += EliasGamma::bit_len(value) + EliasDelta::bit_len(value);
+            }
+            for golomb in golombs.iter().filter(|v| value / v.divisor() < 5000) {
+                golomb.write(&mut lsb, value);
+                golomb.write(&mut msb, value);
+                bit_len += golomb.bit_len(value);
+            }
+            for rice in rices.iter().filter(|v| value >> v.k() < 5000) {
+                rice.write(&mut lsb, value);
+                rice.write(&mut msb, value);
+                bit_len += rice.bit_len(value);
+            }
+            for exp_golomb in exp_golombs.iter() {
+                exp_golomb.write(&mut lsb, value);
+                exp_golomb.write(&mut msb, value);
+                bit_len += exp_golomb.bit_len(value);
+            }
+            assert_eq!(lsb.bit_count(), bit_len);
+            assert_eq!(msb.bit_count(), bit_len);
+        }
+        let lsb = lsb.into_bytes();
+        let msb = msb.into_bytes();
+
+        fn check<R: ReadBits>(
+            reader: &mut R,
+            values: &[u32],
+            golombs: &[Golomb],
+            rices: &[Rice],
+            exp_golombs: &[ExpGolomb],
+        ) {
+            for &value in values.iter() {
+                if let Some(value) = NonZeroU32::new(value) {
+                    assert_eq!(EliasGamma::read(reader), Some(value));
+                    assert_eq!(EliasDelta::read(reader), Some(value));
+                }
+                for golomb in golombs.iter().filter(|v| value / v.divisor() < 5000) {
+                    assert_eq!(golomb.read(reader), Some(value));
+                }
+                for rice in rices.iter().filter(|v| value >> v.k() < 5000) {
+                    assert_eq!(rice.read(reader), Some(value));
+                }
+                for exp_golomb in exp_golombs.iter() {
+                    assert_eq!(exp_golomb.read(reader), Some(value));
+                }
+            }
+        }
+        check(
+            &mut BitStreamReader::new(&lsb),
+            &values,
+            &golombs,
+            &rices,
+            &exp_golombs,
+        );
+        check(
+            &mut MsbBitStreamReader::new(&msb),
+            &values,
+            &golombs,
+            &rices,
+            &exp_golombs,
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        // Too many zeros for a 32-bit value
+        let zeros = [0u8; 16];
+        assert_eq!(EliasGamma::read(&mut MsbBitStreamReader::new(&zeros)), None);
+        assert_eq!(EliasDelta::read(&mut MsbBitStreamReader::new(&zeros)), None);
+        let reader = &mut MsbBitStreamReader::new(&[0, 0, 0, 0, 0x40, 0, 0, 0, 0]);
+        assert_eq!(ExpGolomb::new(0).unwrap().read(reader), None);
+        let reader = &mut MsbBitStreamReader::new(&[0x20]);
+        assert_eq!(Rice::new(31).unwrap().read(reader), None);
+
+        // Truncated
+        let reader = &mut MsbBitStreamReader::new(&[0x01]);
+        assert_eq!(EliasGamma::read(reader), None);
+        let reader = &mut MsbBitStreamReader::new(&[0x00]);
+        assert_eq!(Golomb::new(3).unwrap().read(reader), None);
+
+        assert_eq!(Golomb::new(0), None);
+        assert_eq!(Rice::new(32), None);
+        assert_eq!(ExpGolomb::new(32), None);
+    }
+
+    #[test]
+    fn optimal_rice() {
+        assert_eq!(Rice::optimal(&[]).k(), 0);
+        assert_eq!(Rice::optimal(&[0, 1, 0, 2, 1]).k(), 0);
+        assert_eq!(Rice::optimal(&[1000, 1200, 900, 1100]).k(), 9);
+        assert_eq!(Rice::optimal(&[u32::MAX; 4]).k(), 31);
+
+        let values = [3, 80, 17, 250, 0, 64, 1000, 5, 33, 129];
+        let optimal = Rice::optimal(&values);
+        let len = |rice: Rice| values.iter().map(|&v| rice.bit_len(v)).sum::<usize>();
+        for k in 0..=Rice::MAX_K {
+            assert!(len(optimal) <= len(Rice::new(k).unwrap()));
+        }
+    }
+}
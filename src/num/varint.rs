@@ -0,0 +1,381 @@
+//! Variable-length integers in bytes
+//!
+//! * [`Leb128`]: 7 bits per byte from the lowest, with the continuation flag in the highest bit,
+//!   as protobuf, WebAssembly and DWARF
+//! * [`Sleb128`]: signed LEB128, whose last byte is sign-extended from its bit 6
+//! * [`ZigZagLeb128`]: LEB128 of the ZigZag mapped value, as `sint64` of protobuf
+//! * [`PrefixVarint`]: the number of the trailing zeros of the first byte tells the length,
+//!   so that the reader knows it without looking at the following bytes
+//!
+//! The readers return [`DecodeError::UnexpectedEof`] if the input ends in the middle of a value,
+//! and [`DecodeError::InvalidData`] if the value overflows instead of wrapping it.
+//!
+//! See also [`S7s`](crate::stk1::S7s) for the big-endian format of OSASK.
+
+use crate::transform::ZigZag;
+use crate::*;
+use core::marker::PhantomData;
+
+/// A format of variable-length integers
+pub trait Varint {
+    type Value: Copy;
+
+    /// Maximum number of bytes of a value
+    const MAX_LEN: usize;
+
+    /// Returns the number of bytes the value is written in.
+    fn encoded_len(value: Self::Value) -> usize;
+
+    fn write(output: &mut Vec<u8>, value: Self::Value);
+
+    fn read<'a, T>(iter: &mut T) -> Result<Self::Value, DecodeError>
+    where
+        T: Iterator<Item = &'a u8>;
+
+    /// Reads a value from the head of the slice, and returns it with the number of bytes read.
+    #[inline]
+    fn read_slice(input: &[u8]) -> Result<(Self::Value, usize), DecodeError> {
+        let mut iter = input.iter();
+        let value = Self::read(&mut iter)?;
+        Ok((value, input.len() - iter.len()))
+    }
+}
+
+/// Unsigned LEB128
+///
+/// `624485 -> e5 8e 26`
+pub struct Leb128;
+
+impl Varint for Leb128 {
+    type Value = u64;
+
+    const MAX_LEN: usize = 10;
+
+    #[inline]
+    fn encoded_len(value: u64) -> usize {
+        ((u64::BITS - (value | 1).leading_zeros()) as usize).div_ceil(7)
+    }
+
+    #[inline]
+    fn write(output: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            output.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        output.push(value as u8);
+    }
+
+    #[inline]
+    fn read<'a, T>(iter: &mut T) -> Result<u64, DecodeError>
+    where
+        T: Iterator<Item = &'a u8>,
+    {
+        let mut value = 0;
+        for shift in (0..u64::BITS).step_by(7) {
+            let byte = *iter.next().ok_or(DecodeError::UnexpectedEof)?;
+            let bits = (byte & 0x7f) as u64;
+            // The last byte has only one bit left.
+            if shift == 63 && bits > 1 {
+                return Err(DecodeError::InvalidData);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::InvalidData)
+    }
+}
+
+/// Signed LEB128
+///
+/// `-123456 -> c0 bb 78`
+pub struct Sleb128;
+
+impl Varint for Sleb128 {
+    type Value = i64;
+
+    const MAX_LEN: usize = 10;
+
+    #[inline]
+    fn encoded_len(value: i64) -> usize {
+        let magnitude = if value < 0 { !value } else { value };
+        // One more bit for the sign
+        ((u64::BITS + 1 - magnitude.leading_zeros()) as usize).div_ceil(7)
+    }
+
+    #[inline]
+    fn write(output: &mut Vec<u8>, mut value: i64) {
+        loop {
+            let byte = value as u8 & 0x7f;
+            value >>= 7;
+            let sign = byte & 0x40 != 0;
+            if (value == 0 && !sign) || (value == -1 && sign) {
+                output.push(byte);
+                return;
+            }
+            output.push(byte | 0x80);
+        }
+    }
+
+    #[inline]
+    fn read<'a, T>(iter: &mut T) -> Result<i64, DecodeError>
+    where
+        T: Iterator<Item = &'a u8>,
+    {
+        let mut value = 0u64;
+        for shift in (0..u64::BITS).step_by(7) {
+            let byte = *iter.next().ok_or(DecodeError::UnexpectedEof)?;
+            let bits = (byte & 0x7f) as u64;
+            // The last byte has only the sign bit left, which must be extended to the others.
+            if shift == 63 && bits != 0 && bits != 0x7f {
+                return Err(DecodeError::InvalidData);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                let shift = shift + 7;
+                if shift < u64::BITS && bits & 0x40 != 0 {
+                    value |= u64::MAX << shift;
+                }
+                return Ok(value as i64);
+            }
+        }
+        Err(DecodeError::InvalidData)
+    }
+}
+
+/// Prefix varint of up to 9 bytes in little-endian
+///
+/// A value of `7 * n` bits for `n` up to 8 is shifted left by `n` bits, and its lowest `n` bits are
+/// `n - 1` zeros followed by a one. The first byte of zero is followed by the 8 bytes of the value.
+///
+/// `0 -> 01`, `128 -> 02 02`, `u64::MAX -> 00 ff ff ff ff ff ff ff ff`
+pub struct PrefixVarint;
+
+impl Varint for PrefixVarint {
+    type Value = u64;
+
+    const MAX_LEN: usize = 9;
+
+    #[inline]
+    fn encoded_len(value: u64) -> usize {
+        let bits = (u64::BITS - (value | 1).leading_zeros()) as usize;
+        if bits > 56 { 9 } else { bits.div_ceil(7) }
+    }
+
+    #[inline]
+    fn write(output: &mut Vec<u8>, value: u64) {
+        let len = Self::encoded_len(value);
+        if len == Self::MAX_LEN {
+            output.push(0);
+            output.extend_from_slice(&value.to_le_bytes());
+        } else {
+            let bytes = ((value << len) | (1 << (len - 1))).to_le_bytes();
+            output.extend_from_slice(&bytes[..len]);
+        }
+    }
+
+    #[inline]
+    fn read<'a, T>(iter: &mut T) -> Result<u64, DecodeError>
+    where
+        T: Iterator<Item = &'a u8>,
+    {
+        let first = *iter.next().ok_or(DecodeError::UnexpectedEof)?;
+        let len = first.trailing_zeros() as usize + 1;
+        let mut bytes = [first, 0, 0, 0, 0, 0, 0, 0];
+        let rest = if len == Self::MAX_LEN {
+            &mut bytes[..]
+        } else {
+            &mut bytes[1..len]
+        };
+        for byte in rest.iter_mut() {
+            *byte = *iter.next().ok_or(DecodeError::UnexpectedEof)?;
+        }
+        let value = u64::from_le_bytes(bytes);
+        if len == Self::MAX_LEN {
+            Ok(value)
+        } else {
+            Ok(value >> len)
+        }
+    }
+}
+
+/// Signed integers in the unsigned format of `V` after the [`ZigZag`] mapping
+pub struct ZigZagVarint<V>(PhantomData<V>);
+
+/// `-1 -> 01`, `1 -> 02`, `-2 -> 03`
+pub type ZigZagLeb128 = ZigZagVarint<Leb128>;
+
+pub type ZigZagPrefixVarint = ZigZagVarint<PrefixVarint>;
+
+impl<V: Varint<Value = u64>> Varint for ZigZagVarint<V> {
+    type Value = i64;
+
+    const MAX_LEN: usize = V::MAX_LEN;
+
+    #[inline]
+    fn encoded_len(value: i64) -> usize {
+        V::encoded_len(value.zigzag())
+    }
+
+    #[inline]
+    fn write(output: &mut Vec<u8>, value: i64) {
+        V::write(output, value.zigzag());
+    }
+
+    #[inline]
+    fn read<'a, T>(iter: &mut T) -> Result<i64, DecodeError>
+    where
+        T: Iterator<Item = &'a u8>,
+    {
+        V::read(iter).map(i64::from_zigzag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unsigned_values() -> Vec<u64> {
+        let mut values = (0..=300).collect::<Vec<_>>();
+        for bits in 7..=64 {
+            let max = u64::MAX >> (64 - bits);
+            values.extend_from_slice(&[max / 3, max - 1, max, max.wrapping_add(1)]);
+        }
+        values
+    }
+
+    fn signed_values() -> Vec<i64> {
+        let mut values = (-300..=300).collect::<Vec<_>>();
+        for bits in 6..=63 {
+            let max = i64::MAX >> (63 - bits);
+            values.extend_from_slice(&[
+                max,
+                max.wrapping_add(1),
+                !max,
+                (!max).wrapping_sub(1),
+                max / -3,
+            ]);
+        }
+        values.push(i64::MIN);
+        values
+    }
+
+    fn roundtrip<V: Varint>(values: &[V::Value])
+    where
+        V::Value: PartialEq + core::fmt::Debug,
+    {
+        let mut output = Vec::new();
+        for &value in values.iter() {
+            let start = output.len();
+            V::write(&mut output, value);
+            let len = output.len() - start;
+            assert_eq!(V::encoded_len(value), len, "{value:?}");
+            assert!(len <= V::MAX_LEN);
+            assert_eq!(V::read_slice(&output[start..]), Ok((value, len)));
+            // Truncated
+            for end in start..output.len() {
+                assert_eq!(
+                    V::read_slice(&output[start..end]),
+                    Err(DecodeError::UnexpectedEof)
+                );
+            }
+        }
+        let mut iter = output.iter();
+        for &value in values.iter() {
+            assert_eq!(V::read(&mut iter), Ok(value));
+        }
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn encode_decode() {
+        roundtrip::<Leb128>(&unsigned_values());
+        roundtrip::<PrefixVarint>(&unsigned_values());
+        roundtrip::<Sleb128>(&signed_values());
+        roundtrip::<ZigZagLeb128>(&signed_values());
+        roundtrip::<ZigZagPrefixVarint>(&signed_values());
+    }
+
+    #[test]
+    fn reference() {
+        fn encode<V: Varint>(value: V::Value) -> Vec<u8> {
+            let mut output = Vec::new();
+            V::write(&mut output, value);
+            output
+        }
+
+        assert_eq!(encode::<Leb128>(0), [0x00]);
+        assert_eq!(encode::<Leb128>(150), [0x96, 0x01]);
+        assert_eq!(encode::<Leb128>(624485), [0xe5, 0x8e, 0x26]);
+        assert_eq!(
+            encode::<Leb128>(u64::MAX),
+            [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+
+        assert_eq!(encode::<Sleb128>(2), [0x02]);
+        assert_eq!(encode::<Sleb128>(-2), [0x7e]);
+        assert_eq!(encode::<Sleb128>(63), [0x3f]);
+        assert_eq!(encode::<Sleb128>(64), [0xc0, 0x00]);
+        assert_eq!(encode::<Sleb128>(-64), [0x40]);
+        assert_eq!(encode::<Sleb128>(-123456), [0xc0, 0xbb, 0x78]);
+        assert_eq!(
+            encode::<Sleb128>(i64::MIN),
+            [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f]
+        );
+
+        assert_eq!(encode::<ZigZagLeb128>(0), [0x00]);
+        assert_eq!(encode::<ZigZagLeb128>(-1), [0x01]);
+        assert_eq!(encode::<ZigZagLeb128>(1), [0x02]);
+        assert_eq!(encode::<ZigZagLeb128>(-2), [0x03]);
+        assert_eq!(
+            encode::<ZigZagLeb128>(2147483647),
+            [0xfe, 0xff, 0xff, 0xff, 0x0f]
+        );
+
+        assert_eq!(encode::<PrefixVarint>(0), [0x01]);
+        assert_eq!(encode::<PrefixVarint>(127), [0xff]);
+        assert_eq!(encode::<PrefixVarint>(128), [0x02, 0x02]);
+        assert_eq!(encode::<PrefixVarint>(0x3fff), [0xfe, 0xff]);
+        assert_eq!(encode::<PrefixVarint>(0x4000), [0x04, 0x00, 0x02]);
+        assert_eq!(
+            encode::<PrefixVarint>(u64::MAX >> 8),
+            [0x80, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(
+            encode::<PrefixVarint>(u64::MAX),
+            [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        // Over 64 bits in the last byte
+        let mut input = [0xff; 10];
+        input[9] = 0x02;
+        assert_eq!(Leb128::read_slice(&input), Err(DecodeError::InvalidData));
+        input[9] = 0x01;
+        assert_eq!(Leb128::read_slice(&input), Ok((u64::MAX, 10)));
+        input[9] = 0x00;
+        assert_eq!(Sleb128::read_slice(&input), Ok((i64::MAX, 10)));
+        input[9] = 0x40;
+        assert_eq!(Sleb128::read_slice(&input), Err(DecodeError::InvalidData));
+        input[9] = 0x01;
+        assert_eq!(Sleb128::read_slice(&input), Err(DecodeError::InvalidData));
+
+        // Too long
+        let input = [
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00,
+        ];
+        assert_eq!(Leb128::read_slice(&input), Err(DecodeError::InvalidData));
+        assert_eq!(Sleb128::read_slice(&input), Err(DecodeError::InvalidData));
+        assert_eq!(
+            ZigZagLeb128::read_slice(&input),
+            Err(DecodeError::InvalidData)
+        );
+
+        // Redundant bytes are allowed.
+        assert_eq!(Leb128::read_slice(&[0x81, 0x80, 0x00]), Ok((1, 3)));
+        assert_eq!(PrefixVarint::read_slice(&[0x04, 0x00, 0x00]), Ok((0, 3)));
+    }
+}
@@ -7,11 +7,16 @@ use crate::*;
 use core::fmt;
 
 /// A Variable-length integer
+///
+/// The value holds at most 24 bits, see [`VarLenInteger::MAX_SIZE`].
 #[repr(transparent)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct VarLenInteger(u32);
 
 impl VarLenInteger {
+    /// Maximum size of the value
+    pub const MAX_SIZE: BitSize = BitSize::Bit24;
+
     /// # Safety
     ///
     /// The `value` must fit within the `size`.
@@ -31,7 +36,7 @@ impl VarLenInteger {
 
     #[inline]
     pub const fn new_checked(size: BitSize, value: u32) -> Option<Self> {
-        if value <= size.mask() {
+        if size.as_u8() <= Self::MAX_SIZE.as_u8() && value <= size.mask() {
             // Safety: The value is checked
             Some(unsafe { Self::from_raw_parts(size, value) })
         } else {
@@ -0,0 +1,174 @@
+//! PNG decoder
+
+use super::*;
+use crate::deflate::inflate_in_place;
+use alloc::vec;
+
+/// Decodes a PNG file.
+///
+/// Ancillary chunks other than `tRNS` are ignored.
+pub fn decode(input: &[u8]) -> Result<PngImage, DecodeError> {
+    let chunks = chunks(input)?;
+    let header = match chunks.first() {
+        Some(chunk) if &chunk.chunk_type == b"IHDR" => Header::from_bytes(chunk.data)?,
+        _ => return Err(DecodeError::InvalidData),
+    };
+
+    let mut palette = Vec::new();
+    let mut transparency = Vec::new();
+    let mut idat = Vec::new();
+    for chunk in chunks[1..].iter() {
+        match &chunk.chunk_type {
+            b"PLTE" => {
+                if chunk.data.is_empty()
+                    || !chunk.data.len().is_multiple_of(3)
+                    || chunk.data.len() > 768
+                {
+                    return Err(DecodeError::InvalidData);
+                }
+                palette = chunk.data.to_vec();
+            }
+            b"tRNS" => transparency = chunk.data.to_vec(),
+            b"IDAT" => idat.extend_from_slice(chunk.data),
+            b"IEND" => break,
+            b"IHDR" => return Err(DecodeError::InvalidData),
+            _ if chunk.is_critical() => return Err(DecodeError::UnsupportedFormat),
+            _ => {}
+        }
+    }
+    if header.color_type == ColorType::Indexed && palette.is_empty() {
+        return Err(DecodeError::InvalidData);
+    }
+
+    let data = decode_image_data(&header, &idat)?;
+    Ok(PngImage {
+        header,
+        palette,
+        transparency,
+        data,
+    })
+}
+
+/// Decodes the zlib stream of the concatenated `IDAT` chunks into unfiltered rows.
+///
+/// Interlaced images are also returned row by row.
+pub fn decode_image_data(header: &Header, idat: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let width = header.width as usize;
+    let height = header.height as usize;
+    if !header.interlaced {
+        let raw = inflate_image_data(idat, filtered_size(header, width, height)?)?;
+        return unfilter_image(header, raw, width, height);
+    }
+
+    let passes = ADAM7.map(|(x, y, dx, dy)| {
+        let pass_width = width.saturating_sub(x).div_ceil(dx);
+        let pass_height = height.saturating_sub(y).div_ceil(dy);
+        (x, y, dx, dy, pass_width, pass_height)
+    });
+    let mut total_size = 0usize;
+    for &(.., pass_width, pass_height) in passes.iter() {
+        total_size = total_size
+            .checked_add(filtered_size(header, pass_width, pass_height)?)
+            .ok_or(DecodeError::OutOfMemory)?;
+    }
+    let mut raw = inflate_image_data(idat, total_size)?;
+
+    let stride = header.stride();
+    let bits_per_pixel = header.bits_per_pixel();
+    let output_size = stride
+        .checked_mul(height)
+        .ok_or(DecodeError::OutOfMemory)?;
+    let mut output = Vec::new();
+    output
+        .try_reserve_exact(output_size)
+        .map_err(|_| DecodeError::OutOfMemory)?;
+    output.resize(output_size, 0);
+    for (x, y, dx, dy, pass_width, pass_height) in passes {
+        let size = filtered_size(header, pass_width, pass_height)?;
+        let rest = raw.split_off(size);
+        let pass = unfilter_image(header, raw, pass_width, pass_height)?;
+        raw = rest;
+
+        let pass_stride = header.row_bytes(pass_width);
+        for (row, pass_row) in pass.chunks_exact(pass_stride).enumerate() {
+            let output_row = &mut output[(y + row * dy) * stride..][..stride];
+            for column in 0..pass_width {
+                copy_pixel(
+                    pass_row,
+                    column,
+                    output_row,
+                    x + column * dx,
+                    bits_per_pixel,
+                );
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Decompresses the zlib stream of `size` bytes, which is declared by the untrusted header.
+fn inflate_image_data(idat: &[u8], size: usize) -> Result<Vec<u8>, DecodeError> {
+    // The longest match of deflate takes two bits at least.
+    if size > idat.len().saturating_mul(1032) {
+        return Err(DecodeError::InvalidData);
+    }
+    let mut output = Vec::new();
+    output
+        .try_reserve_exact(size)
+        .map_err(|_| DecodeError::OutOfMemory)?;
+    output.resize(size, 0);
+    inflate_in_place(idat, &mut output)?;
+    Ok(output)
+}
+
+/// Returns the size of the filtered rows, or zero for an empty pass.
+fn filtered_size(header: &Header, width: usize, height: usize) -> Result<usize, DecodeError> {
+    if width == 0 || height == 0 {
+        return Ok(0);
+    }
+    width
+        .checked_mul(header.bits_per_pixel())
+        .and_then(|v| (v.div_ceil(8) + 1).checked_mul(height))
+        .ok_or(DecodeError::OutOfMemory)
+}
+
+/// Reverses the filters of the rows, removing the filter types.
+fn unfilter_image(
+    header: &Header,
+    raw: Vec<u8>,
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    if width == 0 || height == 0 {
+        return Ok(Vec::new());
+    }
+    let stride = header.row_bytes(width);
+    let distance = header.filter_distance();
+    let mut output = Vec::with_capacity(stride * height);
+    let zeros = vec![0; stride];
+    for row in raw.chunks_exact(stride + 1) {
+        let filter = FilterType::from_u8(row[0]).ok_or(DecodeError::InvalidData)?;
+        let start = output.len();
+        output.extend_from_slice(&row[1..]);
+        let (prev, current) = output.split_at_mut(start);
+        let prev = prev.get(start.wrapping_sub(stride)..).unwrap_or(&zeros);
+        unfilter_row(filter, current, prev, distance);
+    }
+    Ok(output)
+}
+
+/// Copies a pixel between rows packed in big endian.
+#[inline]
+fn copy_pixel(src: &[u8], src_index: usize, dst: &mut [u8], dst_index: usize, bits: usize) {
+    if bits >= 8 {
+        let bytes = bits / 8;
+        dst[dst_index * bytes..][..bytes].copy_from_slice(&src[src_index * bytes..][..bytes]);
+    } else {
+        let mask = (1u8 << bits) - 1;
+        let src_shift = 8 - bits - (src_index * bits) % 8;
+        let dst_shift = 8 - bits - (dst_index * bits) % 8;
+        let value = (src[src_index * bits / 8] >> src_shift) & mask;
+        let p = &mut dst[dst_index * bits / 8];
+        *p = (*p & !(mask << dst_shift)) | (value << dst_shift);
+    }
+}
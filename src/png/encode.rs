@@ -0,0 +1,115 @@
+//! PNG encoder
+
+use super::*;
+use crate::deflate::{CompressionLevel, deflate_zlib};
+use alloc::vec;
+
+/// Maximum size of an `IDAT` chunk written by the encoder
+const MAX_IDAT_SIZE: usize = 0x1_0000;
+
+/// Encoder configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Configuration {
+    level: CompressionLevel,
+    filter: Option<FilterType>,
+}
+
+impl Configuration {
+    pub const DEFAULT: Self = Self::new(CompressionLevel::Default);
+
+    /// Creates a configuration that chooses the filter for each row.
+    #[inline]
+    pub const fn new(level: CompressionLevel) -> Self {
+        Self {
+            level,
+            filter: None,
+        }
+    }
+
+    /// Uses the filter for all rows.
+    #[inline]
+    pub const fn filter(mut self, filter: FilterType) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    #[inline]
+    pub const fn level(&self) -> CompressionLevel {
+        self.level
+    }
+}
+
+impl Default for Configuration {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Encodes an image into a non-interlaced PNG file.
+pub fn encode(image: &PngImage, config: Configuration) -> Result<Vec<u8>, EncodeError> {
+    let mut header = image.header;
+    header.interlaced = false;
+    if image.data.len() != header.stride() * header.height as usize
+        || !image.palette.len().is_multiple_of(3)
+        || image.palette.len() > 768
+        || (header.color_type == ColorType::Indexed && image.palette.is_empty())
+    {
+        return Err(EncodeError::InvalidInput);
+    }
+
+    let mut output = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut output, b"IHDR", &header.to_bytes());
+    if !image.palette.is_empty() {
+        write_chunk(&mut output, b"PLTE", &image.palette);
+    }
+    if !image.transparency.is_empty() {
+        write_chunk(&mut output, b"tRNS", &image.transparency);
+    }
+    let filtered = filter_image(&header, &image.data, config);
+    let compressed = deflate_zlib(&filtered, config.level, None)?;
+    for idat in compressed.chunks(MAX_IDAT_SIZE) {
+        write_chunk(&mut output, b"IDAT", idat);
+    }
+    write_chunk(&mut output, b"IEND", &[]);
+    Ok(output)
+}
+
+/// Filters the rows, prepending the filter types.
+///
+/// Unless specified, the filter with the minimum sum of absolute differences is chosen for each row,
+/// except for indexed or low bit depth images, which are not filtered.
+fn filter_image(header: &Header, data: &[u8], config: Configuration) -> Vec<u8> {
+    let stride = header.stride();
+    let distance = header.filter_distance();
+    let adaptive = header.bit_depth >= 8 && header.color_type != ColorType::Indexed;
+    let mut output = Vec::with_capacity(data.len() + header.height as usize);
+    let mut candidate = Vec::with_capacity(stride);
+    let zeros = vec![0; stride];
+    let mut prev = zeros.as_slice();
+    for row in data.chunks_exact(stride) {
+        let filter = match config.filter {
+            Some(filter) => filter,
+            None if adaptive => {
+                let mut best = (usize::MAX, FilterType::None);
+                for filter in FilterType::ALL {
+                    candidate.clear();
+                    filter_row(filter, row, prev, distance, &mut candidate);
+                    let cost = candidate
+                        .iter()
+                        .map(|&v| (v as i8).unsigned_abs() as usize)
+                        .sum::<usize>();
+                    if cost < best.0 {
+                        best = (cost, filter);
+                    }
+                }
+                best.1
+            }
+            None => FilterType::None,
+        };
+        output.push(filter as u8);
+        filter_row(filter, row, prev, distance, &mut output);
+        prev = row;
+    }
+    output
+}
@@ -0,0 +1,393 @@
+//! PNG image format
+//!
+//! This module covers the codec layer only, the color spaces and gamma are not handled.
+//!
+//! See also: <https://www.w3.org/TR/png-3/>
+
+use crate::{deflate::crc32, *};
+
+#[cfg(test)]
+mod tests;
+
+mod decode;
+mod encode;
+pub use decode::*;
+pub use encode::*;
+
+/// Signature of PNG files
+pub const PNG_SIGNATURE: [u8; 8] = *b"\x89PNG\r\n\x1a\n";
+
+/// Maximum width and height of an image
+pub const MAX_DIMENSION: u32 = 0x7fff_ffff;
+
+/// Passes of the Adam7 interlacing, `(x, y, dx, dy)`
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    Grayscale = 0,
+    Rgb = 2,
+    Indexed = 3,
+    GrayscaleAlpha = 4,
+    Rgba = 6,
+}
+
+impl ColorType {
+    #[inline]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Grayscale),
+            2 => Some(Self::Rgb),
+            3 => Some(Self::Indexed),
+            4 => Some(Self::GrayscaleAlpha),
+            6 => Some(Self::Rgba),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of samples per pixel.
+    #[inline]
+    pub const fn channels(&self) -> usize {
+        match self {
+            Self::Grayscale | Self::Indexed => 1,
+            Self::GrayscaleAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4,
+        }
+    }
+
+    /// Returns whether the bit depth is allowed for this color type.
+    #[inline]
+    pub const fn is_valid_bit_depth(&self, bit_depth: u8) -> bool {
+        match self {
+            Self::Grayscale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+            Self::Indexed => matches!(bit_depth, 1 | 2 | 4 | 8),
+            Self::Rgb | Self::GrayscaleAlpha | Self::Rgba => matches!(bit_depth, 8 | 16),
+        }
+    }
+}
+
+/// Filter type of a scanline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterType {
+    None = 0,
+    Sub,
+    Up,
+    Average,
+    Paeth,
+}
+
+impl FilterType {
+    pub const ALL: [Self; 5] = [Self::None, Self::Sub, Self::Up, Self::Average, Self::Paeth];
+
+    #[inline]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::None),
+            1 => Some(Self::Sub),
+            2 => Some(Self::Up),
+            3 => Some(Self::Average),
+            4 => Some(Self::Paeth),
+            _ => None,
+        }
+    }
+}
+
+/// Image header (`IHDR`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: ColorType,
+    interlaced: bool,
+}
+
+impl Header {
+    /// Creates a header of a non-interlaced image.
+    ///
+    /// Returns `None` if the size or the bit depth is invalid.
+    #[inline]
+    pub const fn new(
+        width: u32,
+        height: u32,
+        bit_depth: u8,
+        color_type: ColorType,
+    ) -> Option<Self> {
+        if width == 0
+            || height == 0
+            || width > MAX_DIMENSION
+            || height > MAX_DIMENSION
+            || !color_type.is_valid_bit_depth(bit_depth)
+        {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced: false,
+        })
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self, DecodeError> {
+        let data: &[u8; 13] = data.try_into().map_err(|_| DecodeError::InvalidData)?;
+        let width = u32::from_be_bytes(data[0..4].try_into().unwrap());
+        let height = u32::from_be_bytes(data[4..8].try_into().unwrap());
+        let color_type = ColorType::from_u8(data[9]).ok_or(DecodeError::InvalidData)?;
+        let mut header =
+            Self::new(width, height, data[8], color_type).ok_or(DecodeError::InvalidData)?;
+        // compression method and filter method
+        if data[10] != 0 || data[11] != 0 {
+            return Err(DecodeError::UnsupportedFormat);
+        }
+        header.interlaced = match data[12] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::UnsupportedFormat),
+        };
+        Ok(header)
+    }
+
+    fn to_bytes(self) -> [u8; 13] {
+        let mut output = [0; 13];
+        output[0..4].copy_from_slice(&self.width.to_be_bytes());
+        output[4..8].copy_from_slice(&self.height.to_be_bytes());
+        output[8] = self.bit_depth;
+        output[9] = self.color_type as u8;
+        output[12] = self.interlaced as u8;
+        output
+    }
+
+    #[inline]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub const fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    #[inline]
+    pub const fn color_type(&self) -> ColorType {
+        self.color_type
+    }
+
+    #[inline]
+    pub const fn is_interlaced(&self) -> bool {
+        self.interlaced
+    }
+
+    #[inline]
+    pub const fn bits_per_pixel(&self) -> usize {
+        self.color_type.channels() * self.bit_depth as usize
+    }
+
+    /// Returns the number of bytes of a row, excluding the filter type.
+    #[inline]
+    pub const fn stride(&self) -> usize {
+        self.row_bytes(self.width as usize)
+    }
+
+    #[inline]
+    const fn row_bytes(&self, width: usize) -> usize {
+        (width * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    /// Returns the distance to the corresponding byte of the left pixel used by the filters.
+    #[inline]
+    const fn filter_distance(&self) -> usize {
+        let bytes = self.bits_per_pixel() / 8;
+        if bytes > 1 { bytes } else { 1 }
+    }
+}
+
+/// Decoded image, whose rows are unfiltered and not interlaced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PngImage {
+    header: Header,
+    palette: Vec<u8>,
+    transparency: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl PngImage {
+    /// Creates an image from the rows of `header.stride()` bytes.
+    ///
+    /// Returns `None` if the size of the data does not match the header.
+    #[inline]
+    pub fn new(header: Header, data: Vec<u8>) -> Option<Self> {
+        let mut header = header;
+        header.interlaced = false;
+        (data.len() == header.stride() * header.height as usize).then_some(Self {
+            header,
+            palette: Vec::new(),
+            transparency: Vec::new(),
+            data,
+        })
+    }
+
+    /// Sets the palette (`PLTE`) of RGB triples.
+    #[inline]
+    pub fn with_palette(mut self, palette: Vec<u8>) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Sets the contents of the `tRNS` chunk.
+    #[inline]
+    pub fn with_transparency(mut self, transparency: Vec<u8>) -> Self {
+        self.transparency = transparency;
+        self
+    }
+
+    /// Returns the header, which is `interlaced` if so is the original file.
+    #[inline]
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    #[inline]
+    pub fn palette(&self) -> &[u8] {
+        &self.palette
+    }
+
+    #[inline]
+    pub fn transparency(&self) -> &[u8] {
+        &self.transparency
+    }
+
+    /// Rows of samples packed in big endian
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[inline]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Chunk of a PNG file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk<'a> {
+    pub chunk_type: [u8; 4],
+    pub data: &'a [u8],
+}
+
+impl Chunk<'_> {
+    /// Returns whether the chunk is necessary to decode the image.
+    #[inline]
+    pub const fn is_critical(&self) -> bool {
+        self.chunk_type[0] & 0x20 == 0
+    }
+}
+
+/// Returns the chunks of a PNG file up to `IEND`, whose CRC-32 are verified.
+pub fn chunks(input: &[u8]) -> Result<Vec<Chunk<'_>>, DecodeError> {
+    let signature = input.get(..8).ok_or(DecodeError::UnexpectedEof)?;
+    if signature != PNG_SIGNATURE {
+        return Err(DecodeError::InvalidInput);
+    }
+
+    let mut chunks = Vec::new();
+    let mut rest = &input[8..];
+    loop {
+        let header = rest.get(..8).ok_or(DecodeError::UnexpectedEof)?;
+        let size = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        let body = rest.get(4..8 + size).ok_or(DecodeError::UnexpectedEof)?;
+        let crc = rest
+            .get(8 + size..12 + size)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        if crc32::checksum(body) != u32::from_be_bytes(crc.try_into().unwrap()) {
+            return Err(DecodeError::InvalidData);
+        }
+        let chunk = Chunk {
+            chunk_type: body[0..4].try_into().unwrap(),
+            data: &body[4..],
+        };
+        chunks.push(chunk);
+        if &chunk.chunk_type == b"IEND" {
+            return Ok(chunks);
+        }
+        rest = &rest[12 + size..];
+    }
+}
+
+/// Appends a chunk with its CRC-32.
+pub fn write_chunk(output: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    output.extend_from_slice(chunk_type);
+    output.extend_from_slice(data);
+    let crc = crc32::update(crc32::checksum(chunk_type), data);
+    output.extend_from_slice(&crc.to_be_bytes());
+}
+
+#[inline]
+const fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Returns the prediction of the filter from the left, the upper and the upper left bytes.
+#[inline]
+const fn predict(filter: FilterType, a: u8, b: u8, c: u8) -> u8 {
+    match filter {
+        FilterType::None => 0,
+        FilterType::Sub => a,
+        FilterType::Up => b,
+        FilterType::Average => ((a as u16 + b as u16) / 2) as u8,
+        FilterType::Paeth => paeth(a, b, c),
+    }
+}
+
+/// Reverses the filter of a row in place.
+///
+/// `prev` is the unfiltered previous row, which is all zeros for the first row.
+fn unfilter_row(filter: FilterType, row: &mut [u8], prev: &[u8], distance: usize) {
+    for i in 0..row.len() {
+        let (a, c) = if i >= distance {
+            (row[i - distance], prev[i - distance])
+        } else {
+            (0, 0)
+        };
+        row[i] = row[i].wrapping_add(predict(filter, a, prev[i], c));
+    }
+}
+
+/// Appends the filtered row.
+fn filter_row(filter: FilterType, row: &[u8], prev: &[u8], distance: usize, output: &mut Vec<u8>) {
+    output.extend(row.iter().enumerate().map(|(i, &v)| {
+        let (a, c) = if i >= distance {
+            (row[i - distance], prev[i - distance])
+        } else {
+            (0, 0)
+        };
+        v.wrapping_sub(predict(filter, a, prev[i], c))
+    }));
+}
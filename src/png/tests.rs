@@ -0,0 +1,157 @@
+use super::*;
+use crate::deflate::CompressionLevel;
+use base64::prelude::*;
+
+/// Indexed 16x16 image with `tRNS` and ancillary chunks
+const PYTHON_PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAABAAAAAQCAMAAAAoLQ9TAAAAIGNIUk0AAHomAACAhAAA+gAAAIDoAAB1MAAA6mAAADqYAAAXcJy6UTwAAAHFUExURQAAAE6NwEqGujxxnjdolgAAAAAAAAAAAAAAAAAAAE6PxEyKv0iFtkN9rT10oTlsljZmkAAAAAAAAFKRxv3dSvnVPgAAADJghwAAAP3VNgAAADVokgAAAOzORQAAADZpkxsxRQAAAAAAAOLDRf3ZQQAAAEaBsjZnkQAAAPLhbv3pav3LG0N9rhwyS/Xjb/PBGkB1oz1zogAAAMKaEgAAAAAAAAAAAAAAAAAAAOjHPevFNOvCKuu+Ieu7GMabEgAAAAAAAAAAAAAAAAAAAAAAAAAAAP3eTgAAAAAAAAAAAAAAAPHQP/rTOf3SL/rHGcCXEgAAAAAAAAAAAAAAAAAAAAAAAEiDtER+rUB4p0yKvP///0V/r0F5qD50ojpvmzZplEmFt0aAsUJ7qj51oztwnTdqljhsl0+NwUuIu0R9rUB4pj1zoDltmf/iVVCPw0yJvEiEtT10of/eS//aQU2LvkaAsDtwnP/aQv/WN//TLUqHuUeBskN8qz92pf/XOP/PI//mYf/jV//fTf/bQv/TLv/PJP/rbP/nYf/bQ//XOf/MHDpwnP/nYv/jWP/fTv/TL//bRP/XOv/TMP/QJf/YOv/UMP/QJpJJAAIAAABWdFJOUwCv7feSCwMoQRV+oKGjp6qqSBi994IKtkPtJPdPiD22XExHa/dG96pOpvf311uzwm/3S3o0CCE8NaqpqampeSACCRMSLB73QBwFAUzM99h7Ox1CRTgf+DIQcQAAAAFiS0dEWgO7paIAAAD1SURBVBjTY2AAAkamsPAIZhZWNgYoiIyKjomNi2dnhQkkJCYlp6TGc3CCeVzcPLx8/AJp8fGCQiC+cHpGWGZWdk48UCBXRFSMIS+/IDqmMA7IF5coKpaUYihJKE1KLkuNlxaXkS2vqJRjqKquqZVXUBRU4lBWqausV2VQy8xS19DUamhsaq5radVWYtCJKdTVa2tvbOrobGnt0udgMDDsNmrr6e3r6Oxv7eoyNmEwNTO3APItraxtbG3tOOwZHBydnHv7JkycNLmLw8LFFeJ6twlTpk6L0nb3cPT0Agt4+/hO6/LzD3CGec400CJIKTgk1BTIBgBAYkJ/yC2b5QAAACV0RVh0ZGF0ZTpjcmVhdGUAMjAxNC0wMS0yNlQyMDo1OTozNyswMjowMPuaB3cAAAAldEVYdGRhdGU6bW9kaWZ5ADIwMTQtMDEtMjZUMjA6NTk6MDArMDI6MDDB74amAAAAAElFTkSuQmCC";
+
+/// RGB pixels of the image above
+const PYTHON_RGB: &str = "AAAAAAAAAAAAAAAATo3ASoa6SIO0RH6tQHinPHGeN2iWAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAATIq8////RX+vQXmoPnSiOm+bNmmUAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAASYW3RoCxQnuqPnWjO3CdN2qWNmmUAAAAAAAAAAAAAAAAAAAAAAAATo/ETIq/SIW2Q32tPXShOWyWNmaQOGyXNmmUNmmUAAAAAAAAAAAAAAAAAAAAUpHGT43BS4i7SIO0RH2tQHimPXOgOW2ZNmmUNmmUNmmUAAAA/+JV/d1K+dU+AAAAUI/DTIm8SIS1RX+vQXmoPXShOm+bNmmUNmmUNmmUMmCHAAAA/95L/9pB/dU2AAAATYu+SYW3RoCwQnuqPnWjO3CcN2qWNmmUNWiSMmCHAAAA7M5F/9pC/9Y3/9MtAAAASoe5R4GyQ3yrP3alNmmTGzFFAAAAAAAAAAAAAAAA4sNF/dlB/9c4/9Mt/88jAAAARoGyRH2tQHimNmeRAAAA8uFu/elq/+Zh/+NX/99N/9tC/9c4/9Mu/88k/csbAAAAQ32uQXmoPXShHDJL9eNv/+ts/+dh/+NX/99N/9tD/9c5/9Mu/88k/8wc88EaAAAAQHWjPXOiOnCcAAAA/+ts/+di/+NY/99O/9tD/9c5/9Mv/88k/8wc/8wcwpoSAAAAAAAAAAAAAAAAAAAA/+di/+NY/99O6Mc968U068Iq674h67sY67sYxpsSAAAAAAAAAAAAAAAAAAAAAAAA/+NY/99O/9tE/9c6/9Mw/9Al/8wcAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA/d5O/9tE/9g6/9Qw/9Am/////csbAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA8dA/+tM5/dIv/9Am/8wc+scZwJcSAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+
+/// 7x5 RGB 16-bit image, interlaced, with all the filter types
+const RGB16_ADAM7: &str = "iVBORw0KGgoAAAANSUhEUgAAAAcAAAAFEAIAAAEhb41aAAAADHRFWHRDb21tZW50AHRlc3RXYSvpAAAAYUlEQVR42mNgYKjK/HKJsXrPN9WCPqaUlHtnI80eGkTPvMbEbHtvh7vRhtnaNx5MFWFJfQOCaX9q/qT9YTAyWjNbnaUg5NXeVLV1ZRr3F3u8mZHO+DCLUe79zAjhg3b3EJDJOMQo0/kR/QAAAGFJREFUxDjEeIbRDOMZxneM7hjfYY4um7kqNN6ifud/i3rLhZaKlgtZZkoKN/W+lmcgDBlOe7tuOSD7Kjfl2n17zv7mH/8SNTYulpJtdr98yMZmadrXh3HRx9vEGRqqXgIAtAtYU9UQL5sAAAAASUVORK5CYII=";
+
+/// 13x6 grayscale 2-bit image with all the filter types
+const GRAY2: &str = "iVBORw0KGgoAAAANSUhEUgAAAA0AAAAGAgAAAAB391PCAAAADHRFWHRDb21tZW50AHRlc3RXYSvpAAAAE0lEQVR42mOwtLRkYAxlYHjNFBMT48DcIIcLRwAAABNJREFUKiYmxBLDwHCcITQ01AEAW2QGb4tWlHMAAAAASUVORK5CYII=";
+
+/// 9x9 indexed 4-bit image, interlaced, with all the filter types
+const INDEXED4_ADAM7: &str = "iVBORw0KGgoAAAANSUhEUgAAAAkAAAAJBAMAAAFluCu1AAAAMFBMVEUA/wAQ7wcg3w4wzxVAvxxQryNgnypwjzGAfziQbz+gX0awT03AP1TQL1vgH2LwD2mp4hopAAAADHRFWHRDb21tZW50AHRlc3RXYSvpAAAAJElEQVR42mNgYGRgamB2YGFgYDjCeIbpB3PDiRoWhgoGhqRXjM86mGq8UTKeAAAAJElEQVS+MG91YqnpYPBgOdDjwHigAyjF1LGHwaWBec6zimc1ADYpFMrZsD87AAAAAElFTkSuQmCC";
+
+#[test]
+fn decode_indexed() {
+    let png = BASE64_STANDARD.decode(PYTHON_PNG).unwrap();
+    let expected = BASE64_STANDARD.decode(PYTHON_RGB).unwrap();
+    let image = decode(&png).unwrap();
+    assert_eq!(
+        image.header(),
+        &Header::new(16, 16, 8, ColorType::Indexed).unwrap()
+    );
+    assert_eq!(image.transparency().len(), 86);
+    let rgb = image
+        .data()
+        .iter()
+        .flat_map(|&v| image.palette()[v as usize * 3..][..3].iter().copied())
+        .collect::<Vec<_>>();
+    assert_eq!(rgb, expected);
+
+    assert_eq!(decode(&png[..100]), Err(DecodeError::UnexpectedEof));
+    let mut broken = png.clone();
+    broken[40] ^= 1;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    broken[0] = 0;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidInput));
+}
+
+#[test]
+fn decode_huge_header() {
+    // The sizes declared by the header are rejected before the allocation.
+    let idat = crate::deflate::deflate(&[0; 16], CompressionLevel::Default, None).unwrap();
+    for interlaced in [false, true] {
+        let mut header = Header::new(MAX_DIMENSION, 0x10000, 8, ColorType::Rgba).unwrap();
+        header.interlaced = interlaced;
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &header.to_bytes());
+        write_chunk(&mut png, b"IDAT", &idat);
+        write_chunk(&mut png, b"IEND", &[]);
+        assert_eq!(decode(&png), Err(DecodeError::InvalidData));
+    }
+}
+
+/// Returns the rows of the samples, packed in big endian.
+fn pack_rows<F>(header: &Header, sample: F) -> Vec<u8>
+where
+    F: Fn(usize, usize, usize) -> u16,
+{
+    let channels = header.color_type().channels();
+    let bit_depth = header.bit_depth() as usize;
+    let mut output = Vec::new();
+    for y in 0..header.height() as usize {
+        let mut acc = 0u32;
+        let mut bits = 0;
+        for x in 0..header.width() as usize {
+            for c in 0..channels {
+                acc = (acc << bit_depth) | sample(x, y, c) as u32;
+                bits += bit_depth;
+                while bits >= 8 {
+                    bits -= 8;
+                    output.push((acc >> bits) as u8);
+                }
+            }
+        }
+        if bits > 0 {
+            output.push((acc << (8 - bits)) as u8);
+        }
+    }
+    output
+}
+
+#[test]
+fn decode_filters() {
+    let image = decode(&BASE64_STANDARD.decode(RGB16_ADAM7).unwrap()).unwrap();
+    let header = image.header();
+    assert!(header.is_interlaced());
+    let expected = pack_rows(header, |x, y, c| {
+        ((x * 7919 + y * 104729 + c * 31337 + x * y * 17) & 0xffff) as u16
+    });
+    assert_eq!(image.data(), expected);
+
+    let image = decode(&BASE64_STANDARD.decode(GRAY2).unwrap()).unwrap();
+    let expected = pack_rows(image.header(), |x, y, _| {
+        ((x * 3 + y * 5 + x * y) & 3) as u16
+    });
+    assert_eq!(image.data(), expected);
+
+    let image = decode(&BASE64_STANDARD.decode(INDEXED4_ADAM7).unwrap()).unwrap();
+    let expected = pack_rows(image.header(), |x, y, _| {
+        ((x * 5 + y * 3 + (x ^ y)) & 15) as u16
+    });
+    assert_eq!(image.data(), expected);
+    assert_eq!(image.palette().len(), 48);
+}
+
+#[test]
+fn roundtrip() {
+    let sample = |x: usize, y: usize, c: usize| ((x * x + y * 3 + c * 50) ^ (x * y)) as u16;
+    for (color_type, bit_depth) in [
+        (ColorType::Grayscale, 1),
+        (ColorType::Grayscale, 2),
+        (ColorType::Grayscale, 4),
+        (ColorType::Grayscale, 8),
+        (ColorType::Grayscale, 16),
+        (ColorType::Rgb, 8),
+        (ColorType::Rgb, 16),
+        (ColorType::Indexed, 4),
+        (ColorType::GrayscaleAlpha, 8),
+        (ColorType::Rgba, 16),
+    ] {
+        for (width, height) in [(1, 1), (3, 7), (33, 20)] {
+            let header = Header::new(width, height, bit_depth, color_type).unwrap();
+            let mask = ((1u32 << bit_depth) - 1) as usize;
+            let data = pack_rows(&header, |x, y, c| (sample(x, y, c) as usize & mask) as u16);
+            let mut image = PngImage::new(header, data).unwrap();
+            if color_type == ColorType::Indexed {
+                image = image.with_palette((0..48).collect());
+            }
+            let mut configs = FilterType::ALL
+                .map(|v| Configuration::DEFAULT.filter(v))
+                .to_vec();
+            configs.push(Configuration::DEFAULT);
+            configs.push(Configuration::new(CompressionLevel::Fastest));
+            for config in configs {
+                let encoded = encode(&image, config).unwrap();
+                assert_eq!(decode(&encoded).unwrap(), image, "{header:?} {config:?}");
+            }
+        }
+    }
+
+    assert!(Header::new(0, 1, 8, ColorType::Rgb).is_none());
+    assert!(Header::new(1, 1, 4, ColorType::Rgb).is_none());
+    assert!(Header::new(1, 1, 16, ColorType::Indexed).is_none());
+    let header = Header::new(2, 2, 8, ColorType::Indexed).unwrap();
+    assert!(PngImage::new(header, [0; 3].to_vec()).is_none());
+    let image = PngImage::new(header, [0; 4].to_vec()).unwrap();
+    assert_eq!(
+        encode(&image, Configuration::DEFAULT),
+        Err(EncodeError::InvalidInput)
+    );
+}
@@ -0,0 +1,34 @@
+//! CRC-32C (Castagnoli) checksum
+//!
+//! See also: <https://en.wikipedia.org/wiki/Cyclic_redundancy_check>
+
+use crate::deflate::crc32::make_table;
+
+/// Reversed polynomial of CRC-32C
+const POLYNOMIAL: u32 = 0x82f6_3b78;
+
+const TABLE: [u32; 256] = make_table(POLYNOMIAL);
+
+/// Added to the rotated checksum in the framing format
+const MASK_DELTA: u32 = 0xa282_ead8;
+
+/// CRC-32C checksum
+#[inline]
+pub fn checksum(data: &[u8]) -> u32 {
+    update(0, data)
+}
+
+/// Continues the checksum `crc` of the preceding data with `data`.
+pub fn update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc = (crc >> 8) ^ TABLE[((crc as u8) ^ byte) as usize];
+    }
+    !crc
+}
+
+/// Masked checksum of the framing format, which is robust to the data containing its own checksum
+#[inline]
+pub fn masked_checksum(data: &[u8]) -> u32 {
+    checksum(data).rotate_right(15).wrapping_add(MASK_DELTA)
+}
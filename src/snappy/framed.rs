@@ -0,0 +1,88 @@
+//! Snappy framing format
+
+use super::*;
+
+/// The stream identifier chunk, which starts the stream
+const STREAM_IDENTIFIER: [u8; 10] = *b"\xff\x06\x00\x00sNaPpY";
+
+const CHUNK_COMPRESSED: u8 = 0x00;
+const CHUNK_UNCOMPRESSED: u8 = 0x01;
+const CHUNK_PADDING: u8 = 0xfe;
+const CHUNK_STREAM_IDENTIFIER: u8 = 0xff;
+
+/// Compresses the input in the framing format.
+///
+/// The chunks that do not shrink by 1/8 are stored uncompressed, as the reference encoder does.
+pub fn encode_framed(input: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    let mut output = Vec::with_capacity(max_compressed_len(input.len()) + STREAM_IDENTIFIER.len());
+    output.extend_from_slice(&STREAM_IDENTIFIER);
+    for chunk in input.chunks(MAX_CHUNK_SIZE) {
+        let compressed = encode(chunk)?;
+        let (chunk_type, data) = if compressed.len() < chunk.len() - chunk.len() / 8 {
+            (CHUNK_COMPRESSED, compressed.as_slice())
+        } else {
+            (CHUNK_UNCOMPRESSED, chunk)
+        };
+        output.push(chunk_type);
+        output.extend_from_slice(&(data.len() as u32 + 4).to_le_bytes()[..3]);
+        output.extend_from_slice(&crc32c::masked_checksum(chunk).to_le_bytes());
+        output.extend_from_slice(data);
+    }
+    Ok(output)
+}
+
+/// Decompresses the data in the framing format, which may be concatenated.
+pub fn decode_framed(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if !input.starts_with(&STREAM_IDENTIFIER) {
+        return Err(if input.len() < STREAM_IDENTIFIER.len() {
+            DecodeError::UnexpectedEof
+        } else {
+            DecodeError::InvalidData
+        });
+    }
+
+    let mut output = Vec::new();
+    let mut input = input;
+    while !input.is_empty() {
+        let header = input.get(..4).ok_or(DecodeError::UnexpectedEof)?;
+        let chunk_type = header[0];
+        let len = u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize;
+        let data = input.get(4..4 + len).ok_or(DecodeError::UnexpectedEof)?;
+        input = &input[4 + len..];
+
+        match chunk_type {
+            CHUNK_COMPRESSED | CHUNK_UNCOMPRESSED => {
+                let (checksum, data) = data
+                    .split_first_chunk::<4>()
+                    .ok_or(DecodeError::InvalidData)?;
+                let start = output.len();
+                if chunk_type == CHUNK_COMPRESSED {
+                    let len = decoded_len(data)?;
+                    if len > MAX_CHUNK_SIZE {
+                        return Err(DecodeError::InvalidData);
+                    }
+                    output.resize(start + len, 0);
+                    decode_in_place(data, &mut output[start..])?;
+                } else {
+                    if data.len() > MAX_CHUNK_SIZE {
+                        return Err(DecodeError::InvalidData);
+                    }
+                    output.extend_from_slice(data);
+                }
+                if u32::from_le_bytes(*checksum) != crc32c::masked_checksum(&output[start..]) {
+                    return Err(DecodeError::InvalidData);
+                }
+            }
+            CHUNK_STREAM_IDENTIFIER => {
+                if data != &STREAM_IDENTIFIER[4..] {
+                    return Err(DecodeError::InvalidData);
+                }
+            }
+            // Unskippable chunks are reserved for the future formats.
+            0x02..=0x7f => return Err(DecodeError::UnsupportedFormat),
+            // Padding and the reserved skippable chunks
+            CHUNK_PADDING | 0x80..=0xfd => {}
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,38 @@
+//! Snappy compressed format
+//!
+//! The raw format starts with the decompressed length in a varint, followed by the elements of
+//! literals and copies, whose tags have the type in the lowest 2 bits:
+//!
+//! * `00`: literals up to 60 bytes in the tag, otherwise the length in the following 1 to 4 bytes
+//! * `01`: copy of 4 to 11 bytes with an 11-bit offset
+//! * `10`: copy of 1 to 64 bytes with a 16-bit offset
+//! * `11`: copy of 1 to 64 bytes with a 32-bit offset
+//!
+//! The framing format splits the data into chunks of up to 64 KiB with the masked CRC-32C.
+//!
+//! See also: <https://github.com/google/snappy/blob/main/format_description.txt> and <https://github.com/google/snappy/blob/main/framing_format.txt>
+
+use crate::*;
+
+#[cfg(test)]
+mod tests;
+
+pub mod crc32c;
+
+mod framed;
+mod raw;
+pub use framed::*;
+pub use raw::*;
+
+const TAG_LITERAL: u8 = 0b00;
+const TAG_COPY1: u8 = 0b01;
+const TAG_COPY2: u8 = 0b10;
+
+/// Minimum length of the matches found by the encoder
+const MIN_MATCH: usize = 4;
+
+/// Maximum offset of the matches found by the encoder, which fits in the 2-byte offset
+const MAX_DISTANCE: usize = 0xffff;
+
+/// Maximum size of the decompressed data of a chunk in the framing format
+const MAX_CHUNK_SIZE: usize = 65536;
@@ -0,0 +1,199 @@
+//! Snappy raw format
+
+use super::*;
+use crate::lz::cache::{OffsetCache, OffsetCache3};
+use crate::lz::{self, LzOutputBuffer};
+use crate::num::varint::{Leb128, Varint};
+
+/// Maximum size of the decompressed data, which is limited to 32 bits
+pub const MAX_INPUT_SIZE: usize = u32::MAX as usize;
+
+/// Returns the maximum size of the compressed data of `len` bytes, as the reference implementation.
+#[inline]
+pub const fn max_compressed_len(len: usize) -> usize {
+    32 + len + len / 6
+}
+
+/// Compresses the input in the raw format.
+pub fn encode(input: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    if input.len() > MAX_INPUT_SIZE {
+        return Err(EncodeError::InvalidInput);
+    }
+    let mut output = Vec::with_capacity(max_compressed_len(input.len()));
+    Leb128::write(&mut output, input.len() as u64);
+
+    let mut cache = OffsetCache3::new(input, MAX_DISTANCE, 0);
+    let guaranteed_min_len = cache.guaranteed_min_len();
+    let mut cursor = 0;
+    let mut literal_start = 0;
+    while cursor < input.len() {
+        let len = cache
+            .matches()
+            .and_then(|mut iter| iter.next())
+            .map(|distance| {
+                let len = lz::matching_len(input, cursor + guaranteed_min_len, distance);
+                (len + guaranteed_min_len, distance)
+            });
+        match len {
+            Some((len, distance)) if len >= MIN_MATCH => {
+                write_literals(&mut output, &input[literal_start..cursor]);
+                write_copy(&mut output, len, distance);
+                cache.advance(len);
+                cursor += len;
+                literal_start = cursor;
+            }
+            _ => {
+                cache.advance(1);
+                cursor += 1;
+            }
+        }
+    }
+    write_literals(&mut output, &input[literal_start..]);
+    Ok(output)
+}
+
+fn write_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    if literals.is_empty() {
+        return;
+    }
+    let len = literals.len() - 1;
+    if len < 60 {
+        output.push((len << 2) as u8 | TAG_LITERAL);
+    } else {
+        let bytes = (len as u32).to_le_bytes();
+        let count = 4 - (len as u32).leading_zeros() as usize / 8;
+        output.push(((59 + count) << 2) as u8 | TAG_LITERAL);
+        output.extend_from_slice(&bytes[..count]);
+    }
+    output.extend_from_slice(literals);
+}
+
+/// Writes a copy of 4 bytes or more in the elements of up to 64 bytes, as the reference encoder does.
+fn write_copy(output: &mut Vec<u8>, mut len: usize, distance: usize) {
+    debug_assert!(len >= MIN_MATCH && distance <= MAX_DISTANCE);
+    while len >= 68 {
+        write_copy2(output, 64, distance);
+        len -= 64;
+    }
+    if len > 64 {
+        write_copy2(output, 60, distance);
+        len -= 60;
+    }
+    if len < 12 && distance < 2048 {
+        output.push((((distance >> 8) << 5) | ((len - 4) << 2)) as u8 | TAG_COPY1);
+        output.push(distance as u8);
+    } else {
+        write_copy2(output, len, distance);
+    }
+}
+
+#[inline]
+fn write_copy2(output: &mut Vec<u8>, len: usize, distance: usize) {
+    output.push(((len - 1) << 2) as u8 | TAG_COPY2);
+    output.extend_from_slice(&(distance as u16).to_le_bytes());
+}
+
+/// Returns the size of the decompressed data in the preamble.
+#[inline]
+pub fn decoded_len(input: &[u8]) -> Result<usize, DecodeError> {
+    read_len(&mut input.iter())
+}
+
+/// Reads the length in a varint of up to 32 bits.
+#[inline]
+fn read_len(iter: &mut core::slice::Iter<u8>) -> Result<usize, DecodeError> {
+    let len = Leb128::read(iter)?;
+    u32::try_from(len)
+        .map(|v| v as usize)
+        .map_err(|_| DecodeError::InvalidData)
+}
+
+/// Decompresses the data in the raw format.
+pub fn decode(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let len = decoded_len(input)?;
+    // A copy of 64 bytes takes 3 bytes at least.
+    if len / 22 > input.len() {
+        return Err(DecodeError::InvalidData);
+    }
+    let mut output = Vec::new();
+    output
+        .try_reserve_exact(len)
+        .map_err(|_| DecodeError::OutOfMemory)?;
+    output.resize(len, 0);
+    decode_in_place(input, &mut output)?;
+    Ok(output)
+}
+
+/// Decompresses the data in the raw format into the output buffer,
+/// whose size must be the same as [`decoded_len`].
+pub fn decode_in_place(input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+    let mut iter = input.iter();
+    let len = read_len(&mut iter)?;
+    if len != output.len() {
+        return Err(DecodeError::InvalidInput);
+    }
+    let mut output = LzOutputBuffer::new(output);
+    while let Some(&tag) = iter.next() {
+        let (len, offset) = match tag & 3 {
+            TAG_LITERAL => {
+                let len = match tag >> 2 {
+                    len @ 0..60 => len as usize,
+                    count => {
+                        let count = count as usize - 59;
+                        let bytes = iter
+                            .as_slice()
+                            .get(..count)
+                            .ok_or(DecodeError::UnexpectedEof)?;
+                        iter = iter.as_slice()[count..].iter();
+                        bytes
+                            .iter()
+                            .rev()
+                            .fold(0, |acc, &v| (acc << 8) | v as usize)
+                    }
+                }
+                .saturating_add(1);
+                let literals = iter
+                    .as_slice()
+                    .get(..len)
+                    .ok_or(DecodeError::UnexpectedEof)?;
+                iter = iter.as_slice()[len..].iter();
+                output
+                    .extend_from_slice(literals)
+                    .ok_or(DecodeError::InvalidData)?;
+                continue;
+            }
+            TAG_COPY1 => {
+                let low = *iter.next().ok_or(DecodeError::UnexpectedEof)? as usize;
+                let len = ((tag >> 2) & 7) as usize + 4;
+                (len, ((tag as usize >> 5) << 8) | low)
+            }
+            TAG_COPY2 => {
+                let bytes = read_array::<2>(&mut iter)?;
+                ((tag >> 2) as usize + 1, u16::from_le_bytes(bytes) as usize)
+            }
+            // TAG_COPY4
+            _ => {
+                let bytes = read_array::<4>(&mut iter)?;
+                ((tag >> 2) as usize + 1, u32::from_le_bytes(bytes) as usize)
+            }
+        };
+        if offset == 0 || len > output.remaining() {
+            return Err(DecodeError::InvalidData);
+        }
+        output
+            .copy_lz(offset, len)
+            .ok_or(DecodeError::InvalidData)?;
+    }
+    if !output.is_eof() {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    Ok(())
+}
+
+#[inline]
+fn read_array<const N: usize>(iter: &mut core::slice::Iter<u8>) -> Result<[u8; N], DecodeError> {
+    let bytes = iter.as_slice().get(..N).ok_or(DecodeError::UnexpectedEof)?;
+    let bytes = bytes.try_into().unwrap();
+    *iter = iter.as_slice()[N..].iter();
+    Ok(bytes)
+}
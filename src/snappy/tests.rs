@@ -0,0 +1,118 @@
+use super::*;
+use base64::prelude::*;
+
+/// Literals, copies with the 1, 2 and 4-byte offsets
+const RAW: &str = "YAxhYmNkEQTwPDAxMjM0NTY3ODkwMTIzNDU2Nzg5MDEyMzQ1Njc4OTAxMjM0NTY3ODkwMTIzNDU2Nzg5MDEyMzQ1Njc4OXhOQQALAQAAAA==";
+
+const RAW_OUTPUT: &[u8] = b"abcdabcdabcd012345678901234567890123456789012345678901234567890123456789xabcd0123456789012345555";
+
+/// [`RAW`] in a compressed chunk, padding, a skippable chunk, another stream identifier and `"hello"` in an uncompressed chunk
+const FRAMED: &str = "/wYAAHNOYVBwWQBTAADUvF1cYAxhYmNkEQTwPDAxMjM0NTY3ODkwMTIzNDU2Nzg5MDEyMzQ1Njc4OTAxMjM0NTY3ODkwMTIzNDU2Nzg5MDEyMzQ1Njc4OXhOQQALAQAAAP4CAAAAAIAEAABza2lw/wYAAHNOYVBwWQEJAAC7HxwZaGVsbG8=";
+
+#[test]
+fn crc32c() {
+    assert_eq!(crc32c::checksum(b""), 0);
+    assert_eq!(crc32c::checksum(b"123456789"), 0xe306_9283);
+    assert_eq!(
+        crc32c::update(crc32c::checksum(b"1234"), b"56789"),
+        0xe306_9283
+    );
+    assert_eq!(crc32c::masked_checksum(b"hello"), 0x191c_1fbb);
+}
+
+#[test]
+fn decode_reference() {
+    let input = BASE64_STANDARD.decode(RAW).unwrap();
+    assert_eq!(decoded_len(&input), Ok(RAW_OUTPUT.len()));
+    assert_eq!(decode(&input).unwrap(), RAW_OUTPUT);
+
+    let input = BASE64_STANDARD.decode(FRAMED).unwrap();
+    assert_eq!(
+        decode_framed(&input).unwrap(),
+        [RAW_OUTPUT, b"hello"].concat()
+    );
+}
+
+#[test]
+fn malformed() {
+    let cases: [&[u8]; 11] = [
+        // Empty
+        b"",
+        // Varint too long
+        b"\x80\x80\x80\x80\x80\x01",
+        // Varint over 32 bits
+        b"\xff\xff\xff\xff\x1f",
+        // Too large for the input
+        b"\xff\xff\xff\xff\x0f",
+        // Literals beyond the input
+        b"\x04\x0cab",
+        // Literals beyond the length
+        b"\x01\x04ab",
+        // Truncated output
+        b"\x04\x04ab",
+        // Zero offset
+        b"\x08\x0cabcd\x01\x00",
+        // Offset before the start
+        b"\x08\x0cabcd\x01\x05",
+        // Copy beyond the length
+        b"\x08\x0cabcd\x05\x04",
+        // Truncated copy
+        b"\x08\x0cabcd\x0e\x04",
+    ];
+    for input in cases {
+        assert!(decode(input).is_err(), "{input:?}");
+    }
+
+    let framed = BASE64_STANDARD.decode(FRAMED).unwrap();
+    assert_eq!(decode_framed(&framed[..5]), Err(DecodeError::UnexpectedEof));
+    assert_eq!(decode_framed(&framed[10..]), Err(DecodeError::InvalidData));
+    assert!(decode_framed(&framed[..framed.len() - 1]).is_err());
+    // Checksums
+    for index in [14, framed.len() - 7] {
+        let mut broken = framed.clone();
+        broken[index] ^= 1;
+        assert_eq!(decode_framed(&broken), Err(DecodeError::InvalidData));
+    }
+    // Unskippable chunk
+    let broken = [&framed[..10], b"\x02\x00\x00\x00"].concat();
+    assert_eq!(decode_framed(&broken), Err(DecodeError::UnsupportedFormat));
+
+    // Random data never panics.
+    let noise = random_bytes(0, 0xff, 100_000);
+    for chunk in noise.chunks(37) {
+        let _ = decode(chunk);
+        let _ = decode_framed(&[&framed[..14], chunk].concat());
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let mut runs = Vec::new();
+    for len in [1, 3, 4, 5, 11, 12, 60, 61, 64, 65, 67, 68, 1000] {
+        runs.extend(core::iter::repeat_n(len as u8, len));
+    }
+    let inputs = [
+        Vec::new(),
+        b"a".to_vec(),
+        b"abcabcabcabc".to_vec(),
+        runs,
+        fib_str(b'a', b'b', 10000),
+        random_bytes(0, 0xff, 100_000),
+        [fib_str(b'x', b'y', 100_000), random_bytes(0, 0xff, 100_000)].concat(),
+        include_bytes!("../lib.rs").to_vec(),
+    ];
+    for input in inputs.iter() {
+        let encoded = encode(input).unwrap();
+        assert!(encoded.len() <= max_compressed_len(input.len()));
+        assert_eq!(decode(&encoded).unwrap(), *input);
+
+        let encoded = encode_framed(input).unwrap();
+        assert_eq!(decode_framed(&encoded).unwrap(), *input);
+    }
+
+    let input = b"The quick brown fox jumps over the lazy dog.\n".repeat(100);
+    let encoded = encode(&input).unwrap();
+    assert!(encoded.len() < 300);
+    let encoded = encode_framed(&input).unwrap();
+    assert_eq!(encoded[10], 0x00);
+}
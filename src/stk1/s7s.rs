@@ -1,4 +1,4 @@
-use alloc::vec::Vec;
+use crate::*;
 
 /// One of the representation formats for multibyte integers
 ///
@@ -10,86 +10,51 @@ use alloc::vec::Vec;
 pub struct S7s;
 
 impl S7s {
+    /// Maximum number of bytes of a `usize`
+    pub const MAX_ENCODED_LEN: usize = usize::BITS.div_ceil(7) as usize;
+
+    /// Returns the number of bytes the value is written in.
+    #[inline]
+    pub const fn encoded_len(value: usize) -> usize {
+        ((usize::BITS - (value | 1).leading_zeros()) as usize).div_ceil(7)
+    }
+
+    #[inline]
     pub fn write(output: &mut Vec<u8>, value: usize) {
-        let value = value as u64;
-        if value < 0x80 {
-            output.push((value << 1) as u8 | 1);
-        } else if value < 0x40_00 {
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
-        } else if value < 0x20_00_00 {
-            output.push((value >> 13) as u8 & 0xFE);
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
-        } else if value < 0x10_00_00_00 {
-            output.push((value >> 20) as u8 & 0xFE);
-            output.push((value >> 13) as u8 & 0xFE);
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
-        } else if value < 0x8_00_00_00_00 {
-            output.push((value >> 27) as u8 & 0xFE);
-            output.push((value >> 20) as u8 & 0xFE);
-            output.push((value >> 13) as u8 & 0xFE);
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
-        } else if value < 0x4_00_00_00_00_00 {
-            output.push((value >> 34) as u8 & 0xFE);
-            output.push((value >> 27) as u8 & 0xFE);
-            output.push((value >> 20) as u8 & 0xFE);
-            output.push((value >> 13) as u8 & 0xFE);
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
-        } else if value < 0x2_00_00_00_00_00_00 {
-            output.push((value >> 41) as u8 & 0xFE);
-            output.push((value >> 34) as u8 & 0xFE);
-            output.push((value >> 27) as u8 & 0xFE);
-            output.push((value >> 20) as u8 & 0xFE);
-            output.push((value >> 13) as u8 & 0xFE);
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
-        } else if value < 0x1_00_00_00_00_00_00_00 {
-            output.push((value >> 48) as u8 & 0xFE);
-            output.push((value >> 41) as u8 & 0xFE);
-            output.push((value >> 34) as u8 & 0xFE);
-            output.push((value >> 27) as u8 & 0xFE);
-            output.push((value >> 20) as u8 & 0xFE);
-            output.push((value >> 13) as u8 & 0xFE);
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
-        } else if value < 0x80_00_00_00_00_00_00_00 {
-            output.push((value >> 55) as u8 & 0xFE);
-            output.push((value >> 48) as u8 & 0xFE);
-            output.push((value >> 41) as u8 & 0xFE);
-            output.push((value >> 34) as u8 & 0xFE);
-            output.push((value >> 27) as u8 & 0xFE);
-            output.push((value >> 20) as u8 & 0xFE);
-            output.push((value >> 13) as u8 & 0xFE);
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
-        } else {
-            output.push((value >> 62) as u8 & 0xFE);
-            output.push((value >> 55) as u8 & 0xFE);
-            output.push((value >> 48) as u8 & 0xFE);
-            output.push((value >> 41) as u8 & 0xFE);
-            output.push((value >> 34) as u8 & 0xFE);
-            output.push((value >> 27) as u8 & 0xFE);
-            output.push((value >> 20) as u8 & 0xFE);
-            output.push((value >> 13) as u8 & 0xFE);
-            output.push((value >> 6) as u8 & 0xFE);
-            output.push((value << 1) as u8 | 1);
+        let mut buf = [0; Self::MAX_ENCODED_LEN];
+        let len = Self::write_into(&mut buf, value).unwrap();
+        output.extend_from_slice(&buf[..len]);
+    }
+
+    /// Writes the value to the head of the buffer, and returns the number of bytes written.
+    ///
+    /// Returns `None` if the buffer is too small.
+    #[inline]
+    pub fn write_into(output: &mut [u8], value: usize) -> Option<usize> {
+        let len = Self::encoded_len(value);
+        let output = output.get_mut(..len)?;
+        for (index, byte) in output.iter_mut().enumerate() {
+            let shift = (len - 1 - index) * 7;
+            *byte = ((value >> shift) << 1) as u8;
         }
+        output[len - 1] |= 1;
+        Some(len)
     }
 
+    /// Reads the rest of the value whose first byte has been read into `acc`.
+    ///
+    /// Returns `None` if the input ends, or the value overflows.
     pub fn read_with_acc<'a, T>(iter: &mut T, acc: usize) -> Option<usize>
     where
         T: Iterator<Item = &'a u8>,
     {
+        let mut value = acc >> 1;
         let mut acc = acc;
         while (acc & 1) == 0 {
-            let next = *iter.next()? as usize;
-            acc = (acc << 7) | next;
+            acc = *iter.next()? as usize;
+            value = Self::_push(value, acc)?;
         }
-        Some(acc >> 1)
+        Some(value)
     }
 
     #[inline]
@@ -99,11 +64,45 @@ impl S7s {
     {
         Self::read_with_acc(iter, 0)
     }
+
+    /// Reads the value from the head of the slice, and returns it with the number of bytes read.
+    ///
+    /// Returns [`DecodeError::UnexpectedEof`] if the input ends,
+    /// or [`DecodeError::InvalidData`] if the value overflows.
+    #[inline]
+    pub fn read_slice(input: &[u8]) -> Result<(usize, usize), DecodeError> {
+        Self::read_slice_with_acc(input, 0)
+    }
+
+    /// Reads the rest of the value whose first byte has been read into `acc` from the slice,
+    /// and returns it with the number of bytes read.
+    pub fn read_slice_with_acc(input: &[u8], acc: usize) -> Result<(usize, usize), DecodeError> {
+        let mut value = acc >> 1;
+        if acc & 1 != 0 {
+            return Ok((value, 0));
+        }
+        for (index, &byte) in input.iter().enumerate() {
+            value = Self::_push(value, byte as usize).ok_or(DecodeError::InvalidData)?;
+            if byte & 1 != 0 {
+                return Ok((value, index + 1));
+            }
+        }
+        Err(DecodeError::UnexpectedEof)
+    }
+
+    /// Appends the 7 bits of the byte to the value, or returns `None` on overflow.
+    #[inline]
+    fn _push(value: usize, byte: usize) -> Option<usize> {
+        if value > usize::MAX >> 7 {
+            return None;
+        }
+        Some((value << 7) | (byte >> 1))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::S7s;
+    use super::*;
 
     #[test]
     fn scaled_value() {
@@ -121,7 +120,65 @@ mod tests {
                 let decoded = S7s::read(&mut iter).unwrap();
 
                 assert_eq!(value, decoded);
+                assert_eq!(S7s::read_slice(&vec), Ok((value, vec.len())));
+                assert_eq!(S7s::encoded_len(value), vec.len());
+
+                let mut buf = [0; S7s::MAX_ENCODED_LEN];
+                assert_eq!(S7s::write_into(&mut buf, value), Some(vec.len()));
+                assert_eq!(&buf[..vec.len()], vec);
+                assert_eq!(S7s::write_into(&mut buf[..vec.len() - 1], value), None);
             }
         }
     }
+
+    #[test]
+    fn reference() {
+        for (value, expected) in [
+            (0, &[0x01][..]),
+            (0x7f, &[0xff]),
+            (0x80, &[0x02, 0x01]),
+            (0x3fff, &[0xfe, 0xff]),
+            (0x4000, &[0x02, 0x00, 0x01]),
+        ] {
+            let mut vec = Vec::new();
+            S7s::write(&mut vec, value);
+            assert_eq!(vec, expected);
+        }
+    }
+
+    #[test]
+    fn large_value() {
+        let mut vec = Vec::new();
+        S7s::write(&mut vec, usize::MAX);
+        assert_eq!(vec.len(), S7s::MAX_ENCODED_LEN);
+
+        for value in [usize::MAX >> 1, (usize::MAX >> 1) + 1, usize::MAX] {
+            let mut vec = Vec::new();
+            S7s::write(&mut vec, value);
+            assert_eq!(vec.len(), S7s::encoded_len(value));
+            assert_eq!(S7s::read(&mut vec.iter()), Some(value));
+            assert_eq!(S7s::read_slice(&vec), Ok((value, vec.len())));
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let mut input = vec![0xfe; S7s::MAX_ENCODED_LEN];
+        input.push(0x01);
+        assert_eq!(S7s::read(&mut input.iter()), None);
+        assert_eq!(S7s::read_slice(&input), Err(DecodeError::InvalidData));
+
+        // Leading zeros do not overflow.
+        let mut input = vec![0x00; 100];
+        input.push(0x03);
+        assert_eq!(S7s::read(&mut input.iter()), Some(1));
+        assert_eq!(S7s::read_slice(&input), Ok((1, 101)));
+
+        assert_eq!(
+            S7s::read_slice(&[0x00, 0x02]),
+            Err(DecodeError::UnexpectedEof)
+        );
+        assert_eq!(S7s::read_slice_with_acc(&[], 0x05), Ok((2, 0)));
+        assert_eq!(S7s::read_slice_with_acc(&[0x03], 0x04), Ok((0x101, 1)));
+    }
 }
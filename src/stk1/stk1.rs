@@ -25,7 +25,7 @@ use crate::lz::{
     cache::{OffsetCache, OffsetCache3},
 };
 use crate::*;
-use alloc::{format, string::String, vec::Vec};
+use alloc::{format, string::String, vec, vec::Vec};
 
 mod s7s;
 pub use s7s::S7s;
@@ -113,11 +113,37 @@ impl Stk1 {
     }
 
     pub fn encode(input: &[u8], config: Configuration) -> Result<Vec<u8>, EncodeError> {
-        let mut output = Vec::new();
+        let mut output = vec![0; Self::encode_bound(input.len())];
+        let len = Self::encode_into(input, &mut output, config)?;
+        output.truncate(len);
+        Ok(output)
+    }
+
+    /// Returns the size of the output buffer that is always large enough for [`Stk1::encode_into`]
+    /// to compress `len` bytes.
+    pub const fn encode_bound(len: usize) -> usize {
+        // Each set of literals and matches takes a leading byte, and covers three bytes at least
+        // except the last one. The lengths in S7s take at most an eighth of what they count.
+        len + len / 8 + len / 3 + 2
+    }
+
+    /// Compresses `input` into `output` and returns the number of bytes written.
+    ///
+    /// Returns [`EncodeError::OutOfMemory`] if `output` is too small.
+    /// An `output` of [`Stk1::encode_bound`] bytes is always large enough.
+    pub fn encode_into(
+        input: &[u8],
+        output: &mut [u8],
+        config: Configuration,
+    ) -> Result<usize, EncodeError> {
+        let mut output = SliceWriter { output, len: 0 };
 
         let mut offset_cache = OffsetCache3::new(input, config.max_distance(), 0);
         let mut lit_buf = SliceWindow::new(input, 0);
-        let mut lz_buf = Vec::new();
+        // The matches are written right after the room for the leading byte and the literals,
+        // and moved into place when the set is flushed.
+        let mut set_start = 0;
+        let mut lz_count = 0;
 
         let mut cursor = 1;
         offset_cache.advance(cursor);
@@ -152,81 +178,110 @@ impl Stk1 {
                 }
 
                 if matches.is_zero() {
-                    if lz_buf.len() > 0 {
-                        Self::_flush(&mut output, lit_buf, &mut lz_buf)?;
+                    if lz_count > 0 {
+                        Self::_flush(&mut output, set_start, lit_buf, lz_count)?;
+                        set_start = output.len;
                         lit_buf = SliceWindow::new(input, cursor);
+                        lz_count = 0;
                     } else {
                         lit_buf.expand(1);
                     }
                     1
                 } else {
                     matches.len = matches.len.min(config.max_len());
-                    lz_buf.push(matches);
+                    if lz_count == 0 {
+                        output.len = set_start + 1 + lit_buf.len();
+                    }
+                    Self::_write_match(&mut output, matches)?;
+                    lz_count += 1;
                     matches.len
                 }
             };
             offset_cache.advance(count);
             cursor += count;
         }
-        Self::_flush(&mut output, lit_buf, &mut lz_buf)?;
+        Self::_flush(&mut output, set_start, lit_buf, lz_count)?;
 
-        Ok(output)
+        Ok(output.len)
     }
 
+    /// Writes the header and the literals of the set starting at `set_start`, in front of its
+    /// matches which have already been written.
     fn _flush(
-        output: &mut Vec<u8>,
+        output: &mut SliceWriter,
+        set_start: usize,
         lit_buf: SliceWindow<u8>,
-        lz_buf: &mut Vec<Match>,
+        lz_count: usize,
     ) -> Result<(), EncodeError> {
         // Literals of length 0 are impossible.
         assert!(lit_buf.len() > 0);
 
         let lit_len = lit_buf.len();
-        let lz_count = lz_buf.len();
+        let lz_start = set_start + 1 + lit_len;
+        let lz_end = if lz_count > 0 { output.len } else { lz_start };
+
+        let lit_len_size = if lit_len > 15 {
+            S7s::encoded_len(lit_len)
+        } else {
+            0
+        };
+        // Usually the number of LZs is not zero, but may be generated as a set with the last literal in the file
+        let lz_count_size = if lz_count > 15 || lz_count == 0 {
+            S7s::encoded_len(lz_count)
+        } else {
+            0
+        };
+        let shift = lit_len_size + lz_count_size;
+        if lz_end + shift > output.output.len() {
+            return Err(EncodeError::OutOfMemory);
+        }
+        output
+            .output
+            .copy_within(lz_start..lz_end, lz_start + shift);
+
         let leading = ((if lit_len > 15 { 0 } else { lit_len })
             | ((if lz_count > 15 { 0 } else { lz_count }) << 4)) as u8;
-        output.push(leading);
-        if lit_len > 15 {
-            S7s::write(output, lit_len);
+        output.len = set_start;
+        output.push(leading)?;
+        if lit_len_size > 0 {
+            output.write_s7s(lit_len)?;
         }
-        if lz_count > 15 || lz_count == 0 {
-            // Usually the number of LZs is not zero, but may be generated as a set with the last literal in the file
-            S7s::write(output, lz_count);
+        if lz_count_size > 0 {
+            output.write_s7s(lz_count)?;
         }
+        output.extend_from_slice(lit_buf.into_slice())?;
+        output.len = lz_end + shift;
 
-        output.extend_from_slice(lit_buf.into_slice());
-
-        for matches in lz_buf.iter() {
-            let lz_len = matches.len - 1;
-            let distance = matches.distance - 1;
-            let (dist_lead, dist_len, dist_trail) = if distance < 8 {
-                ((distance << 1) as u8 | 0x01, 0, 0)
-            } else if distance < 0x4_00 {
-                (((distance >> 6) & 0x0E) as u8, 1, distance & 0x7F)
-            } else if distance < 0x2_00_00 {
-                (((distance >> 13) & 0x0E) as u8, 2, distance & 0x3F_FF)
-            } else {
-                unreachable!()
-            };
-            let leading = dist_lead | (if lz_len > 15 { 0 } else { (lz_len << 4) as u8 });
-            output.push(leading);
-            match dist_len {
-                1 => {
-                    output.push(((dist_trail << 1) as u8) | 1);
-                }
-                2 => {
-                    output.push(((dist_trail >> 6) as u8) & 0xFE);
-                    output.push(((dist_trail << 1) as u8) | 1);
-                }
-                _ => {}
+        Ok(())
+    }
+
+    fn _write_match(output: &mut SliceWriter, matches: Match) -> Result<(), EncodeError> {
+        let lz_len = matches.len - 1;
+        let distance = matches.distance - 1;
+        let (dist_lead, dist_len, dist_trail) = if distance < 8 {
+            ((distance << 1) as u8 | 0x01, 0, 0)
+        } else if distance < 0x4_00 {
+            (((distance >> 6) & 0x0E) as u8, 1, distance & 0x7F)
+        } else if distance < 0x2_00_00 {
+            (((distance >> 13) & 0x0E) as u8, 2, distance & 0x3F_FF)
+        } else {
+            unreachable!()
+        };
+        let leading = dist_lead | (if lz_len > 15 { 0 } else { (lz_len << 4) as u8 });
+        output.push(leading)?;
+        match dist_len {
+            1 => {
+                output.push(((dist_trail << 1) as u8) | 1)?;
             }
-            if lz_len > 15 {
-                S7s::write(output, lz_len);
+            2 => {
+                output.push(((dist_trail >> 6) as u8) & 0xFE)?;
+                output.push(((dist_trail << 1) as u8) | 1)?;
             }
+            _ => {}
+        }
+        if lz_len > 15 {
+            output.write_s7s(lz_len)?;
         }
-
-        lz_buf.clear();
-
         Ok(())
     }
 
@@ -259,18 +314,17 @@ impl Stk1 {
                 let lead_cp = *iter.next().ok_or(DecodeError::InvalidData)?;
                 let ds = S7s::read_with_acc(iter, lead_cp as usize & 0x0F)
                     .ok_or(DecodeError::InvalidData)?;
-                let ds = ds + 1;
                 let cp = lead_cp >> 4;
                 let cp = if cp == 0 {
                     S7s::read(iter).ok_or(DecodeError::InvalidData)?
                 } else {
                     cp as usize
                 };
-                let cp = cp + 1;
-                if ds > cursor {
+                if ds >= cursor {
                     return Err(DecodeError::InvalidData);
                 }
-                let cp = cp.min(output.len() - cursor);
+                let ds = ds + 1;
+                let cp = cp.saturating_add(1).min(output.len() - cursor);
                 for _ in 0..cp {
                     output[cursor] = output[cursor - ds];
                     cursor += 1;
@@ -288,3 +342,85 @@ impl Stk1 {
         Self::decode(input, &mut vec).map(|_| vec)
     }
 }
+
+/// Byte writer into a fixed buffer
+struct SliceWriter<'a> {
+    output: &'a mut [u8],
+    len: usize,
+}
+
+impl SliceWriter<'_> {
+    #[inline]
+    fn push(&mut self, value: u8) -> Result<(), EncodeError> {
+        *self
+            .output
+            .get_mut(self.len)
+            .ok_or(EncodeError::OutOfMemory)? = value;
+        self.len += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), EncodeError> {
+        self.output
+            .get_mut(self.len..self.len + data.len())
+            .ok_or(EncodeError::OutOfMemory)?
+            .copy_from_slice(data);
+        self.len += data.len();
+        Ok(())
+    }
+
+    #[inline]
+    fn write_s7s(&mut self, value: usize) -> Result<(), EncodeError> {
+        self.len +=
+            S7s::write_into(&mut self.output[self.len..], value).ok_or(EncodeError::OutOfMemory)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_into() {
+        use rand::RngCore;
+        let mut rng = rand::rng();
+        let mut noise = vec![0u8; 0x10000];
+        rng.fill_bytes(&mut noise);
+        // Single literals between the shortest matches
+        let mut sparse = Vec::new();
+        for index in 0..0x4000u32 {
+            sparse.extend_from_slice(&[(index * 7) as u8, 0, 0, 0]);
+        }
+        for input in [
+            b"a".to_vec(),
+            noise,
+            sparse,
+            random_bytes(0, 1, 0x10000),
+            fib_str(b'a', b'b', 0x10000),
+            vec![0u8; 0x10000],
+        ] {
+            let mut output = vec![0u8; Stk1::encode_bound(input.len())];
+            let len = Stk1::encode_into(&input, &mut output, Configuration::DEFAULT).unwrap();
+            let decoded = Stk1::decode_to_vec(&output[..len], input.len()).unwrap();
+            assert_eq!(decoded, input);
+            assert_eq!(
+                Stk1::encode(&input, Configuration::DEFAULT).unwrap(),
+                &output[..len]
+            );
+            let mut exact = vec![0u8; len];
+            assert_eq!(
+                Stk1::encode_into(&input, &mut exact, Configuration::DEFAULT),
+                Ok(len)
+            );
+            assert_eq!(exact, &output[..len]);
+            if len > 0 {
+                assert_eq!(
+                    Stk1::encode_into(&input, &mut output[..len - 1], Configuration::DEFAULT),
+                    Err(EncodeError::OutOfMemory)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Delta coding
+
+/// Width of the little-endian lanes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaneWidth {
+    U8 = 1,
+    U16 = 2,
+    U32 = 4,
+}
+
+impl LaneWidth {
+    #[inline]
+    pub const fn bytes(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Delta coding of the lanes, which replaces each lane with the wrapping difference from the previous one
+///
+/// The difference is calculated a byte at a time with the borrow,
+/// so the data may be split anywhere, even within a lane.
+#[derive(Debug, Clone)]
+pub struct Delta {
+    width: LaneWidth,
+    prev: [u8; 4],
+    position: usize,
+    carry: bool,
+}
+
+impl Delta {
+    /// Creates a delta coder whose previous lane is zero.
+    #[inline]
+    pub const fn new(width: LaneWidth) -> Self {
+        Self {
+            width,
+            prev: [0; 4],
+            position: 0,
+            carry: false,
+        }
+    }
+
+    #[inline]
+    pub const fn width(&self) -> LaneWidth {
+        self.width
+    }
+
+    /// Replaces the lanes with the differences in place.
+    pub fn encode(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            let prev = &mut self.prev[self.position];
+            let (value, borrow1) = byte.overflowing_sub(*prev);
+            let (value, borrow2) = value.overflowing_sub(self.carry as u8);
+            *prev = *byte;
+            *byte = value;
+            self.carry = borrow1 | borrow2;
+            self.advance();
+        }
+    }
+
+    /// Replaces the differences with the lanes in place.
+    pub fn decode(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            let prev = &mut self.prev[self.position];
+            let (value, carry1) = byte.overflowing_add(*prev);
+            let (value, carry2) = value.overflowing_add(self.carry as u8);
+            *prev = value;
+            *byte = value;
+            self.carry = carry1 | carry2;
+            self.advance();
+        }
+    }
+
+    #[inline]
+    fn advance(&mut self) {
+        self.position += 1;
+        if self.position == self.width.bytes() {
+            self.position = 0;
+            self.carry = false;
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Reversible transforms
+//!
+//! Small transforms that make the data easier to compress before entropy coding,
+//! such as [`FSE::encode_bytes`](crate::entropy::fse::FSE::encode_bytes) or the prefix coding.
+//!
+//! Each of them keeps its state between the calls, so the data can be processed in pieces.
+
+use crate::*;
+
+#[cfg(test)]
+mod tests;
+
+mod delta;
+mod mtf;
+mod rle;
+mod zigzag;
+pub use delta::*;
+pub use mtf::*;
+pub use rle::*;
+pub use zigzag::*;
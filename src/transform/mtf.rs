@@ -0,0 +1,69 @@
+//! Move-to-front transform
+//!
+//! See also: <https://en.wikipedia.org/wiki/Move-to-front_transform>
+
+/// Move-to-front transform, which replaces each byte with its position in the recently used list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveToFront {
+    order: [u8; 256],
+}
+
+impl MoveToFront {
+    /// Creates a list in the order of the values.
+    #[inline]
+    pub const fn new() -> Self {
+        let mut order = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            order[i] = i as u8;
+            i += 1;
+        }
+        Self { order }
+    }
+
+    /// Returns the position of the byte, and moves it to the front.
+    #[inline]
+    pub fn encode_byte(&mut self, byte: u8) -> u8 {
+        let index = self
+            .order
+            .iter()
+            .position(|&v| v == byte)
+            .unwrap_or_default();
+        self.order.copy_within(0..index, 1);
+        self.order[0] = byte;
+        index as u8
+    }
+
+    /// Returns the byte at the position, and moves it to the front.
+    #[inline]
+    pub fn decode_byte(&mut self, index: u8) -> u8 {
+        let index = index as usize;
+        let byte = self.order[index];
+        self.order.copy_within(0..index, 1);
+        self.order[0] = byte;
+        byte
+    }
+
+    /// Replaces the bytes with their positions in place.
+    #[inline]
+    pub fn encode(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            *byte = self.encode_byte(*byte);
+        }
+    }
+
+    /// Replaces the positions with the bytes in place.
+    #[inline]
+    pub fn decode(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            *byte = self.decode_byte(*byte);
+        }
+    }
+}
+
+impl Default for MoveToFront {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,220 @@
+//! Run-length encodings
+
+use super::*;
+
+/// One in a digit of the zero run length
+pub const RUNA: u16 = 0;
+
+/// Two in a digit of the zero run length
+pub const RUNB: u16 = 1;
+
+/// Maximum symbol of the zero-run encoding, the byte 255
+pub const MAX_ZERO_RUN_SYMBOL: u16 = 256;
+
+/// Zero-run encoder as used in bzip2
+///
+/// A run of zeros is written in bijective base 2 with [`RUNA`] for 1 and [`RUNB`] for 2 in each digit,
+/// least significant first, and the other bytes `n` are written as `n + 1`.
+/// It suits the output of [`MoveToFront`], which is mostly zeros.
+#[derive(Debug, Clone, Default)]
+pub struct ZeroRunEncoder {
+    run_len: usize,
+}
+
+impl ZeroRunEncoder {
+    #[inline]
+    pub const fn new() -> Self {
+        Self { run_len: 0 }
+    }
+
+    /// Encodes the bytes, and keeps the last run of zeros until the next call.
+    pub fn encode(&mut self, input: &[u8], output: &mut Vec<u16>) {
+        for &byte in input.iter() {
+            if byte == 0 {
+                self.run_len += 1;
+            } else {
+                self.flush(output);
+                output.push(byte as u16 + 1);
+            }
+        }
+    }
+
+    /// Writes the pending run of zeros.
+    #[inline]
+    pub fn finish(&mut self, output: &mut Vec<u16>) {
+        self.flush(output);
+    }
+
+    fn flush(&mut self, output: &mut Vec<u16>) {
+        if self.run_len == 0 {
+            return;
+        }
+        let mut len = self.run_len - 1;
+        loop {
+            output.push(if len & 1 != 0 { RUNB } else { RUNA });
+            if len < 2 {
+                break;
+            }
+            len = (len - 2) / 2;
+        }
+        self.run_len = 0;
+    }
+}
+
+/// Zero-run decoder, see [`ZeroRunEncoder`]
+#[derive(Debug, Clone)]
+pub struct ZeroRunDecoder {
+    run_len: usize,
+    weight: usize,
+    remaining: usize,
+}
+
+impl ZeroRunDecoder {
+    /// Creates a decoder that writes up to `limit` bytes.
+    #[inline]
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            run_len: 0,
+            weight: 1,
+            remaining: limit,
+        }
+    }
+
+    /// Decodes the symbols, and keeps the last run of zeros until the next call.
+    pub fn decode(&mut self, input: &[u16], output: &mut Vec<u8>) -> Result<(), DecodeError> {
+        for &symbol in input.iter() {
+            if symbol <= RUNB {
+                if self.weight > self.remaining {
+                    return Err(DecodeError::InvalidData);
+                }
+                self.run_len += (symbol as usize + 1) * self.weight;
+                self.weight <<= 1;
+                if self.run_len > self.remaining {
+                    return Err(DecodeError::InvalidData);
+                }
+                continue;
+            }
+            self.flush(output);
+            if symbol > MAX_ZERO_RUN_SYMBOL || self.remaining == 0 {
+                return Err(DecodeError::InvalidData);
+            }
+            output.push((symbol - 1) as u8);
+            self.remaining -= 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending run of zeros.
+    #[inline]
+    pub fn finish(&mut self, output: &mut Vec<u8>) {
+        self.flush(output);
+    }
+
+    #[inline]
+    fn flush(&mut self, output: &mut Vec<u8>) {
+        output.resize(output.len() + self.run_len, 0);
+        self.remaining -= self.run_len;
+        self.run_len = 0;
+        self.weight = 1;
+    }
+}
+
+/// Number of identical bytes after which the count follows
+const BYTE_RUN_THRESHOLD: usize = 4;
+
+/// Maximum length of a run in [`ByteRunEncoder`], where the count is up to 251
+pub const MAX_BYTE_RUN: usize = 255;
+
+/// Byte-run encoder as used in the first stage of bzip2
+///
+/// A run of 4 to 255 identical bytes is written as 4 bytes followed by the number of the rest.
+#[derive(Debug, Clone, Default)]
+pub struct ByteRunEncoder {
+    byte: u8,
+    run_len: usize,
+}
+
+impl ByteRunEncoder {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            byte: 0,
+            run_len: 0,
+        }
+    }
+
+    /// Returns the size of a run of `len` bytes, up to [`MAX_BYTE_RUN`], in the output.
+    #[inline]
+    pub const fn encoded_len(len: usize) -> usize {
+        if len >= BYTE_RUN_THRESHOLD {
+            BYTE_RUN_THRESHOLD + 1
+        } else {
+            len
+        }
+    }
+
+    /// Encodes the bytes, and keeps the last run until the next call.
+    pub fn encode(&mut self, input: &[u8], output: &mut Vec<u8>) {
+        for &byte in input.iter() {
+            if self.run_len > 0 && byte == self.byte && self.run_len < MAX_BYTE_RUN {
+                self.run_len += 1;
+            } else {
+                self.flush(output);
+                self.byte = byte;
+                self.run_len = 1;
+            }
+        }
+    }
+
+    /// Writes the pending run.
+    #[inline]
+    pub fn finish(&mut self, output: &mut Vec<u8>) {
+        self.flush(output);
+    }
+
+    fn flush(&mut self, output: &mut Vec<u8>) {
+        let len = self.run_len.min(BYTE_RUN_THRESHOLD);
+        output.extend(core::iter::repeat_n(self.byte, len));
+        if self.run_len >= BYTE_RUN_THRESHOLD {
+            output.push((self.run_len - BYTE_RUN_THRESHOLD) as u8);
+        }
+        self.run_len = 0;
+    }
+}
+
+/// Byte-run decoder, see [`ByteRunEncoder`]
+#[derive(Debug, Clone, Default)]
+pub struct ByteRunDecoder {
+    last: Option<u8>,
+    run_len: usize,
+}
+
+impl ByteRunDecoder {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            run_len: 0,
+        }
+    }
+
+    /// Decodes the bytes. The count may be in the next call.
+    pub fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) {
+        for &byte in input.iter() {
+            if self.run_len == BYTE_RUN_THRESHOLD {
+                if let Some(last) = self.last.take() {
+                    output.resize(output.len() + byte as usize, last);
+                }
+                self.run_len = 0;
+                continue;
+            }
+            if Some(byte) == self.last {
+                self.run_len += 1;
+            } else {
+                self.last = Some(byte);
+                self.run_len = 1;
+            }
+            output.push(byte);
+        }
+    }
+}
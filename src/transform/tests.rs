@@ -0,0 +1,195 @@
+use super::*;
+use crate::entropy::fse::FSE;
+
+#[test]
+fn move_to_front() {
+    let mut data = b"bananaaa".to_vec();
+    MoveToFront::new().encode(&mut data);
+    assert_eq!(data, [98, 98, 110, 1, 1, 1, 0, 0]);
+    MoveToFront::new().decode(&mut data);
+    assert_eq!(data, b"bananaaa");
+
+    // In pieces
+    let input = fib_str(b'a', b'z', 1000);
+    let mut data = input.clone();
+    let mut encoder = MoveToFront::new();
+    for chunk in data.chunks_mut(7) {
+        encoder.encode(chunk);
+    }
+    let mut decoder = MoveToFront::new();
+    for chunk in data.chunks_mut(13) {
+        decoder.decode(chunk);
+    }
+    assert_eq!(data, input);
+}
+
+#[test]
+fn zero_run() {
+    let mut output = Vec::new();
+    let mut encoder = ZeroRunEncoder::new();
+    encoder.encode(&[0, 5, 0, 0, 7, 0, 0, 0, 255], &mut output);
+    encoder.encode(&[0; 4], &mut output);
+    encoder.finish(&mut output);
+    assert_eq!(
+        output,
+        [
+            RUNA,
+            6,
+            RUNB,
+            8,
+            RUNA,
+            RUNA,
+            MAX_ZERO_RUN_SYMBOL,
+            RUNB,
+            RUNA
+        ]
+    );
+
+    for len in 0..300 {
+        let input = [vec![0; len], vec![1], vec![0; len * 7]].concat();
+        let mut symbols = Vec::new();
+        let mut encoder = ZeroRunEncoder::new();
+        encoder.encode(&input, &mut symbols);
+        encoder.finish(&mut symbols);
+
+        let mut output = Vec::new();
+        let mut decoder = ZeroRunDecoder::new(input.len());
+        for chunk in symbols.chunks(3) {
+            decoder.decode(chunk, &mut output).unwrap();
+        }
+        decoder.finish(&mut output);
+        assert_eq!(output, input);
+
+        let mut decoder = ZeroRunDecoder::new(input.len() - 1);
+        let result = decoder.decode(&symbols, &mut output);
+        assert!(result.is_err() || len == 0);
+    }
+    let mut decoder = ZeroRunDecoder::new(usize::MAX);
+    assert!(decoder.decode(&[257], &mut Vec::new()).is_err());
+}
+
+#[test]
+fn byte_run() {
+    let mut runs = Vec::new();
+    for len in [1, 2, 3, 4, 5, 255, 256, 259, 1000] {
+        runs.extend(core::iter::repeat_n(len as u8, len));
+    }
+    let inputs = [b"abbcccddddeeeee".to_vec(), runs, random_bytes(0, 1, 1000)];
+    for input in inputs.iter() {
+        let mut encoded = Vec::new();
+        let mut encoder = ByteRunEncoder::new();
+        for chunk in input.chunks(5) {
+            encoder.encode(chunk, &mut encoded);
+        }
+        encoder.finish(&mut encoded);
+
+        let mut output = Vec::new();
+        let mut decoder = ByteRunDecoder::new();
+        for chunk in encoded.chunks(3) {
+            decoder.decode(chunk, &mut output);
+        }
+        assert_eq!(output, *input);
+    }
+
+    let mut encoded = Vec::new();
+    let mut encoder = ByteRunEncoder::new();
+    encoder.encode(b"abbbbbbcccc", &mut encoded);
+    encoder.finish(&mut encoded);
+    assert_eq!(encoded, b"abbbb\x02cccc\x00");
+}
+
+#[test]
+fn delta() {
+    let mut data = [1, 3, 6, 10, 5];
+    Delta::new(LaneWidth::U8).encode(&mut data);
+    assert_eq!(data, [1, 2, 3, 4, 0xfb]);
+
+    let values = [0x1234u16, 0x1300, 0x12ff, 0xffff, 0];
+    let mut data = values
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<_>>();
+    Delta::new(LaneWidth::U16).encode(&mut data);
+    let deltas = data
+        .chunks_exact(2)
+        .map(|v| u16::from_le_bytes([v[0], v[1]]))
+        .collect::<Vec<_>>();
+    assert_eq!(deltas, [0x1234, 0x00cc, 0xffff, 0xed00, 0x0001]);
+
+    for width in [LaneWidth::U8, LaneWidth::U16, LaneWidth::U32] {
+        let input = (0..1001u32)
+            .flat_map(|v| v.wrapping_mul(v).wrapping_mul(0x0101_0101).to_le_bytes())
+            .collect::<Vec<_>>();
+        let mut data = input.clone();
+        let mut encoder = Delta::new(width);
+        for chunk in data.chunks_mut(3) {
+            encoder.encode(chunk);
+        }
+        let mut decoder = Delta::new(width);
+        for chunk in data.chunks_mut(5) {
+            decoder.decode(chunk);
+        }
+        assert_eq!(data, input);
+    }
+}
+
+#[test]
+fn zigzag() {
+    assert_eq!(0i8.zigzag(), 0);
+    assert_eq!((-1i8).zigzag(), 1);
+    assert_eq!(1i8.zigzag(), 2);
+    assert_eq!(i8::MIN.zigzag(), 255);
+    assert_eq!(i8::MAX.zigzag(), 254);
+    assert_eq!(i64::MIN.zigzag(), u64::MAX);
+    for value in [0, 1, -1, 1000, -1000, i32::MAX, i32::MIN] {
+        assert_eq!(i32::from_zigzag(value.zigzag()), value);
+    }
+    for value in i16::MIN..=i16::MAX {
+        assert_eq!(i16::from_zigzag(value.zigzag()), value);
+    }
+
+    for width in [LaneWidth::U8, LaneWidth::U16, LaneWidth::U32] {
+        let input = random_bytes(0x80, 0x7f, 999);
+        let mut data = input.clone();
+        zigzag_encode_lanes(&mut data, width);
+        zigzag_decode_lanes(&mut data, width);
+        assert_eq!(data, input);
+
+        // Split in the middle of the lanes
+        let mut expected = input.clone();
+        zigzag_encode_lanes(&mut expected, width);
+        let mut encoder = ZigZagLanes::new(width);
+        let mut encoded = Vec::new();
+        for chunk in input.chunks(7) {
+            encoder.encode(chunk, &mut encoded);
+        }
+        encoder.finish(&mut encoded);
+        assert_eq!(encoded, expected);
+
+        let mut decoder = ZigZagLanes::new(width);
+        let mut decoded = Vec::new();
+        for chunk in encoded.chunks(3) {
+            decoder.decode(chunk, &mut decoded);
+        }
+        decoder.finish(&mut decoded);
+        assert_eq!(decoded, input);
+    }
+}
+
+#[test]
+fn compose() {
+    // A slowly increasing 16-bit signal
+    let input = (0..4000u32)
+        .flat_map(|v| ((v * 7 + (v % 5)) as u16).to_le_bytes())
+        .collect::<Vec<_>>();
+    let mut data = input.clone();
+    Delta::new(LaneWidth::U16).encode(&mut data);
+    zigzag_encode_lanes(&mut data, LaneWidth::U16);
+    let encoded = FSE::encode_bytes(&data);
+    assert!(encoded.len() * 3 < FSE::encode_bytes(&input).len());
+
+    let mut data = FSE::decode_bytes(&encoded, input.len()).unwrap();
+    zigzag_decode_lanes(&mut data, LaneWidth::U16);
+    Delta::new(LaneWidth::U16).decode(&mut data);
+    assert_eq!(data, input);
+}
@@ -0,0 +1,158 @@
+//! ZigZag mapping
+//!
+//! Maps the signed integers to the unsigned ones as `0, -1, 1, -2, 2, ...`,
+//! so that small differences in either direction become small values.
+
+use super::LaneWidth;
+use crate::*;
+
+/// ZigZag mapping between a signed integer and the unsigned one of the same size
+pub trait ZigZag: Sized {
+    type Unsigned;
+
+    fn zigzag(self) -> Self::Unsigned;
+
+    fn from_zigzag(value: Self::Unsigned) -> Self;
+}
+
+macro_rules! zigzag {
+    ($signed:ty, $unsigned:ty) => {
+        impl ZigZag for $signed {
+            type Unsigned = $unsigned;
+
+            #[inline]
+            fn zigzag(self) -> $unsigned {
+                ((self << 1) ^ (self >> (<$signed>::BITS - 1))) as $unsigned
+            }
+
+            #[inline]
+            fn from_zigzag(value: $unsigned) -> Self {
+                ((value >> 1) as $signed) ^ -((value & 1) as $signed)
+            }
+        }
+    };
+}
+
+zigzag!(i8, u8);
+zigzag!(i16, u16);
+zigzag!(i32, u32);
+zigzag!(i64, u64);
+
+/// Maps the little-endian signed lanes to the unsigned ones in place.
+///
+/// The trailing bytes shorter than a lane are left as they are, so each call must cover whole lanes.
+/// Use [`ZigZagLanes`] for the data split anywhere.
+pub fn zigzag_encode_lanes(data: &mut [u8], width: LaneWidth) {
+    match width {
+        LaneWidth::U8 => {
+            for byte in data.iter_mut() {
+                *byte = (*byte as i8).zigzag();
+            }
+        }
+        LaneWidth::U16 => {
+            for lane in data.chunks_exact_mut(2) {
+                let value = i16::from_le_bytes([lane[0], lane[1]]).zigzag();
+                lane.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        LaneWidth::U32 => {
+            for lane in data.chunks_exact_mut(4) {
+                let value = i32::from_le_bytes([lane[0], lane[1], lane[2], lane[3]]).zigzag();
+                lane.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Maps the unsigned lanes back to the little-endian signed ones in place.
+///
+/// The trailing bytes shorter than a lane are left as they are, so each call must cover whole lanes.
+pub fn zigzag_decode_lanes(data: &mut [u8], width: LaneWidth) {
+    match width {
+        LaneWidth::U8 => {
+            for byte in data.iter_mut() {
+                *byte = i8::from_zigzag(*byte) as u8;
+            }
+        }
+        LaneWidth::U16 => {
+            for lane in data.chunks_exact_mut(2) {
+                let value = i16::from_zigzag(u16::from_le_bytes([lane[0], lane[1]]));
+                lane.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        LaneWidth::U32 => {
+            for lane in data.chunks_exact_mut(4) {
+                let value =
+                    i32::from_zigzag(u32::from_le_bytes([lane[0], lane[1], lane[2], lane[3]]));
+                lane.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// ZigZag mapping of the lanes, which keeps the partial lane until the next call
+#[derive(Debug, Clone)]
+pub struct ZigZagLanes {
+    width: LaneWidth,
+    lane: [u8; 4],
+    len: usize,
+}
+
+impl ZigZagLanes {
+    #[inline]
+    pub const fn new(width: LaneWidth) -> Self {
+        Self {
+            width,
+            lane: [0; 4],
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub const fn width(&self) -> LaneWidth {
+        self.width
+    }
+
+    /// Maps the signed lanes to the unsigned ones.
+    pub fn encode(&mut self, input: &[u8], output: &mut Vec<u8>) {
+        self.process(input, output, zigzag_encode_lanes);
+    }
+
+    /// Maps the unsigned lanes back to the signed ones.
+    pub fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) {
+        self.process(input, output, zigzag_decode_lanes);
+    }
+
+    /// Writes the pending bytes shorter than a lane as they are.
+    pub fn finish(&mut self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.lane[..self.len]);
+        self.len = 0;
+    }
+
+    fn process<F>(&mut self, mut input: &[u8], output: &mut Vec<u8>, f: F)
+    where
+        F: Fn(&mut [u8], LaneWidth),
+    {
+        let width = self.width.bytes();
+        if self.len > 0 {
+            let len = (width - self.len).min(input.len());
+            self.lane[self.len..self.len + len].copy_from_slice(&input[..len]);
+            self.len += len;
+            input = &input[len..];
+            if self.len < width {
+                return;
+            }
+            f(&mut self.lane[..width], self.width);
+            output.extend_from_slice(&self.lane[..width]);
+            self.len = 0;
+        }
+
+        let whole = input.len() - input.len() % width;
+        let start = output.len();
+        output.extend_from_slice(&input[..whole]);
+        f(&mut output[start..], self.width);
+
+        self.len = input.len() - whole;
+        self.lane[..self.len].copy_from_slice(&input[whole..]);
+    }
+}
@@ -0,0 +1,464 @@
+//! WebP lossless decoder
+
+use super::*;
+use crate::{
+    entropy::prefix::CanonicalPrefixDecoder,
+    num::bits::{BitSize, BitStreamReader},
+};
+
+/// Decodes a WebP file.
+///
+/// Both the simple format and the extended format with a `VP8L` chunk are supported.
+/// Lossy and animated images are not supported.
+pub fn decode(input: &[u8]) -> Result<WebPImage, DecodeError> {
+    let chunks = riff_chunks(input)?;
+    let mut has_alpha = None;
+    for chunk in chunks.iter() {
+        match &chunk.fourcc {
+            b"VP8X" => {
+                let flags = *chunk.data.first().ok_or(DecodeError::UnexpectedEof)?;
+                if flags & 0x02 != 0 {
+                    // animation
+                    return Err(DecodeError::UnsupportedFormat);
+                }
+                has_alpha = Some(flags & 0x10 != 0);
+            }
+            b"VP8L" => {
+                let mut image = decode_vp8l(chunk.data)?;
+                if let Some(has_alpha) = has_alpha {
+                    image.has_alpha = has_alpha;
+                }
+                return Ok(image);
+            }
+            b"VP8 " | b"ANIM" => return Err(DecodeError::UnsupportedFormat),
+            _ => {}
+        }
+    }
+    Err(DecodeError::InvalidData)
+}
+
+/// Decodes a VP8L bitstream, which is the content of a `VP8L` chunk.
+pub fn decode_vp8l(input: &[u8]) -> Result<WebPImage, DecodeError> {
+    let (&signature, input) = input.split_first().ok_or(DecodeError::UnexpectedEof)?;
+    if signature != VP8L_SIGNATURE {
+        return Err(DecodeError::InvalidInput);
+    }
+    let mut reader = BitStreamReader::new(input);
+    let width = 1 + reader
+        .read_bits(BitSize::Bit14)
+        .ok_or(DecodeError::UnexpectedEof)?;
+    let height = 1 + reader
+        .read_bits(BitSize::Bit14)
+        .ok_or(DecodeError::UnexpectedEof)?;
+    let has_alpha = reader.read_bool().ok_or(DecodeError::UnexpectedEof)?;
+    let version = reader
+        .read_bits(BitSize::Bit3)
+        .ok_or(DecodeError::UnexpectedEof)?;
+    if version != 0 {
+        return Err(DecodeError::UnsupportedFormat);
+    }
+
+    let pixels = decode_image_stream(&mut reader, width as usize, height as usize, true)?;
+    Ok(WebPImage {
+        width,
+        height,
+        has_alpha,
+        pixels,
+    })
+}
+
+/// Decodes a VP8L image stream without the header, such as the content of an `ALPH` chunk.
+pub fn decode_vp8l_image_stream(
+    input: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<Vec<u32>, DecodeError> {
+    if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(DecodeError::InvalidInput);
+    }
+    let mut reader = BitStreamReader::new(input);
+    decode_image_stream(&mut reader, width as usize, height as usize, true)
+}
+
+/// Inverse transform
+enum Transform {
+    Predictor {
+        bits: u8,
+        width: usize,
+        data: Vec<u32>,
+    },
+    Color {
+        bits: u8,
+        width: usize,
+        data: Vec<u32>,
+    },
+    SubtractGreen,
+    ColorIndexing {
+        width_bits: u8,
+        width: usize,
+        palette: Vec<u32>,
+    },
+}
+
+impl Transform {
+    fn read(
+        reader: &mut BitStreamReader,
+        kind: u32,
+        width: usize,
+        height: usize,
+    ) -> Result<Self, DecodeError> {
+        match kind {
+            0 | 1 => {
+                let bits = 2 + reader
+                    .read_bits(BitSize::Bit3)
+                    .ok_or(DecodeError::UnexpectedEof)? as u8;
+                let data = decode_image_stream(
+                    reader,
+                    subsample_size(width, bits),
+                    subsample_size(height, bits),
+                    false,
+                )?;
+                if kind == 0 {
+                    Ok(Self::Predictor { bits, width, data })
+                } else {
+                    Ok(Self::Color { bits, width, data })
+                }
+            }
+            2 => Ok(Self::SubtractGreen),
+            _ => {
+                let size = 1 + reader
+                    .read_bits(BitSize::Bit8)
+                    .ok_or(DecodeError::UnexpectedEof)? as usize;
+                let mut palette = decode_image_stream(reader, size, 1, false)?;
+                for i in 1..size {
+                    palette[i] = add_pixels(palette[i], palette[i - 1]);
+                }
+                let width_bits = match size {
+                    0..=2 => 3,
+                    3..=4 => 2,
+                    5..=16 => 1,
+                    _ => 0,
+                };
+                Ok(Self::ColorIndexing {
+                    width_bits,
+                    width,
+                    palette,
+                })
+            }
+        }
+    }
+
+    /// Returns the width of the image that the transform is applied to.
+    #[inline]
+    fn coded_width(&self, width: usize) -> usize {
+        match self {
+            Self::ColorIndexing { width_bits, .. } => subsample_size(width, *width_bits),
+            _ => width,
+        }
+    }
+
+    fn apply_inverse(&self, pixels: Vec<u32>, height: usize) -> Result<Vec<u32>, DecodeError> {
+        match self {
+            Self::Predictor { bits, width, data } => {
+                let mut pixels = pixels;
+                let width = *width;
+                let tiles_per_row = subsample_size(width, *bits);
+                for y in 0..height {
+                    let tile_row = &data[(y >> bits) * tiles_per_row..];
+                    for x in 0..width {
+                        let pos = y * width + x;
+                        let prediction = if y == 0 {
+                            if x == 0 { 0xff00_0000 } else { pixels[pos - 1] }
+                        } else if x == 0 {
+                            pixels[pos - width]
+                        } else {
+                            let mode = (tile_row[x >> bits] >> 8) & 0x0f;
+                            predict(
+                                mode,
+                                pixels[pos - 1],
+                                pixels[pos - width],
+                                pixels[pos - width + 1],
+                                pixels[pos - width - 1],
+                            )
+                        };
+                        pixels[pos] = add_pixels(pixels[pos], prediction);
+                    }
+                }
+                Ok(pixels)
+            }
+            Self::Color { bits, width, data } => {
+                let mut pixels = pixels;
+                let width = *width;
+                let tiles_per_row = subsample_size(width, *bits);
+                for (y, row) in pixels.chunks_exact_mut(width).enumerate() {
+                    let tile_row = &data[(y >> bits) * tiles_per_row..];
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let element = tile_row[x >> bits];
+                        let green_to_red = element as u8;
+                        let green_to_blue = (element >> 8) as u8;
+                        let red_to_blue = (element >> 16) as u8;
+                        let green = (*pixel >> 8) as u8;
+                        let red = ((*pixel >> 16) as u8)
+                            .wrapping_add(color_transform_delta(green_to_red, green));
+                        let blue = (*pixel as u8)
+                            .wrapping_add(color_transform_delta(green_to_blue, green))
+                            .wrapping_add(color_transform_delta(red_to_blue, red));
+                        *pixel = (*pixel & 0xff00_ff00) | ((red as u32) << 16) | blue as u32;
+                    }
+                }
+                Ok(pixels)
+            }
+            Self::SubtractGreen => {
+                let mut pixels = pixels;
+                for pixel in pixels.iter_mut() {
+                    let green = (*pixel >> 8) & 0xff;
+                    *pixel = add_pixels(*pixel, (green << 16) | green);
+                }
+                Ok(pixels)
+            }
+            Self::ColorIndexing {
+                width_bits,
+                width,
+                palette,
+            } => {
+                let width = *width;
+                let coded_width = subsample_size(width, *width_bits);
+                let bits_per_pixel = 8 >> width_bits;
+                let index_mask = (1 << bits_per_pixel) - 1;
+                let x_mask = (1 << width_bits) - 1;
+                let mut output = Vec::new();
+                output
+                    .try_reserve_exact(width * height)
+                    .map_err(|_| DecodeError::OutOfMemory)?;
+                for row in pixels.chunks_exact(coded_width) {
+                    for x in 0..width {
+                        let packed = (row[x >> width_bits] >> 8) & 0xff;
+                        let index = (packed >> ((x & x_mask) * bits_per_pixel)) & index_mask;
+                        // Out of range indices are transparent black.
+                        output.push(palette.get(index as usize).copied().unwrap_or_default());
+                    }
+                }
+                Ok(output)
+            }
+        }
+    }
+}
+
+/// Prefix code that may have a single symbol, which takes no bits.
+enum PrefixCode {
+    Single(u32),
+    Tree(CanonicalPrefixDecoder),
+}
+
+impl PrefixCode {
+    fn read(reader: &mut BitStreamReader, alphabet_size: usize) -> Result<Self, DecodeError> {
+        let lengths = CanonicalPrefixDecoder::decode_prefix_table_webp(reader, alphabet_size)?;
+        let mut symbols = lengths.iter().enumerate().filter(|(_, v)| **v > 0);
+        match (symbols.next(), symbols.next()) {
+            (None, _) => Err(DecodeError::InvalidData),
+            (Some((symbol, _)), None) => Ok(Self::Single(symbol as u32)),
+            _ => CanonicalPrefixDecoder::with_lengths(&lengths, false).map(Self::Tree),
+        }
+    }
+
+    #[inline]
+    fn decode(&self, reader: &mut BitStreamReader) -> Result<u32, DecodeError> {
+        match self {
+            Self::Single(symbol) => Ok(*symbol),
+            Self::Tree(decoder) => decoder.decode(reader),
+        }
+    }
+}
+
+/// Prefix codes for green + lengths + color cache, red, blue, alpha and distance
+struct PrefixCodeGroup([PrefixCode; 5]);
+
+impl PrefixCodeGroup {
+    fn read(reader: &mut BitStreamReader, color_cache_size: usize) -> Result<Self, DecodeError> {
+        Ok(Self([
+            PrefixCode::read(reader, 256 + NUM_LENGTH_CODES + color_cache_size)?,
+            PrefixCode::read(reader, 256)?,
+            PrefixCode::read(reader, 256)?,
+            PrefixCode::read(reader, 256)?,
+            PrefixCode::read(reader, NUM_DISTANCE_CODES)?,
+        ]))
+    }
+}
+
+struct ColorCache {
+    bits: u8,
+    entries: Vec<u32>,
+}
+
+impl ColorCache {
+    fn new(bits: Option<u8>) -> Self {
+        let mut entries = Vec::new();
+        if let Some(bits) = bits {
+            entries.resize(1 << bits, 0);
+        }
+        Self {
+            bits: bits.unwrap_or_default(),
+            entries,
+        }
+    }
+
+    #[inline]
+    fn insert(&mut self, pixel: u32) {
+        if self.bits > 0 {
+            self.entries[color_cache_hash(pixel, self.bits)] = pixel;
+        }
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> Option<u32> {
+        self.entries.get(index).copied()
+    }
+}
+
+/// Entropy image that selects a prefix code group for each block
+struct MetaPrefixCodes {
+    bits: u8,
+    width: usize,
+    data: Vec<u32>,
+}
+
+fn decode_image_stream(
+    reader: &mut BitStreamReader,
+    width: usize,
+    height: usize,
+    is_main_image: bool,
+) -> Result<Vec<u32>, DecodeError> {
+    let mut transforms = Vec::new();
+    let mut coded_width = width;
+    if is_main_image {
+        let mut seen = 0u8;
+        while reader.read_bool().ok_or(DecodeError::UnexpectedEof)? {
+            let kind = reader
+                .read_bits(BitSize::Bit2)
+                .ok_or(DecodeError::UnexpectedEof)?;
+            if seen & (1 << kind) != 0 {
+                return Err(DecodeError::InvalidData);
+            }
+            seen |= 1 << kind;
+            let transform = Transform::read(reader, kind, coded_width, height)?;
+            coded_width = transform.coded_width(coded_width);
+            transforms.push(transform);
+        }
+    }
+
+    let color_cache_bits = if reader.read_bool().ok_or(DecodeError::UnexpectedEof)? {
+        let bits = reader
+            .read_bits(BitSize::Bit4)
+            .ok_or(DecodeError::UnexpectedEof)? as u8;
+        if !(1..=MAX_COLOR_CACHE_BITS).contains(&bits) {
+            return Err(DecodeError::InvalidData);
+        }
+        Some(bits)
+    } else {
+        None
+    };
+    let color_cache_size = color_cache_bits.map(|v| 1 << v).unwrap_or_default();
+
+    let mut meta_codes = None;
+    let mut num_groups = 1;
+    if is_main_image && reader.read_bool().ok_or(DecodeError::UnexpectedEof)? {
+        let bits = 2 + reader
+            .read_bits(BitSize::Bit3)
+            .ok_or(DecodeError::UnexpectedEof)? as u8;
+        let meta_width = subsample_size(coded_width, bits);
+        let mut data =
+            decode_image_stream(reader, meta_width, subsample_size(height, bits), false)?;
+        for pixel in data.iter_mut() {
+            *pixel = (*pixel >> 8) & 0xffff;
+            num_groups = num_groups.max(*pixel as usize + 1);
+        }
+        meta_codes = Some(MetaPrefixCodes {
+            bits,
+            width: meta_width,
+            data,
+        });
+    }
+
+    let mut groups = Vec::new();
+    for _ in 0..num_groups {
+        groups.push(PrefixCodeGroup::read(reader, color_cache_size)?);
+    }
+
+    let mut pixels = decode_pixels(
+        reader,
+        coded_width,
+        height,
+        &groups,
+        meta_codes.as_ref(),
+        color_cache_bits,
+    )?;
+
+    for transform in transforms.iter().rev() {
+        pixels = transform.apply_inverse(pixels, height)?;
+    }
+    Ok(pixels)
+}
+
+fn decode_pixels(
+    reader: &mut BitStreamReader,
+    width: usize,
+    height: usize,
+    groups: &[PrefixCodeGroup],
+    meta_codes: Option<&MetaPrefixCodes>,
+    color_cache_bits: Option<u8>,
+) -> Result<Vec<u32>, DecodeError> {
+    let total = width * height;
+    let mut pixels = Vec::new();
+    pixels
+        .try_reserve_exact(total)
+        .map_err(|_| DecodeError::OutOfMemory)?;
+    let mut color_cache = ColorCache::new(color_cache_bits);
+
+    while pixels.len() < total {
+        let pos = pixels.len();
+        let group = match meta_codes {
+            Some(meta) => {
+                let x = (pos % width) >> meta.bits;
+                let y = (pos / width) >> meta.bits;
+                &groups[meta.data[y * meta.width + x] as usize].0
+            }
+            None => &groups[0].0,
+        };
+
+        let green = group[0].decode(reader)?;
+        if green < 256 {
+            let red = group[1].decode(reader)?;
+            let blue = group[2].decode(reader)?;
+            let alpha = group[3].decode(reader)?;
+            let pixel = (alpha << 24) | (red << 16) | (green << 8) | blue;
+            pixels.push(pixel);
+            color_cache.insert(pixel);
+        } else if green < (256 + NUM_LENGTH_CODES) as u32 {
+            let len = prefix_value(green - 256, |bits| {
+                reader.read_bits(BitSize::new(bits as u8)?)
+            })
+            .ok_or(DecodeError::UnexpectedEof)?;
+            let distance_code = group[4].decode(reader)?;
+            let distance_code = prefix_value(distance_code, |bits| {
+                reader.read_bits(BitSize::new(bits as u8)?)
+            })
+            .ok_or(DecodeError::UnexpectedEof)?;
+            let distance = plane_code_to_distance(width, distance_code);
+            if distance > pos || len > total - pos {
+                return Err(DecodeError::InvalidData);
+            }
+            for _ in 0..len {
+                let pixel = pixels[pixels.len() - distance];
+                pixels.push(pixel);
+                color_cache.insert(pixel);
+            }
+        } else {
+            let index = (green as usize) - 256 - NUM_LENGTH_CODES;
+            let pixel = color_cache.get(index).ok_or(DecodeError::InvalidData)?;
+            pixels.push(pixel);
+            color_cache.insert(pixel);
+        }
+    }
+
+    Ok(pixels)
+}
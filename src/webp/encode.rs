@@ -0,0 +1,718 @@
+//! WebP lossless encoder
+
+use super::*;
+use crate::{
+    entropy::prefix::{CanonicalPrefixCoder, LengthLimitMethod},
+    lz::lzss::{self, LZSS},
+    num::{
+        VarLenInteger,
+        bits::{BitSize, BitStreamWriter, Write},
+        math,
+    },
+};
+use alloc::vec;
+
+/// Minimum length of backward references in pixels
+const MIN_COPY_LEN: usize = 3;
+
+/// Maximum length of backward references in pixels
+const MAX_COPY_LEN: usize = 4096;
+
+/// Maximum distance of backward references in pixels
+const MAX_COPY_DISTANCE: usize = (1 << 20) - NUM_PLANE_CODES;
+
+/// Encoder configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Configuration {
+    effort: u8,
+    subtract_green: bool,
+    predictor: bool,
+    color_cache_bits: Option<u8>,
+}
+
+impl Configuration {
+    pub const MAX_EFFORT: u8 = 9;
+
+    pub const DEFAULT: Self = Self::new(5);
+
+    pub const FASTEST: Self = Self::new(0);
+
+    pub const BEST: Self = Self::new(Self::MAX_EFFORT);
+
+    /// Creates a configuration with the effort level from 0 (fastest) to 9 (best).
+    ///
+    /// Both transforms are enabled and the color cache size is chosen by the effort level.
+    #[inline]
+    pub const fn new(effort: u8) -> Self {
+        Self {
+            effort: if effort > Self::MAX_EFFORT {
+                Self::MAX_EFFORT
+            } else {
+                effort
+            },
+            subtract_green: true,
+            predictor: true,
+            color_cache_bits: None,
+        }
+    }
+
+    /// Allows the subtract green transform, which is used only if it seems to reduce the residuals.
+    #[inline]
+    pub const fn subtract_green(mut self, enabled: bool) -> Self {
+        self.subtract_green = enabled;
+        self
+    }
+
+    #[inline]
+    pub const fn predictor(mut self, enabled: bool) -> Self {
+        self.predictor = enabled;
+        self
+    }
+
+    /// Uses the color cache of `1 << bits` entries, or no color cache if `bits` is 0.
+    ///
+    /// `bits` is clamped to `MAX_COLOR_CACHE_BITS`.
+    #[inline]
+    pub const fn color_cache_bits(mut self, bits: u8) -> Self {
+        self.color_cache_bits = Some(if bits > MAX_COLOR_CACHE_BITS {
+            MAX_COLOR_CACHE_BITS
+        } else {
+            bits
+        });
+        self
+    }
+
+    #[inline]
+    pub const fn effort(&self) -> u8 {
+        self.effort
+    }
+
+    /// Returns the color cache sizes to try.
+    fn color_cache_candidates(&self) -> &'static [u8] {
+        match self.color_cache_bits {
+            Some(bits) => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11][bits as usize..=bits as usize],
+            None => match self.effort {
+                0..=2 => &[0],
+                3..=6 => &[0, 8],
+                _ => &[0, 4, 6, 8, 10],
+            },
+        }
+    }
+
+    /// Returns the predictor modes to try.
+    fn predictor_modes(&self) -> &'static [u32] {
+        match self.effort {
+            0..=2 => &[1, 2, 11],
+            _ => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13],
+        }
+    }
+
+    #[inline]
+    fn predictor_bits(&self) -> u8 {
+        if self.effort <= 2 { 5 } else { 4 }
+    }
+
+    /// Returns the block size in bits and the maximum number of prefix code groups of the entropy
+    /// image to try.
+    #[inline]
+    fn entropy_image(&self) -> Option<(u8, usize)> {
+        match self.effort {
+            0..=2 => None,
+            3..=6 => Some((5, 8)),
+            _ => Some((4, 32)),
+        }
+    }
+}
+
+impl Default for Configuration {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Encodes ARGB pixels into a lossless WebP file.
+pub fn encode(
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+    config: Configuration,
+) -> Result<Vec<u8>, EncodeError> {
+    let vp8l = encode_vp8l(width, height, pixels, config)?;
+    let mut output = Vec::with_capacity(vp8l.len() + 21);
+    output.extend_from_slice(b"RIFF");
+    output.extend_from_slice(&((vp8l.len() + (vp8l.len() & 1) + 12) as u32).to_le_bytes());
+    output.extend_from_slice(b"WEBPVP8L");
+    output.extend_from_slice(&(vp8l.len() as u32).to_le_bytes());
+    output.extend_from_slice(&vp8l);
+    if vp8l.len() & 1 != 0 {
+        output.push(0);
+    }
+    Ok(output)
+}
+
+/// Encodes ARGB pixels into a VP8L bitstream, which is the content of a `VP8L` chunk.
+pub fn encode_vp8l(
+    width: u32,
+    height: u32,
+    pixels: &[u32],
+    config: Configuration,
+) -> Result<Vec<u8>, EncodeError> {
+    if width == 0
+        || height == 0
+        || width > MAX_DIMENSION
+        || height > MAX_DIMENSION
+        || pixels.len() != width as usize * height as usize
+    {
+        return Err(EncodeError::InvalidInput);
+    }
+    let has_alpha = pixels.iter().any(|&v| v < 0xff00_0000);
+
+    let mut header = BitStreamWriter::new();
+    header.write(VarLenInteger::new(BitSize::Bit14, width - 1));
+    header.write(VarLenInteger::new(BitSize::Bit14, height - 1));
+    header.write(has_alpha);
+    header.write(VarLenInteger::new(BitSize::Bit3, 0)); // version
+
+    let width = width as usize;
+    let height = height as usize;
+    let mut pixels = pixels.to_vec();
+    if config.subtract_green {
+        let subtracted = pixels
+            .iter()
+            .map(|&pixel| {
+                let green = (pixel >> 8) & 0xff;
+                sub_pixels(pixel, (green << 16) | green)
+            })
+            .collect::<Vec<_>>();
+        if neighbor_cost(&subtracted, width) < neighbor_cost(&pixels, width) {
+            header.write(true);
+            header.write(VarLenInteger::new(BitSize::Bit2, 2));
+            pixels = subtracted;
+        }
+    }
+    if config.predictor && width * height > 1 {
+        let bits = config.predictor_bits();
+        let (residuals, modes) = apply_predictor(&pixels, width, height, bits, &config);
+        header.write(true);
+        header.write(VarLenInteger::new(BitSize::Bit2, 0));
+        header.write(VarLenInteger::new(BitSize::Bit3, bits as u32 - 2));
+        encode_image_stream(
+            &mut header,
+            &modes,
+            subsample_size(width, bits),
+            0,
+            false,
+            &config,
+        )?;
+        pixels = residuals;
+    }
+    header.write(false); // end of transforms
+
+    let mut best: Option<BitStreamWriter> = None;
+    for &cache_bits in config.color_cache_candidates() {
+        let mut writer = header.clone();
+        encode_image_stream(&mut writer, &pixels, width, cache_bits, true, &config)?;
+        if best
+            .as_ref()
+            .is_none_or(|best| writer.bit_count() < best.bit_count())
+        {
+            best = Some(writer);
+        }
+    }
+
+    let mut output = [VP8L_SIGNATURE].to_vec();
+    output.extend_from_slice(&best.unwrap().into_bytes());
+    Ok(output)
+}
+
+/// Returns the residuals and the entropy image of the predictor modes.
+fn apply_predictor(
+    pixels: &[u32],
+    width: usize,
+    height: usize,
+    bits: u8,
+    config: &Configuration,
+) -> (Vec<u32>, Vec<u32>) {
+    let tiles_per_row = subsample_size(width, bits);
+    let tiles_per_column = subsample_size(height, bits);
+    let tile_size = 1 << bits;
+    let prediction = |mode: u32, x: usize, y: usize| {
+        let pos = y * width + x;
+        if y == 0 {
+            if x == 0 { 0xff00_0000 } else { pixels[pos - 1] }
+        } else if x == 0 {
+            pixels[pos - width]
+        } else {
+            predict(
+                mode,
+                pixels[pos - 1],
+                pixels[pos - width],
+                pixels[pos - width + 1],
+                pixels[pos - width - 1],
+            )
+        }
+    };
+
+    let mut modes = Vec::with_capacity(tiles_per_row * tiles_per_column);
+    for tile_y in 0..tiles_per_column {
+        let y_range = tile_y * tile_size..((tile_y + 1) * tile_size).min(height);
+        for tile_x in 0..tiles_per_row {
+            let x_range = tile_x * tile_size..((tile_x + 1) * tile_size).min(width);
+            let mut best = (usize::MAX, 0);
+            for &mode in config.predictor_modes() {
+                let mut cost = 0;
+                for y in y_range.clone() {
+                    for x in x_range.clone() {
+                        let residual = sub_pixels(pixels[y * width + x], prediction(mode, x, y));
+                        cost += residual_cost(residual);
+                    }
+                }
+                if cost < best.0 {
+                    best = (cost, mode);
+                }
+            }
+            modes.push(0xff00_0000 | (best.1 << 8));
+        }
+    }
+
+    let mut residuals = Vec::with_capacity(pixels.len());
+    for y in 0..height {
+        let tile_row = &modes[(y >> bits) * tiles_per_row..];
+        for x in 0..width {
+            let mode = (tile_row[x >> bits] >> 8) & 0x0f;
+            residuals.push(sub_pixels(pixels[y * width + x], prediction(mode, x, y)));
+        }
+    }
+    (residuals, modes)
+}
+
+/// Returns a rough cost of the red and blue channels predicted by the left and the upper pixels.
+fn neighbor_cost(pixels: &[u32], width: usize) -> usize {
+    let cost = |distance: usize| {
+        pixels
+            .iter()
+            .zip(pixels.iter().skip(distance))
+            .map(|(&prev, &pixel)| residual_cost(sub_pixels(pixel, prev) & 0x00ff_00ff))
+            .sum::<usize>()
+    };
+    cost(1) + cost(width)
+}
+
+/// Returns a rough cost of the residual, which is smaller near zero.
+#[inline]
+fn residual_cost(residual: u32) -> usize {
+    residual
+        .to_le_bytes()
+        .iter()
+        .map(|&v| (v as i8).unsigned_abs() as usize)
+        .sum()
+}
+
+/// Entropy coded pixel
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    Literal(u32),
+    CacheIndex(u32),
+    Copy { len: usize, distance: usize },
+}
+
+impl Token {
+    /// Returns the number of pixels of the token.
+    #[inline]
+    fn len(&self) -> usize {
+        match *self {
+            Token::Literal(_) | Token::CacheIndex(_) => 1,
+            Token::Copy { len, .. } => len,
+        }
+    }
+
+    /// Calls `f` with the index of the prefix code and the symbol of each prefix coded value.
+    ///
+    /// The distance must have been converted to the distance code.
+    #[inline]
+    fn for_each_symbol<F: FnMut(usize, usize)>(&self, mut f: F) {
+        match *self {
+            Token::Literal(pixel) => {
+                f(0, (pixel >> 8) as usize & 0xff);
+                f(1, (pixel >> 16) as usize & 0xff);
+                f(2, pixel as usize & 0xff);
+                f(3, (pixel >> 24) as usize);
+            }
+            Token::CacheIndex(index) => f(0, 256 + NUM_LENGTH_CODES + index as usize),
+            Token::Copy { len, distance } => {
+                f(0, 256 + prefix_encode(len).0);
+                f(4, prefix_encode(distance).0);
+            }
+        }
+    }
+}
+
+/// Finds backward references in pixels with LZSS over their bytes.
+///
+/// The bytes of a match repeat with its distance, so the pixels in it repeat with the least
+/// common multiple of the distance and the pixel size. The pixels at both ends that are not
+/// covered by a whole period are left as literals.
+fn backward_references(pixels: &[u32]) -> Result<Vec<Token>, EncodeError> {
+    let bytes = pixels
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<_>>();
+    // The distance in pixels is not greater than the distance in bytes.
+    let config = lzss::Configuration::new(MAX_COPY_DISTANCE, LZSS::MAX_LEN, 0, 0, 0, 0);
+
+    // Matches in bytes as `(start, end, distance)`, where the consecutive matches of the same
+    // distance are joined.
+    let mut runs: Vec<(usize, usize, usize)> = Vec::new();
+    let mut cursor = 0;
+    LZSS::encode_lcp(&bytes, config, |lzss| {
+        match lzss {
+            LZSS::Literal(_) => cursor += 1,
+            LZSS::Match(matches) => {
+                match runs.last_mut() {
+                    Some(run) if run.1 == cursor && run.2 == matches.distance => {
+                        run.1 += matches.len
+                    }
+                    _ => runs.push((cursor, cursor + matches.len, matches.distance)),
+                }
+                cursor += matches.len;
+            }
+        }
+        Ok(())
+    })?;
+
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    for (start, end, distance) in runs {
+        let period = distance << (2 - distance.trailing_zeros().min(2));
+        let first = (start + period - distance).div_ceil(4);
+        let last = end / 4;
+        if last < first + MIN_COPY_LEN {
+            continue;
+        }
+        tokens.extend(pixels[pos..first].iter().map(|&v| Token::Literal(v)));
+        // Longer matches are split, as the prefix coded length is limited.
+        for offset in (first..last).step_by(MAX_COPY_LEN) {
+            tokens.push(Token::Copy {
+                len: (last - offset).min(MAX_COPY_LEN),
+                distance: period / 4,
+            });
+        }
+        pos = last;
+    }
+    tokens.extend(pixels[pos..].iter().map(|&v| Token::Literal(v)));
+    Ok(tokens)
+}
+
+/// Returns the prefix code, the number of extra bits and the extra bits of the length or distance.
+#[inline]
+fn prefix_encode(value: usize) -> (usize, usize, u32) {
+    let value = value - 1;
+    if value < 4 {
+        return (value, 0, 0);
+    }
+    let highest_bit = value.ilog2() as usize;
+    let second_bit = (value >> (highest_bit - 1)) & 1;
+    let extra_bits = highest_bit - 1;
+    (
+        2 * highest_bit + second_bit,
+        extra_bits,
+        (value & ((1 << extra_bits) - 1)) as u32,
+    )
+}
+
+/// Converts a distance in pixels to the distance code.
+fn distance_to_plane_code(width: usize, distance: usize) -> usize {
+    (1..=NUM_PLANE_CODES)
+        .find(|&code| plane_code_to_distance(width, code) == distance)
+        .unwrap_or(distance + NUM_PLANE_CODES)
+}
+
+/// Estimated bits of the prefix codes of a group, which are written in addition to the symbols
+const GROUP_COST: f64 = 400.0;
+
+/// Histograms of the five prefix codes of a group
+#[derive(Debug, Clone)]
+struct Histograms {
+    counts: [Vec<usize>; 5],
+    totals: [usize; 5],
+}
+
+impl Histograms {
+    fn new(color_cache_size: usize) -> Self {
+        Self {
+            counts: [
+                vec![0; 256 + NUM_LENGTH_CODES + color_cache_size],
+                vec![0; 256],
+                vec![0; 256],
+                vec![0; 256],
+                vec![0; NUM_DISTANCE_CODES],
+            ],
+            totals: [0; 5],
+        }
+    }
+
+    /// Adds the symbols as `(code, symbol, count)`.
+    fn add(&mut self, symbols: &[(usize, usize, usize)]) {
+        for &(code, symbol, count) in symbols.iter() {
+            self.counts[code][symbol] += count;
+            self.totals[code] += count;
+        }
+    }
+
+    /// Returns the increase of the entropy in bits by adding the symbols.
+    fn cost_of(&self, symbols: &[(usize, usize, usize)]) -> f64 {
+        let mut totals = [0; 5];
+        let mut cost = 0.0;
+        for &(code, symbol, count) in symbols.iter() {
+            let current = self.counts[code][symbol];
+            cost -= weighted_log2(current + count) - weighted_log2(current);
+            totals[code] += count;
+        }
+        for (&total, &added) in self.totals.iter().zip(totals.iter()) {
+            cost += weighted_log2(total + added) - weighted_log2(total);
+        }
+        cost
+    }
+}
+
+/// Returns `value * log2(value)`, as the entropy of a histogram in bits is
+/// `total * log2(total) - sum(count * log2(count))`.
+#[inline]
+fn weighted_log2(value: usize) -> f64 {
+    if value > 1 {
+        value as f64 * math::log2(value as f64)
+    } else {
+        0.0
+    }
+}
+
+/// Entropy image that selects a prefix code group for each block of `1 << bits` pixels square
+struct EntropyImage {
+    bits: u8,
+    width: usize,
+    groups: Vec<u32>,
+}
+
+impl EntropyImage {
+    /// Assigns each block to the group whose entropy increases the least by its tokens, or to a
+    /// new group while there are less than `max_groups` groups.
+    fn new(
+        tokens: &[Token],
+        width: usize,
+        height: usize,
+        bits: u8,
+        max_groups: usize,
+        color_cache_size: usize,
+    ) -> (Self, Vec<Histograms>) {
+        let image_width = subsample_size(width, bits);
+        let mut blocks = vec![Vec::new(); image_width * subsample_size(height, bits)];
+        let mut pos = 0;
+        for token in tokens.iter() {
+            let block =
+                &mut blocks[((pos / width) >> bits) * image_width + ((pos % width) >> bits)];
+            token.for_each_symbol(|code, symbol| block.push((code, symbol, 1)));
+            pos += token.len();
+        }
+
+        let mut histograms: Vec<Histograms> = Vec::new();
+        let mut groups = Vec::with_capacity(blocks.len());
+        for mut symbols in blocks.into_iter() {
+            symbols.sort_unstable();
+            symbols.dedup_by(|next, prev| {
+                let is_same = next.0 == prev.0 && next.1 == prev.1;
+                if is_same {
+                    prev.2 += next.2;
+                }
+                is_same
+            });
+
+            let mut best = (f64::INFINITY, histograms.len());
+            for (index, histogram) in histograms.iter().enumerate() {
+                let cost = histogram.cost_of(&symbols);
+                if cost < best.0 {
+                    best = (cost, index);
+                }
+            }
+            if histograms.len() < max_groups {
+                let histogram = Histograms::new(color_cache_size);
+                let cost = histogram.cost_of(&symbols) + GROUP_COST;
+                if cost < best.0 || histograms.is_empty() {
+                    best = (cost, histograms.len());
+                    histograms.push(histogram);
+                }
+            }
+            histograms[best.1].add(&symbols);
+            groups.push(best.1 as u32);
+        }
+
+        (
+            Self {
+                bits,
+                width: image_width,
+                groups,
+            },
+            histograms,
+        )
+    }
+
+    #[inline]
+    fn group_at(&self, width: usize, pos: usize) -> usize {
+        self.groups[((pos / width) >> self.bits) * self.width + ((pos % width) >> self.bits)]
+            as usize
+    }
+}
+
+/// Writes an image stream, where the main image may have an entropy image of several prefix code
+/// groups if it is smaller.
+///
+/// The transforms of the main image are written by the caller.
+fn encode_image_stream(
+    writer: &mut BitStreamWriter,
+    pixels: &[u32],
+    width: usize,
+    color_cache_bits: u8,
+    is_main_image: bool,
+    config: &Configuration,
+) -> Result<(), EncodeError> {
+    let mut tokens = backward_references(pixels)?;
+
+    if color_cache_bits > 0 {
+        let mut cache = Vec::new();
+        cache.resize(1 << color_cache_bits, None);
+        let mut position = 0;
+        for token in tokens.iter_mut() {
+            match *token {
+                Token::Literal(pixel) => {
+                    let index = color_cache_hash(pixel, color_cache_bits);
+                    if cache[index] == Some(pixel) {
+                        *token = Token::CacheIndex(index as u32);
+                    }
+                    cache[index] = Some(pixel);
+                    position += 1;
+                }
+                Token::Copy { len, .. } => {
+                    for &pixel in pixels[position..position + len].iter() {
+                        cache[color_cache_hash(pixel, color_cache_bits)] = Some(pixel);
+                    }
+                    position += len;
+                }
+                Token::CacheIndex(_) => unreachable!(),
+            }
+        }
+    }
+
+    for token in tokens.iter_mut() {
+        if let Token::Copy { len, distance } = *token {
+            let distance = distance_to_plane_code(width, distance);
+            *token = Token::Copy { len, distance };
+        }
+    }
+
+    let color_cache_size = if color_cache_bits > 0 {
+        1 << color_cache_bits
+    } else {
+        0
+    };
+
+    if color_cache_bits > 0 {
+        writer.write(true);
+        writer.write(VarLenInteger::new(BitSize::Bit4, color_cache_bits as u32));
+    } else {
+        writer.write(false);
+    }
+
+    let mut histograms = Histograms::new(color_cache_size);
+    for token in tokens.iter() {
+        token.for_each_symbol(|code, symbol| histograms.add(&[(code, symbol, 1)]));
+    }
+    if !is_main_image {
+        return write_tokens(writer, &tokens, width, None, &[histograms]);
+    }
+
+    let mut best = writer.clone();
+    best.write(false); // no meta prefix codes
+    write_tokens(&mut best, &tokens, width, None, &[histograms])?;
+
+    if let Some((bits, max_groups)) = config.entropy_image() {
+        let height = pixels.len() / width;
+        let (image, groups) =
+            EntropyImage::new(&tokens, width, height, bits, max_groups, color_cache_size);
+        if groups.len() > 1 {
+            let mut candidate = writer.clone();
+            candidate.write(true);
+            candidate.write(VarLenInteger::new(BitSize::Bit3, bits as u32 - 2));
+            let group_pixels = image.groups.iter().map(|&v| v << 8).collect::<Vec<_>>();
+            encode_image_stream(&mut candidate, &group_pixels, image.width, 0, false, config)?;
+            write_tokens(&mut candidate, &tokens, width, Some(&image), &groups)?;
+            if candidate.bit_count() < best.bit_count() {
+                best = candidate;
+            }
+        }
+    }
+
+    *writer = best;
+    Ok(())
+}
+
+/// Writes the prefix code groups and the tokens coded with them.
+fn write_tokens(
+    writer: &mut BitStreamWriter,
+    tokens: &[Token],
+    width: usize,
+    image: Option<&EntropyImage>,
+    groups: &[Histograms],
+) -> Result<(), EncodeError> {
+    let mut codes = Vec::with_capacity(groups.len());
+    for group in groups.iter() {
+        let mut group_codes = Vec::with_capacity(group.counts.len());
+        for histogram in group.counts.iter() {
+            let table = CanonicalPrefixCoder::make_prefix_table(
+                histogram,
+                BitSize::Bit15,
+                histogram.len(),
+                LengthLimitMethod::PackageMerge,
+            );
+            let lengths = table
+                .iter()
+                .map(|v| v.map(|v| v.size().as_u8()).unwrap_or_default())
+                .collect::<Vec<_>>();
+            writer.write(CanonicalPrefixCoder::encode_prefix_table_webp(&lengths).as_slice());
+
+            // A code with a single symbol takes no bits.
+            let is_single = table.iter().filter(|v| v.is_some()).count() <= 1;
+            group_codes.push(
+                table
+                    .into_iter()
+                    .map(|v| v.filter(|_| !is_single).map(|v| v.reversed()))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        codes.push(group_codes);
+    }
+
+    let mut pos = 0;
+    for token in tokens.iter() {
+        let group = &codes[image.map_or(0, |image| image.group_at(width, pos))];
+        let write_symbol = |writer: &mut BitStreamWriter, code: usize, symbol: usize| {
+            if let Some(code) = group[code][symbol] {
+                writer.write(code);
+            }
+        };
+        match *token {
+            Token::Copy { len, distance } => {
+                for (code, base, value) in [(0, 256, len), (4, 0, distance)] {
+                    let (symbol, extra_bits, extra) = prefix_encode(value);
+                    write_symbol(writer, code, base + symbol);
+                    if let Some(bits) = BitSize::new(extra_bits as u8) {
+                        writer.write(VarLenInteger::new(bits, extra));
+                    }
+                }
+            }
+            _ => token.for_each_symbol(|code, symbol| write_symbol(writer, code, symbol)),
+        }
+        pos += token.len();
+    }
+
+    Ok(())
+}
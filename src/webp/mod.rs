@@ -0,0 +1,231 @@
+//! WebP lossless (VP8L) image format
+//!
+//! See also: <https://www.rfc-editor.org/rfc/rfc9649.html>
+
+use crate::*;
+
+#[cfg(test)]
+mod tests;
+
+mod decode;
+mod encode;
+pub use decode::*;
+pub use encode::*;
+
+/// Signature byte of the VP8L bitstream
+pub const VP8L_SIGNATURE: u8 = 0x2f;
+
+/// Maximum width and height of an image
+pub const MAX_DIMENSION: u32 = 1 << 14;
+
+/// Maximum bits of the color cache
+pub const MAX_COLOR_CACHE_BITS: u8 = 11;
+
+/// Number of length prefix codes in the green alphabet
+const NUM_LENGTH_CODES: usize = 24;
+
+/// Number of distance prefix codes
+const NUM_DISTANCE_CODES: usize = 40;
+
+/// Number of distance codes mapped to the 2D neighborhood
+const NUM_PLANE_CODES: usize = 120;
+
+/// Decoded image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebPImage {
+    width: u32,
+    height: u32,
+    has_alpha: bool,
+    pixels: Vec<u32>,
+}
+
+impl WebPImage {
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns whether the alpha channel may have values other than 255.
+    #[inline]
+    pub fn has_alpha(&self) -> bool {
+        self.has_alpha
+    }
+
+    /// Pixels in ARGB order, row by row
+    #[inline]
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    #[inline]
+    pub fn into_pixels(self) -> Vec<u32> {
+        self.pixels
+    }
+}
+
+/// Chunk of the RIFF container
+struct Chunk<'a> {
+    fourcc: [u8; 4],
+    data: &'a [u8],
+}
+
+/// Returns the chunks in the RIFF container of a WebP file.
+fn riff_chunks(input: &[u8]) -> Result<Vec<Chunk<'_>>, DecodeError> {
+    let header = input.get(..12).ok_or(DecodeError::UnexpectedEof)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WEBP" {
+        return Err(DecodeError::InvalidInput);
+    }
+    let riff_size = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+    let mut rest = input
+        .get(12..riff_size.saturating_add(8))
+        .ok_or(DecodeError::UnexpectedEof)?;
+
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        let header = rest.get(..8).ok_or(DecodeError::UnexpectedEof)?;
+        let fourcc = header[0..4].try_into().unwrap();
+        let size = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        let data = rest.get(8..8 + size).ok_or(DecodeError::UnexpectedEof)?;
+        chunks.push(Chunk { fourcc, data });
+        rest = rest.get(8 + size + (size & 1)..).unwrap_or_default();
+    }
+    Ok(chunks)
+}
+
+/// Returns `ceil(value / (1 << bits))`.
+#[inline]
+const fn subsample_size(value: usize, bits: u8) -> usize {
+    (value + (1 << bits) - 1) >> bits
+}
+
+#[inline]
+const fn add_pixels(a: u32, b: u32) -> u32 {
+    let alpha_green = (a & 0xff00ff00).wrapping_add(b & 0xff00ff00);
+    let red_blue = (a & 0x00ff00ff).wrapping_add(b & 0x00ff00ff);
+    (alpha_green & 0xff00ff00) | (red_blue & 0x00ff00ff)
+}
+
+#[inline]
+const fn sub_pixels(a: u32, b: u32) -> u32 {
+    let alpha_green = (0x00ff_00ff + (a & 0xff00_ff00)).wrapping_sub(b & 0xff00_ff00);
+    let red_blue = (0xff00_ff00 + (a & 0x00ff_00ff)).wrapping_sub(b & 0x00ff_00ff);
+    (alpha_green & 0xff00_ff00) | (red_blue & 0x00ff_00ff)
+}
+
+#[inline]
+const fn average2(a: u32, b: u32) -> u32 {
+    (((a ^ b) & 0xfefefefe) >> 1) + (a & b)
+}
+
+#[inline]
+const fn channel(pixel: u32, shift: u32) -> i32 {
+    ((pixel >> shift) & 0xff) as i32
+}
+
+#[inline]
+fn map_channels<F: Fn(u32) -> i32>(f: F) -> u32 {
+    [24, 16, 8, 0].iter().fold(0, |acc, &shift| {
+        acc | ((f(shift).clamp(0, 255) as u32) << shift)
+    })
+}
+
+#[inline]
+fn select(l: u32, t: u32, tl: u32) -> u32 {
+    let mut pl = 0;
+    let mut pt = 0;
+    for shift in [24, 16, 8, 0] {
+        let p = channel(l, shift) + channel(t, shift) - channel(tl, shift);
+        pl += (p - channel(l, shift)).abs();
+        pt += (p - channel(t, shift)).abs();
+    }
+    if pl < pt { l } else { t }
+}
+
+#[inline]
+fn clamp_add_subtract_full(a: u32, b: u32, c: u32) -> u32 {
+    map_channels(|shift| channel(a, shift) + channel(b, shift) - channel(c, shift))
+}
+
+#[inline]
+fn clamp_add_subtract_half(a: u32, b: u32) -> u32 {
+    map_channels(|shift| {
+        let a = channel(a, shift);
+        a + (a - channel(b, shift)) / 2
+    })
+}
+
+/// Returns the prediction of the predictor transform.
+///
+/// The modes 14 and 15 are not defined, and they behave like the mode 0.
+fn predict(mode: u32, l: u32, t: u32, tr: u32, tl: u32) -> u32 {
+    match mode {
+        1 => l,
+        2 => t,
+        3 => tr,
+        4 => tl,
+        5 => average2(average2(l, tr), t),
+        6 => average2(l, tl),
+        7 => average2(l, t),
+        8 => average2(tl, t),
+        9 => average2(t, tr),
+        10 => average2(average2(l, tl), average2(t, tr)),
+        11 => select(l, t, tl),
+        12 => clamp_add_subtract_full(l, t, tl),
+        13 => clamp_add_subtract_half(average2(l, t), tl),
+        _ => 0xff00_0000,
+    }
+}
+
+#[inline]
+const fn color_transform_delta(t: u8, c: u8) -> u8 {
+    ((t as i8 as i32 * c as i8 as i32) >> 5) as u8
+}
+
+#[inline]
+const fn color_cache_hash(argb: u32, bits: u8) -> usize {
+    (argb.wrapping_mul(0x1e35_a7bd) >> (32 - bits)) as usize
+}
+
+/// Returns the value of the prefix coded length or distance.
+///
+/// `extra` is called with the number of extra bits when they are needed.
+#[inline]
+fn prefix_value<F>(prefix_code: u32, extra: F) -> Option<usize>
+where
+    F: FnOnce(u32) -> Option<u32>,
+{
+    if prefix_code < 4 {
+        return Some(prefix_code as usize + 1);
+    }
+    let extra_bits = (prefix_code - 2) >> 1;
+    let offset = (2 + (prefix_code & 1)) << extra_bits;
+    Some((offset + extra(extra_bits)?) as usize + 1)
+}
+
+/// Neighborhood of the distance codes, `(y << 4) | (8 - x)`
+const PLANE_CODES: [u8; NUM_PLANE_CODES] = [
+    0x18, 0x07, 0x17, 0x19, 0x28, 0x06, 0x27, 0x29, 0x16, 0x1a, 0x26, 0x2a, 0x38, 0x05, 0x37, 0x39,
+    0x15, 0x1b, 0x36, 0x3a, 0x25, 0x2b, 0x48, 0x04, 0x47, 0x49, 0x14, 0x1c, 0x35, 0x3b, 0x46, 0x4a,
+    0x24, 0x2c, 0x58, 0x45, 0x4b, 0x34, 0x3c, 0x03, 0x57, 0x59, 0x13, 0x1d, 0x56, 0x5a, 0x23, 0x2d,
+    0x44, 0x4c, 0x55, 0x5b, 0x33, 0x3d, 0x68, 0x02, 0x67, 0x69, 0x12, 0x1e, 0x66, 0x6a, 0x22, 0x2e,
+    0x54, 0x5c, 0x43, 0x4d, 0x65, 0x6b, 0x32, 0x3e, 0x78, 0x01, 0x77, 0x79, 0x53, 0x5d, 0x11, 0x1f,
+    0x64, 0x6c, 0x42, 0x4e, 0x76, 0x7a, 0x21, 0x2f, 0x75, 0x7b, 0x31, 0x3f, 0x63, 0x6d, 0x52, 0x5e,
+    0x00, 0x74, 0x7c, 0x41, 0x4f, 0x10, 0x20, 0x62, 0x6e, 0x30, 0x73, 0x7d, 0x51, 0x5f, 0x40, 0x72,
+    0x7e, 0x61, 0x6f, 0x50, 0x71, 0x7f, 0x60, 0x70,
+];
+
+/// Converts a distance code to the distance in pixels.
+fn plane_code_to_distance(width: usize, code: usize) -> usize {
+    if code > NUM_PLANE_CODES {
+        return code - NUM_PLANE_CODES;
+    }
+    let plane_code = PLANE_CODES[code - 1] as isize;
+    let y = plane_code >> 4;
+    let x = 8 - (plane_code & 0xf);
+    (y * width as isize + x).max(1) as usize
+}
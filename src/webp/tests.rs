@@ -0,0 +1,240 @@
+use super::*;
+use crate::{
+    entropy::prefix::CanonicalPrefixCoder,
+    num::{
+        VarLenInteger,
+        bits::{BitSize, BitStreamWriter, Write},
+    },
+};
+use base64::prelude::*;
+
+/// `ALPH` chunk of a 16x16 image, compressed with VP8L
+const ALPH_16X16: &str = "ASeiqJEk5XrnGF/n3yqZiJj/dHGN4CYw4uGLdzLIwRFcgysw6LB4FY54UTXBCAwCT5KgarBVGRzWtm1GL07Gdjy27Xf7rymuIaL/SdH9j5D3ukRJJBs6JZE08xRtDsfT5RYg9AsUvpDhg7caMp42gn8dKX5Odgj7iJ6zke+Zc0boMoLb+MxIsvdFMH0g/TYXjCEyVi2l1msjvF3jpVkV1ZyBpNlulnWKGDEPiqosUDT6MILfumtQUim1Lc/pVBQKAQAA";
+
+/// Alpha channel of the image above
+const ALPHA_16X16: &str = "AAAAAK/t////95ILAwAAAAAAAAD/////////KAsAAAAAAAAA/////////0EVAAAAAH6goaOnqqr///9IGAAAAL3/////////////SP/3ggr/////////////tkP//+0k///////////3tk+I////Pf////+2XExIR0Fr9////0b3//+qTqb3//////////dH1///W7P////////////CQW/3/0v/////////////ejQIITw1////qqmpqampeUEgAgkTEv////////9BNSweCwAAAAD3///////3QBwJBQEAAAAATMz3///YezsSAAAAAAAAAAgdNUJHRTgfCAAAAA==";
+
+#[test]
+fn alpha_plane() {
+    let alph = BASE64_STANDARD.decode(ALPH_16X16).unwrap();
+    let expected = BASE64_STANDARD.decode(ALPHA_16X16).unwrap();
+    // compression: 1 (lossless), filtering: none, pre-processing: none
+    assert_eq!(alph[0], 0x01);
+
+    let pixels = decode_vp8l_image_stream(&alph[1..], 16, 16).unwrap();
+    let alpha = pixels.iter().map(|v| (v >> 8) as u8).collect::<Vec<_>>();
+    assert_eq!(alpha, expected);
+
+    for len in [0, 1, alph.len() / 2, alph.len() - 4] {
+        assert!(decode_vp8l_image_stream(&alph[1..len.max(1)], 16, 16).is_err());
+    }
+}
+
+/// Writes a tiny image with simple prefix codes only.
+fn tiny_vp8l() -> Vec<u8> {
+    let mut writer = BitStreamWriter::new();
+    writer.write(VarLenInteger::new(BitSize::Bit14, 3 - 1)); // width
+    writer.write(VarLenInteger::new(BitSize::Bit14, 2 - 1)); // height
+    writer.write(false); // alpha is used
+    writer.write(VarLenInteger::new(BitSize::Bit3, 0)); // version
+    writer.write(false); // no transform
+    writer.write(false); // no color cache
+    writer.write(false); // no meta prefix codes
+
+    let mut green = [0; 280];
+    green[0x10] = 1;
+    green[0x20] = 1;
+    for (index, lengths) in [&green[..], &[0; 256], &[0; 256], &[0; 256], &[0; 40]]
+        .into_iter()
+        .enumerate()
+    {
+        let mut lengths = lengths.to_vec();
+        match index {
+            1 => lengths[0x80] = 1,
+            2 => lengths[0x40] = 1,
+            3 => lengths[0xff] = 1,
+            _ => {}
+        }
+        writer.write(CanonicalPrefixCoder::encode_prefix_table_webp(&lengths).as_slice());
+    }
+    for bit in [false, true, false, true, true, false] {
+        writer.write(bit);
+    }
+
+    let mut output = [VP8L_SIGNATURE].to_vec();
+    output.extend_from_slice(&writer.into_bytes());
+    output
+}
+
+#[test]
+fn simple_image() {
+    let vp8l = tiny_vp8l();
+    let image = decode_vp8l(&vp8l).unwrap();
+    assert_eq!(image.width(), 3);
+    assert_eq!(image.height(), 2);
+    assert!(!image.has_alpha());
+    assert_eq!(
+        image.pixels(),
+        &[
+            0xff801040, 0xff802040, 0xff801040, 0xff802040, 0xff802040, 0xff801040
+        ]
+    );
+
+    let mut riff = b"RIFF\0\0\0\0WEBPVP8L".to_vec();
+    riff.extend_from_slice(&(vp8l.len() as u32).to_le_bytes());
+    riff.extend_from_slice(&vp8l);
+    if vp8l.len() & 1 != 0 {
+        riff.push(0);
+    }
+    let riff_size = riff.len() as u32 - 8;
+    riff[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    assert_eq!(decode(&riff).unwrap(), image);
+
+    assert_eq!(decode(&riff[..20]), Err(DecodeError::UnexpectedEof));
+    let mut broken = riff.clone();
+    broken[8] = b'X';
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidInput));
+    let mut broken = riff.clone();
+    broken[15] = b' ';
+    assert_eq!(decode(&broken), Err(DecodeError::UnsupportedFormat));
+}
+
+#[test]
+fn predictors() {
+    let l = 0xff10_2030;
+    let t = 0x8040_6080;
+    let tr = 0x0102_0304;
+    let tl = 0x2000_ff10;
+    assert_eq!(predict(0, l, t, tr, tl), 0xff00_0000);
+    assert_eq!(predict(7, l, t, tr, tl), 0xbf28_4058);
+    assert_eq!(predict(11, l, t, tr, tl), l);
+    assert_eq!(predict(12, l, t, tr, tl), 0xff50_00a0);
+    assert_eq!(predict(13, l, t, tr, tl), 0xff3c_007c);
+    assert_eq!(predict(14, l, t, tr, tl), 0xff00_0000);
+
+    assert_eq!(plane_code_to_distance(100, 1), 100);
+    assert_eq!(plane_code_to_distance(100, 2), 1);
+    assert_eq!(plane_code_to_distance(100, 3), 101);
+    assert_eq!(plane_code_to_distance(100, 4), 99);
+    assert_eq!(plane_code_to_distance(1, 4), 1);
+    assert_eq!(plane_code_to_distance(100, 121), 1);
+}
+
+fn test_images() -> Vec<(u32, u32, Vec<u32>)> {
+    let mut images = Vec::new();
+
+    let (width, height) = (67, 45);
+    let gradient = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            0xff00_0000 | ((x * 3) << 16) | ((y * 5) << 8) | ((x + y) & 0xff)
+        })
+        .collect::<Vec<_>>();
+    images.push((width, height, gradient));
+
+    let noise = random_bytes(0, 255, 4 * 33 * 17);
+    let noise = noise
+        .chunks_exact(4)
+        .map(|v| u32::from_le_bytes(v.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    images.push((33, 17, noise));
+
+    let palette = [
+        0xff20_4060,
+        0x8000_ff00,
+        0x0000_0000,
+        0xffff_ffff,
+        0xff12_3456,
+    ];
+    let indices = random_bytes(0, 4, 50 * 40);
+    let mut paletted = indices
+        .iter()
+        .map(|&v| palette[v as usize])
+        .collect::<Vec<_>>();
+    paletted[1000..1600].fill(0xff12_3456);
+    images.push((50, 40, paletted));
+
+    let text = fib_str(b'a', b'c', 4 * 100 * 30);
+    let repeated = text
+        .chunks_exact(4)
+        .map(|v| u32::from_le_bytes(v.try_into().unwrap()) | 0xff00_0000)
+        .collect::<Vec<_>>();
+    images.push((100, 30, repeated));
+
+    // Two regions of different colors, which are better coded with separate prefix code groups
+    let left = random_bytes(0, 3, 64 * 64);
+    let right = random_bytes(0, 3, 64 * 64);
+    let regions = (0..64 * 64)
+        .map(|i| match i % 64 {
+            0..32 => 0xff00_0000 | (left[i] as u32 * 0x1f_2f3f),
+            _ => 0x8000_0000 | (right[i] as u32 * 0x40_0507) ^ 0x00a0_b0c0,
+        })
+        .collect::<Vec<_>>();
+    images.push((64, 64, regions));
+
+    images.push((1, 1, [0x1234_5678].to_vec()));
+    images.push((
+        1,
+        20,
+        (0..20).map(|v| 0xff00_0000 | (v * 0x010101)).collect(),
+    ));
+    images.push((20, 1, (0..20).map(|v| v << 24).collect()));
+    images.push((100, 50, [0xff00_0000; 5000].to_vec()));
+    images
+}
+
+#[test]
+fn roundtrip() {
+    for (width, height, pixels) in test_images() {
+        for config in [
+            Configuration::FASTEST,
+            Configuration::DEFAULT,
+            Configuration::BEST,
+            Configuration::DEFAULT
+                .subtract_green(false)
+                .predictor(false),
+            Configuration::DEFAULT.color_cache_bits(1),
+            Configuration::DEFAULT.color_cache_bits(11),
+        ] {
+            let encoded = encode(width, height, &pixels, config).unwrap();
+            let image =
+                decode(&encoded).unwrap_or_else(|e| panic!("{e:?} {width}x{height} {config:?}"));
+            assert_eq!(image.width(), width);
+            assert_eq!(image.height(), height);
+            assert_eq!(image.has_alpha(), pixels.iter().any(|&v| v < 0xff00_0000));
+            assert!(image.pixels() == pixels, "{width}x{height} {config:?}");
+        }
+    }
+
+    assert_eq!(
+        encode(3, 3, &[0; 8], Configuration::DEFAULT),
+        Err(EncodeError::InvalidInput)
+    );
+    assert_eq!(
+        encode(0, 0, &[], Configuration::DEFAULT),
+        Err(EncodeError::InvalidInput)
+    );
+}
+
+#[test]
+fn effort() {
+    let (width, height, pixels) = test_images().swap_remove(0);
+    let raw = pixels.len() * 4;
+    let fastest = encode(width, height, &pixels, Configuration::FASTEST).unwrap();
+    let best = encode(width, height, &pixels, Configuration::BEST).unwrap();
+    let plain = encode(
+        width,
+        height,
+        &pixels,
+        Configuration::BEST.subtract_green(false).predictor(false),
+    )
+    .unwrap();
+    assert!(
+        fastest.len() < plain.len(),
+        "{} {}",
+        fastest.len(),
+        plain.len()
+    );
+    assert!(best.len() < plain.len(), "{} {}", best.len(), plain.len());
+    assert!(best.len() * 10 < raw, "{} {raw}", best.len());
+}
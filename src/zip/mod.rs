@@ -0,0 +1,409 @@
+//! ZIP archive format
+//!
+//! Only the stored and deflated entries are extracted, and encrypted or multi-disk archives are not supported.
+//!
+//! See also: <https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT>
+
+use crate::{
+    deflate::{crc32, inflate_in_place},
+    *,
+};
+
+#[cfg(test)]
+mod tests;
+
+mod read;
+mod write;
+pub use read::*;
+pub use write::*;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE: usize = 56;
+const ZIP64_LOCATOR_SIZE: usize = 20;
+
+/// Maximum size of the end of central directory record including the comment and the Zip64 locator
+const MAX_TAIL_SIZE: usize = END_OF_CENTRAL_DIRECTORY_SIZE + 0xffff + ZIP64_LOCATOR_SIZE;
+
+/// Upper bound of the compression ratio of deflate, whose longest match takes two bits at least
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+/// Header ID of the Zip64 extended information extra field
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+/// Version needed to extract deflated entries
+const VERSION_DEFLATE: u16 = 20;
+/// Version needed to extract Zip64 entries
+const VERSION_ZIP64: u16 = 45;
+
+const FLAG_ENCRYPTED: u16 = 0x0001;
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+const FLAG_UTF8: u16 = 0x0800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionMethod {
+    Stored,
+    Deflated,
+}
+
+impl CompressionMethod {
+    #[inline]
+    pub const fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(Self::Stored),
+            8 => Some(Self::Deflated),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub const fn as_u16(&self) -> u16 {
+        match self {
+            Self::Stored => 0,
+            Self::Deflated => 8,
+        }
+    }
+}
+
+/// Entry of the central directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZipEntry {
+    name: Vec<u8>,
+    comment: Vec<u8>,
+    method: u16,
+    flags: u16,
+    dos_time: u32,
+    crc32: u32,
+    compressed_size: u64,
+    size: u64,
+    external_attributes: u32,
+    local_header_offset: u64,
+}
+
+impl ZipEntry {
+    #[inline]
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    /// Returns the name if it is valid UTF-8.
+    #[inline]
+    pub fn name_str(&self) -> Option<&str> {
+        core::str::from_utf8(&self.name).ok()
+    }
+
+    #[inline]
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    /// Returns the compression method, or `None` if it is not supported.
+    #[inline]
+    pub fn method(&self) -> Option<CompressionMethod> {
+        CompressionMethod::from_u16(self.method)
+    }
+
+    /// Modification time in the MS-DOS format, `(date << 16) | time`
+    #[inline]
+    pub fn dos_time(&self) -> u32 {
+        self.dos_time
+    }
+
+    #[inline]
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    #[inline]
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    #[inline]
+    pub fn external_attributes(&self) -> u32 {
+        self.external_attributes
+    }
+
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.name.last() == Some(&b'/')
+    }
+
+    #[inline]
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// Returns whether the sizes and the CRC-32 follow the data instead of the local header.
+    #[inline]
+    pub fn has_data_descriptor(&self) -> bool {
+        self.flags & FLAG_DATA_DESCRIPTOR != 0
+    }
+
+    #[inline]
+    fn needs_zip64(&self) -> bool {
+        self.size >= u32::MAX as u64
+            || self.compressed_size >= u32::MAX as u64
+            || self.local_header_offset >= u32::MAX as u64
+    }
+}
+
+/// Little endian reader of the records
+struct RecordReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RecordReader<'a> {
+    #[inline]
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    #[inline]
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let result = self.data.get(..len).ok_or(DecodeError::UnexpectedEof)?;
+        self.data = &self.data[len..];
+        Ok(result)
+    }
+
+    #[inline]
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        self.bytes(2)
+            .map(|v| u16::from_le_bytes(v.try_into().unwrap()))
+    }
+
+    #[inline]
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        self.bytes(4)
+            .map(|v| u32::from_le_bytes(v.try_into().unwrap()))
+    }
+
+    #[inline]
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        self.bytes(8)
+            .map(|v| u64::from_le_bytes(v.try_into().unwrap()))
+    }
+
+    #[inline]
+    fn signature(&mut self, signature: u32) -> Result<(), DecodeError> {
+        if self.u32()? != signature {
+            return Err(DecodeError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+/// End of central directory record, which may be updated by the Zip64 one
+struct EndOfCentralDirectory {
+    entries: u64,
+    size: u64,
+    offset: u64,
+    comment: Vec<u8>,
+    /// Position of the Zip64 end of central directory record
+    zip64_offset: Option<u64>,
+}
+
+impl EndOfCentralDirectory {
+    /// Finds the record in the tail of the archive.
+    fn find(tail: &[u8]) -> Result<Self, DecodeError> {
+        let last = tail
+            .len()
+            .checked_sub(END_OF_CENTRAL_DIRECTORY_SIZE)
+            .ok_or(DecodeError::InvalidInput)?;
+        let first = tail
+            .len()
+            .saturating_sub(MAX_TAIL_SIZE - ZIP64_LOCATOR_SIZE);
+        for position in (first..=last).rev() {
+            let mut reader = RecordReader::new(&tail[position..]);
+            if reader
+                .signature(END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+                .is_err()
+            {
+                continue;
+            }
+            let disk = reader.u16()?;
+            let central_disk = reader.u16()?;
+            let _entries_on_disk = reader.u16()?;
+            let entries = reader.u16()?;
+            let size = reader.u32()?;
+            let offset = reader.u32()?;
+            let comment_len = reader.u16()? as usize;
+            let Ok(comment) = reader.bytes(comment_len) else {
+                continue;
+            };
+            if disk != 0 || central_disk != 0 {
+                return Err(DecodeError::UnsupportedFormat);
+            }
+
+            let zip64_offset = position
+                .checked_sub(ZIP64_LOCATOR_SIZE)
+                .map(|v| RecordReader::new(&tail[v..position]))
+                .and_then(|mut reader| {
+                    reader.signature(ZIP64_LOCATOR_SIGNATURE).ok()?;
+                    let _disk = reader.u32().ok()?;
+                    reader.u64().ok()
+                });
+            return Ok(Self {
+                entries: entries as u64,
+                size: size as u64,
+                offset: offset as u64,
+                comment: comment.to_vec(),
+                zip64_offset,
+            });
+        }
+        Err(DecodeError::InvalidInput)
+    }
+
+    /// Reads the Zip64 end of central directory record.
+    fn read_zip64(&mut self, record: &[u8]) -> Result<(), DecodeError> {
+        let mut reader = RecordReader::new(record);
+        reader.signature(ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE)?;
+        let _record_size = reader.u64()?;
+        let _version_made_by = reader.u16()?;
+        let _version_needed = reader.u16()?;
+        let disk = reader.u32()?;
+        let central_disk = reader.u32()?;
+        let _entries_on_disk = reader.u64()?;
+        self.entries = reader.u64()?;
+        self.size = reader.u64()?;
+        self.offset = reader.u64()?;
+        if disk != 0 || central_disk != 0 {
+            return Err(DecodeError::UnsupportedFormat);
+        }
+        Ok(())
+    }
+}
+
+/// Reads the entries of the central directory.
+fn read_central_directory(data: &[u8], entries: u64) -> Result<Vec<ZipEntry>, DecodeError> {
+    let mut reader = RecordReader::new(data);
+    let mut output = Vec::with_capacity((entries as usize).min(data.len() / CENTRAL_HEADER_SIZE));
+    for _ in 0..entries {
+        reader.signature(CENTRAL_HEADER_SIGNATURE)?;
+        let _version_made_by = reader.u16()?;
+        let _version_needed = reader.u16()?;
+        let flags = reader.u16()?;
+        let method = reader.u16()?;
+        let dos_time = reader.u32()?;
+        let crc32 = reader.u32()?;
+        let compressed_size = reader.u32()?;
+        let size = reader.u32()?;
+        let name_len = reader.u16()? as usize;
+        let extra_len = reader.u16()? as usize;
+        let comment_len = reader.u16()? as usize;
+        let _disk = reader.u16()?;
+        let _internal_attributes = reader.u16()?;
+        let external_attributes = reader.u32()?;
+        let local_header_offset = reader.u32()?;
+        let name = reader.bytes(name_len)?;
+        let extra = reader.bytes(extra_len)?;
+        let comment = reader.bytes(comment_len)?;
+
+        let mut entry = ZipEntry {
+            name: name.to_vec(),
+            comment: comment.to_vec(),
+            method,
+            flags,
+            dos_time,
+            crc32,
+            compressed_size: compressed_size as u64,
+            size: size as u64,
+            external_attributes,
+            local_header_offset: local_header_offset as u64,
+        };
+
+        let mut extra = RecordReader::new(extra);
+        while let Ok(id) = extra.u16() {
+            let len = extra.u16()? as usize;
+            let data = extra.bytes(len)?;
+            if id != ZIP64_EXTRA_ID {
+                continue;
+            }
+            // Only the fields saturated in the header are present, in this order.
+            let mut zip64 = RecordReader::new(data);
+            for (field, value) in [
+                (&mut entry.size, size),
+                (&mut entry.compressed_size, compressed_size),
+                (&mut entry.local_header_offset, local_header_offset),
+            ] {
+                if value == u32::MAX {
+                    *field = zip64.u64()?;
+                }
+            }
+        }
+        output.push(entry);
+    }
+    Ok(output)
+}
+
+/// Returns the size of the local header including the name and the extra field.
+fn local_header_size(header: &[u8]) -> Result<usize, DecodeError> {
+    let mut reader = RecordReader::new(header);
+    reader.signature(LOCAL_HEADER_SIGNATURE)?;
+    // version, flags, method, time, CRC-32 and sizes
+    reader.bytes(22)?;
+    let name_len = reader.u16()? as usize;
+    let extra_len = reader.u16()? as usize;
+    Ok(LOCAL_HEADER_SIZE + name_len + extra_len)
+}
+
+/// Decompresses the data of the entry and verifies its CRC-32.
+fn extract_data(entry: &ZipEntry, data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if entry.is_encrypted() {
+        return Err(DecodeError::UnsupportedFormat);
+    }
+    let size = usize::try_from(entry.size).map_err(|_| DecodeError::OutOfMemory)?;
+    let output = match entry.method() {
+        Some(CompressionMethod::Stored) => {
+            if data.len() != size {
+                return Err(DecodeError::InvalidData);
+            }
+            data.to_vec()
+        }
+        Some(CompressionMethod::Deflated) => {
+            // The declared size is untrusted, so it is bounded before the allocation.
+            if size > data.len().saturating_mul(MAX_DEFLATE_RATIO) {
+                return Err(DecodeError::InvalidData);
+            }
+            let mut output = Vec::new();
+            output
+                .try_reserve_exact(size)
+                .map_err(|_| DecodeError::OutOfMemory)?;
+            output.resize(size, 0);
+            inflate_in_place(data, &mut output)?;
+            output
+        }
+        None => return Err(DecodeError::UnsupportedFormat),
+    };
+    if crc32::checksum(&output) != entry.crc32 {
+        return Err(DecodeError::InvalidData);
+    }
+    Ok(output)
+}
+
+#[inline]
+fn write_u16(output: &mut Vec<u8>, value: u16) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+#[inline]
+fn write_u32(output: &mut Vec<u8>, value: u32) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+#[inline]
+fn write_u64(output: &mut Vec<u8>, value: u64) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
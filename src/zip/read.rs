@@ -0,0 +1,177 @@
+//! ZIP archive readers
+
+use super::*;
+
+/// ZIP archive in a slice
+pub struct ZipArchive<'a> {
+    input: &'a [u8],
+    entries: Vec<ZipEntry>,
+    comment: Vec<u8>,
+}
+
+impl<'a> ZipArchive<'a> {
+    /// Reads the central directory of the archive.
+    pub fn new(input: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut end = EndOfCentralDirectory::find(input)?;
+        if let Some(offset) = end.zip64_offset {
+            let record = usize::try_from(offset)
+                .ok()
+                .and_then(|v| input.get(v..))
+                .ok_or(DecodeError::InvalidData)?;
+            end.read_zip64(record)?;
+        }
+        let central_directory = slice_at(input, end.offset, end.size)?;
+        let entries = read_central_directory(central_directory, end.entries)?;
+        Ok(Self {
+            input,
+            entries,
+            comment: end.comment,
+        })
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    #[inline]
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    /// Returns the entry with the name.
+    #[inline]
+    pub fn find<T: AsRef<[u8]>>(&self, name: T) -> Option<&ZipEntry> {
+        self.entries.iter().find(|v| v.name == name.as_ref())
+    }
+
+    /// Returns the compressed data of the entry.
+    pub fn raw_data(&self, entry: &ZipEntry) -> Result<&'a [u8], DecodeError> {
+        let header = slice_at(
+            self.input,
+            entry.local_header_offset,
+            LOCAL_HEADER_SIZE as u64,
+        )?;
+        let offset = entry.local_header_offset + local_header_size(header)? as u64;
+        slice_at(self.input, offset, entry.compressed_size)
+    }
+
+    /// Extracts the entry and verifies its CRC-32.
+    #[inline]
+    pub fn extract(&self, entry: &ZipEntry) -> Result<Vec<u8>, DecodeError> {
+        extract_data(entry, self.raw_data(entry)?)
+    }
+}
+
+#[inline]
+fn slice_at(input: &[u8], offset: u64, len: u64) -> Result<&[u8], DecodeError> {
+    usize::try_from(offset)
+        .ok()
+        .zip(usize::try_from(len).ok())
+        .and_then(|(offset, len)| input.get(offset..offset.checked_add(len)?))
+        .ok_or(DecodeError::UnexpectedEof)
+}
+
+#[cfg(any(test, feature = "std"))]
+pub use stream::*;
+
+#[cfg(any(test, feature = "std"))]
+mod stream {
+    use super::*;
+    use alloc::vec;
+    use std::io::{self, Read, Seek, SeekFrom};
+
+    /// ZIP archive read from a seekable stream
+    pub struct ZipReader<R> {
+        reader: R,
+        len: u64,
+        entries: Vec<ZipEntry>,
+        comment: Vec<u8>,
+    }
+
+    impl<R: Read + Seek> ZipReader<R> {
+        /// Reads the central directory of the archive.
+        pub fn new(mut reader: R) -> Result<Self, DecodeError> {
+            let len = reader.seek(SeekFrom::End(0)).map_err(io_error)?;
+            let tail_len = len.min(MAX_TAIL_SIZE as u64);
+            let tail = read_at(&mut reader, len, len - tail_len, tail_len)?;
+            let mut end = EndOfCentralDirectory::find(&tail)?;
+            if let Some(offset) = end.zip64_offset {
+                let record = read_at(
+                    &mut reader,
+                    len,
+                    offset,
+                    ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE as u64,
+                )?;
+                end.read_zip64(&record)?;
+            }
+            let central_directory = read_at(&mut reader, len, end.offset, end.size)?;
+            let entries = read_central_directory(&central_directory, end.entries)?;
+            Ok(Self {
+                reader,
+                len,
+                entries,
+                comment: end.comment,
+            })
+        }
+
+        #[inline]
+        pub fn entries(&self) -> &[ZipEntry] {
+            &self.entries
+        }
+
+        #[inline]
+        pub fn comment(&self) -> &[u8] {
+            &self.comment
+        }
+
+        /// Returns the entry with the name.
+        #[inline]
+        pub fn find<T: AsRef<[u8]>>(&self, name: T) -> Option<&ZipEntry> {
+            self.entries.iter().find(|v| v.name == name.as_ref())
+        }
+
+        /// Extracts the entry and verifies its CRC-32.
+        pub fn extract(&mut self, entry: &ZipEntry) -> Result<Vec<u8>, DecodeError> {
+            let header = read_at(
+                &mut self.reader,
+                self.len,
+                entry.local_header_offset,
+                LOCAL_HEADER_SIZE as u64,
+            )?;
+            let offset = entry.local_header_offset + local_header_size(&header)? as u64;
+            let data = read_at(&mut self.reader, self.len, offset, entry.compressed_size)?;
+            extract_data(entry, &data)
+        }
+
+        #[inline]
+        pub fn into_inner(self) -> R {
+            self.reader
+        }
+    }
+
+    /// Reads `size` bytes at `offset`, which must be within the stream of `len` bytes.
+    fn read_at<R: Read + Seek>(
+        reader: &mut R,
+        len: u64,
+        offset: u64,
+        size: u64,
+    ) -> Result<Vec<u8>, DecodeError> {
+        if offset.checked_add(size).is_none_or(|end| end > len) {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let size = usize::try_from(size).map_err(|_| DecodeError::OutOfMemory)?;
+        reader.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+        let mut output = vec![0; size];
+        reader.read_exact(&mut output).map_err(io_error)?;
+        Ok(output)
+    }
+
+    #[inline]
+    fn io_error(err: io::Error) -> DecodeError {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => DecodeError::UnexpectedEof,
+            _ => DecodeError::InvalidInput,
+        }
+    }
+}
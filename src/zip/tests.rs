@@ -0,0 +1,155 @@
+use super::*;
+use crate::deflate::CompressionLevel;
+use base64::prelude::*;
+use std::io::Cursor;
+
+/// Written to a non-seekable stream by Python's `zipfile`, with data descriptors
+///
+/// * `hello.txt`: stored, `"Hello, world!\n"` 20 times
+/// * `dir/bytes.bin`: deflated, `0..=255`
+const DATA_DESCRIPTOR_ZIP: &str = "UEsDBBQACAAAAAU5plgAAAAAAAAAAAAAAAAJAAAAaGVsbG8udHh0SGVsbG8sIHdvcmxkIQpIZWxsbywgd29ybGQhCkhlbGxvLCB3b3JsZCEKSGVsbG8sIHdvcmxkIQpIZWxsbywgd29ybGQhCkhlbGxvLCB3b3JsZCEKSGVsbG8sIHdvcmxkIQpIZWxsbywgd29ybGQhCkhlbGxvLCB3b3JsZCEKSGVsbG8sIHdvcmxkIQpIZWxsbywgd29ybGQhCkhlbGxvLCB3b3JsZCEKSGVsbG8sIHdvcmxkIQpIZWxsbywgd29ybGQhCkhlbGxvLCB3b3JsZCEKSGVsbG8sIHdvcmxkIQpIZWxsbywgd29ybGQhCkhlbGxvLCB3b3JsZCEKSGVsbG8sIHdvcmxkIQpIZWxsbywgd29ybGQhClBLBwgrw648GAEAABgBAABQSwMEFAAIAAgABTmmWAAAAAAAAAAAAAAAAA0AAABkaXIvYnl0ZXMuYmluAQAB//4AAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYnKCkqKywtLi8wMTIzNDU2Nzg5Ojs8PT4/QEFCQ0RFRkdISUpLTE1OT1BRUlNUVVZXWFlaW1xdXl9gYWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1+f4CBgoOEhYaHiImKi4yNjo+QkZKTlJWWl5iZmpucnZ6foKGio6SlpqeoqaqrrK2ur7CxsrO0tba3uLm6u7y9vr/AwcLDxMXGx8jJysvMzc7P0NHS09TV1tfY2drb3N3e3+Dh4uPk5ebn6Onq6+zt7u/w8fLz9PX29/j5+vv8/f7/UEsHCHOMBSkFAQAAAAEAAFBLAQIUAxQACAAAAAU5plgrw648GAEAABgBAAAJAAAAAAAAAAAAAACAAQAAAABoZWxsby50eHRQSwECFAMUAAgACAAFOaZYc4wFKQUBAAAAAQAADQAAAAAAAAAAAAAAgAFPAQAAZGlyL2J5dGVzLmJpblBLBQYAAAAAAgACAHIAAACPAgAAAAA=";
+
+/// Zip64 archive with a deflated entry `zip64.txt`, `"zip64 "` 50 times
+const ZIP64_ZIP: &str = "UEsDBC0AAAAIAAU5plgkWigW//////////8JABQAemlwNjQudHh0AQAQACwBAAAAAAAADAAAAAAAAACryiwwM1GoGiWJIAFQSwECHgMtAAAACAAFOaZYJFooFv//////////CQAcAAAAAAAAAAAApIH/////emlwNjQudHh0AQAYACwBAAAAAAAADAAAAAAAAAAAAAAAAAAAAFBLBgYsAAAAAAAAAC0ALQAAAAAAAAAAAAEAAAAAAAAAAQAAAAAAAABTAAAAAAAAAEcAAAAAAAAAUEsGBwAAAACaAAAAAAAAAAEAAABQSwUGAAAAAP///////////////wAA";
+
+#[test]
+fn read_data_descriptor() {
+    let input = BASE64_STANDARD.decode(DATA_DESCRIPTOR_ZIP).unwrap();
+    let archive = ZipArchive::new(&input).unwrap();
+    let names = archive
+        .entries()
+        .iter()
+        .map(|v| v.name_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["hello.txt", "dir/bytes.bin"]);
+
+    let entry = archive.find("hello.txt").unwrap();
+    assert!(entry.has_data_descriptor());
+    assert_eq!(entry.method(), Some(CompressionMethod::Stored));
+    assert_eq!(
+        archive.extract(entry).unwrap(),
+        b"Hello, world!\n".repeat(20)
+    );
+
+    let entry = archive.find("dir/bytes.bin").unwrap();
+    assert_eq!(entry.method(), Some(CompressionMethod::Deflated));
+    assert_eq!(entry.size(), 256);
+    let expected = (0..=255).collect::<Vec<u8>>();
+    assert_eq!(archive.extract(entry).unwrap(), expected);
+
+    let mut reader = ZipReader::new(Cursor::new(&input)).unwrap();
+    let entry = reader.find("dir/bytes.bin").unwrap().clone();
+    assert_eq!(reader.extract(&entry).unwrap(), expected);
+
+    let mut broken = input.clone();
+    broken[100] ^= 1;
+    let archive = ZipArchive::new(&broken).unwrap();
+    let entry = archive.find("hello.txt").unwrap();
+    assert_eq!(archive.extract(entry), Err(DecodeError::InvalidData));
+    assert!(ZipArchive::new(&input[..input.len() - 1]).is_err());
+    assert!(ZipReader::new(Cursor::new(&input[..100])).is_err());
+}
+
+#[test]
+fn read_zip64() {
+    let input = BASE64_STANDARD.decode(ZIP64_ZIP).unwrap();
+    let archive = ZipArchive::new(&input).unwrap();
+    assert_eq!(archive.entries().len(), 1);
+    let entry = &archive.entries()[0];
+    assert_eq!(entry.name(), b"zip64.txt");
+    assert_eq!(entry.size(), 300);
+    assert_eq!(archive.extract(entry).unwrap(), b"zip64 ".repeat(50));
+
+    let mut reader = ZipReader::new(Cursor::new(&input)).unwrap();
+    let entry = reader.entries()[0].clone();
+    assert_eq!(reader.extract(&entry).unwrap(), b"zip64 ".repeat(50));
+
+    // The declared size of 2^50 + 300 bytes is rejected before the allocation.
+    let mut huge = input.clone();
+    let extra = [0x01, 0x00, 0x18, 0x00, 0x2c, 0x01];
+    let pos = huge.windows(extra.len()).position(|v| v == extra).unwrap();
+    huge[pos + 10] = 0x04;
+    let archive = ZipArchive::new(&huge).unwrap();
+    let entry = &archive.entries()[0];
+    assert_eq!(entry.size(), (1 << 50) + 300);
+    assert_eq!(archive.extract(entry), Err(DecodeError::InvalidData));
+}
+
+#[test]
+fn write() {
+    let text = fib_str(b'a', b'b', 10000);
+    let mut writer = ZipWriter::new();
+    writer
+        .add("stored.txt", &text, Compression::Stored)
+        .unwrap();
+    writer
+        .add(
+            "deflated.txt",
+            &text,
+            Compression::Deflated(CompressionLevel::Best),
+        )
+        .unwrap();
+    writer
+        .add("short.txt", b"abc", Compression::default())
+        .unwrap();
+    writer.add("empty", &[], Compression::default()).unwrap();
+    writer.add_directory("dir").unwrap();
+    writer
+        .add(
+            "dir/\u{30c6}\u{30ad}\u{30b9}\u{30c8}",
+            b"text",
+            Compression::default(),
+        )
+        .unwrap();
+    let output = writer.finish(b"comment").unwrap();
+
+    let archive = ZipArchive::new(&output).unwrap();
+    assert_eq!(archive.comment(), b"comment");
+    let methods = archive
+        .entries()
+        .iter()
+        .map(|v| v.method().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        [
+            CompressionMethod::Stored,
+            CompressionMethod::Deflated,
+            CompressionMethod::Stored,
+            CompressionMethod::Stored,
+            CompressionMethod::Stored,
+            CompressionMethod::Stored,
+        ]
+    );
+    assert!(archive.entries()[1].compressed_size() < text.len() as u64 / 10);
+    assert!(archive.entries()[4].is_dir());
+    assert_eq!(
+        archive.entries()[5].name_str(),
+        Some("dir/\u{30c6}\u{30ad}\u{30b9}\u{30c8}")
+    );
+    for (entry, expected) in
+        archive
+            .entries()
+            .iter()
+            .zip([text.as_slice(), &text, b"abc", &[], &[], b"text"])
+    {
+        assert_eq!(archive.extract(entry).unwrap(), expected);
+    }
+}
+
+#[test]
+fn write_many_entries() {
+    let count = u16::MAX as usize + 10;
+    let mut writer = ZipWriter::new();
+    for i in 0..count {
+        let name = std::format!("{i}");
+        writer
+            .add(&name, name.as_bytes(), Compression::Stored)
+            .unwrap();
+    }
+    let output = writer.finish(&[]).unwrap();
+    let archive = ZipArchive::new(&output).unwrap();
+    assert_eq!(archive.entries().len(), count);
+    let entry = archive.find("65540").unwrap();
+    assert_eq!(archive.extract(entry).unwrap(), b"65540");
+}
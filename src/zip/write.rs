@@ -0,0 +1,235 @@
+//! ZIP archive writer
+
+use super::*;
+use crate::deflate::{CompressionLevel, deflate};
+use alloc::string::String;
+
+/// MS-DOS time of the entries, 1980-01-01 00:00:00
+const DEFAULT_DOS_TIME: u32 = 0x0021_0000;
+
+/// MS-DOS directory attribute
+const ATTRIBUTE_DIRECTORY: u32 = 0x10;
+
+/// Compression of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Stored,
+    Deflated(CompressionLevel),
+    /// Deflated if it is smaller than the original, stored otherwise
+    Auto(CompressionLevel),
+}
+
+impl Default for Compression {
+    #[inline]
+    fn default() -> Self {
+        Self::Auto(CompressionLevel::Default)
+    }
+}
+
+/// ZIP archive writer into memory
+///
+/// Zip64 records are written only when they are necessary.
+#[derive(Default)]
+pub struct ZipWriter {
+    output: Vec<u8>,
+    entries: Vec<ZipEntry>,
+}
+
+impl ZipWriter {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file entry.
+    pub fn add(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        compression: Compression,
+    ) -> Result<(), EncodeError> {
+        let compressed = match compression {
+            Compression::Stored => None,
+            _ if data.is_empty() => None,
+            Compression::Deflated(level) => Some(deflate(data, level, None)?),
+            Compression::Auto(level) => {
+                Some(deflate(data, level, None)?).filter(|v| v.len() < data.len())
+            }
+        };
+        let (method, payload) = match compressed.as_ref() {
+            Some(compressed) => (CompressionMethod::Deflated, compressed.as_slice()),
+            None => (CompressionMethod::Stored, data),
+        };
+        self.add_entry(name, 0, method, crc32::checksum(data), data.len(), payload)
+    }
+
+    /// Adds a directory entry, whose name ends with `/`.
+    pub fn add_directory(&mut self, name: &str) -> Result<(), EncodeError> {
+        let mut name = String::from(name);
+        if !name.ends_with('/') {
+            name.push('/');
+        }
+        self.add_entry(
+            &name,
+            ATTRIBUTE_DIRECTORY,
+            CompressionMethod::Stored,
+            0,
+            0,
+            &[],
+        )
+    }
+
+    fn add_entry(
+        &mut self,
+        name: &str,
+        external_attributes: u32,
+        method: CompressionMethod,
+        crc32: u32,
+        size: usize,
+        payload: &[u8],
+    ) -> Result<(), EncodeError> {
+        if name.len() > u16::MAX as usize {
+            return Err(EncodeError::InvalidInput);
+        }
+        let entry = ZipEntry {
+            name: name.as_bytes().to_vec(),
+            comment: Vec::new(),
+            method: method.as_u16(),
+            flags: if name.is_ascii() { 0 } else { FLAG_UTF8 },
+            dos_time: DEFAULT_DOS_TIME,
+            crc32,
+            compressed_size: payload.len() as u64,
+            size: size as u64,
+            external_attributes,
+            local_header_offset: self.output.len() as u64,
+        };
+
+        let output = &mut self.output;
+        let is_zip64 = entry.size >= u32::MAX as u64 || entry.compressed_size >= u32::MAX as u64;
+        write_u32(output, LOCAL_HEADER_SIGNATURE);
+        write_u16(output, entry.version_needed());
+        write_u16(output, entry.flags);
+        write_u16(output, entry.method);
+        write_u32(output, entry.dos_time);
+        write_u32(output, entry.crc32);
+        if is_zip64 {
+            write_u32(output, u32::MAX);
+            write_u32(output, u32::MAX);
+        } else {
+            write_u32(output, entry.compressed_size as u32);
+            write_u32(output, entry.size as u32);
+        }
+        write_u16(output, entry.name.len() as u16);
+        write_u16(output, if is_zip64 { 20 } else { 0 });
+        output.extend_from_slice(&entry.name);
+        if is_zip64 {
+            write_u16(output, ZIP64_EXTRA_ID);
+            write_u16(output, 16);
+            write_u64(output, entry.size);
+            write_u64(output, entry.compressed_size);
+        }
+        output.extend_from_slice(payload);
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the archive.
+    pub fn finish(self, comment: &[u8]) -> Result<Vec<u8>, EncodeError> {
+        if comment.len() > u16::MAX as usize {
+            return Err(EncodeError::InvalidInput);
+        }
+        let mut output = self.output;
+        let offset = output.len() as u64;
+        for entry in self.entries.iter() {
+            let mut zip64 = Vec::new();
+            let mut saturate = |value: u64| {
+                if value >= u32::MAX as u64 {
+                    write_u64(&mut zip64, value);
+                    u32::MAX
+                } else {
+                    value as u32
+                }
+            };
+            let size = saturate(entry.size);
+            let compressed_size = saturate(entry.compressed_size);
+            let local_header_offset = saturate(entry.local_header_offset);
+
+            write_u32(&mut output, CENTRAL_HEADER_SIGNATURE);
+            write_u16(&mut output, entry.version_needed());
+            write_u16(&mut output, entry.version_needed());
+            write_u16(&mut output, entry.flags);
+            write_u16(&mut output, entry.method);
+            write_u32(&mut output, entry.dos_time);
+            write_u32(&mut output, entry.crc32);
+            write_u32(&mut output, compressed_size);
+            write_u32(&mut output, size);
+            write_u16(&mut output, entry.name.len() as u16);
+            write_u16(
+                &mut output,
+                if zip64.is_empty() {
+                    0
+                } else {
+                    zip64.len() as u16 + 4
+                },
+            );
+            write_u16(&mut output, 0); // comment
+            write_u16(&mut output, 0); // disk
+            write_u16(&mut output, 0); // internal attributes
+            write_u32(&mut output, entry.external_attributes);
+            write_u32(&mut output, local_header_offset);
+            output.extend_from_slice(&entry.name);
+            if !zip64.is_empty() {
+                write_u16(&mut output, ZIP64_EXTRA_ID);
+                write_u16(&mut output, zip64.len() as u16);
+                output.extend_from_slice(&zip64);
+            }
+        }
+        let size = output.len() as u64 - offset;
+        let entries = self.entries.len() as u64;
+
+        let is_zip64 =
+            entries >= u16::MAX as u64 || size >= u32::MAX as u64 || offset >= u32::MAX as u64;
+        if is_zip64 {
+            let zip64_offset = output.len() as u64;
+            write_u32(&mut output, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+            write_u64(&mut output, ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE as u64 - 12);
+            write_u16(&mut output, VERSION_ZIP64);
+            write_u16(&mut output, VERSION_ZIP64);
+            write_u32(&mut output, 0); // disk
+            write_u32(&mut output, 0); // disk of the central directory
+            write_u64(&mut output, entries);
+            write_u64(&mut output, entries);
+            write_u64(&mut output, size);
+            write_u64(&mut output, offset);
+
+            write_u32(&mut output, ZIP64_LOCATOR_SIGNATURE);
+            write_u32(&mut output, 0); // disk
+            write_u64(&mut output, zip64_offset);
+            write_u32(&mut output, 1); // number of disks
+        }
+
+        let entries = entries.min(u16::MAX as u64) as u16;
+        write_u32(&mut output, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        write_u16(&mut output, 0); // disk
+        write_u16(&mut output, 0); // disk of the central directory
+        write_u16(&mut output, entries);
+        write_u16(&mut output, entries);
+        write_u32(&mut output, size.min(u32::MAX as u64) as u32);
+        write_u32(&mut output, offset.min(u32::MAX as u64) as u32);
+        write_u16(&mut output, comment.len() as u16);
+        output.extend_from_slice(comment);
+        Ok(output)
+    }
+}
+
+impl ZipEntry {
+    #[inline]
+    fn version_needed(&self) -> u16 {
+        if self.needs_zip64() {
+            VERSION_ZIP64
+        } else {
+            VERSION_DEFLATE
+        }
+    }
+}
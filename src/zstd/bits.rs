@@ -0,0 +1,121 @@
+//! Backward bit stream of zstd
+//!
+//! The stream is written forward in LSB-first order and read from the end,
+//! where the highest set bit of the last byte marks the end of the stream.
+
+use super::*;
+
+pub(super) struct BackwardBitReader<'a> {
+    input: &'a [u8],
+    /// Number of bits left, which becomes negative when reading past the start
+    position: isize,
+}
+
+impl<'a> BackwardBitReader<'a> {
+    #[inline]
+    pub(super) fn new(input: &'a [u8]) -> Result<Self, DecodeError> {
+        let last = *input.last().ok_or(DecodeError::InvalidData)?;
+        if last == 0 {
+            return Err(DecodeError::InvalidData);
+        }
+        let padding = last.leading_zeros() as isize + 1;
+        Ok(Self {
+            input,
+            position: input.len() as isize * 8 - padding,
+        })
+    }
+
+    /// Returns the number of bits left, which is negative after reading past the start.
+    #[inline]
+    pub(super) fn bits_left(&self) -> isize {
+        self.position
+    }
+
+    /// Whether all bits are read exactly
+    #[inline]
+    pub(super) fn is_empty(&self) -> bool {
+        self.position == 0
+    }
+
+    /// Returns the next `bits` bits, up to 56 bits, without consuming them.
+    ///
+    /// The bits past the start of the stream are zeros.
+    #[inline]
+    pub(super) fn peek(&self, bits: u32) -> u64 {
+        if bits == 0 {
+            return 0;
+        }
+        let start = self.position - bits as isize;
+        if start >= 0 {
+            self.bits_at(start as usize, bits)
+        } else if self.position > 0 {
+            self.bits_at(0, self.position as u32) << -start
+        } else {
+            0
+        }
+    }
+
+    /// Consumes `bits` bits.
+    #[inline]
+    pub(super) fn skip(&mut self, bits: u32) {
+        self.position -= bits as isize;
+    }
+
+    /// Reads `bits` bits, up to 56 bits, where the first bit read is the most significant.
+    #[inline]
+    pub(super) fn read(&mut self, bits: u32) -> u64 {
+        let value = self.peek(bits);
+        self.skip(bits);
+        value
+    }
+
+    #[inline]
+    fn bits_at(&self, position: usize, bits: u32) -> u64 {
+        debug_assert!(bits <= 56);
+        let index = position / 8;
+        let mut bytes = [0; 8];
+        let available = self.input.len().saturating_sub(index).min(8);
+        bytes[..available].copy_from_slice(&self.input[index..index + available]);
+        (u64::from_le_bytes(bytes) >> (position % 8)) & ((1 << bits) - 1)
+    }
+}
+
+pub(super) struct BackwardBitWriter {
+    output: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BackwardBitWriter {
+    #[inline]
+    pub(super) fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            acc: 0,
+            bits: 0,
+        }
+    }
+
+    /// Writes the lowest `bits` bits of the value, up to 32 bits, which are read back as a whole.
+    #[inline]
+    pub(super) fn write(&mut self, value: u64, bits: u32) {
+        debug_assert!(bits <= 32);
+        self.acc |= (value & ((1 << bits) - 1)) << self.bits;
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.output.push(self.acc as u8);
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    /// Writes the end mark and returns the stream.
+    #[inline]
+    pub(super) fn finish(mut self) -> Vec<u8> {
+        self.write(1, 1);
+        if self.bits > 0 {
+            self.output.push(self.acc as u8);
+        }
+        self.output
+    }
+}
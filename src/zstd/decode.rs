@@ -0,0 +1,502 @@
+//! zstd decoder
+
+use super::bits::BackwardBitReader;
+use super::fse::FseTable;
+use super::huffman::HuffmanTable;
+use super::*;
+use crate::lz::LzOutputBuffer;
+
+/// Frame header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Minimum size of the history the decoder keeps
+    pub window_size: u64,
+    /// Size of the decompressed data, if known
+    pub content_size: Option<u64>,
+    /// ID of the dictionary, or 0 if there is none
+    pub dictionary_id: u32,
+    /// Whether the frame ends with the xxHash64 checksum
+    pub has_checksum: bool,
+}
+
+impl FrameHeader {
+    /// Reads the frame header including the signature and returns it with the number of bytes read.
+    pub fn read(input: &[u8]) -> Result<(Self, usize), DecodeError> {
+        let (magic, input) = input
+            .split_first_chunk::<4>()
+            .ok_or(DecodeError::UnexpectedEof)?;
+        if u32::from_le_bytes(*magic) != FRAME_MAGIC {
+            return Err(DecodeError::InvalidData);
+        }
+        let descriptor = *input.first().ok_or(DecodeError::UnexpectedEof)?;
+        if descriptor & 0x08 != 0 {
+            return Err(DecodeError::InvalidData);
+        }
+        let single_segment = descriptor & 0x20 != 0;
+        let has_checksum = descriptor & 0x04 != 0;
+        let window_len = if single_segment { 0 } else { 1 };
+        let dictionary_id_len = [0, 1, 2, 4][descriptor as usize & 3];
+        let content_size_len = match descriptor >> 6 {
+            0 if single_segment => 1,
+            0 => 0,
+            1 => 2,
+            2 => 4,
+            _ => 8,
+        };
+        let len = 1 + window_len + dictionary_id_len + content_size_len;
+        let fields = input.get(1..len).ok_or(DecodeError::UnexpectedEof)?;
+        let (window, fields) = fields.split_at(window_len);
+        let (dictionary_id, content_size) = fields.split_at(dictionary_id_len);
+
+        let read_le = |bytes: &[u8]| bytes.iter().rev().fold(0, |acc, &v| (acc << 8) | v as u64);
+        let content_size = (content_size_len > 0).then(|| {
+            let value = read_le(content_size);
+            if content_size_len == 2 {
+                value + 256
+            } else {
+                value
+            }
+        });
+        let window_size = match (window.first(), content_size) {
+            (Some(&descriptor), _) => {
+                let base = 1u64 << (10 + (descriptor >> 3));
+                base + (base / 8) * (descriptor & 7) as u64
+            }
+            (None, Some(content_size)) => content_size,
+            (None, None) => unreachable!(),
+        };
+        Ok((
+            Self {
+                window_size,
+                content_size,
+                dictionary_id: read_le(dictionary_id) as u32,
+                has_checksum,
+            },
+            4 + len,
+        ))
+    }
+}
+
+/// Decompresses the frames, which may be concatenated or interleaved with skippable frames.
+#[inline]
+pub fn decode(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    decode_with_dictionaries(input, &[])
+}
+
+/// Decompresses the frames, which may refer to the dictionaries by their IDs.
+///
+/// A frame without a dictionary ID uses the dictionary of ID 0 if there is one.
+pub fn decode_with_dictionaries(
+    input: &[u8],
+    dictionaries: &[Dictionary],
+) -> Result<Vec<u8>, DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    let mut output = Vec::new();
+    let mut input = input;
+    while !input.is_empty() {
+        let magic = input
+            .first_chunk::<4>()
+            .map(|v| u32::from_le_bytes(*v))
+            .ok_or(DecodeError::UnexpectedEof)?;
+        let len = if magic & !0x0f == SKIPPABLE_MAGIC {
+            let size = input
+                .get(4..8)
+                .map(|v| u32::from_le_bytes(v.try_into().unwrap()) as usize)
+                .ok_or(DecodeError::UnexpectedEof)?;
+            if input.len() - 8 < size {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            8 + size
+        } else {
+            decode_frame(input, &mut output, dictionaries)?
+        };
+        input = &input[len..];
+    }
+    Ok(output)
+}
+
+/// Decodes a frame at the end of the output and returns the number of bytes read.
+fn decode_frame(
+    input: &[u8],
+    output: &mut Vec<u8>,
+    dictionaries: &[Dictionary],
+) -> Result<usize, DecodeError> {
+    let (header, mut cursor) = FrameHeader::read(input)?;
+    let dictionary = dictionaries.iter().find(|v| v.id() == header.dictionary_id);
+    if dictionary.is_none() && header.dictionary_id != 0 {
+        return Err(DecodeError::InvalidInput);
+    }
+
+    // The dictionary content is placed right before the frame during decoding.
+    let history_start = output.len();
+    let mut context = Context::new(dictionary);
+    if let Some(dictionary) = dictionary {
+        output.extend_from_slice(dictionary.content());
+    }
+    let frame_start = output.len();
+    let block_max = header.window_size.min(MAX_BLOCK_SIZE as u64) as usize;
+
+    loop {
+        let block_header = input
+            .get(cursor..cursor + 3)
+            .map(|v| u32::from_le_bytes([v[0], v[1], v[2], 0]) as usize)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        cursor += 3;
+        let is_last = block_header & 1 != 0;
+        let size = block_header >> 3;
+        if size > block_max {
+            return Err(DecodeError::InvalidData);
+        }
+        match (block_header >> 1) & 3 {
+            // Raw
+            0 => {
+                let data = input
+                    .get(cursor..cursor + size)
+                    .ok_or(DecodeError::UnexpectedEof)?;
+                output.extend_from_slice(data);
+                cursor += size;
+            }
+            // RLE
+            1 => {
+                let byte = *input.get(cursor).ok_or(DecodeError::UnexpectedEof)?;
+                output.resize(output.len() + size, byte);
+                cursor += 1;
+            }
+            // Compressed
+            2 => {
+                let data = input
+                    .get(cursor..cursor + size)
+                    .ok_or(DecodeError::UnexpectedEof)?;
+                context.decode_block(data, output, history_start, block_max)?;
+                cursor += size;
+            }
+            _ => return Err(DecodeError::InvalidData),
+        }
+        if is_last {
+            break;
+        }
+    }
+
+    let content = &output[frame_start..];
+    if header
+        .content_size
+        .is_some_and(|size| size != content.len() as u64)
+    {
+        return Err(DecodeError::InvalidData);
+    }
+    if header.has_checksum {
+        let checksum = input
+            .get(cursor..cursor + 4)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        if checksum != (xxhash64::checksum(content) as u32).to_le_bytes() {
+            return Err(DecodeError::InvalidData);
+        }
+        cursor += 4;
+    }
+    output.drain(history_start..frame_start);
+    Ok(cursor)
+}
+
+/// The state carried over the blocks of a frame
+struct Context {
+    huffman: Option<HuffmanTable>,
+    literal_lengths: Option<FseTable>,
+    offsets: Option<FseTable>,
+    match_lengths: Option<FseTable>,
+    repeat_offsets: [usize; 3],
+    literals: Vec<u8>,
+}
+
+impl Context {
+    fn new(dictionary: Option<&Dictionary>) -> Self {
+        match dictionary {
+            Some(dictionary) => Self {
+                huffman: dictionary.huffman.clone(),
+                literal_lengths: dictionary.literal_lengths.clone(),
+                offsets: dictionary.offsets.clone(),
+                match_lengths: dictionary.match_lengths.clone(),
+                repeat_offsets: dictionary.repeat_offsets,
+                literals: Vec::new(),
+            },
+            None => Self {
+                huffman: None,
+                literal_lengths: None,
+                offsets: None,
+                match_lengths: None,
+                repeat_offsets: INITIAL_REPEAT_OFFSETS,
+                literals: Vec::new(),
+            },
+        }
+    }
+
+    /// Decodes a compressed block at the end of the output,
+    /// whose matches may refer back to `history_start`.
+    fn decode_block(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        history_start: usize,
+        block_max: usize,
+    ) -> Result<(), DecodeError> {
+        let len = self.read_literals(input, block_max)?;
+        let input = &input[len..];
+
+        let (sequences, mut len) = match input {
+            [] => return Err(DecodeError::UnexpectedEof),
+            &[0, ..] => (0, 1),
+            &[v @ 1..=127, ..] => (v as usize, 1),
+            &[v @ 128..=254, w, ..] => ((((v - 128) as usize) << 8) | w as usize, 2),
+            &[255, v, w, ..] => (u16::from_le_bytes([v, w]) as usize + 0x7f00, 3),
+            _ => return Err(DecodeError::UnexpectedEof),
+        };
+        let start = output.len();
+        output.resize(start + block_max, 0);
+        let mut buffer = LzOutputBuffer::with_position(output, start);
+
+        if sequences == 0 {
+            if len != input.len() {
+                return Err(DecodeError::InvalidData);
+            }
+            buffer
+                .extend_from_slice(&self.literals)
+                .ok_or(DecodeError::InvalidData)?;
+            let end = buffer.position();
+            output.truncate(end);
+            return Ok(());
+        }
+
+        let modes = *input.get(len).ok_or(DecodeError::UnexpectedEof)?;
+        if modes & 3 != 0 {
+            return Err(DecodeError::InvalidData);
+        }
+        len += 1;
+        len += read_table(
+            &mut self.literal_lengths,
+            modes >> 6,
+            &input[len..],
+            &LITERAL_LENGTH_DEFAULT_DISTRIBUTION,
+            LITERAL_LENGTH_DEFAULT_ACCURACY_LOG,
+            MAX_LITERAL_LENGTH_ACCURACY_LOG,
+            MAX_LITERAL_LENGTH_CODE,
+        )?;
+        len += read_table(
+            &mut self.offsets,
+            (modes >> 4) & 3,
+            &input[len..],
+            &OFFSET_DEFAULT_DISTRIBUTION,
+            OFFSET_DEFAULT_ACCURACY_LOG,
+            MAX_OFFSET_ACCURACY_LOG,
+            MAX_OFFSET_CODE,
+        )?;
+        len += read_table(
+            &mut self.match_lengths,
+            (modes >> 2) & 3,
+            &input[len..],
+            &MATCH_LENGTH_DEFAULT_DISTRIBUTION,
+            MATCH_LENGTH_DEFAULT_ACCURACY_LOG,
+            MAX_MATCH_LENGTH_ACCURACY_LOG,
+            MAX_MATCH_LENGTH_CODE,
+        )?;
+        let (Some(ll_table), Some(of_table), Some(ml_table)) =
+            (&self.literal_lengths, &self.offsets, &self.match_lengths)
+        else {
+            unreachable!()
+        };
+
+        let mut reader = BackwardBitReader::new(&input[len..])?;
+        let mut ll_state = ll_table.init_state(&mut reader);
+        let mut of_state = of_table.init_state(&mut reader);
+        let mut ml_state = ml_table.init_state(&mut reader);
+        let mut literals = self.literals.as_slice();
+        for i in 0..sequences {
+            let of_code = of_table.symbol(of_state) as u32;
+            let offset_value = (1 << of_code) + reader.read(of_code) as usize;
+            let (base, bits) = MATCH_LENGTH_CODES[ml_table.symbol(ml_state) as usize];
+            let match_len = base as usize + reader.read(bits as u32) as usize;
+            let (base, bits) = LITERAL_LENGTH_CODES[ll_table.symbol(ll_state) as usize];
+            let literal_len = base as usize + reader.read(bits as u32) as usize;
+            if i + 1 < sequences {
+                ll_state = ll_table.next_state(ll_state, &mut reader);
+                ml_state = ml_table.next_state(ml_state, &mut reader);
+                of_state = of_table.next_state(of_state, &mut reader);
+            }
+            if reader.bits_left() < 0 {
+                return Err(DecodeError::UnexpectedEof);
+            }
+
+            let offset = resolve_offset(&mut self.repeat_offsets, offset_value, literal_len)?;
+            if literal_len > literals.len() {
+                return Err(DecodeError::InvalidData);
+            }
+            let (head, tail) = literals.split_at(literal_len);
+            buffer
+                .extend_from_slice(head)
+                .ok_or(DecodeError::InvalidData)?;
+            literals = tail;
+            if offset > buffer.position() - history_start || match_len > buffer.remaining() {
+                return Err(DecodeError::InvalidData);
+            }
+            buffer
+                .copy_lz(offset, match_len)
+                .ok_or(DecodeError::InvalidData)?;
+        }
+        if !reader.is_empty() {
+            return Err(DecodeError::InvalidData);
+        }
+        buffer
+            .extend_from_slice(literals)
+            .ok_or(DecodeError::InvalidData)?;
+        let end = buffer.position();
+        output.truncate(end);
+        Ok(())
+    }
+
+    /// Reads the literals section into `self.literals` and returns the number of bytes read.
+    fn read_literals(&mut self, input: &[u8], block_max: usize) -> Result<usize, DecodeError> {
+        let first = *input.first().ok_or(DecodeError::UnexpectedEof)? as usize;
+        let literals_type = first & 3;
+        let size_format = (first >> 2) & 3;
+        self.literals.clear();
+
+        if literals_type < 2 {
+            // Raw or RLE
+            let header_len = match size_format {
+                0 | 2 => 1,
+                1 => 2,
+                _ => 3,
+            };
+            let header = input.get(..header_len).ok_or(DecodeError::UnexpectedEof)?;
+            let size = match size_format {
+                0 | 2 => first >> 3,
+                _ => {
+                    header[1..]
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, &v| (acc << 8) | v as usize)
+                        << 4
+                        | (first >> 4)
+                }
+            };
+            if size > block_max {
+                return Err(DecodeError::InvalidData);
+            }
+            return if literals_type == 0 {
+                let data = input
+                    .get(header_len..header_len + size)
+                    .ok_or(DecodeError::UnexpectedEof)?;
+                self.literals.extend_from_slice(data);
+                Ok(header_len + size)
+            } else {
+                let byte = *input.get(header_len).ok_or(DecodeError::UnexpectedEof)?;
+                self.literals.resize(size, byte);
+                Ok(header_len + 1)
+            };
+        }
+
+        // Compressed or treeless
+        let (header_len, size_bits) = match size_format {
+            0 | 1 => (3, 10),
+            2 => (4, 14),
+            _ => (5, 18),
+        };
+        let header = input
+            .get(..header_len)
+            .ok_or(DecodeError::UnexpectedEof)?
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &v| (acc << 8) | v as u64);
+        let mask = (1 << size_bits) - 1;
+        let size = ((header >> 4) & mask) as usize;
+        let compressed_size = ((header >> (4 + size_bits)) & mask) as usize;
+        if size > block_max {
+            return Err(DecodeError::InvalidData);
+        }
+        let mut data = input
+            .get(header_len..header_len + compressed_size)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        if literals_type == 2 {
+            let (table, len) = HuffmanTable::read(data)?;
+            self.huffman = Some(table);
+            data = &data[len..];
+        }
+        let table = self.huffman.as_ref().ok_or(DecodeError::InvalidData)?;
+        self.literals.resize(size, 0);
+        table.decode_literals(data, &mut self.literals, size_format != 0)?;
+        Ok(header_len + compressed_size)
+    }
+}
+
+/// Converts the offset value to the actual offset, updating the repeat offsets.
+#[inline]
+pub(super) fn resolve_offset(
+    reps: &mut [usize; 3],
+    value: usize,
+    literal_len: usize,
+) -> Result<usize, DecodeError> {
+    if value > 3 {
+        let offset = value - 3;
+        *reps = [offset, reps[0], reps[1]];
+        return Ok(offset);
+    }
+    // The repeat offsets shift by one when the literal length is zero.
+    let index = if literal_len == 0 { value } else { value - 1 };
+    let offset = match index {
+        0 => return Ok(reps[0]),
+        1 | 2 => reps[index],
+        _ => reps[0] - 1,
+    };
+    if offset == 0 {
+        return Err(DecodeError::InvalidData);
+    }
+    if index == 1 {
+        reps[1] = reps[0];
+    } else {
+        *reps = [offset, reps[0], reps[1]];
+    }
+    reps[0] = offset;
+    Ok(offset)
+}
+
+/// Reads the table of the compression mode into `slot` and returns the number of bytes read.
+fn read_table(
+    slot: &mut Option<FseTable>,
+    mode: u8,
+    input: &[u8],
+    default_distribution: &[i16],
+    default_accuracy_log: u8,
+    max_accuracy_log: u8,
+    max_symbol: u8,
+) -> Result<usize, DecodeError> {
+    match mode {
+        // Predefined
+        0 => {
+            *slot = Some(FseTable::from_distribution(
+                default_distribution,
+                default_accuracy_log,
+            )?);
+            Ok(0)
+        }
+        // RLE
+        1 => {
+            let symbol = *input.first().ok_or(DecodeError::UnexpectedEof)?;
+            if symbol > max_symbol {
+                return Err(DecodeError::InvalidData);
+            }
+            *slot = Some(FseTable::rle(symbol));
+            Ok(1)
+        }
+        // FSE compressed
+        2 => {
+            let (table, len) = FseTable::read(input, max_accuracy_log, max_symbol)?;
+            *slot = Some(table);
+            Ok(len)
+        }
+        // Repeat
+        _ => match slot {
+            Some(_) => Ok(0),
+            None => Err(DecodeError::InvalidData),
+        },
+    }
+}
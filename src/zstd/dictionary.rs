@@ -0,0 +1,94 @@
+//! zstd dictionaries
+
+use super::fse::FseTable;
+use super::huffman::HuffmanTable;
+use super::*;
+
+/// A dictionary, which provides the history and optionally the entropy tables for the frames of the same ID
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    id: u32,
+    content: Vec<u8>,
+    pub(super) huffman: Option<HuffmanTable>,
+    pub(super) literal_lengths: Option<FseTable>,
+    pub(super) offsets: Option<FseTable>,
+    pub(super) match_lengths: Option<FseTable>,
+    pub(super) repeat_offsets: [usize; 3],
+}
+
+impl Dictionary {
+    /// Reads a dictionary in the zstd format,
+    /// or takes the data as the raw content of ID 0 if it does not start with the signature.
+    pub fn new(data: &[u8]) -> Result<Self, DecodeError> {
+        if !data.starts_with(&DICTIONARY_MAGIC.to_le_bytes()) {
+            return Ok(Self::raw(0, data));
+        }
+        let (id, mut data) = data[4..]
+            .split_first_chunk::<4>()
+            .ok_or(DecodeError::UnexpectedEof)?;
+
+        let (huffman, len) = HuffmanTable::read(data)?;
+        data = &data[len..];
+        let (offsets, len) = FseTable::read(data, MAX_OFFSET_ACCURACY_LOG, MAX_OFFSET_CODE)?;
+        data = &data[len..];
+        let (match_lengths, len) =
+            FseTable::read(data, MAX_MATCH_LENGTH_ACCURACY_LOG, MAX_MATCH_LENGTH_CODE)?;
+        data = &data[len..];
+        let (literal_lengths, len) = FseTable::read(
+            data,
+            MAX_LITERAL_LENGTH_ACCURACY_LOG,
+            MAX_LITERAL_LENGTH_CODE,
+        )?;
+        data = &data[len..];
+
+        let mut repeat_offsets = [0; 3];
+        for offset in repeat_offsets.iter_mut() {
+            let (bytes, rest) = data
+                .split_first_chunk::<4>()
+                .ok_or(DecodeError::UnexpectedEof)?;
+            *offset = u32::from_le_bytes(*bytes) as usize;
+            data = rest;
+        }
+        if repeat_offsets
+            .iter()
+            .any(|&offset| offset == 0 || offset > data.len())
+        {
+            return Err(DecodeError::InvalidData);
+        }
+
+        Ok(Self {
+            id: u32::from_le_bytes(*id),
+            content: data.to_vec(),
+            huffman: Some(huffman),
+            literal_lengths: Some(literal_lengths),
+            offsets: Some(offsets),
+            match_lengths: Some(match_lengths),
+            repeat_offsets,
+        })
+    }
+
+    /// Creates a dictionary of the raw content, without the entropy tables.
+    pub fn raw(id: u32, content: &[u8]) -> Self {
+        Self {
+            id,
+            content: content.to_vec(),
+            huffman: None,
+            literal_lengths: None,
+            offsets: None,
+            match_lengths: None,
+            repeat_offsets: INITIAL_REPEAT_OFFSETS,
+        }
+    }
+
+    /// Returns the ID, which the frames refer to.
+    #[inline]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the content, which precedes the data of the frames.
+    #[inline]
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+}
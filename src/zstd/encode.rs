@@ -0,0 +1,528 @@
+//! zstd encoder
+
+use super::bits::BackwardBitWriter;
+use super::fse::{self, FseEncoder};
+use super::huffman::HuffmanEncoder;
+use super::*;
+use crate::lz::lzss::{self, LZSS};
+
+/// Minimum length of the matches
+const MIN_MATCH: usize = 3;
+
+/// The matches shorter than this are taken only within [`FAR_DISTANCE`].
+const MIN_FAR_MATCH: usize = 4;
+
+const FAR_DISTANCE: usize = 1 << 10;
+
+/// The single stream of the compressed literals is limited by the 10 bits sizes.
+const MAX_SINGLE_STREAM_LITERALS: usize = 1023;
+
+/// How hard the encoder tries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionLevel {
+    /// Takes the first match found, and codes the sequences with the predefined tables.
+    Fast,
+    /// Searches for the longer matches, and codes the sequences with the tables of the block if they are smaller.
+    #[default]
+    Default,
+}
+
+/// Frame configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Configuration {
+    level: CompressionLevel,
+    checksum: bool,
+    content_size: bool,
+}
+
+impl Configuration {
+    /// The default level with the checksum and the content size, as the reference command line tool
+    pub const DEFAULT: Self = Self::new(CompressionLevel::Default);
+
+    #[inline]
+    pub const fn new(level: CompressionLevel) -> Self {
+        Self {
+            level,
+            checksum: true,
+            content_size: true,
+        }
+    }
+
+    /// Sets whether the frame ends with the lowest 32 bits of the xxHash64 of the decompressed data.
+    #[inline]
+    pub const fn checksum(mut self, value: bool) -> Self {
+        self.checksum = value;
+        self
+    }
+
+    /// Sets whether the header has the size of the decompressed data,
+    /// otherwise the header has the window size instead.
+    #[inline]
+    pub const fn content_size(mut self, value: bool) -> Self {
+        self.content_size = value;
+        self
+    }
+
+    #[inline]
+    pub const fn level(&self) -> CompressionLevel {
+        self.level
+    }
+}
+
+impl Default for Configuration {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Compresses the input into a frame.
+pub fn encode(input: &[u8], config: Configuration) -> Result<Vec<u8>, EncodeError> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 32);
+    output.extend_from_slice(&FRAME_MAGIC.to_le_bytes());
+
+    let len = input.len() as u64;
+    let mut descriptor = if config.checksum { 0x04 } else { 0 };
+    let block_size = if config.content_size {
+        // A single segment frame has the content size as the window size.
+        let (flag, value) = match len {
+            0..0x100 => (0, len),
+            0x100..0x1_0100 => (1, len - 0x100),
+            0x1_0100..0x1_0000_0000 => (2, len),
+            _ => (3, len),
+        };
+        descriptor |= (flag << 6) | 0x20;
+        output.push(descriptor);
+        let size_len = [1, 2, 4, 8][flag as usize];
+        output.extend_from_slice(&value.to_le_bytes()[..size_len]);
+        MAX_BLOCK_SIZE
+    } else {
+        let window_log = input
+            .len()
+            .min(LZSS::MAX_DISTANCE)
+            .next_power_of_two()
+            .trailing_zeros()
+            .max(10);
+        output.push(descriptor);
+        output.push(((window_log - 10) << 3) as u8);
+        MAX_BLOCK_SIZE.min(1 << window_log)
+    };
+
+    if input.is_empty() {
+        // An empty raw block
+        output.extend_from_slice(&[1, 0, 0]);
+    } else {
+        let matches = find_matches(input, config.level)?;
+        let mut context = Context::new(config.level);
+        let mut matches = matches.as_slice();
+        for start in (0..input.len()).step_by(block_size) {
+            let end = (start + block_size).min(input.len());
+            let is_last = end == input.len();
+            let block = &input[start..end];
+            if block.iter().all(|&v| v == block[0]) {
+                write_block_header(&mut output, 1, block.len(), is_last);
+                output.push(block[0]);
+                continue;
+            }
+
+            let (literals, sequences) = split_sequences(input, start, end, &mut matches);
+            let mut next = context.clone();
+            let mut compressed = Vec::new();
+            next.encode_block(&literals, &sequences, &mut compressed);
+            if compressed.len() < block.len() {
+                // The decoder only updates its state with the compressed blocks.
+                context = next;
+                write_block_header(&mut output, 2, compressed.len(), is_last);
+                output.extend_from_slice(&compressed);
+            } else {
+                write_block_header(&mut output, 0, block.len(), is_last);
+                output.extend_from_slice(block);
+            }
+        }
+    }
+
+    if config.checksum {
+        output.extend_from_slice(&(xxhash64::checksum(input) as u32).to_le_bytes());
+    }
+    Ok(output)
+}
+
+#[inline]
+fn write_block_header(output: &mut Vec<u8>, block_type: u32, size: usize, is_last: bool) {
+    let header = ((size as u32) << 3) | (block_type << 1) | is_last as u32;
+    output.extend_from_slice(&header.to_le_bytes()[..3]);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MatchRange {
+    start: usize,
+    len: usize,
+    distance: usize,
+}
+
+/// A literal length, a match length and the actual offset
+#[derive(Debug, Clone, Copy)]
+struct Sequence {
+    literal_len: usize,
+    match_len: usize,
+    offset: usize,
+}
+
+/// Finds the matches of the whole input, merging the adjacent ones of the same distance.
+fn find_matches(input: &[u8], level: CompressionLevel) -> Result<Vec<MatchRange>, EncodeError> {
+    let mut matches = Vec::<MatchRange>::new();
+    let mut cursor = 0;
+    let mut push = |lzss| {
+        match lzss {
+            LZSS::Literal(_) => cursor += 1,
+            // The short matches far away take more bits than the literals.
+            LZSS::Match(m) if m.len < MIN_FAR_MATCH && m.distance > FAR_DISTANCE => cursor += m.len,
+            LZSS::Match(m) => {
+                match matches.last_mut() {
+                    Some(last)
+                        if last.start + last.len == cursor && last.distance == m.distance =>
+                    {
+                        last.len += m.len;
+                    }
+                    _ => matches.push(MatchRange {
+                        start: cursor,
+                        len: m.len,
+                        distance: m.distance,
+                    }),
+                }
+                cursor += m.len;
+            }
+        }
+        Ok(())
+    };
+    let config = lzss::Configuration::new(LZSS::MAX_DISTANCE, LZSS::MAX_LEN, 0, 0, 0, 0);
+    match level {
+        CompressionLevel::Fast => LZSS::encode_fast(input, config, &mut push)?,
+        CompressionLevel::Default => LZSS::encode_lcp(input, config, &mut push)?,
+    }
+    Ok(matches)
+}
+
+/// Takes the matches within the block, which are cut at its boundaries,
+/// and returns the literals and the sequences of the block.
+fn split_sequences(
+    input: &[u8],
+    start: usize,
+    end: usize,
+    matches: &mut &[MatchRange],
+) -> (Vec<u8>, Vec<Sequence>) {
+    let mut literals = Vec::new();
+    let mut sequences = Vec::new();
+    let mut cursor = start;
+    while let Some(m) = matches.first() {
+        if m.start >= end {
+            break;
+        }
+        let match_start = m.start.max(cursor);
+        let match_end = (m.start + m.len).min(end);
+        // The parts too short to be a match remain literals.
+        if match_end >= match_start + MIN_MATCH {
+            literals.extend_from_slice(&input[cursor..match_start]);
+            sequences.push(Sequence {
+                literal_len: match_start - cursor,
+                match_len: match_end - match_start,
+                offset: m.distance,
+            });
+            cursor = match_end;
+        }
+        if m.start + m.len > end {
+            // The rest continues in the next block.
+            break;
+        }
+        *matches = &matches[1..];
+    }
+    literals.extend_from_slice(&input[cursor..end]);
+    (literals, sequences)
+}
+
+/// The state carried over the compressed blocks, which mirrors the decoder
+#[derive(Debug, Clone)]
+struct Context {
+    level: CompressionLevel,
+    huffman: Option<HuffmanEncoder>,
+    literal_lengths: Option<FseEncoder>,
+    offsets: Option<FseEncoder>,
+    match_lengths: Option<FseEncoder>,
+    repeat_offsets: [usize; 3],
+}
+
+impl Context {
+    fn new(level: CompressionLevel) -> Self {
+        Self {
+            level,
+            huffman: None,
+            literal_lengths: None,
+            offsets: None,
+            match_lengths: None,
+            repeat_offsets: INITIAL_REPEAT_OFFSETS,
+        }
+    }
+
+    /// Encodes the literals and the sequences of a block, not including the block header.
+    fn encode_block(&mut self, literals: &[u8], sequences: &[Sequence], output: &mut Vec<u8>) {
+        self.write_literals(literals, output);
+
+        let count = sequences.len();
+        match count {
+            0..0x80 => output.push(count as u8),
+            0x80..0x7f00 => output.extend_from_slice(&[(count >> 8) as u8 + 0x80, count as u8]),
+            _ => {
+                output.push(0xff);
+                output.extend_from_slice(&((count - 0x7f00) as u16).to_le_bytes());
+            }
+        }
+        if count == 0 {
+            return;
+        }
+
+        let mut codes = Vec::with_capacity(count);
+        let mut literal_length_counts = [0; MAX_LITERAL_LENGTH_CODE as usize + 1];
+        let mut offset_counts = [0; MAX_OFFSET_CODE as usize + 1];
+        let mut match_length_counts = [0; MAX_MATCH_LENGTH_CODE as usize + 1];
+        for sequence in sequences {
+            let value = offset_value(&self.repeat_offsets, sequence.offset, sequence.literal_len);
+            let resolved = resolve_offset(&mut self.repeat_offsets, value, sequence.literal_len);
+            debug_assert_eq!(resolved.ok(), Some(sequence.offset));
+
+            let literal_length = length_code(&LITERAL_LENGTH_CODES, sequence.literal_len);
+            let offset = value.ilog2() as u8;
+            let match_length = length_code(&MATCH_LENGTH_CODES, sequence.match_len);
+            literal_length_counts[literal_length as usize] += 1;
+            offset_counts[offset as usize] += 1;
+            match_length_counts[match_length as usize] += 1;
+            codes.push((literal_length, offset, match_length, value));
+        }
+
+        let modes_index = output.len();
+        output.push(0);
+        let literal_length_mode = self.select_table(
+            Slot::LiteralLengths,
+            &literal_length_counts,
+            &LITERAL_LENGTH_DEFAULT_DISTRIBUTION,
+            LITERAL_LENGTH_DEFAULT_ACCURACY_LOG,
+            MAX_LITERAL_LENGTH_ACCURACY_LOG,
+            output,
+        );
+        let offset_mode = self.select_table(
+            Slot::Offsets,
+            &offset_counts,
+            &OFFSET_DEFAULT_DISTRIBUTION,
+            OFFSET_DEFAULT_ACCURACY_LOG,
+            MAX_OFFSET_ACCURACY_LOG,
+            output,
+        );
+        let match_length_mode = self.select_table(
+            Slot::MatchLengths,
+            &match_length_counts,
+            &MATCH_LENGTH_DEFAULT_DISTRIBUTION,
+            MATCH_LENGTH_DEFAULT_ACCURACY_LOG,
+            MAX_MATCH_LENGTH_ACCURACY_LOG,
+            output,
+        );
+        output[modes_index] =
+            (literal_length_mode << 6) | (offset_mode << 4) | (match_length_mode << 2);
+
+        let (Some(ll_table), Some(of_table), Some(ml_table)) =
+            (&self.literal_lengths, &self.offsets, &self.match_lengths)
+        else {
+            unreachable!()
+        };
+
+        // The sequences are written backward, the last one starts the states.
+        let mut writer = BackwardBitWriter::new();
+        let mut states = None;
+        for (sequence, &(ll_code, of_code, ml_code, value)) in
+            sequences.iter().zip(codes.iter()).rev()
+        {
+            match &mut states {
+                Some((ll_state, of_state, ml_state)) => {
+                    of_table.encode(of_state, of_code, &mut writer);
+                    ml_table.encode(ml_state, ml_code, &mut writer);
+                    ll_table.encode(ll_state, ll_code, &mut writer);
+                }
+                None => {
+                    states = Some((
+                        ll_table.init_state(ll_code),
+                        of_table.init_state(of_code),
+                        ml_table.init_state(ml_code),
+                    ))
+                }
+            }
+            let (base, bits) = LITERAL_LENGTH_CODES[ll_code as usize];
+            writer.write((sequence.literal_len - base as usize) as u64, bits as u32);
+            let (base, bits) = MATCH_LENGTH_CODES[ml_code as usize];
+            writer.write((sequence.match_len - base as usize) as u64, bits as u32);
+            writer.write(value as u64, of_code as u32);
+        }
+        let (ll_state, of_state, ml_state) = states.unwrap();
+        ml_table.flush(ml_state, &mut writer);
+        of_table.flush(of_state, &mut writer);
+        ll_table.flush(ll_state, &mut writer);
+        output.extend_from_slice(&writer.finish());
+    }
+
+    /// Writes the literals section in the smallest of the raw, RLE, compressed and treeless types.
+    fn write_literals(&mut self, literals: &[u8], output: &mut Vec<u8>) {
+        let mut counts = [0; 256];
+        for &literal in literals {
+            counts[literal as usize] += 1;
+        }
+        if literals.len() > 1 && counts[literals[0] as usize] == literals.len() {
+            write_literals_header(output, 1, literals.len());
+            output.push(literals[0]);
+            return;
+        }
+
+        let four_streams = literals.len() > MAX_SINGLE_STREAM_LITERALS;
+        let overhead = if four_streams { 6 } else { 0 };
+        let treeless = self
+            .huffman
+            .as_ref()
+            .and_then(|v| v.cost(&counts))
+            .map(|v| v.div_ceil(8) + overhead);
+        let table = HuffmanEncoder::new(&counts);
+        let compressed = table
+            .as_ref()
+            .and_then(|v| Some(v.description().len() + v.cost(&counts)?.div_ceil(8) + overhead));
+
+        let mut data = Vec::new();
+        let literals_type = match (compressed, treeless) {
+            (_, Some(treeless)) if compressed.is_none_or(|v| treeless <= v) => {
+                let huffman = self.huffman.as_ref().unwrap();
+                huffman.encode_literals(literals, four_streams, &mut data);
+                3
+            }
+            (Some(_), _) => {
+                let huffman = table.as_ref().unwrap();
+                data.extend_from_slice(huffman.description());
+                huffman.encode_literals(literals, four_streams, &mut data);
+                2
+            }
+            _ => 0,
+        };
+        if literals_type == 0 || data.len() >= literals.len() {
+            write_literals_header(output, 0, literals.len());
+            output.extend_from_slice(literals);
+            return;
+        }
+        if literals_type == 2 {
+            self.huffman = table;
+        }
+
+        let (size_format, size_bits, header_len) = match literals.len().max(data.len()) {
+            _ if !four_streams => (0, 10, 3),
+            0..0x400 => (1, 10, 3),
+            0x400..0x4000 => (2, 14, 4),
+            _ => (3, 18, 5),
+        };
+        let header = literals_type as u64
+            | (size_format << 2)
+            | ((literals.len() as u64) << 4)
+            | ((data.len() as u64) << (4 + size_bits));
+        output.extend_from_slice(&header.to_le_bytes()[..header_len]);
+        output.extend_from_slice(&data);
+    }
+
+    /// Chooses the cheapest of the predefined, RLE, FSE compressed and repeat modes,
+    /// and writes the table description if any.
+    fn select_table(
+        &mut self,
+        slot: Slot,
+        counts: &[usize],
+        default_distribution: &[i16],
+        default_accuracy_log: u8,
+        max_accuracy_log: u8,
+        output: &mut Vec<u8>,
+    ) -> u8 {
+        let total: usize = counts.iter().sum();
+        let max_symbol = counts.iter().rposition(|&v| v > 0).unwrap();
+        let previous = match slot {
+            Slot::LiteralLengths => &mut self.literal_lengths,
+            Slot::Offsets => &mut self.offsets,
+            Slot::MatchLengths => &mut self.match_lengths,
+        };
+
+        let predefined = FseEncoder::from_distribution(default_distribution, default_accuracy_log);
+        let (mut mode, mut best) = (0, predefined.cost(counts).unwrap_or(f64::INFINITY));
+        if counts[max_symbol] == total && 8.0 < best {
+            (mode, best) = (1, 8.0);
+        }
+        if let Some(cost) = previous
+            .as_ref()
+            .and_then(|v| v.cost(counts))
+            .filter(|&v| v < best)
+        {
+            (mode, best) = (3, cost);
+        }
+        let mut description = Vec::new();
+        let mut custom = None;
+        if self.level != CompressionLevel::Fast && counts[max_symbol] < total {
+            let accuracy_log = fse::optimal_accuracy_log(total, max_symbol, max_accuracy_log);
+            let distribution = fse::normalize(counts, accuracy_log, 1 << accuracy_log);
+            fse::write_distribution(&distribution, accuracy_log, &mut description);
+            let table = FseEncoder::from_distribution(&distribution, accuracy_log);
+            if table.cost(counts).unwrap() + description.len() as f64 * 8.0 < best {
+                mode = 2;
+                custom = Some(table);
+            }
+        }
+
+        match mode {
+            0 => *previous = Some(predefined),
+            1 => {
+                *previous = Some(FseEncoder::rle(max_symbol as u8));
+                output.push(max_symbol as u8);
+            }
+            2 => {
+                *previous = custom;
+                output.extend_from_slice(&description);
+            }
+            _ => {}
+        }
+        mode
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    LiteralLengths,
+    Offsets,
+    MatchLengths,
+}
+
+fn write_literals_header(output: &mut Vec<u8>, literals_type: u32, size: usize) {
+    let size = size as u32;
+    match size {
+        0..0x20 => output.push((literals_type | (size << 3)) as u8),
+        0x20..0x1000 => {
+            output.extend_from_slice(&(literals_type | (1 << 2) | (size << 4)).to_le_bytes()[..2])
+        }
+        _ => output.extend_from_slice(&(literals_type | (3 << 2) | (size << 4)).to_le_bytes()[..3]),
+    }
+}
+
+/// Converts the actual offset to the offset value, which refers to the repeat offsets if possible.
+#[inline]
+fn offset_value(reps: &[usize; 3], offset: usize, literal_len: usize) -> usize {
+    // The repeat offsets shift by one when the literal length is zero.
+    let candidates = if literal_len == 0 {
+        [reps[1], reps[2], reps[0] - 1]
+    } else {
+        *reps
+    };
+    candidates
+        .iter()
+        .position(|&v| v == offset)
+        .map_or(offset + 3, |index| index + 1)
+}
+
+/// Returns the code of the length, whose baseline is the largest one not greater than the length.
+#[inline]
+fn length_code(codes: &[(u32, u8)], len: usize) -> u8 {
+    (codes.partition_point(|&(base, _)| base as usize <= len) - 1) as u8
+}
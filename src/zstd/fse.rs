@@ -0,0 +1,469 @@
+//! Finite state entropy tables of zstd
+
+use super::bits::{BackwardBitReader, BackwardBitWriter};
+use super::*;
+use crate::num::{
+    VarLenInteger,
+    bits::{BitSize, BitStreamWriter},
+    math,
+};
+use alloc::vec;
+
+/// The accuracy log of the table descriptions is stored minus this value.
+const MIN_ACCURACY_LOG: u8 = 5;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    symbol: u8,
+    bits: u8,
+    base: u16,
+}
+
+/// Decoding table of FSE
+#[derive(Debug, Clone)]
+pub(super) struct FseTable {
+    accuracy_log: u8,
+    entries: Vec<Entry>,
+}
+
+impl FseTable {
+    /// Creates a table that always decodes `symbol` without reading any bits.
+    pub(super) fn rle(symbol: u8) -> Self {
+        Self {
+            accuracy_log: 0,
+            entries: vec![Entry {
+                symbol,
+                bits: 0,
+                base: 0,
+            }],
+        }
+    }
+
+    /// Reads the table description and returns the table with the number of bytes read.
+    pub(super) fn read(
+        input: &[u8],
+        max_accuracy_log: u8,
+        max_symbol: u8,
+    ) -> Result<(Self, usize), DecodeError> {
+        let mut reader = ForwardBitReader::new(input);
+        let accuracy_log = reader.read(4) as u8 + MIN_ACCURACY_LOG;
+        if accuracy_log > max_accuracy_log {
+            return Err(DecodeError::InvalidData);
+        }
+
+        let mut distribution = Vec::with_capacity(max_symbol as usize + 1);
+        let mut remaining = (1i32 << accuracy_log) + 1;
+        let mut threshold = 1i32 << accuracy_log;
+        let mut bits = accuracy_log as u32 + 1;
+        let mut previous_zero = false;
+        while remaining > 1 {
+            if previous_zero {
+                loop {
+                    let repeat = reader.read(2) as usize;
+                    distribution.extend(core::iter::repeat_n(0, repeat));
+                    if repeat < 3 {
+                        break;
+                    }
+                }
+            }
+            if distribution.len() > max_symbol as usize {
+                return Err(DecodeError::InvalidData);
+            }
+
+            let max = (2 * threshold - 1) - remaining;
+            let low = reader.peek(bits - 1) as i32;
+            let value = if low < max {
+                reader.skip(bits - 1);
+                low
+            } else {
+                let value = reader.read(bits) as i32;
+                if value >= threshold {
+                    value - max
+                } else {
+                    value
+                }
+            };
+            let count = value - 1;
+            remaining -= count.abs();
+            if remaining < 1 {
+                return Err(DecodeError::InvalidData);
+            }
+            distribution.push(count as i16);
+            previous_zero = count == 0;
+            while remaining < threshold {
+                bits -= 1;
+                threshold >>= 1;
+            }
+        }
+        if remaining != 1 {
+            return Err(DecodeError::InvalidData);
+        }
+        let len = reader.bytes_read();
+        if len > input.len() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        Ok((Self::from_distribution(&distribution, accuracy_log)?, len))
+    }
+
+    /// Builds the table from the normalized distribution, where `-1` stands for the "less than 1" probability.
+    pub(super) fn from_distribution(
+        distribution: &[i16],
+        accuracy_log: u8,
+    ) -> Result<Self, DecodeError> {
+        let table_size = 1usize << accuracy_log;
+        let mut entries = vec![Entry::default(); table_size];
+        let mut next = vec![0u16; distribution.len()];
+
+        let mut high_threshold = table_size;
+        for (symbol, &count) in distribution.iter().enumerate() {
+            if count == -1 {
+                high_threshold = high_threshold
+                    .checked_sub(1)
+                    .ok_or(DecodeError::InvalidData)?;
+                entries[high_threshold].symbol = symbol as u8;
+                next[symbol] = 1;
+            } else {
+                next[symbol] = count.max(0) as u16;
+            }
+        }
+
+        let mask = table_size - 1;
+        let step = (table_size >> 1) + (table_size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in distribution.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                if position >= high_threshold {
+                    return Err(DecodeError::InvalidData);
+                }
+                entries[position].symbol = symbol as u8;
+                position = (position + step) & mask;
+                while position >= high_threshold {
+                    position = (position + step) & mask;
+                }
+            }
+        }
+        if position != 0 {
+            return Err(DecodeError::InvalidData);
+        }
+
+        for entry in entries.iter_mut() {
+            let state = &mut next[entry.symbol as usize];
+            let bits = accuracy_log as u32 - (15 - state.leading_zeros());
+            entry.bits = bits as u8;
+            entry.base = ((*state as usize) << bits).wrapping_sub(table_size) as u16;
+            *state += 1;
+        }
+        Ok(Self {
+            accuracy_log,
+            entries,
+        })
+    }
+
+    /// Reads the initial state.
+    #[inline]
+    pub(super) fn init_state(&self, reader: &mut BackwardBitReader) -> usize {
+        reader.read(self.accuracy_log as u32) as usize
+    }
+
+    #[inline]
+    pub(super) fn symbol(&self, state: usize) -> u8 {
+        self.entries[state].symbol
+    }
+
+    /// Reads the bits to move to the next state.
+    #[inline]
+    pub(super) fn next_state(&self, state: usize, reader: &mut BackwardBitReader) -> usize {
+        let entry = self.entries[state];
+        entry.base as usize + reader.read(entry.bits as u32) as usize
+    }
+}
+
+/// LSB-first bit reader of the table description, which reads zeros past the end
+struct ForwardBitReader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ForwardBitReader<'a> {
+    #[inline]
+    fn new(input: &'a [u8]) -> Self {
+        Self { input, position: 0 }
+    }
+
+    #[inline]
+    fn peek(&self, bits: u32) -> u64 {
+        let mut bytes = [0; 8];
+        if let Some(rest) = self.input.get(self.position / 8..) {
+            let available = rest.len().min(8);
+            bytes[..available].copy_from_slice(&rest[..available]);
+        }
+        (u64::from_le_bytes(bytes) >> (self.position % 8)) & ((1 << bits) - 1)
+    }
+
+    #[inline]
+    fn skip(&mut self, bits: u32) {
+        self.position += bits as usize;
+    }
+
+    #[inline]
+    fn read(&mut self, bits: u32) -> u64 {
+        let value = self.peek(bits);
+        self.skip(bits);
+        value
+    }
+
+    #[inline]
+    fn bytes_read(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Transform {
+    delta_bits: u32,
+    delta_state: i32,
+}
+
+/// Encoding table of FSE
+#[derive(Debug, Clone)]
+pub(super) struct FseEncoder {
+    accuracy_log: u8,
+    distribution: Vec<i16>,
+    states: Vec<u16>,
+    transforms: Vec<Transform>,
+}
+
+impl FseEncoder {
+    /// Creates a table that always encodes `symbol` without writing any bits.
+    pub(super) fn rle(symbol: u8) -> Self {
+        let mut distribution = vec![0; symbol as usize + 1];
+        distribution[symbol as usize] = 1;
+        Self {
+            accuracy_log: 0,
+            distribution,
+            states: Vec::new(),
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Builds the table from the normalized distribution in the same way as [`FseTable::from_distribution`].
+    pub(super) fn from_distribution(distribution: &[i16], accuracy_log: u8) -> Self {
+        let table_size = 1usize << accuracy_log;
+        let mut symbols = vec![0u8; table_size];
+        let mut cumulative = vec![0usize; distribution.len()];
+        let mut high_threshold = table_size;
+        let mut total = 0;
+        for (symbol, &count) in distribution.iter().enumerate() {
+            cumulative[symbol] = total;
+            if count == -1 {
+                high_threshold -= 1;
+                symbols[high_threshold] = symbol as u8;
+                total += 1;
+            } else {
+                total += count as usize;
+            }
+        }
+        debug_assert_eq!(total, table_size);
+
+        let mask = table_size - 1;
+        let step = (table_size >> 1) + (table_size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in distribution.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                symbols[position] = symbol as u8;
+                position = (position + step) & mask;
+                while position >= high_threshold {
+                    position = (position + step) & mask;
+                }
+            }
+        }
+        debug_assert_eq!(position, 0);
+
+        // The states of each symbol in the order of the decoding table
+        let mut states = vec![0u16; table_size];
+        let mut next = cumulative.clone();
+        for (index, &symbol) in symbols.iter().enumerate() {
+            states[next[symbol as usize]] = (table_size + index) as u16;
+            next[symbol as usize] += 1;
+        }
+
+        let accuracy_log32 = accuracy_log as u32;
+        let transforms = distribution
+            .iter()
+            .zip(cumulative)
+            .map(|(&count, start)| match count {
+                0 => Transform {
+                    delta_bits: ((accuracy_log32 + 1) << 16) - table_size as u32,
+                    delta_state: 0,
+                },
+                -1 | 1 => Transform {
+                    delta_bits: (accuracy_log32 << 16) - table_size as u32,
+                    delta_state: start as i32 - 1,
+                },
+                _ => {
+                    let max_bits = accuracy_log32 - (count as u32 - 1).ilog2();
+                    Transform {
+                        delta_bits: (max_bits << 16) - ((count as u32) << max_bits),
+                        delta_state: start as i32 - count as i32,
+                    }
+                }
+            })
+            .collect();
+
+        Self {
+            accuracy_log,
+            distribution: distribution.to_vec(),
+            states,
+            transforms,
+        }
+    }
+
+    /// Returns the estimated size of the symbols in bits,
+    /// or `None` if some of them are not in the distribution.
+    pub(super) fn cost(&self, counts: &[usize]) -> Option<f64> {
+        let mut cost = 0.0;
+        for (symbol, &count) in counts.iter().enumerate().filter(|v| *v.1 > 0) {
+            let probability = match self.distribution.get(symbol) {
+                Some(-1) => 1,
+                Some(&v) if v > 0 => v,
+                _ => return None,
+            };
+            cost += count as f64 * (self.accuracy_log as f64 - math::log2(probability as f64));
+        }
+        Some(cost)
+    }
+
+    /// Returns the state of the last symbol, which is encoded with the fewest bits.
+    #[inline]
+    pub(super) fn init_state(&self, symbol: u8) -> u32 {
+        if self.accuracy_log == 0 {
+            return 0;
+        }
+        let transform = self.transforms[symbol as usize];
+        let bits = (transform.delta_bits + (1 << 15)) >> 16;
+        let value = (bits << 16) - transform.delta_bits;
+        self.states[((value >> bits) as i32 + transform.delta_state) as usize] as u32
+    }
+
+    /// Writes the bits of the state and moves to the state of the previous symbol.
+    #[inline]
+    pub(super) fn encode(&self, state: &mut u32, symbol: u8, writer: &mut BackwardBitWriter) {
+        if self.accuracy_log == 0 {
+            return;
+        }
+        let transform = self.transforms[symbol as usize];
+        let bits = (*state + transform.delta_bits) >> 16;
+        writer.write(*state as u64, bits);
+        *state = self.states[((*state >> bits) as i32 + transform.delta_state) as usize] as u32;
+    }
+
+    /// Writes the state, which the decoder reads first.
+    #[inline]
+    pub(super) fn flush(&self, state: u32, writer: &mut BackwardBitWriter) {
+        writer.write(state as u64, self.accuracy_log as u32);
+    }
+}
+
+/// Chooses the accuracy log for the number of symbols and the largest symbol.
+pub(super) fn optimal_accuracy_log(total: usize, max_symbol: usize, max_accuracy_log: u8) -> u8 {
+    let max_bits_source = (total.max(2) - 1).ilog2().saturating_sub(2) as u8;
+    let min_bits_source = total.max(1).ilog2() as u8 + 1;
+    let min_bits_symbol = max_symbol.max(1).ilog2() as u8 + 2;
+    max_bits_source
+        .min(max_accuracy_log)
+        .max(min_bits_source.min(min_bits_symbol))
+        .clamp(MIN_ACCURACY_LOG, max_accuracy_log)
+}
+
+/// Scales the counts to the sum of `1 << accuracy_log`,
+/// where every symbol that occurs gets at least 1 and at most `max_probability`.
+pub(super) fn normalize(counts: &[usize], accuracy_log: u8, max_probability: usize) -> Vec<i16> {
+    let table_size = 1usize << accuracy_log;
+    let total: usize = counts.iter().sum();
+    let mut distribution = counts
+        .iter()
+        .map(|&count| {
+            if count > 0 {
+                (count * table_size / total).clamp(1, max_probability)
+            } else {
+                0
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Adjusts the probabilities of the symbols that lose or gain the least per step.
+    let mut sum: usize = distribution.iter().sum();
+    while sum > table_size {
+        let (symbol, _) = counts
+            .iter()
+            .zip(distribution.iter())
+            .enumerate()
+            .filter(|(_, (_, p))| **p > 1)
+            .min_by(|(_, (c1, p1)), (_, (c2, p2))| (**c1 * **p2).cmp(&(**c2 * **p1)))
+            .unwrap();
+        distribution[symbol] -= 1;
+        sum -= 1;
+    }
+    while sum < table_size {
+        let (symbol, _) = counts
+            .iter()
+            .zip(distribution.iter())
+            .enumerate()
+            .filter(|(_, (c, p))| **c > 0 && **p < max_probability)
+            .max_by(|(_, (c1, p1)), (_, (c2, p2))| (**c1 * **p2).cmp(&(**c2 * **p1)))
+            .unwrap();
+        distribution[symbol] += 1;
+        sum += 1;
+    }
+
+    let len = distribution
+        .iter()
+        .rposition(|&v| v > 0)
+        .map_or(0, |v| v + 1);
+    distribution[..len].iter().map(|&v| v as i16).collect()
+}
+
+/// Writes the table description of the normalized distribution.
+pub(super) fn write_distribution(distribution: &[i16], accuracy_log: u8, output: &mut Vec<u8>) {
+    let mut writer = BitStreamWriter::new();
+    let mut write = |value: u32, bits: u32| {
+        writer.push(VarLenInteger::new(BitSize::new(bits as u8).unwrap(), value));
+    };
+    write((accuracy_log - MIN_ACCURACY_LOG) as u32, 4);
+
+    let mut remaining = (1i32 << accuracy_log) + 1;
+    let mut threshold = 1i32 << accuracy_log;
+    let mut bits = accuracy_log as u32 + 1;
+    let mut symbol = 0;
+    let mut previous_zero = false;
+    while symbol < distribution.len() && remaining > 1 {
+        if previous_zero {
+            let mut start = symbol;
+            while distribution[symbol] == 0 {
+                symbol += 1;
+            }
+            while symbol >= start + 3 {
+                write(3, 2);
+                start += 3;
+            }
+            write((symbol - start) as u32, 2);
+        }
+
+        let count = distribution[symbol] as i32;
+        symbol += 1;
+        let max = (2 * threshold - 1) - remaining;
+        remaining -= count.abs();
+        let mut value = count + 1;
+        if value >= threshold {
+            value += max;
+        }
+        write(value as u32, if value < max { bits - 1 } else { bits });
+        previous_zero = value == 1;
+        while remaining < threshold {
+            bits -= 1;
+            threshold >>= 1;
+        }
+    }
+    debug_assert_eq!(remaining, 1);
+    output.extend_from_slice(&writer.into_bytes());
+}
@@ -0,0 +1,305 @@
+//! Huffman tables of zstd literals
+
+use super::bits::{BackwardBitReader, BackwardBitWriter};
+use super::fse::{self, FseEncoder, FseTable};
+use super::*;
+use crate::entropy::prefix::{CanonicalPrefixCoder, LengthLimitMethod};
+use crate::num::bits::BitSize;
+use alloc::vec;
+
+/// Maximum length of the codes
+const MAX_BITS: u8 = 11;
+
+/// Maximum accuracy log of the FSE table that compresses the weights
+const MAX_WEIGHT_ACCURACY_LOG: u8 = 6;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    symbol: u8,
+    bits: u8,
+}
+
+/// Decoding table indexed by the next `max_bits` bits
+#[derive(Debug, Clone)]
+pub(super) struct HuffmanTable {
+    max_bits: u8,
+    entries: Vec<Entry>,
+}
+
+impl HuffmanTable {
+    /// Reads the tree description and returns the table with the number of bytes read.
+    pub(super) fn read(input: &[u8]) -> Result<(Self, usize), DecodeError> {
+        let header = *input.first().ok_or(DecodeError::UnexpectedEof)? as usize;
+        let mut weights = Vec::with_capacity(255);
+        let len = if header < 128 {
+            let data = input.get(1..1 + header).ok_or(DecodeError::UnexpectedEof)?;
+            let (table, table_len) = FseTable::read(data, MAX_WEIGHT_ACCURACY_LOG, 255)?;
+            let mut reader = BackwardBitReader::new(&data[table_len..])?;
+            let mut states = [table.init_state(&mut reader), table.init_state(&mut reader)];
+            // Two states take turns until the stream overflows, then the other state emits the last weight.
+            'outer: loop {
+                for i in 0..2 {
+                    if weights.len() >= 254 {
+                        return Err(DecodeError::InvalidData);
+                    }
+                    weights.push(table.symbol(states[i]));
+                    states[i] = table.next_state(states[i], &mut reader);
+                    if reader.bits_left() < 0 {
+                        weights.push(table.symbol(states[i ^ 1]));
+                        break 'outer;
+                    }
+                }
+            }
+            1 + header
+        } else {
+            let count = header - 127;
+            let data = input
+                .get(1..1 + count.div_ceil(2))
+                .ok_or(DecodeError::UnexpectedEof)?;
+            weights.extend(data.iter().flat_map(|&v| [v >> 4, v & 15]).take(count));
+            1 + data.len()
+        };
+        Ok((Self::from_weights(&weights)?, len))
+    }
+
+    /// Builds the table from the weights of the symbols except the last one, whose weight is implied.
+    pub(super) fn from_weights(weights: &[u8]) -> Result<Self, DecodeError> {
+        if weights.len() > 255 || weights.iter().any(|&v| v > MAX_BITS) {
+            return Err(DecodeError::InvalidData);
+        }
+        let total: u32 = weights
+            .iter()
+            .filter(|&&v| v > 0)
+            .map(|&v| 1 << (v - 1))
+            .sum();
+        if total == 0 {
+            return Err(DecodeError::InvalidData);
+        }
+        let max_bits = 32 - total.leading_zeros();
+        if max_bits > MAX_BITS as u32 {
+            return Err(DecodeError::InvalidData);
+        }
+        let left = (1 << max_bits) - total;
+        if !left.is_power_of_two() {
+            return Err(DecodeError::InvalidData);
+        }
+        let last_weight = left.trailing_zeros() as u8 + 1;
+
+        let mut entries = Vec::with_capacity(1 << max_bits);
+        for weight in 1..=max_bits as u8 {
+            let bits = max_bits as u8 + 1 - weight;
+            let symbols = weights.iter().chain([&last_weight]).enumerate();
+            for (symbol, _) in symbols.filter(|(_, v)| **v == weight) {
+                let entry = Entry {
+                    symbol: symbol as u8,
+                    bits,
+                };
+                entries.extend(core::iter::repeat_n(entry, 1 << (weight - 1)));
+            }
+        }
+        debug_assert_eq!(entries.len(), 1 << max_bits);
+        Ok(Self {
+            max_bits: max_bits as u8,
+            entries,
+        })
+    }
+
+    /// Decodes a stream that fills the output exactly.
+    pub(super) fn decode_stream(&self, input: &[u8], output: &mut [u8]) -> Result<(), DecodeError> {
+        let mut reader = BackwardBitReader::new(input)?;
+        for byte in output.iter_mut() {
+            let entry = self.entries[reader.peek(self.max_bits as u32) as usize];
+            reader.skip(entry.bits as u32);
+            *byte = entry.symbol;
+        }
+        if !reader.is_empty() {
+            return Err(DecodeError::InvalidData);
+        }
+        Ok(())
+    }
+
+    /// Decodes the literals in 1 or 4 streams.
+    pub(super) fn decode_literals(
+        &self,
+        input: &[u8],
+        output: &mut [u8],
+        four_streams: bool,
+    ) -> Result<(), DecodeError> {
+        if !four_streams {
+            return self.decode_stream(input, output);
+        }
+        let (jump_table, mut input) = input
+            .split_first_chunk::<6>()
+            .ok_or(DecodeError::UnexpectedEof)?;
+        let segment = output.len().div_ceil(4);
+        let mut output = output;
+        for i in 0..4 {
+            let len = if i < 3 {
+                u16::from_le_bytes([jump_table[i * 2], jump_table[i * 2 + 1]]) as usize
+            } else {
+                input.len()
+            };
+            let stream = input.get(..len).ok_or(DecodeError::UnexpectedEof)?;
+            input = &input[len..];
+            let (head, tail) = output.split_at_mut(segment.min(output.len()));
+            self.decode_stream(stream, head)?;
+            output = tail;
+        }
+        Ok(())
+    }
+}
+
+/// Huffman codes of the literals with their tree description
+#[derive(Debug, Clone)]
+pub(super) struct HuffmanEncoder {
+    /// Code and length of each symbol, or zero length if it is absent
+    codes: [(u16, u8); 256],
+    description: Vec<u8>,
+}
+
+impl HuffmanEncoder {
+    /// Builds the length-limited codes from the counts of the literals,
+    /// or returns `None` if there are fewer than 2 symbols or the tree cannot be described.
+    pub(super) fn new(counts: &[usize; 256]) -> Option<Self> {
+        let lengths = CanonicalPrefixCoder::make_prefix_table(
+            counts,
+            BitSize::new(MAX_BITS).unwrap(),
+            256,
+            LengthLimitMethod::PackageMerge,
+        )
+        .iter()
+        .map(|v| v.map_or(0, |v| v.size().as_u8()))
+        .collect::<Vec<_>>();
+        let max_bits = lengths.iter().copied().max().unwrap_or_default();
+        let last = lengths.iter().rposition(|&v| v > 0)?;
+        if lengths.iter().filter(|&&v| v > 0).count() < 2
+            || lengths
+                .iter()
+                .filter(|&&v| v > 0)
+                .map(|&v| 1u32 << (max_bits - v))
+                .sum::<u32>()
+                != 1 << max_bits
+        {
+            return None;
+        }
+
+        let weights = lengths
+            .iter()
+            .map(|&v| if v > 0 { max_bits + 1 - v } else { 0 })
+            .collect::<Vec<_>>();
+        // The codes are assigned in the same order as the decoding table.
+        let mut codes = [(0, 0); 256];
+        let mut position = 0u32;
+        for weight in 1..=max_bits {
+            for (symbol, _) in weights.iter().enumerate().filter(|v| *v.1 == weight) {
+                codes[symbol] = ((position >> (weight - 1)) as u16, max_bits + 1 - weight);
+                position += 1 << (weight - 1);
+            }
+        }
+
+        let description = Self::describe(&weights[..last])?;
+        Some(Self { codes, description })
+    }
+
+    /// Writes the weights except the last one either compressed with FSE or in 4 bits each,
+    /// whichever is shorter.
+    fn describe(weights: &[u8]) -> Option<Vec<u8>> {
+        let direct = (weights.len() <= 128).then(|| {
+            let mut output = vec![127 + weights.len() as u8];
+            output.extend(
+                weights
+                    .chunks(2)
+                    .map(|v| (v[0] << 4) | v.get(1).unwrap_or(&0)),
+            );
+            output
+        });
+
+        let mut counts = [0; MAX_BITS as usize + 1];
+        for &weight in weights {
+            counts[weight as usize] += 1;
+        }
+        let max_weight = counts.iter().rposition(|&v| v > 0).unwrap_or_default();
+        let compressed = (counts.iter().filter(|&&v| v > 0).count() >= 2)
+            .then(|| {
+                let accuracy_log =
+                    fse::optimal_accuracy_log(weights.len(), max_weight, MAX_WEIGHT_ACCURACY_LOG);
+                // Every state transition takes at least 1 bit, so the decoder detects the end.
+                let distribution = fse::normalize(&counts, accuracy_log, 1 << (accuracy_log - 1));
+                let mut output = vec![0];
+                fse::write_distribution(&distribution, accuracy_log, &mut output);
+
+                // The even and odd weights are in separate states, written backward.
+                let encoder = FseEncoder::from_distribution(&distribution, accuracy_log);
+                let mut writer = BackwardBitWriter::new();
+                let mut states = [None, None];
+                for (index, &weight) in weights.iter().enumerate().rev() {
+                    match &mut states[index & 1] {
+                        Some(state) => encoder.encode(state, weight, &mut writer),
+                        slot => *slot = Some(encoder.init_state(weight)),
+                    }
+                }
+                encoder.flush(states[1].unwrap(), &mut writer);
+                encoder.flush(states[0].unwrap(), &mut writer);
+                output.extend_from_slice(&writer.finish());
+                output[0] = (output.len() - 1) as u8;
+                (output.len() <= 128).then_some(output)
+            })
+            .flatten();
+
+        match (direct, compressed) {
+            (Some(direct), Some(compressed)) if compressed.len() < direct.len() => Some(compressed),
+            (Some(direct), _) => Some(direct),
+            (None, compressed) => compressed,
+        }
+    }
+
+    /// Returns the tree description.
+    #[inline]
+    pub(super) fn description(&self) -> &[u8] {
+        &self.description
+    }
+
+    /// Returns the size of the coded literals in bits, or `None` if some of them have no codes.
+    pub(super) fn cost(&self, counts: &[usize; 256]) -> Option<usize> {
+        counts
+            .iter()
+            .zip(self.codes.iter())
+            .filter(|v| *v.0 > 0)
+            .try_fold(0, |acc, (&count, &(_, bits))| {
+                (bits > 0).then_some(acc + count * bits as usize)
+            })
+    }
+
+    /// Encodes the literals in 1 or 4 streams, without the tree description.
+    pub(super) fn encode_literals(
+        &self,
+        literals: &[u8],
+        four_streams: bool,
+        output: &mut Vec<u8>,
+    ) {
+        if !four_streams {
+            return self.encode_stream(literals, output);
+        }
+        let jump_table = output.len();
+        output.extend_from_slice(&[0; 6]);
+        for (i, segment) in literals.chunks(literals.len().div_ceil(4)).enumerate() {
+            let start = output.len();
+            self.encode_stream(segment, output);
+            if i < 3 {
+                let len = output.len() - start;
+                debug_assert!(len <= u16::MAX as usize);
+                output[jump_table + i * 2..][..2].copy_from_slice(&(len as u16).to_le_bytes());
+            }
+        }
+    }
+
+    /// Encodes a stream backward, so that the decoder reads the first literal first.
+    fn encode_stream(&self, literals: &[u8], output: &mut Vec<u8>) {
+        let mut writer = BackwardBitWriter::new();
+        for &literal in literals.iter().rev() {
+            let (code, bits) = self.codes[literal as usize];
+            writer.write(code as u64, bits as u32);
+        }
+        output.extend_from_slice(&writer.finish());
+    }
+}
@@ -0,0 +1,174 @@
+//! Zstandard compressed format
+//!
+//! A frame consists of blocks of up to 128 KiB, which are stored raw, repeat a single byte, or
+//! compressed. A compressed block has the literals coded with Huffman codes in 1 or 4 streams,
+//! followed by the sequences of the literal length, the match length and the offset coded with
+//! the tabled FSE, which is a variant of the tabled asymmetric numeral systems. Both of them are
+//! read backward from the end of each stream.
+//!
+//! The encoder finds the matches with [`LZSS`](crate::lz::lzss::LZSS), codes the literals with
+//! the length-limited codes of [`CanonicalPrefixCoder`](crate::entropy::prefix::CanonicalPrefixCoder),
+//! and chooses the predefined or the block's own FSE tables for the sequences.
+//!
+//! The entropy coder in [`entropy::fse`](crate::entropy::fse) is an adaptive binary coder,
+//! so this module has its own FSE tables for the format.
+//!
+//! See also: <https://www.rfc-editor.org/rfc/rfc8878>
+
+use crate::*;
+
+#[cfg(test)]
+mod tests;
+
+pub mod xxhash64;
+
+mod bits;
+mod decode;
+mod dictionary;
+mod encode;
+mod fse;
+mod huffman;
+pub use decode::*;
+pub use dictionary::*;
+pub use encode::*;
+
+/// Signature of the frame
+const FRAME_MAGIC: u32 = 0xfd2f_b528;
+
+/// Signature of the skippable frames, whose lowest 4 bits are arbitrary
+const SKIPPABLE_MAGIC: u32 = 0x184d_2a50;
+
+/// Signature of the dictionaries with the entropy tables
+const DICTIONARY_MAGIC: u32 = 0xec30_a437;
+
+/// Maximum size of the decompressed data of a block
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+
+/// The repeat offsets at the start of a frame without a dictionary
+const INITIAL_REPEAT_OFFSETS: [usize; 3] = [1, 4, 8];
+
+const MAX_LITERAL_LENGTH_CODE: u8 = 35;
+const MAX_MATCH_LENGTH_CODE: u8 = 52;
+const MAX_OFFSET_CODE: u8 = 31;
+
+const MAX_LITERAL_LENGTH_ACCURACY_LOG: u8 = 9;
+const MAX_MATCH_LENGTH_ACCURACY_LOG: u8 = 9;
+const MAX_OFFSET_ACCURACY_LOG: u8 = 8;
+
+const LITERAL_LENGTH_DEFAULT_ACCURACY_LOG: u8 = 6;
+const MATCH_LENGTH_DEFAULT_ACCURACY_LOG: u8 = 6;
+const OFFSET_DEFAULT_ACCURACY_LOG: u8 = 5;
+
+/// Predefined distribution of the literal length codes
+const LITERAL_LENGTH_DEFAULT_DISTRIBUTION: [i16; 36] = [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1,
+    -1, -1, -1, -1,
+];
+
+/// Predefined distribution of the match length codes
+const MATCH_LENGTH_DEFAULT_DISTRIBUTION: [i16; 53] = [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+];
+
+/// Predefined distribution of the offset codes
+const OFFSET_DEFAULT_DISTRIBUTION: [i16; 29] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+];
+
+/// Baselines and extra bits of the literal length codes
+const LITERAL_LENGTH_CODES: [(u32, u8); 36] = [
+    (0, 0),
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 1),
+    (18, 1),
+    (20, 1),
+    (22, 1),
+    (24, 2),
+    (28, 2),
+    (32, 3),
+    (40, 3),
+    (48, 4),
+    (64, 6),
+    (128, 7),
+    (256, 8),
+    (512, 9),
+    (1024, 10),
+    (2048, 11),
+    (4096, 12),
+    (8192, 13),
+    (16384, 14),
+    (32768, 15),
+    (65536, 16),
+];
+
+/// Baselines and extra bits of the match length codes
+const MATCH_LENGTH_CODES: [(u32, u8); 53] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 0),
+    (17, 0),
+    (18, 0),
+    (19, 0),
+    (20, 0),
+    (21, 0),
+    (22, 0),
+    (23, 0),
+    (24, 0),
+    (25, 0),
+    (26, 0),
+    (27, 0),
+    (28, 0),
+    (29, 0),
+    (30, 0),
+    (31, 0),
+    (32, 0),
+    (33, 0),
+    (34, 0),
+    (35, 1),
+    (37, 1),
+    (39, 1),
+    (41, 1),
+    (43, 2),
+    (47, 2),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 5),
+    (131, 7),
+    (259, 8),
+    (515, 9),
+    (1027, 10),
+    (2051, 11),
+    (4099, 12),
+    (8195, 13),
+    (16387, 14),
+    (32771, 15),
+    (65539, 16),
+];
@@ -0,0 +1,303 @@
+use super::*;
+use base64::prelude::*;
+
+/// Compressed by `zstd -19`, `fib_str(b'a', b'b', 300000)` in 3 blocks
+/// with the RLE and repeat modes of the sequences
+const FIB_ZSTD: &str = "KLUv/aTgkwQA3AkAeGFiYWFiYWJhYWJhYWJhYoDGqPCF/QzAoxQ6ElAQ+BUEEAT+fwRBwuwHq9v1pV2DYWcw7fKFXcFsty/tCIbdg2nHl3YLpt2Dadcv7AiG3XxpN5jsCmY7f2HXYNgNht18YUcw7P5Lu4Nh92Da8YVdg2nnF+0SDLuCaRdf2g2G3YJh11/YHQy7f2n3YNoRDLt92c5g2DWYdnxht2Da9aVdBsPuYNrlS7uCafdf2A1muwXDLl/aHUx2gmnXX9g5GHb70s5g2DWYdnxht2C260u7BMPuYNrlS7uCaffBtPMLuwTDLl/aHUx2gtmuv7AzGHYHwy5f2CUYdv/S7sGwO5h2+cLOYNr1i3YEw27BtMOXdgfDrmDY7S/sBsPuv7Q7mHYJhl1ftmsw7IxfyiR2fDId+9jyCAA6X5dJwrSlhAgACGGAwajgD+APEvj//08QOKD2A8AXdgTTTjDs9IXdYLITzO34hd1g2OkLu8FkJ5h2/MJuMOz0hd1gshOM7PiF3WDYGQw7fmk3GHb80k4w7Qimnb6wG4zsDqZdX7QTTDt+YTcYdgbDjl/aDYYdv7QTjOwIpp2+sBtMu4Np1xftBNOOX9gNhp3BsOMX2Q2GHcG060u7g2mnL+wG0+5g2vVFO8G04xfYDYadwbDjl3aDYUcw7frS7mDa6Qu7wbQ7GNn1RTvBtOMXdoNhZzDs+KXdYNgRTLu+tDsY2ekLu8G0O5h2fdFOMO0Ihp1f2hkMO3xpNxjYEUy7vrQ7mHb6wm4w7Q6mXV+0E0w7gmHnF8FoAkBtAgAAN1wAAP3sBNMOX9oJph3BtNMXdoNpty/tBMPuYNj1C7vBtDuYdn3RTjDt+IXdYNgZDDt+aTcYdgTTri/tDqadvrAbTLuDadcX7Rh4DamT43E=";
+
+/// Compressed by the reference library at level 19 with a flush every 800 bytes, `letters(2400)` in 3 blocks
+/// with the Huffman-coded literals in 4 streams, the latter 2 of which reuse the Huffman table
+const LETTERS_ZSTD: &str = "KLUv/WRgCFwMAAbyYQ3g6eleOYIXpJvA6K8uXgBdAFwAvRbVPL3blRZ5pvp21m0xg9MAv9txVx7UUBBBeACHwEqH71ozeKXcIekbl5ofWL2oCKcdNNVUhVCf1kXqsK5yy2iGwhExgaqLJUurjDacbTQtKDpwdMPq1M6WCV5cGXUwauq6Vd9W39XeBlCPoxp9HSFn7hcdoaWy/D8EBbIqrVjeqnn2QWGFo0fuaNLUSPgQyyyVDJXwUZJfJyyWEd1gnU2e5kKin49c1WycBk8/m8R+1K9wtTja4aH1B3S9OqH8LA0RqQJp+YrY/kyyBw1jhN5zQrfdm9j7xtN2BVT6swAUp9WsDHMr9K+6RlXLoIAAj+XOba20zq28cjeu3vBwBUW99RmbknpT8g6azC23oJhOaoeq1HcEf3AdjQrbBMLnBIiNDEZfv0cZXUS9C8XHvjcZrLXKski47I4AisEbvAta+mCPMlHhrKhSNPVCRjIarvbYJ7O2uM8qZ5Q3f2qLeoOlprC2vOqJ/q5t18TfCT7KJXUA7AsAd3FcWwBdAFkArwf7aglzrR9nz759qQ/1DgH+K9wNctcs0gRUGlRScLWgBHpeRYM6ShRqr5X0qm5aU6hvj4hHX88vseGw2nhbN4u8xpLO6EILdITFYE8tA+nO1406UMtCpGq4aMRq15K3dy8+6/luQ6gfoo5s+pm7r+CInDJxpLer9LAFwXHcCQHRvnpqCU0l4erhn0eTMZD4W0I76dyLZnCcuEP6cUXBu6c1IA59bMkn1oJZDRAyvcl69BOqcN24AUc9/dt76n/lc1TFWkX/oUk2lSXs62IRUSt5PnKJv+2Yv9D41Ad9M831Uo9XY2lEdh19h6AP/8/zNiI6f3SjFupMB6O/aBFRp0XGe75b2FFItDilZJ8mdN0bZ8t0GENl1AFcCVD0S/dXvIHuUXNDqUWP1667/9xPe4p4b2e8LyRydWUqUAYIf6qFniC0rY8202JbfH8cnvQEPFVVfW2PfpaoPfJVo6ig4/m9RVDyACV3tjkEAgAuGktmOQBS5QsABzJeWwBdAF4AL+tu5/G9X+lO0NNWfTngGSywi/XwtdWbvFgaWturndrxOiZxIBhPJHjg0cPFJShHrjEv8RWdSIg+zgt3jDKScBiXV5ZzxI6Xv3Y+Bbx//9Jx/Wuxy3q07EzKG7XRtrRSSgXT+ApUtSk6aiKqQJdoHIf15///8Xx21woea6QeDbKIrsu2/suw7HWpleDlhWwLJ7vLUmTeETr3KyHU23koudDxcW0BVexehaNTC/qyZe7C+EtjqtqKKEe+21orXIWk9Ie8SJg86smuVcE0hgh0s6sR/N5vHciBo1NRJhUoWe0/bNEQZwFZpMrudNSAWSkFIFT3BO1BX6Afz6lg4zwNsiIcqgSVRtzobxUyvM5c14c6wjch7xGD13Kg/FlhnKvfqfhgIp809MhK09ZNhFSnRxzWnwIeRj742Ckq7u5EE2vmsYuyo3SetTvch7/AjfRCnWuCmnKs2HuFuJApwsyrvNK7LbfynjQpKplqSvxxZ8vlgAAy/PS/";
+
+/// Trained by `zstd --train --maxdict=600` on small JSON records
+const DICTIONARY: &str = "N6Qw7Cbz1l8dEBBcoQMAAACY53medwqsx2jsByEpk0xbyrjvSwtjAwAUAIrTaKYDAAAEoEHCDhSwCwWpTWFQKAAGBEACgAGAJTK8UcYAaAzBDAEAAAAAhBQHBmMAAAAAAAAAAAAAAAAAAQAAAAQAAAAIAAAALCAidGFncyI6IFsieCIsICJ5Il19CnsiaWQiOiAxNDIsICJuYW1lIjogImV0YSBlcHNpbG9uIGdhbW1hICIsIngiLCAieSJdfQp7ImlkIjogMTEwLCAibmFtZSI6ICJldGEgemV0YSBiZXRhICIsICJ0YWdzIjogWyJ4IiwgInkiXX0KeyJpZCI6IDMwLCAibmFtZSI6ICJiZXRhIGJldGEgYWxwaGEgIiwgInRhZ3MiOiBbIngiLCAieSJdfQp7ImlkIjogMTMwLCAibmFtZSI6ICJnYW1tYSBiZXRhIGVwc2lsb24gIiwgInRhZ3MiOiBbIngiLCAieSJdfQp7ImlkIjogODgsICJuYW1lIjogImlvdGEga2FwcGEgdGhldGEgIiwgInRhZ3MiOiBbIngiLCAieSJdfQp7ImlkIjogMTgzLCAibmFtZSI6ICJldGEgZXRhIGV0YSAiLCAidGFncyI6IFsieCIsICJ5Il19CnsiaWQiOiA4MiwgIm5hbWUiOiAiZGVsdGEgZ2FtbWEga2FwcGEgIiwgInRhZ3MiOiBbIngiLCAieSJdfQp7ImlkIjogNDQsICJuYW1lIjogImVwc2lsb24gZXBzaWxvbiB0aGV0YSAiLCAidGFncyI6";
+
+/// Compressed by `zstd -19 -D` with [`DICTIONARY`], whose tables are all repeated
+const DICTIONARY_ZSTD: &str = "KLUv/Scm89ZfecUAACB7N2lvCPy/GKNEKCmLas7cBTYet/JYt1rOTro=";
+
+const DICTIONARY_TEXT: &[u8] =
+    b"{\"id\": 7, \"name\": \"epsilon theta theta \", \"tags\": [\"x\", \"y\"]}\n\
+{\"id\": 8, \"name\": \"kappa gamma iota \", \"tags\": [\"x\", \"y\"]}\n";
+
+/// Compressed by `zstd -19 -D` with the raw content `"The quick brown fox jumps over the lazy dog.\n"`
+const RAW_DICTIONARY_ZSTD: &str = "KLUv/SQtdQAAGFQuCgMASPAgqQNhzCvY1t90";
+
+/// `letters(2400)` encoded at the default level, which the reference tool decodes
+const LETTERS_ENCODED_ZSTD: &str = "KLUv/WRgCOUoAApEtAgVEGkPPJ1m1BEBAAAAAAAAEQCAEAA8gQCFAIYAD6Zxfwo1heaiddxNPDXgKDkr5lK1q8tCakzrotZKqvXFG60UaUakvt6qTzbpC9m7+dh+l/HEecw2r3wfeOBF4L88sDDhBFQKcJgu0ybUHHbtpdww+QHarUyUemViNmMXl6V2NqnJElfAfFa3wzbInrzDEhtYJchKqVmMrw1DH9INNSsqk6vGmGYpo5zpvFuglNCGNVYCS7hA05oa1bllkcPdC4gDlTMuBdWswhlmCirw3JFNsGEl9lFYQXL3uoYKXfns8HzBSZx9xi9VLRuRBjxEIxf1uzvM4QAaBo1/MgMGBZo1Wo5Oc9LROTTYLShOsTjeuLql7CY7IJNDAx9lGfmWohXJBwlOAF3eOjxYIcFgCz7NAUbWGYmo6sOZRtLNWi4D3L6nx92JcQ9JDZAWQIZ7ymNWttBoibS0KwM2KOL3ZaUTQMAib/L4HBcTEYxuyyY5XeyK1yQp+C0FfdQ0Ktgq40R9FARZKXxUkKUEwTc5FS6KF5aki0traP5AVQaBEOLfF7BEgHuSAOUJhHaRhb8SAhwheMjMV6/wBB9c0/0GjsIJTHDgEDdqRG7DPxrIujucsIhbhWvTVkQBMY3mbkoWBzPg1iy1FYXX7I6MAHG4AYjyOUPvaLCocJiFCkfbaOeWeJeyBNEaaYxh09tcg216lEXtWWo4xrzw5w4TctPM2MeC7AFsSObQ75Jz7c6nBYGdqKGlUopERiRJQZLmIQiCMBSGRb4BYbcDo1TH8JzeqQ6OBIE4syJV1mkjNcx0QdJdUIgj9g9CyEccBgd0q2gLqJ+Wb+KHfwJz6Ruws1cueCZTugJN7yD8r4sHFRoBcNHIqKlwwOOqFl2+EIHZxxpmh/MCeoPeYkn+HttcvgrcTWJrDyD2l4gf79DXB+LlNEuUMgEOEd3mSL0oU5tcIRj8mhHAJ5vfNCeUtsE9+wXcXvHA6uSI85Cp9wYcCwUnJzkkiFuMG3jvJxxfpajCUW1Yv9kgF1aIvQg7X2yazEVSz1bnb378p2BllSisOI290d+aSG5160DO3Edgh/rWdWpa/Ipy6yISw6aBXx2vNJgx/zz9ZHpI6Memi6vdjYP5UgqSjUXw5/SxAkn6J1RDiIOI8qwOah1znzPDnK76dNOF1Vz4Xed3s+KsViGC1Yp7BWpD2apmwPAHSjF4gi7B5tKJXmGpO3TpBdVh+bs/MEDANZ1q/oITXB/kg6nRCCdv7lafUB8wpCyXxAvgBp9DZkTkuTPY8p7+0SjniWiQ0jf+xRWQG7NCRdZ4ZReqybxUuieD31NsNmYSvKtuaMXrSn1SzNCN62+sKJS0qwn4CfZDYf/ECV1GurRJpjr60uPbVGU5uT1vTAbNL44ezOIhSFDIwG8+Bfkwt/to0Hdx33eGPnmiEsGJwKm5QRJN2QI5d1pEFPznQhdYwuU1UJ7ni7RAd2fZHIqvLrLfgmCLsOjXMcexPGyepMog4RwuXm/YuSbpcP/CxEojX4FYG7yyOEfBRxHI+vJdssROtHeNTTsgMWGeKCeLg4+z4EWQGrnad0HQF2myXUoDGjG7oGH6mKJxErJX6CCyn15bMlYr9yIIL8XE1nFiaom+jvscoVoEwxq3MJjyVEQkVcSrzOltEpJvlX+EY6gq5DEf1qdQEy8UMeS7H8v9J7OIQUNnC2SxQ9pSxUfyLXUATgI806IaqaCg5G7+zh2SRTJUATL89L8=";
+
+/// `letters(2400)` encoded at the fast level, which the reference tool decodes
+const LETTERS_FAST_ZSTD: &str = "KLUv/WRgCJ01AJpF4AgVEGkPPJ1m1BEBAAAAAAAAEQCAEAA8hACJAIgAohXJP5jG/SnUFJqL1nE38dSAo+SsmEvVri4LqTGti1orqdYXb7RSpBmR+nqrPtmkL2Tv5mP7XcYT5zHbvPJ94IEXgf/ywMKEE1ApwGG6TJtQc9i1l3LD5AdotzJR6pWJ2YxdXJba2aQmS1wB81ndDtsge/IOS2xglSArpWYxvjYMfUg3JQLckwQoT82KyuSqMaZZyihnOu8WKCW0YY2VwBIu0LSmRnVuWeRw9wLiQOWMS0E1q3CGmYIKPHdkE2xYiX0UVpDcva6hQlc+OzxfcBJnn/FLVctGpAEP0chF/e4OcziAhkHj38gMGBRo1mg5Os1JR+fQYLegOMXieOPqlrKb7IBMDg18lGXkWwYuOdfyzqclOAF0eevw4MVYIcFgCz7NAUbWGYmo6sOZRtLNWi4D3L6nx92JcQ9JDZAWQIZ7ymNWttBoibS0KwM2RhG/L50AAhZ5k8fnuFiDiGB0WzbJ6WJXvCZJwW8p6KOmUcFWGSdqHwUFZKXwUUGWEgTf5FS4KF5Yki4uraH5A1UZBEKI/4ANhHaRhb8SwizOqoAjBA+Z+eoVnuCDe9P9Bo7CCUxw4BA3akRux3EDWfezHU5YxK3CtWkrooCYRnM3JYuDGXBrltqKwmt2R0aAONwARPmcoXc0WFQ4zEKFo220c0u8S1mCaI00xrDpbe4aTL6qsqkSZihqpYZjzAt/7jAhN818LMgewGZI5tAfgZ0AowQAdQQA8ARApzm44ATAIACBWADIGgCUAm4dAcZoAUAYAfxUQPAvAEEYAG+AEEbAAaTAbmXA7gfszwJGfAGcfwHApQwGgMDIJkBfUNB5M4gRAbBIABlTAPgLcDoDQQUYQAQBAG4BkBEXUCEAX1lgiAyAvwjgBAoAPhYYMAMIFwATIAC4IQCwlINzTwBjvgBfLYC5EyBjW0APkADYEwASFACPBXDfBjpwAVI2wFgiAJ9JAMWhAEN3AYMDQC8BaFMCEC4DPFQAayOAjy/AmAIClACwNQDcE+C+ABhiANMjgCBHAEJcAFIWAC8AeBkANhagsQWcAgEiZ4DLIUAgvoDFKACAL+AECxAABqD4ArgUBdCwAUK1ACe4QBguAKQCYGWAZC8ACAoAPwCSXABcAUq1INgWwDcZXP8DqKAAlzUgUAyYigWAvQSlbwCMUoBpXQCcATBDQe0QwW0K8PqC0CIHQJEAhi4BlVgEwLQA8FPAjBfApREgxgDcnAB7BUJmAYA0gP0GiKkAEFeA3piDYF6ALigC7QEigoA7f4B+FAADIoD4DbjQBWJoAMQNACIEAI8B0AUAaBUAcy+AmAq8+YKoKgHQDQChAkwKhN8F6AwAbAWcYgFidQEoXADAKgBCAYjfAgDBAuBuANULQNMAYBjgDgiAKQrAPhEAuhQgmgZcpwEdVg7GBYArgLlYAJgJkPwCQKAACxgQFnIARAQAdAQ4uAUEVwGA/AJlMjiNDcJpAdxNoPsIgNQLADAUgLIWAEgIIPC6ggeAEIE7QsDgIQBnD4gVAO8LgP4CgEYCgPMCALQBEKkA8b0A+CsAuCIAkC8AshYwVQGgsUDKDHBRCYBMg1BawXkSgKkWYACw2QVkZgEAXoBTGSCGCgB4C1zFALYCsBkLmI0CgN8FjBUBAOUCuHqBggcwAgPgYwH8DC5OAdANICIGoLUAZC5A6ACwdAGE2wKQGgCYAvDnEpDJAKACAD8BiF4BWEiAQQYoKAJAwIBrXiCyBgAbACgvAOgIAGQAoCfgxCnA+wQYCFgHrAqACRZgEQjQ5iQmaQVgSgAgkADmBgALAUA8gP8NMLoFvFUguCxADxTA8AD3QgEmUAB0R4DNGmBQA9jqAtxOwAkSYK0AgSaY6wPAvACARQBsM2BILoAyAwIXAA4nQFhfAGEnAGouIHQIgK8BmAy4dADqMyAQDZiGhbu4wYkagE8AAQwA+wxAdgmAxQHQRAIALYBNBW5gAGNaoPEsIHIWwOIFwmcAyCLALVoAUwaEy4DzAxAoAC8ADQK2e+BqLGAYEYC4LMCxAOBQQEQFAGcBYCdQXABGQfxsAGsLgIwFDKUAvliAEBYAFAvYikCjLjCSAHBNIHQFcMQA1VqADVtAuS2A8IBS0YsRBYTiAqRqAaQr4HIAkBYAKwPmbwDALQCqWsDwCohDAxbFAokIAKEAwpAFLK8AzBXQTwNaqOA0AV8QIMUHFE2BoATQJQZcMkGAAVxAgGUecGoBwKACbpmgfgbkJkBYAAoKMvz0vw==";
+
+/// Compressed by `zstd`, an empty input
+const EMPTY_ZSTD: [u8; 13] = [
+    0x28, 0xb5, 0x2f, 0xfd, 0x24, 0x00, 0x01, 0x00, 0x00, 0x99, 0xe9, 0xd8, 0x51,
+];
+
+/// A raw block of `"abc"` and an RLE block of 5 `'z'`s
+const RAW_RLE_ZSTD: [u8; 16] = [
+    0x28, 0xb5, 0x2f, 0xfd, 0x20, 0x08, 0x18, 0x00, 0x00, b'a', b'b', b'c', 0x2b, 0x00, 0x00, b'z',
+];
+
+/// `[0, 1, 2, 0]` in a single Huffman stream, whose weights are directly represented in 4 bits
+const DIRECT_WEIGHTS_ZSTD: [u8; 16] = [
+    0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x00, 0x3d, 0x00, 0x00, 0x42, 0xc0, 0x00, 0x81, 0x21, 0x63, 0x00,
+];
+
+/// Raw literals `"abc"` and a sequence of a match of 4 bytes at offset 3 in the RLE modes
+const SEQUENCE_ZSTD: [u8; 19] = [
+    0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x00, 0x55, 0x00, 0x00, 0x18, b'a', b'b', b'c', 0x01, 0x54, 0x03,
+    0x02, 0x01, 0x06,
+];
+
+/// Letters of English-like frequencies from a linear congruential generator
+fn letters(len: usize) -> Vec<u8> {
+    const ALPHABET: &[u8] = b"eeeeeeetttttaaaaooooiiinnnsssrrhhldcu  \n";
+    let mut seed = 7u32;
+    (0..len)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ALPHABET[(seed >> 16) as usize % ALPHABET.len()]
+        })
+        .collect()
+}
+
+#[test]
+fn xxhash64() {
+    assert_eq!(xxhash64::checksum(b""), 0xef46_db37_51d8_e999);
+    assert_eq!(xxhash64::checksum(b"a"), 0xd24e_c4f1_a98c_6e5b);
+    assert_eq!(xxhash64::checksum(b"abc"), 0x44bc_2cf5_ad77_0999);
+    assert_eq!(
+        xxhash64::checksum(b"Nobody inspects the spammish repetition"),
+        0xfbce_a83c_8a37_8bf1
+    );
+}
+
+#[test]
+fn decode_reference() {
+    let input = BASE64_STANDARD.decode(FIB_ZSTD).unwrap();
+    assert_eq!(decode(&input).unwrap(), fib_str(b'a', b'b', 300000));
+
+    let input = BASE64_STANDARD.decode(LETTERS_ZSTD).unwrap();
+    assert_eq!(decode(&input).unwrap(), letters(2400));
+
+    assert_eq!(decode(&EMPTY_ZSTD).unwrap(), b"");
+    assert_eq!(decode(&RAW_RLE_ZSTD).unwrap(), b"abczzzzz");
+    assert_eq!(decode(&DIRECT_WEIGHTS_ZSTD).unwrap(), [0, 1, 2, 0]);
+    assert_eq!(decode(&SEQUENCE_ZSTD).unwrap(), b"abcabca");
+}
+
+#[test]
+fn frame_header() {
+    let input = BASE64_STANDARD.decode(FIB_ZSTD).unwrap();
+    let (header, len) = FrameHeader::read(&input).unwrap();
+    assert_eq!(
+        header,
+        FrameHeader {
+            window_size: 300000,
+            content_size: Some(300000),
+            dictionary_id: 0,
+            has_checksum: true,
+        }
+    );
+    assert_eq!(len, 9);
+
+    let (header, len) = FrameHeader::read(&DIRECT_WEIGHTS_ZSTD).unwrap();
+    assert_eq!(header.window_size, 1024);
+    assert_eq!(header.content_size, None);
+    assert_eq!(len, 6);
+
+    let input = BASE64_STANDARD.decode(DICTIONARY_ZSTD).unwrap();
+    let (header, _) = FrameHeader::read(&input).unwrap();
+    assert_eq!(header.dictionary_id, 0x5fd6_f326);
+}
+
+#[test]
+fn dictionaries() {
+    let dictionary = Dictionary::new(&BASE64_STANDARD.decode(DICTIONARY).unwrap()).unwrap();
+    assert_eq!(dictionary.id(), 0x5fd6_f326);
+    let input = BASE64_STANDARD.decode(DICTIONARY_ZSTD).unwrap();
+    let other = Dictionary::raw(1, b"unrelated");
+    assert_eq!(
+        decode_with_dictionaries(&input, &[other.clone(), dictionary.clone()]).unwrap(),
+        DICTIONARY_TEXT
+    );
+    assert_eq!(decode(&input), Err(DecodeError::InvalidInput));
+    assert_eq!(
+        decode_with_dictionaries(&input, &[other]),
+        Err(DecodeError::InvalidInput)
+    );
+
+    // A raw content dictionary has no ID, so the frame refers to it by ID 0.
+    let input = BASE64_STANDARD.decode(RAW_DICTIONARY_ZSTD).unwrap();
+    let content = b"The quick brown fox jumps over the lazy dog.\n";
+    let dictionary = Dictionary::new(content).unwrap();
+    assert_eq!(dictionary.id(), 0);
+    assert_eq!(dictionary.content(), content);
+    assert_eq!(
+        decode_with_dictionaries(&input, &[dictionary]).unwrap(),
+        b"The lazy dog jumps over the quick brown fox.\n"
+    );
+    assert!(decode(&input).is_err());
+
+    // Every repeat offset must be within the content.
+    let mut data = BASE64_STANDARD.decode(DICTIONARY).unwrap();
+    let offsets = data
+        .windows(12)
+        .rposition(|v| v == [1, 0, 0, 0, 4, 0, 0, 0, 8, 0, 0, 0])
+        .unwrap();
+    data[offsets] = 0;
+    assert_eq!(
+        Dictionary::new(&data).unwrap_err(),
+        DecodeError::InvalidData
+    );
+}
+
+#[test]
+fn frames() {
+    let letters_zstd = BASE64_STANDARD.decode(LETTERS_ZSTD).unwrap();
+    let mut skippable = 0x184d_2a5du32.to_le_bytes().to_vec();
+    skippable.extend_from_slice(&3u32.to_le_bytes());
+    skippable.extend_from_slice(b"xyz");
+    let input = [
+        letters_zstd.as_slice(),
+        &skippable,
+        &EMPTY_ZSTD,
+        &RAW_RLE_ZSTD,
+    ]
+    .concat();
+    assert_eq!(
+        decode(&input).unwrap(),
+        [letters(2400).as_slice(), b"abczzzzz"].concat()
+    );
+
+    // A frame can not refer to the data of the previous frames.
+    let mut input = RAW_RLE_ZSTD.to_vec();
+    // The sequence of `SEQUENCE_ZSTD` without the literals
+    input.extend_from_slice(&[
+        0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x00, 0x3d, 0x00, 0x00, 0x00, 0x01, 0x54, 0x00, 0x02, 0x01,
+        0x06,
+    ]);
+    assert_eq!(decode(&input), Err(DecodeError::InvalidData));
+
+    assert_eq!(decode(&[]), Err(DecodeError::UnexpectedEof));
+    assert_eq!(decode(&skippable[..10]), Err(DecodeError::UnexpectedEof));
+    assert_eq!(decode(&skippable), Ok(Vec::new()));
+    assert_eq!(decode(b"garbage"), Err(DecodeError::InvalidData));
+    assert!(decode(&[RAW_RLE_ZSTD.as_slice(), b"garbage"].concat()).is_err());
+}
+
+#[test]
+fn malformed() {
+    let input = BASE64_STANDARD.decode(FIB_ZSTD).unwrap();
+    for len in 0..input.len() {
+        assert!(decode(&input[..len]).is_err());
+    }
+
+    // Content checksum
+    let mut broken = input.clone();
+    *broken.last_mut().unwrap() ^= 1;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    // Reserved bit of the frame header
+    let mut broken = RAW_RLE_ZSTD;
+    broken[4] |= 0x08;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    // Content size
+    let mut broken = RAW_RLE_ZSTD;
+    broken[5] = 9;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    // Reserved block type
+    let mut broken = RAW_RLE_ZSTD;
+    broken[12] |= 0x06;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    // A block larger than the window
+    let mut broken = RAW_RLE_ZSTD;
+    broken[5] = 4;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+    // Treeless literals without the previous Huffman table
+    let mut broken = DIRECT_WEIGHTS_ZSTD;
+    broken[9] |= 0x01;
+    assert!(decode(&broken).is_err());
+    // Leftover bits of the Huffman stream
+    let mut broken = DIRECT_WEIGHTS_ZSTD;
+    broken[14] = 0xc6;
+    assert_eq!(decode(&broken), Err(DecodeError::InvalidData));
+
+    // Truncated FSE table descriptions
+    for input in [&[0x30][..], &[0x00, 0x00], &[0xff; 3]] {
+        assert!(fse::FseTable::read(input, 9, 52).is_err());
+    }
+
+    // Random bit flips never panic.
+    let input = BASE64_STANDARD.decode(LETTERS_ZSTD).unwrap();
+    let mut seed = 1u32;
+    for _ in 0..2000 {
+        let mut broken = input.clone();
+        for _ in 0..3 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let index = (seed >> 8) as usize % broken.len();
+            broken[index] ^= 1 << (seed & 7);
+        }
+        let _ = decode(&broken);
+    }
+}
+
+#[test]
+fn encode_reference() {
+    let input = letters(2400);
+    let expected = BASE64_STANDARD.decode(LETTERS_ENCODED_ZSTD).unwrap();
+    let encoded = encode(&input, Configuration::DEFAULT).unwrap();
+    assert_eq!(encoded, expected);
+    assert_eq!(decode(&encoded).unwrap(), input);
+
+    let expected = BASE64_STANDARD.decode(LETTERS_FAST_ZSTD).unwrap();
+    let encoded = encode(&input, Configuration::new(CompressionLevel::Fast)).unwrap();
+    assert_eq!(encoded, expected);
+    assert_eq!(decode(&encoded).unwrap(), input);
+}
+
+#[test]
+fn encode_decode() {
+    let mut repeated = letters(20000);
+    repeated.extend_from_slice(&repeated.clone());
+    repeated.extend(0..=255);
+    repeated.resize(150_000, 7);
+    let inputs = [
+        Vec::new(),
+        b"a".to_vec(),
+        b"abcabcabcabcabc".to_vec(),
+        vec![0; 200_000],
+        letters(1023),
+        letters(1024),
+        letters(20_000),
+        fib_str(b'a', b'b', 150_000),
+        random_bytes(b'0', b'9', 5000),
+        repeated,
+    ];
+    for input in inputs.iter() {
+        for level in [CompressionLevel::Fast, CompressionLevel::Default] {
+            let config = Configuration::new(level);
+            let encoded = encode(input, config).unwrap();
+            assert_eq!(decode(&encoded).unwrap(), *input);
+            let (header, _) = FrameHeader::read(&encoded).unwrap();
+            assert_eq!(header.content_size, Some(input.len() as u64));
+            assert!(header.has_checksum);
+
+            let config = config.content_size(false).checksum(false);
+            let encoded = encode(input, config).unwrap();
+            assert_eq!(decode(&encoded).unwrap(), *input);
+            let (header, _) = FrameHeader::read(&encoded).unwrap();
+            assert_eq!(header.content_size, None);
+            assert!(!header.has_checksum);
+        }
+    }
+
+    // The compressed blocks stay smaller than the raw blocks.
+    let input = random_bytes(b'a', b'b', 10000);
+    assert!(encode(&input, Configuration::DEFAULT).unwrap().len() < 2000);
+    assert!(
+        encode(&vec![0; 300_000], Configuration::DEFAULT)
+            .unwrap()
+            .len()
+            < 32
+    );
+}
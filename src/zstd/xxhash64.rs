@@ -0,0 +1,89 @@
+//! xxHash64
+//!
+//! See also: <https://github.com/Cyan4973/xxHash/blob/dev/doc/xxhash_spec.md>
+
+const PRIME64_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME64_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME64_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME64_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME64_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+#[inline]
+const fn round(acc: u64, lane: u64) -> u64 {
+    acc.wrapping_add(lane.wrapping_mul(PRIME64_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME64_1)
+}
+
+#[inline]
+const fn merge_round(acc: u64, value: u64) -> u64 {
+    (acc ^ round(0, value))
+        .wrapping_mul(PRIME64_1)
+        .wrapping_add(PRIME64_4)
+}
+
+#[inline]
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().unwrap())
+}
+
+/// Calculates xxHash64 with the seed of zero.
+#[inline]
+pub fn checksum(data: &[u8]) -> u64 {
+    checksum_with_seed(data, 0)
+}
+
+/// Calculates xxHash64.
+pub fn checksum_with_seed(data: &[u8], seed: u64) -> u64 {
+    let mut stripes = data.chunks_exact(32);
+    let mut acc = if data.len() >= 32 {
+        let mut v = [
+            seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2),
+            seed.wrapping_add(PRIME64_2),
+            seed,
+            seed.wrapping_sub(PRIME64_1),
+        ];
+        for stripe in stripes.by_ref() {
+            for (v, lane) in v.iter_mut().zip(stripe.chunks_exact(8)) {
+                *v = round(*v, read_u64(lane));
+            }
+        }
+        let acc = v[0]
+            .rotate_left(1)
+            .wrapping_add(v[1].rotate_left(7))
+            .wrapping_add(v[2].rotate_left(12))
+            .wrapping_add(v[3].rotate_left(18));
+        v.iter().fold(acc, |acc, &v| merge_round(acc, v))
+    } else {
+        seed.wrapping_add(PRIME64_5)
+    };
+    acc = acc.wrapping_add(data.len() as u64);
+
+    let mut lanes = stripes.remainder().chunks_exact(8);
+    for lane in lanes.by_ref() {
+        acc = (acc ^ round(0, read_u64(lane)))
+            .rotate_left(27)
+            .wrapping_mul(PRIME64_1)
+            .wrapping_add(PRIME64_4);
+    }
+    let mut rest = lanes.remainder();
+    if rest.len() >= 4 {
+        let lane = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+        acc = (acc ^ lane.wrapping_mul(PRIME64_1))
+            .rotate_left(23)
+            .wrapping_mul(PRIME64_2)
+            .wrapping_add(PRIME64_3);
+        rest = &rest[4..];
+    }
+    for &byte in rest {
+        acc = (acc ^ (byte as u64).wrapping_mul(PRIME64_5))
+            .rotate_left(11)
+            .wrapping_mul(PRIME64_1);
+    }
+
+    acc ^= acc >> 33;
+    acc = acc.wrapping_mul(PRIME64_2);
+    acc ^= acc >> 29;
+    acc = acc.wrapping_mul(PRIME64_3);
+    acc ^ (acc >> 32)
+}